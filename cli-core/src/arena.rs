@@ -0,0 +1,390 @@
+use std::cmp::{max, min};
+use std::collections::BTreeMap;
+use ahash::AHashMap as HashMap;
+use crate::data::{Allocation, AllocationId, Data, MmapOperation, Operation, ThreadId, Timestamp};
+
+pub type ArenaId = u32;
+
+/// The main arena always has this ID; non-main arenas are numbered
+/// from one in the order in which they were first used.
+pub const MAIN_ARENA: ArenaId = 0;
+
+/// Returns glibc's `HEAP_MAX_SIZE` for a given architecture.
+///
+/// Every heap of a non-main arena is aligned to this, so the heap
+/// to which a chunk belongs can be found by masking its address.
+pub fn heap_max_size( pointer_size: u64 ) -> u64 {
+    if pointer_size == 8 {
+        2 * 4 * 1024 * 1024 * 8
+    } else {
+        2 * 512 * 1024
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ArenaHeap {
+    pub address: u64,
+    pub first_used_at: Timestamp,
+    pub last_used_at: Timestamp,
+    pub mapped_at: Option< Timestamp >,
+    pub unmapped_at: Option< Timestamp >
+}
+
+#[derive(Clone, Debug)]
+pub struct ArenaThread {
+    pub thread: ThreadId,
+    pub allocated_count: u64,
+    pub allocated_size: u64
+}
+
+#[derive(Clone, Debug)]
+pub struct Arena {
+    pub id: ArenaId,
+    pub heaps: Vec< ArenaHeap >,
+    pub threads: Vec< ArenaThread >,
+    pub allocated_count: u64,
+    pub allocated_size: u64
+}
+
+impl Arena {
+    #[inline]
+    pub fn is_main( &self ) -> bool {
+        self.id == MAIN_ARENA
+    }
+}
+
+pub(crate) struct Arenas {
+    arenas: Vec< Arena >,
+    heap_to_arena: HashMap< u64, ArenaId >,
+    heap_mask: u64
+}
+
+fn find_root( parents: &mut [usize], mut index: usize ) -> usize {
+    while parents[ index ] != index {
+        parents[ index ] = parents[ parents[ index ] ];
+        index = parents[ index ];
+    }
+
+    index
+}
+
+impl Arenas {
+    /// Reconstructs glibc's arenas from the allocations' addresses.
+    ///
+    /// We don't see which heaps are linked together, but since a thread
+    /// sticks to a single arena until it's forced to switch we assume that
+    /// a heap which was first touched by a given thread belongs to the same
+    /// arena as the heap which that thread has been using right before.
+    pub(crate) fn reconstruct(
+        pointer_size: u64,
+        allocations: &[Allocation],
+        sorted_by_timestamp: &[AllocationId],
+        mmap_operations: &[MmapOperation]
+    ) -> Self {
+        let heap_mask = !(heap_max_size( pointer_size ) - 1);
+
+        let mut heaps: Vec< ArenaHeap > = Vec::new();
+        let mut heap_to_index: HashMap< u64, usize > = HashMap::new();
+        let mut parents: Vec< usize > = Vec::new();
+        let mut last_heap_by_thread: HashMap< ThreadId, usize > = HashMap::new();
+
+        for &id in sorted_by_timestamp {
+            let allocation = &allocations[ id.raw() as usize ];
+            if allocation.is_jemalloc() || allocation.is_mmaped() || allocation.in_main_arena() {
+                continue;
+            }
+
+            let last_used_at = allocation.deallocation.as_ref().map( |deallocation| deallocation.timestamp ).unwrap_or( allocation.timestamp );
+            let address = allocation.pointer & heap_mask;
+            let index = match heap_to_index.get( &address ) {
+                Some( &index ) => {
+                    let heap = &mut heaps[ index ];
+                    heap.last_used_at = max( heap.last_used_at, last_used_at );
+                    index
+                },
+                None => {
+                    let index = heaps.len();
+                    heaps.push( ArenaHeap {
+                        address,
+                        first_used_at: allocation.timestamp,
+                        last_used_at,
+                        mapped_at: None,
+                        unmapped_at: None
+                    });
+                    heap_to_index.insert( address, index );
+                    parents.push( index );
+
+                    if let Some( &previous_index ) = last_heap_by_thread.get( &allocation.thread ) {
+                        let root = find_root( &mut parents, previous_index );
+                        parents[ index ] = root;
+                    }
+
+                    index
+                }
+            };
+
+            last_heap_by_thread.insert( allocation.thread, index );
+        }
+
+        // The heaps are only ever mapped by glibc itself, so these will be
+        // only available if those calls were actually intercepted.
+        for op in mmap_operations {
+            match *op {
+                MmapOperation::Mmap( ref map ) => {
+                    let range = map.pointer..map.pointer + map.length;
+                    for heap in &mut heaps {
+                        if range.contains( &heap.address ) && map.timestamp <= heap.first_used_at {
+                            heap.mapped_at = Some( max( heap.mapped_at.unwrap_or( map.timestamp ), map.timestamp ) );
+                        }
+                    }
+                },
+                MmapOperation::Munmap( ref unmap ) => {
+                    let range = unmap.pointer..unmap.pointer + unmap.length;
+                    for heap in &mut heaps {
+                        if range.contains( &heap.address ) && unmap.timestamp >= heap.last_used_at {
+                            heap.unmapped_at = Some( min( heap.unmapped_at.unwrap_or( unmap.timestamp ), unmap.timestamp ) );
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut arenas = vec![ Arena {
+            id: MAIN_ARENA,
+            heaps: Vec::new(),
+            threads: Vec::new(),
+            allocated_count: 0,
+            allocated_size: 0
+        }];
+
+        // The heaps are already ordered by their first use, so the roots will be too.
+        let mut root_to_arena: HashMap< usize, ArenaId > = HashMap::new();
+        let mut heap_to_arena = HashMap::new();
+        for index in 0..heaps.len() {
+            let root = find_root( &mut parents, index );
+            let arena_id = *root_to_arena.entry( root ).or_insert_with( || {
+                let arena_id = arenas.len() as ArenaId;
+                arenas.push( Arena {
+                    id: arena_id,
+                    heaps: Vec::new(),
+                    threads: Vec::new(),
+                    allocated_count: 0,
+                    allocated_size: 0
+                });
+                arena_id
+            });
+
+            heap_to_arena.insert( heaps[ index ].address, arena_id );
+            arenas[ arena_id as usize ].heaps.push( heaps[ index ].clone() );
+        }
+
+        let mut output = Arenas {
+            arenas,
+            heap_to_arena,
+            heap_mask
+        };
+
+        let mut threads: HashMap< (ArenaId, ThreadId), ArenaThread > = HashMap::new();
+        for allocation in allocations {
+            let arena_id = match output.arena_of( allocation ) {
+                Some( arena_id ) => arena_id,
+                None => continue
+            };
+
+            let arena = &mut output.arenas[ arena_id as usize ];
+            arena.allocated_count += 1;
+            arena.allocated_size += allocation.usable_size();
+
+            let thread = threads.entry( (arena_id, allocation.thread) ).or_insert_with( || ArenaThread {
+                thread: allocation.thread,
+                allocated_count: 0,
+                allocated_size: 0
+            });
+            thread.allocated_count += 1;
+            thread.allocated_size += allocation.usable_size();
+        }
+
+        for ((arena_id, _), thread) in threads {
+            output.arenas[ arena_id as usize ].threads.push( thread );
+        }
+
+        for arena in &mut output.arenas {
+            arena.threads.sort_by_key( |thread| thread.thread );
+        }
+
+        output
+    }
+
+    #[inline]
+    pub(crate) fn arena_of( &self, allocation: &Allocation ) -> Option< ArenaId > {
+        if allocation.is_jemalloc() || allocation.is_mmaped() {
+            return None;
+        }
+
+        if allocation.in_main_arena() {
+            return Some( MAIN_ARENA );
+        }
+
+        self.heap_to_arena.get( &(allocation.pointer & self.heap_mask) ).cloned()
+    }
+
+    #[inline]
+    pub(crate) fn all( &self ) -> &[Arena] {
+        &self.arenas
+    }
+}
+
+/// Keeps track of the live chunks inside of a single region of memory
+/// (either a non-main heap or the whole main arena) so that we can tell
+/// how much of the address space it spans at any given time.
+#[derive(Default)]
+struct Region {
+    boundaries: BTreeMap< u64, u64 >
+}
+
+impl Region {
+    fn add( &mut self, start: u64, end: u64 ) {
+        *self.boundaries.entry( start ).or_insert( 0 ) += 1;
+        *self.boundaries.entry( end ).or_insert( 0 ) += 1;
+    }
+
+    fn remove( &mut self, start: u64, end: u64 ) {
+        for address in &[start, end] {
+            let count = self.boundaries.get_mut( address ).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                self.boundaries.remove( address );
+            }
+        }
+    }
+
+    fn span( &self, base: Option< u64 > ) -> u64 {
+        let end = match self.boundaries.keys().next_back() {
+            Some( &end ) => end,
+            None => return 0
+        };
+
+        let start = base.unwrap_or_else( || *self.boundaries.keys().next().unwrap() );
+        end - start
+    }
+}
+
+struct ArenaUsage {
+    regions: HashMap< Option< u64 >, Region >,
+    usage: Vec< u64 >,
+    span: Vec< u64 >,
+    heap_mask: u64
+}
+
+impl ArenaUsage {
+    fn update( &mut self, data: &Data, allocation: &Allocation, is_allocation: bool ) {
+        let arena_id = match data.get_arena( allocation ) {
+            Some( arena_id ) => arena_id as usize,
+            None => return
+        };
+
+        let base = if arena_id == MAIN_ARENA as usize { None } else { Some( allocation.pointer & self.heap_mask ) };
+        let range = allocation.actual_range( data );
+        let region = self.regions.entry( base ).or_insert_with( Region::default );
+        self.span[ arena_id ] -= region.span( base );
+        if is_allocation {
+            region.add( range.start, range.end );
+            self.usage[ arena_id ] += range.end - range.start;
+        } else {
+            region.remove( range.start, range.end );
+            self.usage[ arena_id ] -= range.end - range.start;
+        }
+        self.span[ arena_id ] += region.span( base );
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ArenaTimelineSeries {
+    pub arena: ArenaId,
+    pub usage: Vec< u64 >,
+    pub fragmentation: Vec< u64 >
+}
+
+#[derive(Clone, Debug)]
+pub struct ArenaTimeline {
+    pub timestamps: Vec< Timestamp >,
+    pub arenas: Vec< ArenaTimelineSeries >
+}
+
+/// Builds a timeline of the live usage and of the memory lost to fragmentation
+/// for every arena, where the fragmentation is the part of the address space
+/// spanned by the arena's heaps which isn't occupied by any live chunk.
+pub fn build_arena_timeline( data: &Data ) -> ArenaTimeline {
+    let arenas = data.arenas();
+    let granularity = max( (data.last_timestamp() - data.initial_timestamp()).as_usecs() / 1000, 1 );
+
+    let mut state = ArenaUsage {
+        regions: HashMap::new(),
+        usage: vec![ 0; arenas.len() ],
+        span: vec![ 0; arenas.len() ],
+        heap_mask: !(heap_max_size( data.pointer_size() ) - 1)
+    };
+
+    let mut output = ArenaTimeline {
+        timestamps: Vec::new(),
+        arenas: arenas.iter().map( |arena| ArenaTimelineSeries {
+            arena: arena.id,
+            usage: Vec::new(),
+            fragmentation: Vec::new()
+        }).collect()
+    };
+
+    let mut current_time = None;
+    for op in data.operations() {
+        let timestamp = match op {
+            Operation::Allocation { allocation, .. } => {
+                state.update( data, allocation, true );
+                allocation.timestamp
+            },
+            Operation::Deallocation { allocation, deallocation, .. } => {
+                state.update( data, allocation, false );
+                deallocation.timestamp
+            },
+            Operation::Reallocation { new_allocation, old_allocation, .. } => {
+                state.update( data, old_allocation, false );
+                state.update( data, new_allocation, true );
+                new_allocation.timestamp
+            }
+        };
+
+        let time = timestamp.as_usecs() / granularity;
+        if current_time != Some( time ) {
+            current_time = Some( time );
+            output.timestamps.push( Timestamp::from_usecs( time * granularity ) );
+            for series in &mut output.arenas {
+                series.usage.push( 0 );
+                series.fragmentation.push( 0 );
+            }
+        }
+
+        for (index, series) in output.arenas.iter_mut().enumerate() {
+            *series.usage.last_mut().unwrap() = state.usage[ index ];
+            *series.fragmentation.last_mut().unwrap() = state.span[ index ].saturating_sub( state.usage[ index ] );
+        }
+    }
+
+    output
+}
+
+#[test]
+fn test_region_span() {
+    let mut region = Region::default();
+    assert_eq!( region.span( None ), 0 );
+
+    region.add( 100, 200 );
+    region.add( 300, 400 );
+    assert_eq!( region.span( None ), 300 );
+    assert_eq!( region.span( Some( 0 ) ), 400 );
+
+    region.remove( 300, 400 );
+    assert_eq!( region.span( None ), 100 );
+    assert_eq!( region.span( Some( 0 ) ), 200 );
+
+    region.remove( 100, 200 );
+    assert_eq!( region.span( Some( 0 ) ), 0 );
+}
@@ -0,0 +1,159 @@
+use std::cmp::{Ordering, max, min};
+use ahash::AHashMap as HashMap;
+use crate::data::{Allocation, AllocationId, BacktraceId, Timestamp};
+
+pub const LIFETIME_BUCKETS: &[&str] = &[
+    "< 10us",
+    "< 100us",
+    "< 1ms",
+    "< 10ms",
+    "< 100ms",
+    "< 1s",
+    "< 10s",
+    "< 1m",
+    ">= 1m",
+    "leaked"
+];
+
+const LIFETIME_BUCKET_COUNT: usize = 10;
+
+fn lifetime_to_bucket( lifetime: Option< Timestamp > ) -> usize {
+    let us = match lifetime {
+        Some( lifetime ) => lifetime.as_usecs(),
+        None => return LIFETIME_BUCKET_COUNT - 1
+    };
+
+    if us < 10 {
+        0
+    } else if us < 100 {
+        1
+    } else if us < 1000 {
+        2
+    } else if us < 10 * 1000 {
+        3
+    } else if us < 100 * 1000 {
+        4
+    } else if us < 1000 * 1000 {
+        5
+    } else if us < 10 * 1000 * 1000 {
+        6
+    } else if us < 60 * 1000 * 1000 {
+        7
+    } else {
+        8
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ChurnSortBy {
+    AllocationRate,
+    ShortLivedCount
+}
+
+#[derive(Clone, Debug)]
+pub struct ChurnGroup {
+    pub backtrace: BacktraceId,
+    pub allocated_count: u64,
+    pub allocated_size: u64,
+    pub short_lived_count: u64,
+    pub short_lived_size: u64,
+    pub first_allocation: Timestamp,
+    pub last_allocation: Timestamp,
+    pub lifetime_histogram: [u64; LIFETIME_BUCKET_COUNT]
+}
+
+impl ChurnGroup {
+    fn new( backtrace: BacktraceId ) -> Self {
+        ChurnGroup {
+            backtrace,
+            allocated_count: 0,
+            allocated_size: 0,
+            short_lived_count: 0,
+            short_lived_size: 0,
+            first_allocation: Timestamp::max(),
+            last_allocation: Timestamp::min(),
+            lifetime_histogram: [0; LIFETIME_BUCKET_COUNT]
+        }
+    }
+
+    /// The span of time during which this group was allocating.
+    ///
+    /// This is never shorter than one second so that groups which
+    /// only allocated a handful of times in a quick succession
+    /// don't end up with absurdly high rates.
+    fn active_interval_secs( &self ) -> f64 {
+        let interval = if self.allocated_count == 0 {
+            0
+        } else {
+            (self.last_allocation - self.first_allocation).as_usecs()
+        };
+
+        max( interval, 1000 * 1000 ) as f64 / (1000.0 * 1000.0)
+    }
+
+    pub fn allocations_per_second( &self ) -> f64 {
+        self.allocated_count as f64 / self.active_interval_secs()
+    }
+
+    pub fn short_lived_per_second( &self ) -> f64 {
+        self.short_lived_count as f64 / self.active_interval_secs()
+    }
+}
+
+/// Groups the given allocations by their backtraces and gathers
+/// statistics about how often they're allocated and how long they live.
+///
+/// An allocation is considered short-lived when it was deallocated
+/// before `short_lived_threshold` has elapsed.
+pub fn collect_churn_groups< 'a >(
+    allocations: impl Iterator< Item = (AllocationId, &'a Allocation) >,
+    short_lived_threshold: Timestamp,
+    sort_by: ChurnSortBy
+) -> Vec< ChurnGroup > {
+    let mut groups: HashMap< BacktraceId, ChurnGroup > = HashMap::new();
+    for (_, allocation) in allocations {
+        let group = groups.entry( allocation.backtrace ).or_insert_with( || ChurnGroup::new( allocation.backtrace ) );
+        let lifetime = allocation.deallocation.as_ref().map( |deallocation| deallocation.timestamp - allocation.timestamp );
+
+        group.allocated_count += 1;
+        group.allocated_size += allocation.size;
+        group.first_allocation = min( group.first_allocation, allocation.timestamp );
+        group.last_allocation = max( group.last_allocation, allocation.timestamp );
+        group.lifetime_histogram[ lifetime_to_bucket( lifetime ) ] += 1;
+
+        if let Some( lifetime ) = lifetime {
+            if lifetime < short_lived_threshold {
+                group.short_lived_count += 1;
+                group.short_lived_size += allocation.size;
+            }
+        }
+    }
+
+    let mut groups: Vec< _ > = groups.into_iter().map( |(_, group)| group ).collect();
+    match sort_by {
+        ChurnSortBy::AllocationRate => {
+            groups.sort_by( |a, b| {
+                b.allocations_per_second().partial_cmp( &a.allocations_per_second() ).unwrap_or( Ordering::Equal )
+                    .then_with( || a.backtrace.raw().cmp( &b.backtrace.raw() ) )
+            });
+        },
+        ChurnSortBy::ShortLivedCount => {
+            groups.sort_by( |a, b| {
+                b.short_lived_count.cmp( &a.short_lived_count )
+                    .then_with( || a.backtrace.raw().cmp( &b.backtrace.raw() ) )
+            });
+        }
+    }
+
+    groups
+}
+
+#[test]
+fn test_lifetime_to_bucket() {
+    assert_eq!( lifetime_to_bucket( None ), LIFETIME_BUCKETS.len() - 1 );
+    assert_eq!( lifetime_to_bucket( Some( Timestamp::from_usecs( 0 ) ) ), 0 );
+    assert_eq!( lifetime_to_bucket( Some( Timestamp::from_usecs( 10 ) ) ), 1 );
+    assert_eq!( lifetime_to_bucket( Some( Timestamp::from_msecs( 1 ) ) ), 3 );
+    assert_eq!( lifetime_to_bucket( Some( Timestamp::from_secs( 59 ) ) ), 7 );
+    assert_eq!( lifetime_to_bucket( Some( Timestamp::from_secs( 3600 ) ) ), 8 );
+}
@@ -0,0 +1,79 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::churn::{ChurnSortBy, LIFETIME_BUCKETS, collect_churn_groups};
+use crate::data::{BacktraceId, Data, Timestamp};
+use crate::exporter_flamegraph::export_as_flamegraph_by_count;
+use crate::filter::{BasicFilter, Filter};
+use crate::io_adapter::IoAdapter;
+use crate::util::{format_count, table_to_string, write_frame};
+
+fn print_backtrace( data: &Data, backtrace: BacktraceId ) {
+    let interner = data.interner();
+    for (index, (_, frame)) in data.get_backtrace( backtrace ).enumerate() {
+        let mut line = format!( "    #{:02}", index );
+        write_frame( &mut line, interner, frame ).unwrap();
+        println!( "{}", line );
+    }
+}
+
+pub fn analyze_churn(
+    data: &Data,
+    short_lived_threshold: Timestamp,
+    sort_by: ChurnSortBy,
+    count: usize,
+    flamegraph_path: Option< &Path >
+) -> Result< (), io::Error > {
+    let groups = collect_churn_groups( data.allocations_with_id(), short_lived_threshold, sort_by );
+
+    let mut table = Vec::new();
+    let mut header = vec![
+        "#".to_owned(),
+        "Allocs/s".to_owned(),
+        "Allocs".to_owned(),
+        "Short-lived".to_owned(),
+        "Short-lived %".to_owned()
+    ];
+    header.extend( LIFETIME_BUCKETS.iter().map( |&name| name.to_owned() ) );
+    table.push( header );
+
+    for (index, group) in groups.iter().take( count ).enumerate() {
+        let mut row = vec![
+            format!( "{}", index ),
+            format!( "{:.1}", group.allocations_per_second() ),
+            format_count( group.allocated_count ),
+            format_count( group.short_lived_count ),
+            format!( "{:.1}%", group.short_lived_count as f64 / group.allocated_count as f64 * 100.0 )
+        ];
+        row.extend( group.lifetime_histogram.iter().map( |&count| format_count( count ) ) );
+        table.push( row );
+    }
+
+    println!( "{}", table_to_string( &table ) );
+
+    for (index, group) in groups.iter().take( count ).enumerate() {
+        println!( "#{}: {} allocations, {} short-lived, {:.1} allocations/s:",
+            index,
+            group.allocated_count,
+            group.short_lived_count,
+            group.allocations_per_second()
+        );
+        print_backtrace( data, group.backtrace );
+        println!();
+    }
+
+    if let Some( path ) = flamegraph_path {
+        let filter: Filter = BasicFilter {
+            only_temporary: true,
+            .. BasicFilter::default()
+        }.into();
+        let filter = filter.compile( data );
+
+        let mut fp = io::BufWriter::new( File::create( path )? );
+        export_as_flamegraph_by_count( data, IoAdapter::new( &mut fp ), |_, allocation| filter.try_match( data, allocation ) );
+        fp.flush()?;
+    }
+
+    Ok(())
+}
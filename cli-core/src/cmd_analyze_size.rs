@@ -3,16 +3,7 @@ use ahash::AHashMap as HashMap;
 use common::event::Event;
 use common::Timestamp;
 use crate::reader::parse_events;
-
-fn format_count( count: usize ) -> String {
-    if count < 1000 {
-        format!( "{}", count )
-    } else if count < 1000 * 1000 {
-        format!( "{}K", count / 1000 )
-    } else {
-        format!( "{}M", count / (1000 * 1000) )
-    }
-}
+use crate::util::format_count;
 
 pub fn analyze_size( fp: impl Read + Send + 'static ) -> Result< (), io::Error > {
     let (_, event_stream) = parse_events( fp )?;
@@ -116,7 +107,7 @@ pub fn analyze_size( fp: impl Read + Send + 'static ) -> Result< (), io::Error >
 
     println!( "Total event sizes:" );
     for (index, stats) in stats {
-        println!( "  {}: {}MB ({} events)", SIZE_TO_NAME[ index ], stats.size / (1024 * 1024), format_count( stats.count ) );
+        println!( "  {}: {}MB ({} events)", SIZE_TO_NAME[ index ], stats.size / (1024 * 1024), format_count( stats.count as u64 ) );
     }
 
     println!( "\nAllocation lifetime buckets:" );
@@ -135,7 +126,7 @@ pub fn analyze_size( fp: impl Read + Send + 'static ) -> Result< (), io::Error >
             _ => unreachable!()
         };
 
-        println!( "  {}: {}", label, format_count( count ) );
+        println!( "  {}: {}", label, format_count( count as u64 ) );
     }
 
     Ok(())
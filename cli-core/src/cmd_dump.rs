@@ -0,0 +1,699 @@
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, Read, Write};
+
+use serde_json::{Map, Value, json};
+
+use bytehound_format::{Writer, WriterOptions};
+
+use common::Timestamp;
+use common::event::{AllocBody, AllocationId, DataId, Event, FramesInvalidated, HeaderBody};
+
+use crate::reader::parse_events;
+
+/*
+    Every event is dumped as a single JSON object on its own line,
+    with a `kind` field set to the name of the event and the rest
+    of the fields named the same as in `common::event::Event`.
+
+    Timestamps are in raw microseconds, exactly as they're in the file.
+
+    Byte payloads are dumped as a string if they're valid UTF-8,
+    or as `{"hex": "..."}` if they're not. The contents of files and
+    memory dumps are replaced with `{"elided": <length>}` unless
+    asked otherwise, except for `/proc/self/maps` which is always
+    needed for symbolication. `undump` refuses to write back elided
+    payloads unless it's explicitly allowed to write them out as empty.
+*/
+
+const MAPS_PATH: &str = "/proc/self/maps";
+
+pub struct DumpOptions {
+    /// Only dump events with these kinds; dumps everything if empty.
+    pub kinds: Vec< String >,
+    /// Only dump events with a timestamp at or after this many microseconds since the start.
+    pub from: Option< Timestamp >,
+    /// Only dump events with a timestamp at or before this many microseconds since the start.
+    pub to: Option< Timestamp >,
+    /// Whether to include the contents of files and memory dumps.
+    pub include_binary: bool
+}
+
+fn invalid_data< T: Into< String > >( message: T ) -> io::Error {
+    io::Error::new( io::ErrorKind::InvalidData, message.into() )
+}
+
+fn bytes_to_json( bytes: &[u8], elide: bool ) -> Value {
+    if elide {
+        return json!({ "elided": bytes.len() });
+    }
+
+    match std::str::from_utf8( bytes ) {
+        Ok( string ) => Value::String( string.to_owned() ),
+        Err( _ ) => {
+            let mut hex = String::with_capacity( bytes.len() * 2 );
+            for byte in bytes {
+                write!( &mut hex, "{:02x}", byte ).unwrap();
+            }
+
+            json!({ "hex": hex })
+        }
+    }
+}
+
+fn json_to_bytes( value: &Value ) -> io::Result< Vec< u8 > > {
+    if let Some( string ) = value.as_str() {
+        return Ok( string.as_bytes().to_owned() );
+    }
+
+    if value.get( "elided" ).is_some() {
+        return Ok( Vec::new() );
+    }
+
+    let hex = value.get( "hex" ).and_then( |hex| hex.as_str() ).ok_or_else( || invalid_data( "expected a byte string" ) )?;
+    if hex.len() % 2 != 0 {
+        return Err( invalid_data( "odd number of hex digits" ) );
+    }
+
+    (0..hex.len()).step_by( 2 ).map( |index| {
+        u8::from_str_radix( &hex[ index..index + 2 ], 16 ).map_err( |_| invalid_data( "invalid hex digits" ) )
+    }).collect()
+}
+
+fn alloc_body_to_json( body: &AllocBody ) -> Value {
+    json!({
+        "pointer": body.pointer,
+        "size": body.size,
+        "backtrace": body.backtrace,
+        "thread": body.thread,
+        "flags": body.flags,
+        "extra_usable_space": body.extra_usable_space,
+        "preceding_free_space": body.preceding_free_space
+    })
+}
+
+fn allocation_id_to_json( id: AllocationId ) -> Value {
+    json!({ "thread": id.thread, "allocation": id.allocation })
+}
+
+fn frames_invalidated_to_json( frames_invalidated: FramesInvalidated ) -> Value {
+    match frames_invalidated {
+        FramesInvalidated::All => json!( "all" ),
+        FramesInvalidated::Some( count ) => json!( count )
+    }
+}
+
+fn kind_of( event: &Event ) -> &'static str {
+    match *event {
+        Event::Header( .. ) => "Header",
+        Event::Alloc { .. } => "Alloc",
+        Event::Realloc { .. } => "Realloc",
+        Event::Free { .. } => "Free",
+        Event::File { .. } => "File",
+        Event::Backtrace { .. } => "Backtrace",
+        Event::MemoryDump { .. } => "MemoryDump",
+        Event::Marker { .. } => "Marker",
+        Event::MemoryMap { .. } => "MemoryMap",
+        Event::MemoryUnmap { .. } => "MemoryUnmap",
+        Event::Mallopt { .. } => "Mallopt",
+        Event::Environ { .. } => "Environ",
+        Event::WallClock { .. } => "WallClock",
+        Event::PartialBacktrace { .. } => "PartialBacktrace",
+        Event::String { .. } => "String",
+        Event::DecodedFrame { .. } => "DecodedFrame",
+        Event::DecodedBacktrace { .. } => "DecodedBacktrace",
+        Event::GroupStatistics { .. } => "GroupStatistics",
+        Event::PartialBacktrace32 { .. } => "PartialBacktrace32",
+        Event::Backtrace32 { .. } => "Backtrace32",
+        Event::AllocEx { .. } => "AllocEx",
+        Event::ReallocEx { .. } => "ReallocEx",
+        Event::FreeEx { .. } => "FreeEx",
+        Event::File64 { .. } => "File64",
+        Event::ProcessInfo { .. } => "ProcessInfo"
+    }
+}
+
+fn timestamp_of( event: &Event ) -> Option< Timestamp > {
+    match *event {
+        Event::Alloc { timestamp, .. } |
+        Event::Realloc { timestamp, .. } |
+        Event::Free { timestamp, .. } |
+        Event::File { timestamp, .. } |
+        Event::MemoryMap { timestamp, .. } |
+        Event::MemoryUnmap { timestamp, .. } |
+        Event::Mallopt { timestamp, .. } |
+        Event::WallClock { timestamp, .. } |
+        Event::AllocEx { timestamp, .. } |
+        Event::ReallocEx { timestamp, .. } |
+        Event::FreeEx { timestamp, .. } |
+        Event::File64 { timestamp, .. } => Some( timestamp ),
+        _ => None
+    }
+}
+
+fn event_to_json( event: &Event, include_binary: bool ) -> Value {
+    let mut value = match *event {
+        Event::Header( ref header ) => json!({
+            "id": header.id.to_string(),
+            "initial_timestamp": header.initial_timestamp.as_usecs(),
+            "timestamp": header.timestamp.as_usecs(),
+            "wall_clock_secs": header.wall_clock_secs,
+            "wall_clock_nsecs": header.wall_clock_nsecs,
+            "pid": header.pid,
+            "cmdline": bytes_to_json( &header.cmdline, false ),
+            "executable": bytes_to_json( &header.executable, false ),
+            "arch": header.arch,
+            "flags": header.flags,
+            "pointer_size": header.pointer_size
+        }),
+        Event::Alloc { timestamp, ref allocation } => json!({
+            "timestamp": timestamp.as_usecs(),
+            "allocation": alloc_body_to_json( allocation )
+        }),
+        Event::Realloc { timestamp, old_pointer, ref allocation } => json!({
+            "timestamp": timestamp.as_usecs(),
+            "old_pointer": old_pointer,
+            "allocation": alloc_body_to_json( allocation )
+        }),
+        Event::Free { timestamp, pointer, backtrace, thread } => json!({
+            "timestamp": timestamp.as_usecs(),
+            "pointer": pointer,
+            "backtrace": backtrace,
+            "thread": thread
+        }),
+        Event::File { timestamp, ref path, ref contents } |
+        Event::File64 { timestamp, ref path, ref contents } => json!({
+            "timestamp": timestamp.as_usecs(),
+            "path": path,
+            "contents": bytes_to_json( contents, !include_binary && path != MAPS_PATH )
+        }),
+        Event::Backtrace { id, ref addresses } => json!({
+            "id": id,
+            "addresses": addresses
+        }),
+        Event::MemoryDump { address, length, ref data } => json!({
+            "address": address,
+            "length": length,
+            "data": bytes_to_json( data, !include_binary )
+        }),
+        Event::Marker { value } => json!({
+            "value": value
+        }),
+        Event::MemoryMap { timestamp, pointer, length, backtrace, requested_address, mmap_protection, mmap_flags, file_descriptor, thread, offset } => json!({
+            "timestamp": timestamp.as_usecs(),
+            "pointer": pointer,
+            "length": length,
+            "backtrace": backtrace,
+            "requested_address": requested_address,
+            "mmap_protection": mmap_protection,
+            "mmap_flags": mmap_flags,
+            "file_descriptor": file_descriptor,
+            "thread": thread,
+            "offset": offset
+        }),
+        Event::MemoryUnmap { timestamp, pointer, length, backtrace, thread } => json!({
+            "timestamp": timestamp.as_usecs(),
+            "pointer": pointer,
+            "length": length,
+            "backtrace": backtrace,
+            "thread": thread
+        }),
+        Event::Mallopt { timestamp, backtrace, thread, param, value, result } => json!({
+            "timestamp": timestamp.as_usecs(),
+            "backtrace": backtrace,
+            "thread": thread,
+            "param": param,
+            "value": value,
+            "result": result
+        }),
+        Event::Environ { ref entry } => json!({
+            "entry": bytes_to_json( entry, false )
+        }),
+        Event::WallClock { timestamp, sec, nsec } => json!({
+            "timestamp": timestamp.as_usecs(),
+            "sec": sec,
+            "nsec": nsec
+        }),
+        Event::ProcessInfo { parent_pid } => json!({
+            "parent_pid": parent_pid
+        }),
+        Event::PartialBacktrace { id, thread, frames_invalidated, ref addresses } => json!({
+            "id": id,
+            "thread": thread,
+            "frames_invalidated": frames_invalidated_to_json( frames_invalidated ),
+            "addresses": addresses
+        }),
+        Event::String { id, ref string } => json!({
+            "id": id,
+            "string": string
+        }),
+        Event::DecodedFrame { address, library, raw_function, function, source, line, column, is_inline } => json!({
+            "address": address,
+            "library": library,
+            "raw_function": raw_function,
+            "function": function,
+            "source": source,
+            "line": line,
+            "column": column,
+            "is_inline": is_inline
+        }),
+        Event::DecodedBacktrace { ref frames } => json!({
+            "frames": frames
+        }),
+        Event::GroupStatistics { backtrace, first_allocation, last_allocation, free_count, free_size, min_size, max_size } => json!({
+            "backtrace": backtrace,
+            "first_allocation": first_allocation.as_usecs(),
+            "last_allocation": last_allocation.as_usecs(),
+            "free_count": free_count,
+            "free_size": free_size,
+            "min_size": min_size,
+            "max_size": max_size
+        }),
+        Event::PartialBacktrace32 { id, thread, frames_invalidated, ref addresses } => json!({
+            "id": id,
+            "thread": thread,
+            "frames_invalidated": frames_invalidated_to_json( frames_invalidated ),
+            "addresses": addresses
+        }),
+        Event::Backtrace32 { id, ref addresses } => json!({
+            "id": id,
+            "addresses": addresses
+        }),
+        Event::AllocEx { id, timestamp, ref allocation } => json!({
+            "id": allocation_id_to_json( id ),
+            "timestamp": timestamp.as_usecs(),
+            "allocation": alloc_body_to_json( allocation )
+        }),
+        Event::ReallocEx { id, timestamp, old_pointer, ref allocation } => json!({
+            "id": allocation_id_to_json( id ),
+            "timestamp": timestamp.as_usecs(),
+            "old_pointer": old_pointer,
+            "allocation": alloc_body_to_json( allocation )
+        }),
+        Event::FreeEx { id, timestamp, pointer, backtrace, thread } => json!({
+            "id": allocation_id_to_json( id ),
+            "timestamp": timestamp.as_usecs(),
+            "pointer": pointer,
+            "backtrace": backtrace,
+            "thread": thread
+        })
+    };
+
+    value.as_object_mut().unwrap().insert( "kind".to_owned(), Value::String( kind_of( event ).to_owned() ) );
+    value
+}
+
+struct Fields< 'a > {
+    map: &'a Map< String, Value >,
+    allow_elided: bool
+}
+
+impl< 'a > Fields< 'a > {
+    fn get( &self, name: &str ) -> io::Result< &'a Value > {
+        self.map.get( name ).ok_or_else( || invalid_data( format!( "missing field '{}'", name ) ) )
+    }
+
+    fn u64( &self, name: &str ) -> io::Result< u64 > {
+        self.get( name )?.as_u64().ok_or_else( || invalid_data( format!( "field '{}' is not an unsigned integer", name ) ) )
+    }
+
+    fn u32( &self, name: &str ) -> io::Result< u32 > {
+        let value = self.u64( name )?;
+        if value > std::u32::MAX as u64 {
+            return Err( invalid_data( format!( "field '{}' is out of range", name ) ) );
+        }
+
+        Ok( value as u32 )
+    }
+
+    fn i32( &self, name: &str ) -> io::Result< i32 > {
+        let value = self.get( name )?.as_i64().ok_or_else( || invalid_data( format!( "field '{}' is not an integer", name ) ) )?;
+        if value < std::i32::MIN as i64 || value > std::i32::MAX as i64 {
+            return Err( invalid_data( format!( "field '{}' is out of range", name ) ) );
+        }
+
+        Ok( value as i32 )
+    }
+
+    fn bool( &self, name: &str ) -> io::Result< bool > {
+        self.get( name )?.as_bool().ok_or_else( || invalid_data( format!( "field '{}' is not a boolean", name ) ) )
+    }
+
+    fn string( &self, name: &str ) -> io::Result< String > {
+        self.get( name )?.as_str().map( |string| string.to_owned() ).ok_or_else( || invalid_data( format!( "field '{}' is not a string", name ) ) )
+    }
+
+    fn bytes( &self, name: &str ) -> io::Result< Vec< u8 > > {
+        let value = self.get( name )?;
+        if value.get( "elided" ).is_some() {
+            if !self.allow_elided {
+                return Err( invalid_data( format!( "the '{}' field was elided; dump the data with `--include-binary` or allow it to be written out as empty", name ) ) );
+            }
+
+            warn!( "The '{}' field was elided; it'll be empty", name );
+        }
+
+        json_to_bytes( value )
+    }
+
+    fn timestamp( &self, name: &str ) -> io::Result< Timestamp > {
+        self.u64( name ).map( Timestamp::from_usecs )
+    }
+
+    fn array< T >( &self, name: &str, convert: impl Fn( &Value ) -> Option< T > ) -> io::Result< Vec< T > > {
+        let array = self.get( name )?.as_array().ok_or_else( || invalid_data( format!( "field '{}' is not an array", name ) ) )?;
+        array.iter().map( |value| convert( value ).ok_or_else( || invalid_data( format!( "field '{}' has an invalid element", name ) ) ) ).collect()
+    }
+
+    fn u64_array( &self, name: &str ) -> io::Result< Vec< u64 > > {
+        self.array( name, |value| value.as_u64() )
+    }
+
+    fn u32_array( &self, name: &str ) -> io::Result< Vec< u32 > > {
+        self.array( name, |value| value.as_u64().filter( |&value| value <= std::u32::MAX as u64 ).map( |value| value as u32 ) )
+    }
+
+    fn object( &self, name: &str ) -> io::Result< Fields< 'a > > {
+        self.get( name )?.as_object().map( Fields ).ok_or_else( || invalid_data( format!( "field '{}' is not an object", name ) ) )
+    }
+
+    fn alloc_body( &self, name: &str ) -> io::Result< AllocBody > {
+        let body = self.object( name )?;
+        Ok( AllocBody {
+            pointer: body.u64( "pointer" )?,
+            size: body.u64( "size" )?,
+            backtrace: body.u64( "backtrace" )?,
+            thread: body.u32( "thread" )?,
+            flags: body.u32( "flags" )?,
+            extra_usable_space: body.u32( "extra_usable_space" )?,
+            preceding_free_space: body.u64( "preceding_free_space" )?
+        })
+    }
+
+    fn allocation_id( &self, name: &str ) -> io::Result< AllocationId > {
+        let id = self.object( name )?;
+        Ok( AllocationId {
+            thread: id.u64( "thread" )?,
+            allocation: id.u64( "allocation" )?
+        })
+    }
+
+    fn frames_invalidated( &self, name: &str ) -> io::Result< FramesInvalidated > {
+        let value = self.get( name )?;
+        if value.as_str() == Some( "all" ) {
+            return Ok( FramesInvalidated::All );
+        }
+
+        self.u32( name ).map( FramesInvalidated::Some )
+    }
+}
+
+fn json_to_event( value: &Value, allow_elided: bool ) -> io::Result< Event< 'static > > {
+    let fields = Fields {
+        map: value.as_object().ok_or_else( || invalid_data( "expected an object" ) )?,
+        allow_elided
+    };
+    let kind = fields.string( "kind" )?;
+    let event = match kind.as_str() {
+        "Header" => Event::Header( HeaderBody {
+            id: fields.string( "id" )?.parse::< DataId >().map_err( |_| invalid_data( "invalid data ID" ) )?,
+            initial_timestamp: fields.timestamp( "initial_timestamp" )?,
+            timestamp: fields.timestamp( "timestamp" )?,
+            wall_clock_secs: fields.u64( "wall_clock_secs" )?,
+            wall_clock_nsecs: fields.u64( "wall_clock_nsecs" )?,
+            pid: fields.u32( "pid" )?,
+            cmdline: fields.bytes( "cmdline" )?,
+            executable: fields.bytes( "executable" )?,
+            arch: fields.string( "arch" )?,
+            flags: fields.u64( "flags" )?,
+            pointer_size: fields.u32( "pointer_size" )? as u8
+        }),
+        "Alloc" => Event::Alloc {
+            timestamp: fields.timestamp( "timestamp" )?,
+            allocation: fields.alloc_body( "allocation" )?
+        },
+        "Realloc" => Event::Realloc {
+            timestamp: fields.timestamp( "timestamp" )?,
+            old_pointer: fields.u64( "old_pointer" )?,
+            allocation: fields.alloc_body( "allocation" )?
+        },
+        "Free" => Event::Free {
+            timestamp: fields.timestamp( "timestamp" )?,
+            pointer: fields.u64( "pointer" )?,
+            backtrace: fields.u64( "backtrace" )?,
+            thread: fields.u32( "thread" )?
+        },
+        "File" => Event::File {
+            timestamp: fields.timestamp( "timestamp" )?,
+            path: Cow::Owned( fields.string( "path" )? ),
+            contents: Cow::Owned( fields.bytes( "contents" )? )
+        },
+        "File64" => Event::File64 {
+            timestamp: fields.timestamp( "timestamp" )?,
+            path: Cow::Owned( fields.string( "path" )? ),
+            contents: Cow::Owned( fields.bytes( "contents" )? )
+        },
+        "Backtrace" => Event::Backtrace {
+            id: fields.u64( "id" )?,
+            addresses: Cow::Owned( fields.u64_array( "addresses" )? )
+        },
+        "MemoryDump" => Event::MemoryDump {
+            address: fields.u64( "address" )?,
+            length: fields.u64( "length" )?,
+            data: Cow::Owned( fields.bytes( "data" )? )
+        },
+        "Marker" => Event::Marker {
+            value: fields.u32( "value" )?
+        },
+        "MemoryMap" => Event::MemoryMap {
+            timestamp: fields.timestamp( "timestamp" )?,
+            pointer: fields.u64( "pointer" )?,
+            length: fields.u64( "length" )?,
+            backtrace: fields.u64( "backtrace" )?,
+            requested_address: fields.u64( "requested_address" )?,
+            mmap_protection: fields.u32( "mmap_protection" )?,
+            mmap_flags: fields.u32( "mmap_flags" )?,
+            file_descriptor: fields.u32( "file_descriptor" )?,
+            thread: fields.u32( "thread" )?,
+            offset: fields.u64( "offset" )?
+        },
+        "MemoryUnmap" => Event::MemoryUnmap {
+            timestamp: fields.timestamp( "timestamp" )?,
+            pointer: fields.u64( "pointer" )?,
+            length: fields.u64( "length" )?,
+            backtrace: fields.u64( "backtrace" )?,
+            thread: fields.u32( "thread" )?
+        },
+        "Mallopt" => Event::Mallopt {
+            timestamp: fields.timestamp( "timestamp" )?,
+            backtrace: fields.u64( "backtrace" )?,
+            thread: fields.u32( "thread" )?,
+            param: fields.i32( "param" )?,
+            value: fields.i32( "value" )?,
+            result: fields.i32( "result" )?
+        },
+        "Environ" => Event::Environ {
+            entry: Cow::Owned( fields.bytes( "entry" )? )
+        },
+        "WallClock" => Event::WallClock {
+            timestamp: fields.timestamp( "timestamp" )?,
+            sec: fields.u64( "sec" )?,
+            nsec: fields.u64( "nsec" )?
+        },
+        "ProcessInfo" => Event::ProcessInfo {
+            parent_pid: fields.u32( "parent_pid" )?
+        },
+        "PartialBacktrace" => Event::PartialBacktrace {
+            id: fields.u64( "id" )?,
+            thread: fields.u32( "thread" )?,
+            frames_invalidated: fields.frames_invalidated( "frames_invalidated" )?,
+            addresses: Cow::Owned( fields.u64_array( "addresses" )? )
+        },
+        "String" => Event::String {
+            id: fields.u32( "id" )?,
+            string: Cow::Owned( fields.string( "string" )? )
+        },
+        "DecodedFrame" => Event::DecodedFrame {
+            address: fields.u64( "address" )?,
+            library: fields.u32( "library" )?,
+            raw_function: fields.u32( "raw_function" )?,
+            function: fields.u32( "function" )?,
+            source: fields.u32( "source" )?,
+            line: fields.u32( "line" )?,
+            column: fields.u32( "column" )?,
+            is_inline: fields.bool( "is_inline" )?
+        },
+        "DecodedBacktrace" => Event::DecodedBacktrace {
+            frames: Cow::Owned( fields.u32_array( "frames" )? )
+        },
+        "GroupStatistics" => Event::GroupStatistics {
+            backtrace: fields.u64( "backtrace" )?,
+            first_allocation: fields.timestamp( "first_allocation" )?,
+            last_allocation: fields.timestamp( "last_allocation" )?,
+            free_count: fields.u64( "free_count" )?,
+            free_size: fields.u64( "free_size" )?,
+            min_size: fields.u64( "min_size" )?,
+            max_size: fields.u64( "max_size" )?
+        },
+        "PartialBacktrace32" => Event::PartialBacktrace32 {
+            id: fields.u64( "id" )?,
+            thread: fields.u32( "thread" )?,
+            frames_invalidated: fields.frames_invalidated( "frames_invalidated" )?,
+            addresses: Cow::Owned( fields.u32_array( "addresses" )? )
+        },
+        "Backtrace32" => Event::Backtrace32 {
+            id: fields.u64( "id" )?,
+            addresses: Cow::Owned( fields.u32_array( "addresses" )? )
+        },
+        "AllocEx" => Event::AllocEx {
+            id: fields.allocation_id( "id" )?,
+            timestamp: fields.timestamp( "timestamp" )?,
+            allocation: fields.alloc_body( "allocation" )?
+        },
+        "ReallocEx" => Event::ReallocEx {
+            id: fields.allocation_id( "id" )?,
+            timestamp: fields.timestamp( "timestamp" )?,
+            old_pointer: fields.u64( "old_pointer" )?,
+            allocation: fields.alloc_body( "allocation" )?
+        },
+        "FreeEx" => Event::FreeEx {
+            id: fields.allocation_id( "id" )?,
+            timestamp: fields.timestamp( "timestamp" )?,
+            pointer: fields.u64( "pointer" )?,
+            backtrace: fields.u64( "backtrace" )?,
+            thread: fields.u32( "thread" )?
+        },
+        _ => return Err( invalid_data( format!( "unknown event kind: '{}'", kind ) ) )
+    };
+
+    Ok( event )
+}
+
+/// Prints every event of the data file as a JSON object on its own line.
+pub fn dump< F: Read + Send + 'static, G: Write >( input: F, mut output: G, options: DumpOptions ) -> io::Result< () > {
+    let (header, event_stream) = parse_events( input )?;
+    let initial_timestamp = header.initial_timestamp;
+    let is_kind_included = |kind: &str| options.kinds.is_empty() || options.kinds.iter().any( |included| included.eq_ignore_ascii_case( kind ) );
+
+    let header = Event::Header( header );
+    let events = std::iter::once( Ok( header ) ).chain( event_stream );
+    for event in events {
+        let event = event?;
+        if !is_kind_included( kind_of( &event ) ) {
+            continue;
+        }
+
+        if let Some( timestamp ) = timestamp_of( &event ) {
+            let elapsed = if timestamp > initial_timestamp { timestamp - initial_timestamp } else { Timestamp::min() };
+            if options.from.map( |from| elapsed < from ).unwrap_or( false ) || options.to.map( |to| elapsed > to ).unwrap_or( false ) {
+                continue;
+            }
+        }
+
+        serde_json::to_writer( &mut output, &event_to_json( &event, options.include_binary ) )?;
+        output.write_all( b"\n" )?;
+    }
+
+    output.flush()
+}
+
+/// Rebuilds a data file from the output of `dump`.
+///
+/// Unless `allow_elided` is set any elided contents of files and memory dumps
+/// result in an error instead of being silently written out as empty.
+pub fn undump< F: BufRead, G: Write >( input: F, output: G, allow_elided: bool ) -> io::Result< () > {
+    let mut output = Some( output );
+    let mut writer = None;
+    for (index, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let value: Value = serde_json::from_str( &line ).map_err( |error| invalid_data( format!( "line {}: {}", index + 1, error ) ) )?;
+        let event = json_to_event( &value, allow_elided ).map_err( |error| invalid_data( format!( "line {}: {}", index + 1, error ) ) )?;
+        match event {
+            Event::Header( header ) => {
+                if writer.is_some() {
+                    // The headers repeated in streamed data are identical to the first one.
+                    continue;
+                }
+
+                // Keep the version of the original file so that every event it contains is accepted.
+                let options = WriterOptions {
+                    version: header.format_version(),
+                    .. WriterOptions::default()
+                };
+
+                writer = Some( Writer::with_options( output.take().unwrap(), header, options )? );
+            },
+            event => match writer {
+                Some( ref mut writer ) => writer.write_event( &event )?,
+                None => return Err( invalid_data( "the first event must be a header" ) )
+            }
+        }
+    }
+
+    match writer {
+        Some( writer ) => writer.finish().map( |_| () ),
+        None => Err( invalid_data( "the input is empty" ) )
+    }
+}
+
+#[test]
+fn test_event_json_roundtrip() {
+    let events = vec![
+        Event::Alloc {
+            timestamp: Timestamp::from_usecs( 1234 ),
+            allocation: AllocBody {
+                pointer: 0x1000,
+                size: 24,
+                backtrace: 3,
+                thread: 1,
+                flags: 0,
+                extra_usable_space: 8,
+                preceding_free_space: 0
+            }
+        },
+        Event::PartialBacktrace {
+            id: 3,
+            thread: 1,
+            frames_invalidated: FramesInvalidated::All,
+            addresses: Cow::Owned( vec![ 0x4000, 0x5000 ] )
+        },
+        Event::Environ {
+            entry: Cow::Owned( b"FOO=\xFF".to_vec() )
+        }
+    ];
+
+    for event in events {
+        let value = event_to_json( &event, false );
+        assert_eq!( json_to_event( &value, false ).unwrap(), event );
+    }
+}
+
+#[test]
+fn test_elided_contents() {
+    let maps = Event::File {
+        timestamp: Timestamp::from_usecs( 1 ),
+        path: Cow::Borrowed( MAPS_PATH ),
+        contents: Cow::Borrowed( &b"00400000-00401000 r-xp 00000000 00:00 0 /bin/foo\n"[..] )
+    };
+
+    // The maps are never elided, since without them nothing can be symbolicated.
+    let value = event_to_json( &maps, false );
+    assert_eq!( json_to_event( &value, false ).unwrap(), maps );
+
+    let binary = Event::File64 {
+        timestamp: Timestamp::from_usecs( 1 ),
+        path: Cow::Borrowed( "/bin/foo" ),
+        contents: Cow::Borrowed( &b"\x7FELF"[..] )
+    };
+
+    let value = event_to_json( &binary, false );
+    assert!( json_to_event( &value, false ).is_err() );
+    match json_to_event( &value, true ).unwrap() {
+        Event::File64 { contents, .. } => assert!( contents.is_empty() ),
+        _ => panic!()
+    }
+}
@@ -0,0 +1,478 @@
+use std::cmp::min;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use ahash::AHashSet as HashSet;
+use byteorder::{ReadBytesExt, LittleEndian};
+
+use bytehound_format::Writer;
+
+use common::event::{AllocationId, AllocBody, Event};
+use common::speedy::Readable;
+use common::zstd;
+
+const MAX_REPORTED_PROBLEMS: usize = 32;
+
+/// Reads the chunks of a data file one by one, and stops at the first
+/// one which is broken instead of returning an error.
+struct ChunkScanner< F: Read > {
+    fp: F,
+    offset: u64,
+    chunk_count: usize,
+    consumed: u64,
+    buffer: Vec< u8 >,
+    position: usize,
+    problem: Option< String >
+}
+
+impl< F: Read > ChunkScanner< F > {
+    fn new( fp: F ) -> Self {
+        ChunkScanner {
+            fp,
+            offset: 0,
+            chunk_count: 0,
+            consumed: 0,
+            buffer: Vec::new(),
+            position: 0,
+            problem: None
+        }
+    }
+
+    fn read_chunk( &mut self ) -> Result< bool, String > {
+        let kind = match self.fp.read_u8() {
+            Ok( kind ) => kind,
+            Err( ref error ) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok( false ),
+            Err( error ) => return Err( format!( "failed to read the chunk at offset {}: {}", self.offset, error ) )
+        };
+
+        if !(1..=4).contains( &kind ) {
+            return Err( format!( "chunk at offset {} has an unknown kind: {}", self.offset, kind ) );
+        }
+
+        let length = self.fp.read_u32::< LittleEndian >()
+            .map_err( |_| format!( "chunk at offset {} is truncated in its header", self.offset ) )?;
+
+        let mut payload = Vec::new();
+        (&mut self.fp).take( length as u64 ).read_to_end( &mut payload )
+            .map_err( |error| format!( "failed to read the chunk at offset {}: {}", self.offset, error ) )?;
+
+        if payload.len() != length as usize {
+            return Err( format!( "chunk at offset {} is truncated; expected {} bytes, got {}", self.offset, length, payload.len() ) );
+        }
+
+        self.buffer.clear();
+        self.position = 0;
+        match kind {
+            1 => {
+                lz4_compress::decompress_into( &payload, &mut self.buffer )
+                    .map_err( |_| format!( "chunk at offset {} failed to decompress", self.offset ) )?;
+            },
+            2 => {
+                self.buffer = payload;
+            },
+            3 => {
+                zstd::stream::copy_decode( &payload[ .. ], &mut self.buffer )
+                    .map_err( |_| format!( "chunk at offset {} failed to decompress", self.offset ) )?;
+            },
+            _ => {
+                // This is the chunk index, which is irrelevant here since it's going to be rebuilt anyway.
+            }
+        }
+
+        self.offset += length as u64 + 5;
+        self.chunk_count += 1;
+        Ok( true )
+    }
+}
+
+impl< F: Read > Read for ChunkScanner< F > {
+    fn read( &mut self, buf: &mut [u8] ) -> io::Result< usize > {
+        while self.position == self.buffer.len() {
+            if self.problem.is_some() {
+                return Ok( 0 );
+            }
+
+            match self.read_chunk() {
+                Ok( true ) => {},
+                Ok( false ) => return Ok( 0 ),
+                Err( problem ) => {
+                    self.problem = Some( problem );
+                    return Ok( 0 );
+                }
+            }
+        }
+
+        let length = min( buf.len(), self.buffer.len() - self.position );
+        buf[ ..length ].copy_from_slice( &self.buffer[ self.position..self.position + length ] );
+        self.position += length;
+        self.consumed += length as u64;
+        Ok( length )
+    }
+}
+
+#[derive(Default)]
+struct Report {
+    problems: Vec< String >,
+    problem_count: usize
+}
+
+impl Report {
+    fn add( &mut self, problem: String ) {
+        self.problem_count += 1;
+        if self.problems.len() < MAX_REPORTED_PROBLEMS {
+            self.problems.push( problem );
+        }
+    }
+}
+
+fn is_tracked( id: AllocationId ) -> bool {
+    !id.is_invalid() && !id.is_untracked()
+}
+
+fn allocation_key( id: AllocationId, pointer: u64 ) -> (u64, u64) {
+    if is_tracked( id ) {
+        (id.thread, id.allocation)
+    } else {
+        (0, pointer)
+    }
+}
+
+#[derive(Default)]
+struct References {
+    backtraces: HashSet< u64 >,
+    decoded_backtrace_count: u64,
+    strings: HashSet< u32 >,
+    frame_count: u32,
+    allocations: HashSet< (u64, u64) >
+}
+
+impl References {
+    /// Checks the references of a single event; returns `false` if the event should be dropped.
+    fn check( &mut self, nth: u64, event: &mut Event, report: &mut Report ) -> bool {
+        match *event {
+            Event::Backtrace { id, .. } |
+            Event::Backtrace32 { id, .. } |
+            Event::PartialBacktrace { id, .. } |
+            Event::PartialBacktrace32 { id, .. } => {
+                self.backtraces.insert( id );
+            },
+            Event::DecodedBacktrace { ref mut frames } => {
+                let frame_count = self.frame_count;
+                if frames.iter().any( |&frame| frame >= frame_count ) {
+                    report.add( format!( "event #{}: backtrace #{} refers to a frame which wasn't defined", nth, self.decoded_backtrace_count ) );
+                    *frames = frames.iter().cloned().filter( |&frame| frame < frame_count ).collect::< Vec< _ > >().into();
+                }
+
+                self.backtraces.insert( self.decoded_backtrace_count );
+                self.decoded_backtrace_count += 1;
+            },
+            Event::String { id, .. } => {
+                self.strings.insert( id );
+            },
+            Event::DecodedFrame { ref mut library, ref mut raw_function, ref mut function, ref mut source, .. } => {
+                for string_id in &mut [ library, raw_function, function, source ] {
+                    if **string_id != 0xFFFFFFFF && !self.strings.contains( &**string_id ) {
+                        report.add( format!( "event #{}: frame #{} refers to a string which wasn't defined: {}", nth, self.frame_count, **string_id ) );
+                        **string_id = 0xFFFFFFFF;
+                    }
+                }
+
+                self.frame_count += 1;
+            },
+            Event::Alloc { allocation: AllocBody { backtrace, pointer, .. }, .. } |
+            Event::AllocEx { allocation: AllocBody { backtrace, pointer, .. }, .. } => {
+                if !self.backtraces.contains( &backtrace ) {
+                    report.add( format!( "event #{}: allocation refers to a backtrace which wasn't defined: {}", nth, backtrace ) );
+                    return false;
+                }
+
+                let id = match *event {
+                    Event::AllocEx { id, .. } => id,
+                    _ => AllocationId::UNTRACKED
+                };
+
+                if !self.allocations.insert( allocation_key( id, pointer ) ) && is_tracked( id ) {
+                    report.add( format!( "event #{}: allocation {} was allocated twice", nth, id ) );
+                }
+            },
+            Event::Realloc { old_pointer, allocation: AllocBody { backtrace, pointer, .. }, .. } |
+            Event::ReallocEx { old_pointer, allocation: AllocBody { backtrace, pointer, .. }, .. } => {
+                if !self.backtraces.contains( &backtrace ) {
+                    report.add( format!( "event #{}: reallocation refers to a backtrace which wasn't defined: {}", nth, backtrace ) );
+                    return false;
+                }
+
+                let id = match *event {
+                    Event::ReallocEx { id, .. } => id,
+                    _ => AllocationId::UNTRACKED
+                };
+
+                if !self.allocations.remove( &allocation_key( id, old_pointer ) ) && is_tracked( id ) {
+                    report.add( format!( "event #{}: reallocation of an unknown allocation {}", nth, id ) );
+                    return false;
+                }
+
+                self.allocations.insert( allocation_key( id, pointer ) );
+            },
+            Event::FreeEx { id, pointer, .. } => {
+                // Deallocations of untracked allocations are perfectly normal, e.g. for memory allocated before the profiler was initialized.
+                if !self.allocations.remove( &allocation_key( id, pointer ) ) && is_tracked( id ) {
+                    report.add( format!( "event #{}: deallocation of an unknown allocation {}", nth, id ) );
+                    return false;
+                }
+            },
+            Event::Free { pointer, .. } => {
+                self.allocations.remove( &allocation_key( AllocationId::UNTRACKED, pointer ) );
+            },
+            Event::MemoryMap { backtrace, .. } |
+            Event::MemoryUnmap { backtrace, .. } |
+            Event::Mallopt { backtrace, .. } => {
+                if !self.backtraces.contains( &backtrace ) {
+                    report.add( format!( "event #{}: event refers to a backtrace which wasn't defined: {}", nth, backtrace ) );
+                    return false;
+                }
+            },
+            Event::Header( .. ) |
+            Event::File { .. } |
+            Event::File64 { .. } |
+            Event::MemoryDump { .. } |
+            Event::Marker { .. } |
+            Event::Environ { .. } |
+            Event::WallClock { .. } |
+            Event::GroupStatistics { .. } |
+            Event::ProcessInfo { .. } => {}
+        }
+
+        true
+    }
+}
+
+struct Summary {
+    report: Report,
+    chunk_count: usize,
+    event_count: u64,
+    dropped_count: u64
+}
+
+/// Checks a whole data file, and writes everything which can be recovered into the `output`, if given.
+fn check< R: Read, W: Write >( input: R, output: Option< W > ) -> Result< (Summary, Option< W >), io::Error > {
+    let mut scanner = ChunkScanner::new( input );
+    let mut output = output;
+    let mut writer = None;
+
+    let mut report = Report::default();
+    let mut references = References::default();
+    let mut event_count = 0;
+    let mut dropped_count = 0;
+    loop {
+        let consumed = scanner.consumed;
+        let mut event = match Event::read_from_stream_unbuffered( &mut scanner ) {
+            Ok( event ) => event,
+            Err( error ) => {
+                let error: io::Error = error.into();
+                if error.kind() != io::ErrorKind::UnexpectedEof {
+                    report.add( format!( "event #{} failed to decode: {}", event_count, error ) );
+                } else if scanner.consumed != consumed {
+                    report.add( format!( "event #{} is truncated", event_count ) );
+                }
+
+                break;
+            }
+        };
+
+        if event_count == 0 {
+            match event {
+                Event::Header( ref header ) => {
+                    if let Some( fp ) = output.take() {
+                        writer = Some( Writer::new( fp, header.clone() )? );
+                    }
+                },
+                _ => {
+                    report.add( "the file doesn't start with a proper header".to_owned() );
+                    break;
+                }
+            }
+
+            event_count += 1;
+            continue;
+        }
+
+        if references.check( event_count, &mut event, &mut report ) {
+            if let Some( ref mut writer ) = writer {
+                // The headers repeated in streamed data are identical to the first one.
+                if let Event::Header( .. ) = event {} else {
+                    writer.write_event( &event )?;
+                }
+            }
+        } else {
+            dropped_count += 1;
+        }
+
+        event_count += 1;
+    }
+
+    if let Some( problem ) = scanner.problem.take() {
+        report.add( problem );
+    }
+
+    if let Some( writer ) = writer {
+        output = Some( writer.finish()? );
+    }
+
+    let summary = Summary {
+        report,
+        chunk_count: scanner.chunk_count,
+        event_count,
+        dropped_count
+    };
+
+    Ok( (summary, output) )
+}
+
+/// Validates the chunks, the events and the cross-references between the events of a data file.
+///
+/// If `repair` is given then everything which can be recovered is written into it;
+/// returns whenever the input file has no problems.
+pub fn fsck( input: &Path, repair: Option< &Path > ) -> Result< bool, io::Error > {
+    let input = io::BufReader::new( File::open( input )? );
+    let output = match repair {
+        Some( path ) => Some( io::BufWriter::new( File::create( path )? ) ),
+        None => None
+    };
+
+    let is_repairing = output.is_some();
+    let (summary, _) = check( input, output )?;
+    let report = &summary.report;
+
+    println!( "Chunks: {}", summary.chunk_count );
+    println!( "Events: {}", summary.event_count );
+    for problem in &report.problems {
+        println!( "Problem: {}", problem );
+    }
+
+    if report.problem_count > report.problems.len() {
+        println!( "...and {} more problems", report.problem_count - report.problems.len() );
+    }
+
+    if is_repairing {
+        println!( "Recovered {} events; {} were dropped", summary.event_count - summary.dropped_count, summary.dropped_count );
+    }
+
+    Ok( report.problem_count == 0 )
+}
+
+#[test]
+fn test_chunk_scanner_stops_at_a_truncated_chunk() {
+    use common::lz4_stream::Lz4Writer;
+
+    let mut data = Vec::new();
+    {
+        let mut fp = Lz4Writer::new( &mut data );
+        fp.write_all( b"Hello world!" ).unwrap();
+        fp.flush().unwrap();
+        fp.write_all( b"Second chunk" ).unwrap();
+    }
+
+    data.truncate( data.len() - 4 );
+
+    let mut scanner = ChunkScanner::new( &data[ .. ] );
+    let mut output = Vec::new();
+    scanner.read_to_end( &mut output ).unwrap();
+    assert_eq!( output, b"Hello world!" );
+    assert_eq!( scanner.chunk_count, 1 );
+    assert!( scanner.problem.unwrap().contains( "truncated" ) );
+}
+
+#[cfg(test)]
+fn check_events( events: &[Event], repair: bool ) -> (Summary, Option< Vec< u8 > >) {
+    let data = bytehound_format::test_util::write_test_file( events );
+    let output = if repair { Some( Vec::new() ) } else { None };
+    check( &data[ .. ], output ).unwrap()
+}
+
+#[cfg(test)]
+fn test_backtrace( id: u64 ) -> Event< 'static > {
+    Event::Backtrace {
+        id,
+        addresses: vec![ 0x1000 ].into()
+    }
+}
+
+#[cfg(test)]
+fn test_alloc( allocation: u64, pointer: u64, backtrace: u64 ) -> Event< 'static > {
+    Event::AllocEx {
+        id: AllocationId { thread: 1, allocation },
+        timestamp: common::Timestamp::from_usecs( 1 ),
+        allocation: AllocBody {
+            pointer,
+            size: 16,
+            backtrace,
+            thread: 1,
+            flags: 0,
+            extra_usable_space: 0,
+            preceding_free_space: 0
+        }
+    }
+}
+
+#[cfg(test)]
+fn assert_repaired( output: Option< Vec< u8 > >, expected_event_count: u64 ) {
+    let (summary, _) = check( &output.unwrap()[ .. ], None::< Vec< u8 > > ).unwrap();
+    assert_eq!( summary.report.problem_count, 0 );
+    assert_eq!( summary.event_count, expected_event_count );
+}
+
+#[test]
+fn test_fsck_undefined_backtrace() {
+    let events = [ test_backtrace( 1 ), test_alloc( 1, 0x1000, 2 ) ];
+
+    let (summary, output) = check_events( &events, false );
+    assert_eq!( summary.report.problem_count, 1 );
+    assert!( summary.report.problems[ 0 ].contains( "backtrace which wasn't defined" ) );
+    assert!( output.is_none() );
+
+    // The allocation can't be salvaged, so it's dropped.
+    let (summary, output) = check_events( &events, true );
+    assert_eq!( summary.dropped_count, 1 );
+    assert_repaired( output, 2 );
+}
+
+#[test]
+fn test_fsck_double_allocation() {
+    let events = [ test_backtrace( 1 ), test_alloc( 1, 0x1000, 1 ), test_alloc( 1, 0x2000, 1 ) ];
+
+    let (summary, output) = check_events( &events, false );
+    assert_eq!( summary.report.problem_count, 1 );
+    assert!( summary.report.problems[ 0 ].contains( "was allocated twice" ) );
+    assert!( output.is_none() );
+
+    // Both allocations are still perfectly usable, so they're kept.
+    let (summary, output) = check_events( &events, true );
+    assert_eq!( summary.dropped_count, 0 );
+
+    let (summary, _) = check( &output.unwrap()[ .. ], None::< Vec< u8 > > ).unwrap();
+    assert_eq!( summary.event_count, 4 );
+}
+
+#[test]
+fn test_fsck_free_of_unknown_allocation() {
+    let free = Event::FreeEx {
+        id: AllocationId { thread: 1, allocation: 5 },
+        timestamp: common::Timestamp::from_usecs( 2 ),
+        pointer: 0x1000,
+        backtrace: 1,
+        thread: 1
+    };
+
+    let events = [ test_backtrace( 1 ), test_alloc( 1, 0x1000, 1 ), free ];
+
+    let (summary, output) = check_events( &events, false );
+    assert_eq!( summary.report.problem_count, 1 );
+    assert!( summary.report.problems[ 0 ].contains( "deallocation of an unknown allocation" ) );
+    assert!( output.is_none() );
+
+    let (summary, output) = check_events( &events, true );
+    assert_eq!( summary.dropped_count, 1 );
+    assert_repaired( output, 3 );
+}
@@ -0,0 +1,49 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use common::chunk_index::read_chunk_index;
+use common::Timestamp;
+
+use crate::reader::parse_events;
+
+fn relative_secs( timestamp: Timestamp, initial_timestamp: Timestamp ) -> f64 {
+    timestamp.as_usecs().saturating_sub( initial_timestamp.as_usecs() ) as f64 / 1_000_000.0
+}
+
+pub fn print_info( path: &Path ) -> Result< (), io::Error > {
+    let (header, _) = parse_events( File::open( path )? )?;
+    println!( "ID: {}", header.id );
+    println!( "PID: {}", header.pid );
+    println!( "Executable: {}", String::from_utf8_lossy( &header.executable ) );
+    println!( "Architecture: {}", header.arch );
+
+    let index = match read_chunk_index( &mut File::open( path )? )? {
+        Some( index ) => index,
+        None => {
+            println!( "The file has no chunk index; run it through `repack` or `postprocess` to add one" );
+            return Ok(());
+        }
+    };
+
+    let compressed_size = index.compressed_size();
+    let uncompressed_size = index.uncompressed_size();
+    println!( "Chunks: {}", index.chunks.len() );
+    println!( "Checkpoints: {}", index.checkpoints().count() );
+    println!( "Events: {}", index.event_count() );
+    println!( "Compressed size: {}", compressed_size );
+    println!( "Uncompressed size: {}", uncompressed_size );
+    if compressed_size != 0 {
+        println!( "Compression ratio: {:.2}", uncompressed_size as f64 / compressed_size as f64 );
+    }
+
+    if let Some( (min, max) ) = index.time_range() {
+        println!(
+            "Time range: {:.3}s - {:.3}s",
+            relative_secs( min, header.initial_timestamp ),
+            relative_secs( max, header.initial_timestamp )
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use ahash::AHashMap as HashMap;
+use crate::data::{Allocation, AllocationId, ThreadId};
+
+#[derive(Clone, Debug)]
+pub struct ThreadPair {
+    pub allocated_on: ThreadId,
+    pub freed_on: ThreadId,
+    pub count: u64,
+    pub size: u64
+}
+
+/// Builds a sparse thread-to-thread matrix of how many bytes
+/// were allocated on one thread and then freed on another one.
+///
+/// Allocations which were never freed are skipped. The pairs are
+/// sorted by the total size, in descending order.
+pub fn collect_thread_matrix< 'a >( allocations: impl Iterator< Item = (AllocationId, &'a Allocation) > ) -> Vec< ThreadPair > {
+    let mut pairs: HashMap< (ThreadId, ThreadId), ThreadPair > = HashMap::new();
+    for (_, allocation) in allocations {
+        let deallocation = match allocation.deallocation {
+            Some( ref deallocation ) => deallocation,
+            None => continue
+        };
+
+        let pair = pairs.entry( (allocation.thread, deallocation.thread) ).or_insert_with( || ThreadPair {
+            allocated_on: allocation.thread,
+            freed_on: deallocation.thread,
+            count: 0,
+            size: 0
+        });
+
+        pair.count += 1;
+        pair.size += allocation.size;
+    }
+
+    let mut pairs: Vec< _ > = pairs.into_iter().map( |(_, pair)| pair ).collect();
+    pairs.sort_by( |a, b| {
+        b.size.cmp( &a.size )
+            .then_with( || (a.allocated_on, a.freed_on).cmp( &(b.allocated_on, b.freed_on) ) )
+    });
+
+    pairs
+}
+
+#[test]
+fn test_collect_thread_matrix() {
+    use common::Timestamp;
+    use common::event::{self, AllocBody, Event};
+
+    // (size, allocated on, freed on)
+    let allocations = [(100, 1, Some( 2 )), (50, 1, Some( 2 )), (10, 1, Some( 1 )), (200, 2, Some( 1 )), (1000, 3, None)];
+    let mut events = vec![ Event::Backtrace { id: 1, addresses: vec![ 0x1000 ].into() } ];
+    for (index, &(size, thread, freed_on)) in allocations.iter().enumerate() {
+        let index = index as u64 + 1;
+        let id = event::AllocationId { thread: thread as u64, allocation: index };
+        events.push( Event::AllocEx {
+            id,
+            timestamp: Timestamp::from_usecs( index ),
+            allocation: AllocBody {
+                pointer: 0x1000 * index,
+                size,
+                backtrace: 1,
+                thread,
+                flags: 0,
+                extra_usable_space: 0,
+                preceding_free_space: 0
+            }
+        });
+
+        if let Some( freed_on ) = freed_on {
+            events.push( Event::FreeEx {
+                id,
+                timestamp: Timestamp::from_usecs( 100 + index ),
+                pointer: 0x1000 * index,
+                backtrace: 1,
+                thread: freed_on
+            });
+        }
+    }
+
+    let data = crate::loader::load_test_data( &events );
+    let pairs: Vec< _ > = collect_thread_matrix( data.allocations_with_id() ).into_iter().map( |pair| {
+        (pair.allocated_on, pair.freed_on, pair.count, pair.size)
+    }).collect();
+
+    assert_eq!( pairs, vec![
+        (2, 1, 1, 200),
+        (1, 2, 2, 150),
+        (1, 1, 1, 10)
+    ]);
+}
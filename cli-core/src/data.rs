@@ -14,6 +14,7 @@ use crate::tree_printer::dump_tree;
 use crate::frame::Frame;
 use crate::vecvec::DenseVecVec;
 use crate::util::{ReadableSize, table_to_string};
+use crate::arena::{Arena, ArenaId, Arenas};
 
 pub use common::{Timestamp};
 pub use common::event::DataId;
@@ -128,6 +129,8 @@ pub struct Data {
     pub(crate) cmdline: String,
     pub(crate) architecture: String,
     pub(crate) pointer_size: u64,
+    pub(crate) pid: u32,
+    pub(crate) parent_pid: Option< u32 >,
     pub(crate) interner: StringInterner,
     pub(crate) operations: Vec< OperationId >,
     pub(crate) allocations: Vec< Allocation >,
@@ -146,7 +149,8 @@ pub struct Data {
     pub(crate) mmap_operations: Vec< MmapOperation >,
     pub(crate) maximum_backtrace_depth: u32,
     pub(crate) group_stats: Vec< GroupStatistics >,
-    pub(crate) chains: HashMap< AllocationId, AllocationChain >
+    pub(crate) chains: HashMap< AllocationId, AllocationChain >,
+    pub(crate) arenas: Arenas
 }
 
 pub type DataPointer = u64;
@@ -254,8 +258,10 @@ pub struct GroupStatistics {
     pub last_allocation: Timestamp,
     pub alloc_count: u64,
     pub alloc_size: u64,
+    pub alloc_slack: u64,
     pub free_count: u64,
     pub free_size: u64,
+    pub cross_thread_free_count: u64,
     pub min_size: u64,
     pub max_size: u64,
     pub max_total_usage_first_seen_at: Timestamp
@@ -268,8 +274,10 @@ impl Default for GroupStatistics {
             last_allocation: Timestamp::min(),
             alloc_count: 0,
             alloc_size: 0,
+            alloc_slack: 0,
             free_count: 0,
             free_size: 0,
+            cross_thread_free_count: 0,
             min_size: -1_i64 as u64,
             max_size: 0,
             max_total_usage_first_seen_at: Timestamp::min()
@@ -375,6 +383,11 @@ impl Allocation {
         self.flags.contains( AllocationFlags::IS_MMAPED )
     }
 
+    #[inline]
+    pub fn is_calloc( &self ) -> bool {
+        self.flags.contains( AllocationFlags::IS_CALLOC )
+    }
+
     #[inline]
     pub fn usable_size( &self ) -> u64 {
         self.size + self.extra_usable_space as u64
@@ -738,6 +751,17 @@ impl Data {
         &self.architecture
     }
 
+    #[inline]
+    pub fn pid( &self ) -> u32 {
+        self.pid
+    }
+
+    /// The PID of the process which spawned this one; only available for newer recordings.
+    #[inline]
+    pub fn parent_pid( &self ) -> Option< u32 > {
+        self.parent_pid
+    }
+
     #[inline]
     pub fn id( &self ) -> DataId {
         self.id
@@ -894,6 +918,15 @@ impl Data {
         &self.mmap_operations
     }
 
+    pub fn arenas( &self ) -> &[Arena] {
+        self.arenas.all()
+    }
+
+    #[inline]
+    pub fn get_arena( &self, allocation: &Allocation ) -> Option< ArenaId > {
+        self.arenas.arena_of( allocation )
+    }
+
     pub fn get_dynamic_constants( &self ) -> BTreeMap< String, BTreeMap< u32, CountAndSize > > {
         self.collate_allocations( |frame| {
             let raw_function = match frame.raw_function() {
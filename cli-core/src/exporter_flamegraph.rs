@@ -9,10 +9,14 @@ use super::{
     Data
 };
 
-use crate::exporter_flamegraph_pl::dump_collation;
+use crate::exporter_flamegraph_pl::{CollationWeight, dump_collation, dump_weighted_collation_from_iter};
 use crate::io_adapter::IoAdapter;
 
 pub fn lines_to_svg( lines: Vec< String >, output: impl fmt::Write ) {
+    lines_to_svg_with_count_name( lines, "bytes", output )
+}
+
+pub fn lines_to_svg_with_count_name( lines: Vec< String >, count_name: &str, output: impl fmt::Write ) {
     lazy_static::lazy_static! {
         pub static ref PALETTE_MAP: Mutex< flamegraph::color::PaletteMap > = Mutex::new( flamegraph::color::PaletteMap::default() );
     }
@@ -22,7 +26,7 @@ pub fn lines_to_svg( lines: Vec< String >, output: impl fmt::Write ) {
     options.bgcolors = Some( flamegraph::color::BackgroundColor::Flat( (255, 255, 255).into() ) );
     options.font_type = r#""Segoe UI", "Source Sans Pro", Calibri, Candara, Arial, sans-serif"#.to_owned();
     options.title = "".to_owned();
-    options.count_name = "bytes".to_owned();
+    options.count_name = count_name.to_owned();
 
     let mut palette_map = PALETTE_MAP.lock();
     if let Ok( ref mut palette_map ) = palette_map {
@@ -49,3 +53,20 @@ pub fn export_as_flamegraph< T, F >( data: &Data, output: T, filter: F )
 
     lines_to_svg( lines, output )
 }
+
+pub fn export_as_flamegraph_by_count< T, F >( data: &Data, output: T, filter: F )
+    where T: fmt::Write,
+          F: Fn( AllocationId, &Allocation ) -> bool
+{
+    let mut lines = Vec::new();
+    let iter = data.allocations_with_id().filter( |(id, allocation)| filter( *id, allocation ) );
+    dump_weighted_collation_from_iter( data, CollationWeight::Count, iter, |line| {
+        lines.push( line.to_owned() );
+        let result: Result< (), () > = Ok(());
+        result
+    }).unwrap();
+
+    lines.sort_unstable();
+
+    lines_to_svg_with_count_name( lines, "allocations", output )
+}
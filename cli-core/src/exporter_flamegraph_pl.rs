@@ -10,9 +10,16 @@ use super::{
 
 use std::fmt::{self, Write};
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CollationWeight {
+    Size,
+    Count
+}
+
 fn dump_collation_impl< O: FnMut( &str ) -> Result< (), E >, K: PartialEq + Clone, E >(
     data: &Data,
     tree: &Tree< K, &Frame >,
+    weight: CollationWeight,
     node_id: NodeId,
     stack: &mut Vec< String >,
     cache: &mut Vec< String >,
@@ -35,7 +42,12 @@ fn dump_collation_impl< O: FnMut( &str ) -> Result< (), E >, K: PartialEq + Clon
 
     if node.self_count != 0 {
         let mut buffer = cache.pop().unwrap_or( String::new() );
-        write!( &mut buffer, "{} {}", stack.join( ";" ), node.self_size ).unwrap();
+        let value = match weight {
+            CollationWeight::Size => node.self_size,
+            CollationWeight::Count => node.self_count
+        };
+
+        write!( &mut buffer, "{} {}", stack.join( ";" ), value ).unwrap();
 
         output( &buffer )?;
 
@@ -44,7 +56,7 @@ fn dump_collation_impl< O: FnMut( &str ) -> Result< (), E >, K: PartialEq + Clon
     }
 
     for &(_, child_id) in tree.get_node( node_id ).children.iter() {
-        dump_collation_impl( data, tree, child_id, stack, cache, output )?;
+        dump_collation_impl( data, tree, weight, child_id, stack, cache, output )?;
     }
 
     if !node.is_root() {
@@ -56,8 +68,9 @@ fn dump_collation_impl< O: FnMut( &str ) -> Result< (), E >, K: PartialEq + Clon
     Ok(())
 }
 
-pub fn dump_collation_from_iter< 'a, O, E >(
+pub fn dump_weighted_collation_from_iter< 'a, O, E >(
     data: &Data,
+    weight: CollationWeight,
     allocations: impl Iterator< Item = (AllocationId, &'a Allocation) >,
     mut output: O
 ) -> Result< (), E >
@@ -68,7 +81,17 @@ pub fn dump_collation_from_iter< 'a, O, E >(
         tree.add_allocation( allocation, allocation_id, data.get_backtrace( allocation.backtrace ) );
     }
 
-    dump_collation_impl( data, &tree, 0, &mut Vec::new(), &mut Vec::new(), &mut output )
+    dump_collation_impl( data, &tree, weight, 0, &mut Vec::new(), &mut Vec::new(), &mut output )
+}
+
+pub fn dump_collation_from_iter< 'a, O, E >(
+    data: &Data,
+    allocations: impl Iterator< Item = (AllocationId, &'a Allocation) >,
+    output: O
+) -> Result< (), E >
+    where O: FnMut( &str ) -> Result< (), E >
+{
+    dump_weighted_collation_from_iter( data, CollationWeight::Size, allocations, output )
 }
 
 pub fn dump_collation< F, O, E >( data: &Data, filter: F, output: O ) -> Result< (), E >
@@ -0,0 +1,230 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::mem;
+use std::path::Path;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use parquet::basic::Compression;
+use parquet::column::writer::ColumnWriter;
+use parquet::data_type::ByteArray;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+
+use crate::data::{Allocation, AllocationId, Data};
+use crate::exporter_tables::{Column, ColumnType, Table, TableSink, Value, export_tables};
+
+const ROW_GROUP_SIZE: usize = 64 * 1024;
+
+fn to_io_error( error: ParquetError ) -> io::Error {
+    io::Error::new( io::ErrorKind::Other, error.to_string() )
+}
+
+enum Buffer {
+    Integer( Vec< i64 > ),
+    Boolean( Vec< bool > ),
+    Text( Vec< ByteArray > )
+}
+
+struct ColumnBuffer {
+    values: Buffer,
+    definition_levels: Vec< i16 >
+}
+
+impl ColumnBuffer {
+    fn new( column: &Column ) -> Self {
+        let values = match column.kind {
+            ColumnType::Integer => Buffer::Integer( Vec::new() ),
+            ColumnType::Boolean => Buffer::Boolean( Vec::new() ),
+            ColumnType::Text => Buffer::Text( Vec::new() )
+        };
+
+        ColumnBuffer {
+            values,
+            definition_levels: Vec::new()
+        }
+    }
+
+    fn push( &mut self, value: &Value ) {
+        match (&mut self.values, *value) {
+            (_, Value::Null) => {
+                self.definition_levels.push( 0 );
+                return;
+            },
+            (&mut Buffer::Integer( ref mut values ), Value::Integer( value )) => values.push( value ),
+            (&mut Buffer::Boolean( ref mut values ), Value::Boolean( value )) => values.push( value ),
+            (&mut Buffer::Text( ref mut values ), Value::Text( value )) => values.push( ByteArray::from( value ) ),
+            _ => unreachable!( "value doesn't match the column's type" )
+        }
+
+        self.definition_levels.push( 1 );
+    }
+}
+
+fn schema_for( table: &Table ) -> String {
+    let mut schema = format!( "message {} {{\n", table.name );
+    for column in table.columns {
+        let repetition = if column.is_nullable { "OPTIONAL" } else { "REQUIRED" };
+        let kind = match column.kind {
+            ColumnType::Integer => "INT64",
+            ColumnType::Boolean => "BOOLEAN",
+            ColumnType::Text => "BYTE_ARRAY"
+        };
+        let annotation = if column.kind == ColumnType::Text { " (UTF8)" } else { "" };
+        schema.push_str( &format!( "    {} {} {}{};\n", repetition, kind, column.name, annotation ) );
+    }
+    schema.push_str( "}\n" );
+    schema
+}
+
+struct TableWriter< W: Write > {
+    writer: SerializedFileWriter< W >,
+    columns: &'static [Column],
+    buffers: Vec< ColumnBuffer >,
+    row_count: usize
+}
+
+impl< W: Write > TableWriter< W > {
+    fn flush( &mut self ) -> Result< (), ParquetError > {
+        if self.row_count == 0 {
+            return Ok(());
+        }
+
+        let mut buffers = self.columns.iter().map( ColumnBuffer::new ).collect();
+        mem::swap( &mut buffers, &mut self.buffers );
+        self.row_count = 0;
+
+        let mut row_group = self.writer.next_row_group()?;
+        let mut buffers = buffers.into_iter();
+        while let Some( mut column ) = row_group.next_column()? {
+            let buffer = buffers.next().unwrap();
+            let definition_levels = Some( &buffer.definition_levels[..] );
+            match (column.untyped(), buffer.values) {
+                (&mut ColumnWriter::Int64ColumnWriter( ref mut writer ), Buffer::Integer( values )) => {
+                    writer.write_batch( &values, definition_levels, None )?;
+                },
+                (&mut ColumnWriter::BoolColumnWriter( ref mut writer ), Buffer::Boolean( values )) => {
+                    writer.write_batch( &values, definition_levels, None )?;
+                },
+                (&mut ColumnWriter::ByteArrayColumnWriter( ref mut writer ), Buffer::Text( values )) => {
+                    writer.write_batch( &values, definition_levels, None )?;
+                },
+                _ => unreachable!()
+            }
+
+            column.close()?;
+        }
+
+        row_group.close()?;
+        Ok(())
+    }
+}
+
+struct ParquetSink< W: Write, F: FnMut( &str ) -> io::Result< W > > {
+    create_output: F,
+    current: Option< TableWriter< W > >
+}
+
+impl< W: Write, F: FnMut( &str ) -> io::Result< W > > TableSink for ParquetSink< W, F > {
+    fn begin_table( &mut self, table: &Table ) -> io::Result< () > {
+        let schema = parse_message_type( &schema_for( table ) ).map_err( to_io_error )?;
+        let properties = WriterProperties::builder()
+            .set_compression( Compression::SNAPPY )
+            .build();
+
+        let output = (self.create_output)( table.name )?;
+        let writer = SerializedFileWriter::new( output, Arc::new( schema ), Arc::new( properties ) ).map_err( to_io_error )?;
+
+        self.current = Some( TableWriter {
+            writer,
+            columns: table.columns,
+            buffers: table.columns.iter().map( ColumnBuffer::new ).collect(),
+            row_count: 0
+        });
+
+        Ok(())
+    }
+
+    fn add_row( &mut self, row: &[Value] ) -> io::Result< () > {
+        let table = self.current.as_mut().unwrap();
+        for (buffer, value) in table.buffers.iter_mut().zip( row ) {
+            buffer.push( value );
+        }
+
+        table.row_count += 1;
+        if table.row_count >= ROW_GROUP_SIZE {
+            table.flush().map_err( to_io_error )?;
+        }
+
+        Ok(())
+    }
+
+    fn end_table( &mut self ) -> io::Result< () > {
+        let mut table = self.current.take().unwrap();
+        table.flush().map_err( to_io_error )?;
+        table.writer.close().map_err( to_io_error )?;
+        Ok(())
+    }
+}
+
+/// Writes every table as a separate Parquet file; `create_output` is called with the table's name.
+pub(crate) fn export_ids_as_parquet< W, F >( data: &Data, allocation_ids: impl Iterator< Item = AllocationId >, create_output: F ) -> io::Result< () >
+    where W: Write,
+          F: FnMut( &str ) -> io::Result< W >
+{
+    let mut sink = ParquetSink {
+        create_output,
+        current: None
+    };
+
+    export_tables( data, allocation_ids, &mut sink )
+}
+
+#[derive(Clone, Default)]
+struct SharedBuffer( Arc< Mutex< Vec< u8 > > > );
+
+impl Write for SharedBuffer {
+    fn write( &mut self, buffer: &[u8] ) -> io::Result< usize > {
+        self.0.lock().extend_from_slice( buffer );
+        Ok( buffer.len() )
+    }
+
+    fn flush( &mut self ) -> io::Result< () > {
+        Ok(())
+    }
+}
+
+/// Same as `export_ids_as_parquet`, except it returns the files' contents along with the tables' names.
+pub(crate) fn export_ids_as_parquet_in_memory( data: &Data, allocation_ids: impl Iterator< Item = AllocationId > ) -> io::Result< Vec< (String, Vec< u8 >) > > {
+    let mut tables = Vec::new();
+    export_ids_as_parquet( data, allocation_ids, |name| {
+        let buffer = SharedBuffer::default();
+        tables.push( (name.to_owned(), buffer.clone()) );
+        Ok( buffer )
+    })?;
+
+    let tables = tables.into_iter().map( |(name, buffer)| (name, mem::replace( &mut *buffer.0.lock(), Vec::new() )) ).collect();
+    Ok( tables )
+}
+
+/// Exports the allocations into a directory of Parquet files, one for every table;
+/// see `exporter_tables.rs` for the schema.
+pub fn export_as_parquet< P: AsRef< Path >, F: Fn( AllocationId, &Allocation ) -> bool >( data: &Data, directory: P, filter: F ) -> io::Result< () > {
+    let directory = directory.as_ref();
+    std::fs::create_dir_all( directory )?;
+
+    let ids = data.allocations_with_id().filter( |&(id, allocation)| filter( id, allocation ) ).map( |(id, _)| id );
+    export_ids_as_parquet( data, ids, |name| {
+        let fp = File::create( directory.join( format!( "{}.parquet", name ) ) )?;
+        Ok( io::BufWriter::new( fp ) )
+    })
+}
+
+#[test]
+fn test_parquet_schema() {
+    let schema = schema_for( &crate::exporter_tables::BACKTRACES );
+    assert!( parse_message_type( &schema ).is_ok() );
+    assert_eq!( schema, "message backtraces {\n    REQUIRED INT64 backtrace_id;\n    REQUIRED INT64 position;\n    REQUIRED INT64 frame_id;\n}\n" );
+}
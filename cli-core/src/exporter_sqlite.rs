@@ -0,0 +1,90 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rusqlite::{Connection, ToSql, params_from_iter};
+use rusqlite::types::{Null, ToSqlOutput};
+
+use crate::data::{Allocation, AllocationId, Data};
+use crate::exporter_tables::{ColumnType, Table, TableSink, Value, export_tables};
+
+fn to_io_error( error: rusqlite::Error ) -> io::Error {
+    io::Error::new( io::ErrorKind::Other, error.to_string() )
+}
+
+impl< 'a > ToSql for Value< 'a > {
+    fn to_sql( &self ) -> rusqlite::Result< ToSqlOutput< '_ > > {
+        let output = match *self {
+            Value::Null => ToSqlOutput::from( Null ),
+            Value::Integer( value ) => ToSqlOutput::from( value ),
+            Value::Boolean( value ) => ToSqlOutput::from( value ),
+            Value::Text( value ) => ToSqlOutput::from( value )
+        };
+
+        Ok( output )
+    }
+}
+
+struct SqliteSink {
+    connection: Connection,
+    insert: String
+}
+
+impl TableSink for SqliteSink {
+    fn begin_table( &mut self, table: &Table ) -> io::Result< () > {
+        let columns: Vec< _ > = table.columns.iter().map( |column| {
+            let kind = match column.kind {
+                ColumnType::Integer | ColumnType::Boolean => "INTEGER",
+                ColumnType::Text => "TEXT"
+            };
+
+            if column.is_nullable {
+                format!( "\"{}\" {}", column.name, kind )
+            } else {
+                format!( "\"{}\" {} NOT NULL", column.name, kind )
+            }
+        }).collect();
+
+        let placeholders: Vec< _ > = (1..=table.columns.len()).map( |index| format!( "?{}", index ) ).collect();
+        self.insert = format!( "INSERT INTO {} VALUES ({})", table.name, placeholders.join( ", " ) );
+
+        self.connection.execute_batch( &format!( "CREATE TABLE {} ({}); BEGIN;", table.name, columns.join( ", " ) ) ).map_err( to_io_error )
+    }
+
+    fn add_row( &mut self, row: &[Value] ) -> io::Result< () > {
+        let mut statement = self.connection.prepare_cached( &self.insert ).map_err( to_io_error )?;
+        statement.execute( params_from_iter( row.iter() ) ).map_err( to_io_error )?;
+        Ok(())
+    }
+
+    fn end_table( &mut self ) -> io::Result< () > {
+        self.connection.execute_batch( "COMMIT;" ).map_err( to_io_error )
+    }
+}
+
+pub(crate) fn export_ids_as_sqlite( data: &Data, path: &Path, allocation_ids: impl Iterator< Item = AllocationId > ) -> io::Result< () > {
+    if path.exists() {
+        fs::remove_file( path )?;
+    }
+
+    let connection = Connection::open( path ).map_err( to_io_error )?;
+    let mut sink = SqliteSink {
+        connection,
+        insert: String::new()
+    };
+
+    export_tables( data, allocation_ids, &mut sink )?;
+    sink.connection.execute_batch( "\
+        CREATE INDEX allocations_by_backtrace ON allocations (backtrace_id);
+        CREATE INDEX backtraces_by_id ON backtraces (backtrace_id);
+        CREATE INDEX frames_by_id ON frames (frame_id);
+    " ).map_err( to_io_error )?;
+
+    Ok(())
+}
+
+/// Exports the allocations as a SQLite database; see `exporter_tables.rs` for the schema.
+pub fn export_as_sqlite< P: AsRef< Path >, F: Fn( AllocationId, &Allocation ) -> bool >( data: &Data, path: P, filter: F ) -> io::Result< () > {
+    let ids = data.allocations_with_id().filter( |&(id, allocation)| filter( id, allocation ) ).map( |(id, _)| id );
+    export_ids_as_sqlite( data, path.as_ref(), ids )
+}
@@ -0,0 +1,302 @@
+use std::io;
+
+use crate::data::{
+    AllocationId,
+    BacktraceId,
+    Data,
+    FrameId,
+    MmapOperation,
+    StringId,
+    Timestamp
+};
+
+/*
+    The data is exported as the following normalized tables:
+
+      allocations:
+        one row per allocation; references `backtraces.backtrace_id`,
+        and through the reallocation columns other rows of the same table
+
+      backtraces:
+        one row per frame of every backtrace; `position` is zero
+        for the innermost frame; references `frames.frame_id`
+
+      frames:
+        one row per unique frame
+
+      mmaps:
+        one row per mmap or munmap call; references `backtraces.backtrace_id`
+
+    All of the timestamps are in microseconds since the start of profiling.
+*/
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum ColumnType {
+    Integer,
+    Boolean,
+    Text
+}
+
+pub(crate) struct Column {
+    pub name: &'static str,
+    pub kind: ColumnType,
+    pub is_nullable: bool
+}
+
+const fn column( name: &'static str, kind: ColumnType, is_nullable: bool ) -> Column {
+    Column { name, kind, is_nullable }
+}
+
+pub(crate) struct Table {
+    pub name: &'static str,
+    pub columns: &'static [Column]
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) enum Value< 'a > {
+    Null,
+    Integer( i64 ),
+    Boolean( bool ),
+    Text( &'a str )
+}
+
+impl< 'a, T > From< Option< T > > for Value< 'a > where T: Into< Value< 'a > > {
+    fn from( value: Option< T > ) -> Self {
+        value.map( |value| value.into() ).unwrap_or( Value::Null )
+    }
+}
+
+impl< 'a > From< i64 > for Value< 'a > {
+    fn from( value: i64 ) -> Self {
+        Value::Integer( value )
+    }
+}
+
+impl< 'a > From< u64 > for Value< 'a > {
+    fn from( value: u64 ) -> Self {
+        Value::Integer( value as i64 )
+    }
+}
+
+impl< 'a > From< u32 > for Value< 'a > {
+    fn from( value: u32 ) -> Self {
+        Value::Integer( value as i64 )
+    }
+}
+
+impl< 'a > From< bool > for Value< 'a > {
+    fn from( value: bool ) -> Self {
+        Value::Boolean( value )
+    }
+}
+
+impl< 'a > From< &'a str > for Value< 'a > {
+    fn from( value: &'a str ) -> Self {
+        Value::Text( value )
+    }
+}
+
+pub(crate) const ALLOCATIONS: Table = Table {
+    name: "allocations",
+    columns: &[
+        column( "allocation_id", ColumnType::Integer, false ),
+        column( "pointer", ColumnType::Integer, false ),
+        column( "size", ColumnType::Integer, false ),
+        column( "usable_size", ColumnType::Integer, false ),
+        column( "allocated_at", ColumnType::Integer, false ),
+        column( "allocated_on_thread", ColumnType::Integer, false ),
+        column( "backtrace_id", ColumnType::Integer, false ),
+        column( "deallocated_at", ColumnType::Integer, true ),
+        column( "deallocated_on_thread", ColumnType::Integer, true ),
+        column( "deallocation_backtrace_id", ColumnType::Integer, true ),
+        column( "flags", ColumnType::Integer, false ),
+        column( "is_mmaped", ColumnType::Boolean, false ),
+        column( "is_calloc", ColumnType::Boolean, false ),
+        column( "in_main_arena", ColumnType::Boolean, false ),
+        column( "marker", ColumnType::Integer, false ),
+        column( "reallocated_from", ColumnType::Integer, true ),
+        column( "reallocated_to", ColumnType::Integer, true ),
+        column( "first_allocation_in_chain", ColumnType::Integer, true ),
+        column( "position_in_chain", ColumnType::Integer, false )
+    ]
+};
+
+pub(crate) const BACKTRACES: Table = Table {
+    name: "backtraces",
+    columns: &[
+        column( "backtrace_id", ColumnType::Integer, false ),
+        column( "position", ColumnType::Integer, false ),
+        column( "frame_id", ColumnType::Integer, false )
+    ]
+};
+
+pub(crate) const FRAMES: Table = Table {
+    name: "frames",
+    columns: &[
+        column( "frame_id", ColumnType::Integer, false ),
+        column( "address", ColumnType::Integer, false ),
+        column( "library", ColumnType::Text, true ),
+        column( "function", ColumnType::Text, true ),
+        column( "raw_function", ColumnType::Text, true ),
+        column( "source", ColumnType::Text, true ),
+        column( "line", ColumnType::Integer, true ),
+        column( "column", ColumnType::Integer, true ),
+        column( "is_inline", ColumnType::Boolean, false )
+    ]
+};
+
+pub(crate) const MMAPS: Table = Table {
+    name: "mmaps",
+    columns: &[
+        column( "kind", ColumnType::Text, false ),
+        column( "timestamp", ColumnType::Integer, false ),
+        column( "pointer", ColumnType::Integer, false ),
+        column( "length", ColumnType::Integer, false ),
+        column( "thread", ColumnType::Integer, false ),
+        column( "backtrace_id", ColumnType::Integer, false ),
+        column( "requested_address", ColumnType::Integer, true ),
+        column( "protection", ColumnType::Integer, true ),
+        column( "flags", ColumnType::Integer, true ),
+        column( "file_descriptor", ColumnType::Integer, true ),
+        column( "offset", ColumnType::Integer, true )
+    ]
+};
+
+pub(crate) trait TableSink {
+    fn begin_table( &mut self, table: &Table ) -> io::Result< () >;
+    fn add_row( &mut self, row: &[Value] ) -> io::Result< () >;
+    fn end_table( &mut self ) -> io::Result< () >;
+}
+
+fn elapsed( data: &Data, timestamp: Timestamp ) -> u64 {
+    (timestamp - data.initial_timestamp()).as_usecs()
+}
+
+fn string( data: &Data, id: Option< StringId > ) -> Option< &str > {
+    id.and_then( |id| data.interner().resolve( id ) )
+}
+
+/// Writes out the given allocations, along with the backtraces and frames
+/// which they reference, and all of the mmap calls.
+pub(crate) fn export_tables( data: &Data, allocation_ids: impl Iterator< Item = AllocationId >, sink: &mut dyn TableSink ) -> io::Result< () > {
+    let mut backtrace_ids: Vec< BacktraceId > = Vec::new();
+
+    sink.begin_table( &ALLOCATIONS )?;
+    for id in allocation_ids {
+        let allocation = data.get_allocation( id );
+        let deallocation = allocation.deallocation.as_ref();
+
+        backtrace_ids.push( allocation.backtrace );
+        if let Some( backtrace ) = deallocation.and_then( |deallocation| deallocation.backtrace ) {
+            backtrace_ids.push( backtrace );
+        }
+
+        sink.add_row( &[
+            id.raw().into(),
+            allocation.pointer.into(),
+            allocation.size.into(),
+            allocation.usable_size().into(),
+            elapsed( data, allocation.timestamp ).into(),
+            allocation.thread.into(),
+            allocation.backtrace.raw().into(),
+            deallocation.map( |deallocation| elapsed( data, deallocation.timestamp ) ).into(),
+            deallocation.map( |deallocation| deallocation.thread ).into(),
+            deallocation.and_then( |deallocation| deallocation.backtrace ).map( |backtrace| backtrace.raw() ).into(),
+            (allocation.flags.bits() as u32).into(),
+            allocation.is_mmaped().into(),
+            allocation.is_calloc().into(),
+            allocation.in_main_arena().into(),
+            allocation.marker.into(),
+            allocation.reallocated_from.map( |id| id.raw() ).into(),
+            allocation.reallocation.map( |id| id.raw() ).into(),
+            allocation.first_allocation_in_chain.map( |id| id.raw() ).into(),
+            allocation.position_in_chain.into()
+        ])?;
+    }
+    sink.end_table()?;
+
+    sink.begin_table( &MMAPS )?;
+    for operation in data.mmap_operations() {
+        match *operation {
+            MmapOperation::Mmap( ref map ) => {
+                backtrace_ids.push( map.backtrace );
+                sink.add_row( &[
+                    "mmap".into(),
+                    elapsed( data, map.timestamp ).into(),
+                    map.pointer.into(),
+                    map.length.into(),
+                    map.thread.into(),
+                    map.backtrace.raw().into(),
+                    map.requested_address.into(),
+                    map.mmap_protection.0.into(),
+                    map.mmap_flags.0.into(),
+                    map.file_descriptor.into(),
+                    map.offset.into()
+                ])?;
+            },
+            MmapOperation::Munmap( ref unmap ) => {
+                backtrace_ids.push( unmap.backtrace );
+                sink.add_row( &[
+                    "munmap".into(),
+                    elapsed( data, unmap.timestamp ).into(),
+                    unmap.pointer.into(),
+                    unmap.length.into(),
+                    unmap.thread.into(),
+                    unmap.backtrace.raw().into(),
+                    Value::Null,
+                    Value::Null,
+                    Value::Null,
+                    Value::Null,
+                    Value::Null
+                ])?;
+            }
+        }
+    }
+    sink.end_table()?;
+
+    backtrace_ids.sort_unstable();
+    backtrace_ids.dedup();
+
+    let mut frame_ids: Vec< FrameId > = Vec::new();
+    sink.begin_table( &BACKTRACES )?;
+    for backtrace_id in backtrace_ids {
+        for (position, &frame_id) in data.get_frame_ids( backtrace_id ).iter().enumerate() {
+            frame_ids.push( frame_id );
+            sink.add_row( &[
+                backtrace_id.raw().into(),
+                (position as u64).into(),
+                (frame_id as u64).into()
+            ])?;
+        }
+    }
+    sink.end_table()?;
+
+    frame_ids.sort_unstable();
+    frame_ids.dedup();
+
+    sink.begin_table( &FRAMES )?;
+    for frame_id in frame_ids {
+        let frame = data.get_frame( frame_id );
+        sink.add_row( &[
+            (frame_id as u64).into(),
+            frame.address().raw().into(),
+            string( data, frame.library() ).into(),
+            string( data, frame.function() ).into(),
+            string( data, frame.raw_function() ).into(),
+            string( data, frame.source() ).into(),
+            frame.line().into(),
+            frame.column().into(),
+            frame.is_inline().into()
+        ])?;
+    }
+    sink.end_table()?;
+
+    Ok(())
+}
+
+#[test]
+fn test_value_conversions() {
+    assert_eq!( Value::from( Some( 10_u32 ) ), Value::Integer( 10 ) );
+    assert_eq!( Value::from( None as Option< u64 > ), Value::Null );
+    assert_eq!( Value::from( Some( "foo" ) ), Value::Text( "foo" ) );
+}
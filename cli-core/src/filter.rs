@@ -84,7 +84,11 @@ pub struct BasicFilter {
     pub only_ptmalloc_not_from_main_arena: bool,
     pub only_jemalloc: bool,
     pub only_not_jemalloc: bool,
-    pub only_with_marker: Option< u32 >
+    pub only_freed_on_other_thread: bool,
+    pub only_freed_on_same_thread: bool,
+    pub only_with_marker: Option< u32 >,
+    pub only_from_thread: Option< u32 >,
+    pub only_from_arena: Option< u32 >
 }
 
 #[derive(Copy, Clone)]
@@ -152,7 +156,10 @@ pub struct CompiledBasicFilter {
     only_ptmalloc_mmaped: Option< bool >,
     only_ptmalloc_from_main_arena: Option< bool >,
     only_jemalloc: Option< bool >,
-    only_with_marker: Option< u32 >
+    only_freed_on_other_thread: Option< bool >,
+    only_with_marker: Option< u32 >,
+    only_from_thread: Option< u32 >,
+    only_from_arena: Option< u32 >
 }
 
 impl From< BasicFilter > for Filter {
@@ -394,6 +401,14 @@ impl BasicFilter {
             is_impossible = true;
         }
 
+        if self.only_freed_on_other_thread && self.only_freed_on_same_thread {
+            is_impossible = true;
+        }
+
+        if (self.only_freed_on_other_thread || self.only_freed_on_same_thread) && self.only_leaked {
+            is_impossible = true;
+        }
+
         if self.only_leaked {
             only_leaked_or_deallocated_after = data.last_timestamp;
         }
@@ -512,7 +527,17 @@ impl BasicFilter {
                 } else {
                     None
                 },
-            only_with_marker: self.only_with_marker
+            only_freed_on_other_thread:
+                if self.only_freed_on_other_thread {
+                    Some( true )
+                } else if self.only_freed_on_same_thread {
+                    Some( false )
+                } else {
+                    None
+                },
+            only_with_marker: self.only_with_marker,
+            only_from_thread: self.only_from_thread,
+            only_from_arena: self.only_from_arena
         }
     }
 }
@@ -752,12 +777,35 @@ impl CompiledBasicFilter {
             }
         }
 
+        if let Some( value ) = self.only_freed_on_other_thread {
+            match allocation.deallocation {
+                Some( ref deallocation ) => {
+                    if (deallocation.thread != allocation.thread) != value {
+                        return false;
+                    }
+                },
+                None => return false
+            }
+        }
+
         if let Some( marker ) = self.only_with_marker {
             if allocation.marker != marker {
                 return false;
             }
         }
 
+        if let Some( thread ) = self.only_from_thread {
+            if allocation.thread != thread {
+                return false;
+            }
+        }
+
+        if let Some( arena ) = self.only_from_arena {
+            if data.get_arena( allocation ) != Some( arena ) {
+                return false;
+            }
+        }
+
         true
     }
 }
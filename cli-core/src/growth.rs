@@ -0,0 +1,181 @@
+use std::cmp::{max, min};
+use ahash::AHashMap as HashMap;
+use ahash::AHashSet as HashSet;
+use crate::data::{AllocationId, BacktraceId, Data};
+
+/// The minimum ratio between two consecutive sizes in a chain
+/// for that reallocation to be considered a part of a geometric growth.
+const MIN_GROWTH_FACTOR: f64 = 1.25;
+
+/// The minimum number of reallocations a chain needs to have
+/// to be considered as a growth pattern.
+const MIN_REALLOCATIONS: u64 = 2;
+
+#[derive(Clone, Debug)]
+pub struct GrowthGroup {
+    pub backtrace: BacktraceId,
+    pub chains: Vec< AllocationId >,
+    pub avoidable_reallocations: u64,
+    pub copied_bytes: u64,
+    pub average_growth_factor: f64,
+    pub min_final_size: u64,
+    pub max_final_size: u64,
+    pub suggested_capacity: u64
+}
+
+#[derive(Clone, PartialEq, Debug)]
+struct ChainStats {
+    reallocations: u64,
+    copied_bytes: u64,
+    growth_factor_sum: f64,
+    growth_factor_count: u64,
+    final_size: u64
+}
+
+/// Checks whether a sequence of `(size, pointer)` pairs looks like
+/// a container which was grown geometrically, e.g. 8 -> 16 -> 32 -> 64.
+///
+/// A reallocation which didn't move the memory block isn't counted
+/// towards the copied bytes, although it's still counted as avoidable.
+fn analyze_chain( steps: impl Iterator< Item = (u64, u64) > ) -> Option< ChainStats > {
+    let mut stats = ChainStats {
+        reallocations: 0,
+        copied_bytes: 0,
+        growth_factor_sum: 0.0,
+        growth_factor_count: 0,
+        final_size: 0
+    };
+
+    let mut previous: Option< (u64, u64) > = None;
+    for (size, pointer) in steps {
+        if let Some( (previous_size, previous_pointer) ) = previous {
+            if (size as f64) < previous_size as f64 * MIN_GROWTH_FACTOR {
+                return None;
+            }
+
+            if previous_size != 0 {
+                stats.growth_factor_sum += size as f64 / previous_size as f64;
+                stats.growth_factor_count += 1;
+            }
+
+            if pointer != previous_pointer {
+                stats.copied_bytes += previous_size;
+            }
+
+            stats.reallocations += 1;
+        }
+
+        stats.final_size = size;
+        previous = Some( (size, pointer) );
+    }
+
+    if stats.reallocations < MIN_REALLOCATIONS {
+        return None;
+    }
+
+    Some( stats )
+}
+
+/// Finds all of the reallocation chains touched by the given allocations
+/// which look like a geometrically growing container, and groups them
+/// by the backtrace of the chain's first allocation.
+///
+/// The groups are sorted by the number of copied bytes, in descending order.
+pub fn collect_growth_groups( data: &Data, allocation_ids: impl Iterator< Item = AllocationId > ) -> Vec< GrowthGroup > {
+    #[derive(Default)]
+    struct Group {
+        chains: Vec< AllocationId >,
+        final_sizes: Vec< u64 >,
+        reallocations: u64,
+        copied_bytes: u64,
+        growth_factor_sum: f64,
+        growth_factor_count: u64
+    }
+
+    let mut seen = HashSet::new();
+    let mut groups: HashMap< BacktraceId, Group > = HashMap::new();
+    for id in allocation_ids {
+        let first = match data.get_allocation( id ).first_allocation_in_chain {
+            Some( first ) => first,
+            None => continue
+        };
+
+        if !seen.insert( first ) {
+            continue;
+        }
+
+        let steps = std::iter::successors( Some( first ), |&current| data.get_allocation( current ).reallocation )
+            .map( |current| {
+                let allocation = data.get_allocation( current );
+                (allocation.size, allocation.pointer)
+            });
+
+        let stats = match analyze_chain( steps ) {
+            Some( stats ) => stats,
+            None => continue
+        };
+
+        let group = groups.entry( data.get_allocation( first ).backtrace ).or_insert_with( Group::default );
+        group.chains.push( first );
+        group.final_sizes.push( stats.final_size );
+        group.reallocations += stats.reallocations;
+        group.copied_bytes += stats.copied_bytes;
+        group.growth_factor_sum += stats.growth_factor_sum;
+        group.growth_factor_count += stats.growth_factor_count;
+    }
+
+    let mut groups: Vec< _ > = groups.into_iter().map( |(backtrace, mut group)| {
+        group.final_sizes.sort_unstable();
+
+        // Suggest a capacity which would have been enough for the vast majority of the chains
+        // without wasting too much memory for the occasional outlier.
+        let suggested_capacity = group.final_sizes[ (group.final_sizes.len() - 1) * 9 / 10 ];
+        let average_growth_factor =
+            if group.growth_factor_count == 0 {
+                0.0
+            } else {
+                group.growth_factor_sum / group.growth_factor_count as f64
+            };
+
+        GrowthGroup {
+            backtrace,
+            chains: group.chains,
+            avoidable_reallocations: group.reallocations,
+            copied_bytes: group.copied_bytes,
+            average_growth_factor,
+            min_final_size: group.final_sizes.iter().copied().fold( !0, min ),
+            max_final_size: group.final_sizes.iter().copied().fold( 0, max ),
+            suggested_capacity
+        }
+    }).collect();
+
+    groups.sort_by( |a, b| {
+        b.copied_bytes.cmp( &a.copied_bytes )
+            .then_with( || a.backtrace.cmp( &b.backtrace ) )
+    });
+
+    groups
+}
+
+#[test]
+fn test_analyze_chain() {
+    assert_eq!(
+        analyze_chain( vec![ (8, 1), (16, 2), (32, 3), (64, 3) ].into_iter() ),
+        Some( ChainStats {
+            reallocations: 3,
+            copied_bytes: 8 + 16,
+            growth_factor_sum: 6.0,
+            growth_factor_count: 3,
+            final_size: 64
+        })
+    );
+
+    // Not enough reallocations.
+    assert_eq!( analyze_chain( vec![ (8, 1), (16, 2) ].into_iter() ), None );
+
+    // Shrinking.
+    assert_eq!( analyze_chain( vec![ (8, 1), (16, 2), (32, 3), (20, 4) ].into_iter() ), None );
+
+    // Growing too slowly.
+    assert_eq!( analyze_chain( vec![ (100, 1), (101, 2), (102, 3) ].into_iter() ), None );
+}
@@ -0,0 +1,413 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::ffi::OsStr;
+use std::hash::{Hash, Hasher};
+use std::collections::hash_map::DefaultHasher;
+use std::borrow::Cow;
+
+use ahash::AHashMap as HashMap;
+use nwind::DebugInfoIndex;
+
+use bytehound_format::Writer;
+
+use common::Timestamp;
+use common::event::{
+    AllocBody,
+    DataId,
+    Event,
+    HeaderBody,
+    FORMAT_VERSION,
+    HEADER_FLAG_IS_LITTLE_ENDIAN
+};
+
+use crate::data::Data;
+use crate::loader::Loader;
+use crate::importer_heaptrack::import_heaptrack;
+use crate::importer_jemalloc::import_jemalloc;
+use crate::importer_massif::import_massif;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ImportFormat {
+    Heaptrack,
+    Massif,
+    Jemalloc
+}
+
+impl ImportFormat {
+    /// Tries to guess the format from the first few bytes of the input.
+    pub fn detect( data: &[u8] ) -> Option< Self > {
+        if data.starts_with( b"v " ) {
+            Some( ImportFormat::Heaptrack )
+        } else if data.starts_with( b"desc:" ) {
+            Some( ImportFormat::Massif )
+        } else if data.starts_with( b"heap_v2/" ) {
+            Some( ImportFormat::Jemalloc )
+        } else {
+            None
+        }
+    }
+
+    fn name( self ) -> &'static str {
+        match self {
+            ImportFormat::Heaptrack => "heaptrack",
+            ImportFormat::Massif => "massif",
+            ImportFormat::Jemalloc => "jemalloc"
+        }
+    }
+}
+
+pub(crate) fn invalid_data< T: Into< String > >( message: T ) -> io::Error {
+    io::Error::new( io::ErrorKind::InvalidData, message.into() )
+}
+
+pub(crate) trait EventSink {
+    fn emit( &mut self, event: Event ) -> io::Result< () >;
+}
+
+struct StreamSink< F: Write > {
+    fp: Option< F >,
+    writer: Option< Writer< F > >
+}
+
+impl< F: Write > EventSink for StreamSink< F > {
+    fn emit( &mut self, event: Event ) -> io::Result< () > {
+        if let Some( ref mut writer ) = self.writer {
+            return writer.write_event( &event );
+        }
+
+        match event {
+            Event::Header( header ) => {
+                self.writer = Some( Writer::new( self.fp.take().unwrap(), header )? );
+                Ok(())
+            },
+            _ => Err( invalid_data( "the importer didn't emit a header" ) )
+        }
+    }
+}
+
+struct LoaderSink {
+    debug_info_index: Option< DebugInfoIndex >,
+    loader: Option< Loader >
+}
+
+impl EventSink for LoaderSink {
+    fn emit( &mut self, event: Event ) -> io::Result< () > {
+        if let Some( ref mut loader ) = self.loader {
+            loader.process( event );
+            return Ok(());
+        }
+
+        match event {
+            Event::Header( header ) => {
+                self.loader = Some( Loader::new( header, self.debug_info_index.take().unwrap() ) );
+                Ok(())
+            },
+            _ => Err( invalid_data( "the importer didn't emit a header" ) )
+        }
+    }
+}
+
+/// The architecture of the profiled program, as far as it can be figured out from the imported profile.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(crate) struct Target {
+    pub arch: &'static str,
+    pub pointer_size: u8,
+    pub is_little_endian: bool
+}
+
+impl Target {
+    /// Figures out the target from the header of an ELF file.
+    pub fn from_elf( contents: &[u8] ) -> Option< Self > {
+        if contents.len() < 20 || !contents.starts_with( b"\x7FELF" ) {
+            return None;
+        }
+
+        let pointer_size = match contents[ 4 ] {
+            1 => 4,
+            2 => 8,
+            _ => return None
+        };
+
+        let is_little_endian = match contents[ 5 ] {
+            1 => true,
+            2 => false,
+            _ => return None
+        };
+
+        let machine = [contents[ 18 ], contents[ 19 ]];
+        let machine = if is_little_endian { u16::from_le_bytes( machine ) } else { u16::from_be_bytes( machine ) };
+        let arch = match (machine, pointer_size) {
+            (0x03E, 8) => "x86_64",
+            (0x028, 4) => "arm",
+            (0x0B7, 8) => "aarch64",
+            (0x008, 8) => "mips64",
+            _ => return None
+        };
+
+        Some( Target {
+            arch,
+            pointer_size,
+            is_little_endian
+        })
+    }
+}
+
+/// Creates a header for an imported profile; if the `target` isn't known
+/// then the architecture is recorded as `unknown`.
+pub(crate) fn new_header( id: DataId, executable: &str, cmdline: &str, target: Option< Target > ) -> HeaderBody {
+    let (arch, pointer_size, flags) = match target {
+        Some( target ) => (target.arch, target.pointer_size, if target.is_little_endian { HEADER_FLAG_IS_LITTLE_ENDIAN } else { 0 }),
+        None => ("unknown", 0, 0)
+    };
+
+    let mut header = HeaderBody {
+        id,
+        initial_timestamp: Timestamp::min(),
+        timestamp: Timestamp::min(),
+        wall_clock_secs: 0,
+        wall_clock_nsecs: 0,
+        pid: 0,
+        cmdline: cmdline.as_bytes().to_owned(),
+        executable: executable.as_bytes().to_owned(),
+        arch: arch.to_owned(),
+        flags,
+        pointer_size
+    };
+
+    header.set_format_version( FORMAT_VERSION );
+    header
+}
+
+/// Calls the `callback` for every line of the input, without requiring it to be valid UTF-8.
+pub(crate) fn for_each_line< R: BufRead, F: FnMut( &str ) -> io::Result< () > >( mut input: R, mut callback: F ) -> io::Result< () > {
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        if input.read_until( b'\n', &mut buffer )? == 0 {
+            return Ok(());
+        }
+
+        let line = String::from_utf8_lossy( &buffer );
+        callback( line.trim_end_matches( |ch| ch == '\n' || ch == '\r' ) )?;
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct ImportedFrame {
+    pub address: u64,
+    pub library: Option< String >,
+    pub function: Option< String >,
+    pub source: Option< String >,
+    pub line: Option< u32 >,
+    pub is_inline: bool
+}
+
+impl ImportedFrame {
+    pub fn new( address: u64 ) -> Self {
+        ImportedFrame {
+            address,
+            library: None,
+            function: None,
+            source: None,
+            line: None,
+            is_inline: false
+        }
+    }
+}
+
+/// Emits already symbolicated frames and backtraces, the same way
+/// as the postprocessor does.
+#[derive(Default)]
+pub(crate) struct FrameEmitter {
+    strings: HashMap< String, u32 >,
+    frames: HashMap< ImportedFrame, u32 >,
+    backtraces: HashMap< Vec< u32 >, u64 >
+}
+
+impl FrameEmitter {
+    fn string( &mut self, sink: &mut dyn EventSink, string: &Option< String > ) -> io::Result< u32 > {
+        let string = match *string {
+            Some( ref string ) => string,
+            None => return Ok( 0xFFFFFFFF )
+        };
+
+        if let Some( &id ) = self.strings.get( string ) {
+            return Ok( id );
+        }
+
+        let id = self.strings.len() as u32;
+        sink.emit( Event::String { id, string: string.as_str().into() } )?;
+        self.strings.insert( string.clone(), id );
+        Ok( id )
+    }
+
+    pub fn frame( &mut self, sink: &mut dyn EventSink, frame: &ImportedFrame ) -> io::Result< u32 > {
+        if let Some( &id ) = self.frames.get( frame ) {
+            return Ok( id );
+        }
+
+        let library = self.string( sink, &frame.library )?;
+        let function = self.string( sink, &frame.function )?;
+        let source = self.string( sink, &frame.source )?;
+
+        let id = self.frames.len() as u32;
+        sink.emit( Event::DecodedFrame {
+            address: frame.address,
+            library,
+            raw_function: 0xFFFFFFFF,
+            function,
+            source,
+            line: frame.line.unwrap_or( 0xFFFFFFFF ),
+            column: 0xFFFFFFFF,
+            is_inline: frame.is_inline
+        })?;
+
+        self.frames.insert( frame.clone(), id );
+        Ok( id )
+    }
+
+    /// Emits a backtrace made out of the given frames, ordered from the innermost one.
+    pub fn backtrace( &mut self, sink: &mut dyn EventSink, frames: Vec< u32 > ) -> io::Result< u64 > {
+        if let Some( &id ) = self.backtraces.get( &frames ) {
+            return Ok( id );
+        }
+
+        let id = self.backtraces.len() as u64;
+        sink.emit( Event::DecodedBacktrace { frames: Cow::Borrowed( &frames[..] ) } )?;
+        self.backtraces.insert( frames, id );
+        Ok( id )
+    }
+}
+
+/// Since none of the supported formats record the actual addresses
+/// of the allocations we hand out fake, non-overlapping ones.
+pub(crate) struct SyntheticHeap {
+    next_pointer: u64
+}
+
+impl SyntheticHeap {
+    pub fn new() -> Self {
+        SyntheticHeap {
+            next_pointer: 0x10000
+        }
+    }
+
+    pub fn alloc( &mut self, sink: &mut dyn EventSink, timestamp: Timestamp, size: u64, backtrace: u64, thread: u32 ) -> io::Result< u64 > {
+        let pointer = self.next_pointer;
+        self.next_pointer += ((size + 15) & !15) + 16;
+
+        sink.emit( Event::Alloc {
+            timestamp,
+            allocation: AllocBody {
+                pointer,
+                size,
+                backtrace,
+                thread,
+                flags: 0,
+                extra_usable_space: 0,
+                preceding_free_space: 0
+            }
+        })?;
+
+        Ok( pointer )
+    }
+
+    pub fn free( &mut self, sink: &mut dyn EventSink, timestamp: Timestamp, pointer: u64, thread: u32 ) -> io::Result< () > {
+        sink.emit( Event::Free {
+            timestamp,
+            pointer,
+            backtrace: -1_i64 as u64,
+            thread
+        })
+    }
+}
+
+fn import_impl< F: Read >( format: Option< ImportFormat >, input: F, sink: &mut dyn EventSink ) -> io::Result< () > {
+    let mut input = BufReader::new( input );
+    let (format, id) = {
+        let head = input.fill_buf()?;
+        if head.starts_with( &[0x1F, 0x8B] ) || head.starts_with( &[0x28, 0xB5, 0x2F, 0xFD] ) {
+            return Err( invalid_data( "the input is compressed; decompress it first" ) );
+        }
+
+        let format = match format.or_else( || ImportFormat::detect( head ) ) {
+            Some( format ) => format,
+            None => return Err( invalid_data( "unknown input format" ) )
+        };
+
+        let mut hasher = DefaultHasher::new();
+        head.hash( &mut hasher );
+        let a = hasher.finish();
+        format.name().hash( &mut hasher );
+        let b = hasher.finish();
+
+        (format, DataId::new( a, b ))
+    };
+
+    info!( "Importing {} data...", format.name() );
+    match format {
+        ImportFormat::Heaptrack => import_heaptrack( input, id, sink ),
+        ImportFormat::Massif => import_massif( input, id, sink ),
+        ImportFormat::Jemalloc => import_jemalloc( input, id, sink )
+    }
+}
+
+/// Converts a heaptrack data file, a massif output file or a jemalloc heap profile
+/// into a bytehound data file. If the `format` is not given it will be guessed.
+pub fn import< F: Read, G: Write >( format: Option< ImportFormat >, input: F, output: G ) -> io::Result< () > {
+    let mut sink = StreamSink {
+        fp: Some( output ),
+        writer: None
+    };
+
+    import_impl( format, input, &mut sink )?;
+    match sink.writer {
+        Some( writer ) => {
+            writer.finish()?;
+            Ok(())
+        },
+        None => Err( invalid_data( "the input is empty" ) )
+    }
+}
+
+/// Same as `import`, except it loads the data directly instead of writing it out.
+pub fn import_as_data< F: Read, D: AsRef< OsStr >, I: IntoIterator< Item = D > >( format: Option< ImportFormat >, input: F, debug_symbols: I ) -> io::Result< Data > {
+    let mut debug_info_index = DebugInfoIndex::new();
+    for path in debug_symbols {
+        debug_info_index.add( path.as_ref() );
+    }
+
+    let mut sink = LoaderSink {
+        debug_info_index: Some( debug_info_index ),
+        loader: None
+    };
+
+    import_impl( format, input, &mut sink )?;
+    match sink.loader {
+        Some( loader ) => Ok( loader.finalize() ),
+        None => Err( invalid_data( "the input is empty" ) )
+    }
+}
+
+#[test]
+fn test_detect_format() {
+    assert_eq!( ImportFormat::detect( b"v 10100 2\nX ./foo\n" ), Some( ImportFormat::Heaptrack ) );
+    assert_eq!( ImportFormat::detect( b"desc: (none)\ncmd: ./foo\n" ), Some( ImportFormat::Massif ) );
+    assert_eq!( ImportFormat::detect( b"heap_v2/524288\n" ), Some( ImportFormat::Jemalloc ) );
+    assert_eq!( ImportFormat::detect( b"MEMORY-PROFILER" ), None );
+}
+
+#[test]
+fn test_target_from_elf() {
+    let mut elf = vec![ 0; 64 ];
+    elf[ ..4 ].copy_from_slice( b"\x7FELF" );
+    elf[ 4 ] = 2;
+    elf[ 5 ] = 1;
+    elf[ 18 ] = 0x3E;
+    assert_eq!( Target::from_elf( &elf ), Some( Target { arch: "x86_64", pointer_size: 8, is_little_endian: true } ) );
+
+    elf[ 4 ] = 1;
+    elf[ 18 ] = 0x28;
+    assert_eq!( Target::from_elf( &elf ), Some( Target { arch: "arm", pointer_size: 4, is_little_endian: true } ) );
+
+    assert_eq!( Target::from_elf( b"#!/bin/sh" ), None );
+}
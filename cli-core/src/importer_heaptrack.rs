@@ -0,0 +1,252 @@
+use std::io::{self, BufRead};
+
+use ahash::AHashMap as HashMap;
+
+use common::Timestamp;
+use common::event::{DataId, Event};
+
+use crate::importer::{
+    EventSink,
+    FrameEmitter,
+    ImportedFrame,
+    SyntheticHeap,
+    for_each_line,
+    invalid_data,
+    new_header
+};
+
+// Heaptrack doesn't record which thread did the allocation.
+const THREAD: u32 = 1;
+
+/*
+    See `exporter_heaptrack.rs` for a description of the format.
+
+    Every index is 1-based, except for the allocation info's index.
+    Since heaptrack only records which allocation info was freed
+    instead of the exact pointer we just free the most recent allocation
+    which was made with the same allocation info.
+*/
+
+struct HeaptrackImporter {
+    id: DataId,
+    header_emitted: bool,
+    file_format_version: u32,
+    executable: String,
+    strings: Vec< String >,
+    ips: Vec< Vec< ImportedFrame > >,
+    traces: Vec< (usize, usize) >,
+    alloc_infos: Vec< (u64, usize) >,
+    ip_to_frames: HashMap< usize, Vec< u32 > >,
+    trace_to_backtrace: HashMap< usize, u64 >,
+    live: HashMap< usize, Vec< u64 > >,
+    timestamp: Timestamp,
+    emitter: FrameEmitter,
+    heap: SyntheticHeap
+}
+
+fn parse_hex( token: Option< &str >, what: &str ) -> io::Result< u64 > {
+    let token = token.ok_or_else( || invalid_data( format!( "missing {}", what ) ) )?;
+    u64::from_str_radix( token, 16 ).map_err( |_| invalid_data( format!( "invalid {}: '{}'", what, token ) ) )
+}
+
+/// Parses the part of an `i` line after the module index, which is
+/// a list of `<function> <file> <line>` triples where the first one is
+/// the actual function and the rest are the functions which were inlined into it.
+///
+/// Older versions could also emit only a lone function index.
+fn parse_ip_frames( tokens: &[&str] ) -> io::Result< Vec< (usize, Option< (usize, u32) >) > > {
+    if tokens.len() == 1 {
+        return Ok( vec![ (parse_hex( Some( tokens[ 0 ] ), "function index" )? as usize, None) ] );
+    }
+
+    if tokens.len() % 3 != 0 {
+        return Err( invalid_data( "malformed instruction pointer entry" ) );
+    }
+
+    tokens.chunks( 3 ).map( |chunk| {
+        let function = parse_hex( Some( chunk[ 0 ] ), "function index" )? as usize;
+        let file = parse_hex( Some( chunk[ 1 ] ), "file index" )? as usize;
+        let line = parse_hex( Some( chunk[ 2 ] ), "line" )? as u32;
+        Ok( (function, Some( (file, line) )) )
+    }).collect()
+}
+
+impl HeaptrackImporter {
+    fn string( &self, index: usize ) -> Option< String > {
+        if index == 0 {
+            return None;
+        }
+
+        self.strings.get( index - 1 ).cloned()
+    }
+
+    fn emit_header( &mut self, sink: &mut dyn EventSink ) -> io::Result< () > {
+        if self.header_emitted {
+            return Ok(());
+        }
+
+        self.header_emitted = true;
+        let executable = self.executable.split_whitespace().next().unwrap_or( "" ).to_owned();
+        sink.emit( Event::Header( new_header( self.id, &executable, &self.executable, None ) ) )
+    }
+
+    fn resolve_trace( &mut self, sink: &mut dyn EventSink, trace: usize ) -> io::Result< u64 > {
+        if let Some( &backtrace ) = self.trace_to_backtrace.get( &trace ) {
+            return Ok( backtrace );
+        }
+
+        let mut frames = Vec::new();
+        let mut current = trace;
+        while current != 0 {
+            let (ip, parent) = *self.traces.get( current - 1 ).ok_or_else( || invalid_data( format!( "unknown trace index: {}", current ) ) )?;
+            if ip != 0 {
+                if !self.ip_to_frames.contains_key( &ip ) {
+                    let ip_frames = self.ips.get( ip - 1 ).ok_or_else( || invalid_data( format!( "unknown instruction pointer index: {}", ip ) ) )?;
+                    let frame_ids = ip_frames.iter().map( |frame| self.emitter.frame( sink, frame ) ).collect::< io::Result< Vec< _ > > >()?;
+                    self.ip_to_frames.insert( ip, frame_ids );
+                }
+
+                frames.extend( self.ip_to_frames.get( &ip ).unwrap().iter().cloned() );
+            }
+
+            current = parent;
+        }
+
+        let backtrace = self.emitter.backtrace( sink, frames )?;
+        self.trace_to_backtrace.insert( trace, backtrace );
+        Ok( backtrace )
+    }
+
+    fn process_line( &mut self, sink: &mut dyn EventSink, line: &str ) -> io::Result< () > {
+        let mut tokens = line.split( ' ' );
+        let kind = match tokens.next() {
+            Some( kind ) => kind,
+            None => return Ok(())
+        };
+
+        match kind {
+            "v" => {
+                tokens.next();
+                self.file_format_version = tokens.next().map( |token| parse_hex( Some( token ), "file format version" ) ).transpose()?.unwrap_or( 0 ) as u32;
+            },
+            "X" => {
+                self.executable = line[ 1.. ].trim().to_owned();
+            },
+            "s" => {
+                let mut string = if line.len() > 2 { &line[ 2.. ] } else { "" };
+                if self.file_format_version >= 3 {
+                    // Newer versions prefix every string with its length.
+                    string = string.find( ' ' ).map( |index| &string[ index + 1.. ] ).unwrap_or( "" );
+                }
+
+                self.strings.push( string.to_owned() );
+            },
+            "i" => {
+                let tokens: Vec< _ > = tokens.filter( |token| !token.is_empty() ).collect();
+                if tokens.len() < 2 {
+                    return Err( invalid_data( "malformed instruction pointer entry" ) );
+                }
+
+                let address = parse_hex( Some( tokens[ 0 ] ), "address" )?;
+                let library = self.string( parse_hex( Some( tokens[ 1 ] ), "module index" )? as usize );
+                let mut frames = Vec::new();
+                if tokens.len() > 2 {
+                    let mut entries = parse_ip_frames( &tokens[ 2.. ] )?;
+
+                    // We want the innermost frame first.
+                    entries.rotate_left( 1 );
+                    let count = entries.len();
+                    for (index, (function, location)) in entries.into_iter().enumerate() {
+                        let mut frame = ImportedFrame::new( address );
+                        frame.library = library.clone();
+                        frame.function = self.string( function );
+                        if let Some( (file, line) ) = location {
+                            frame.source = self.string( file );
+                            if frame.source.is_some() {
+                                frame.line = Some( line );
+                            }
+                        }
+                        frame.is_inline = index + 1 != count;
+                        frames.push( frame );
+                    }
+                } else {
+                    let mut frame = ImportedFrame::new( address );
+                    frame.library = library;
+                    frames.push( frame );
+                }
+
+                self.ips.push( frames );
+            },
+            "t" => {
+                let ip = parse_hex( tokens.next(), "instruction pointer index" )? as usize;
+                let parent = parse_hex( tokens.next(), "parent trace index" )? as usize;
+                self.traces.push( (ip, parent) );
+            },
+            "a" => {
+                let size = parse_hex( tokens.next(), "allocation size" )?;
+                let trace = parse_hex( tokens.next(), "trace index" )? as usize;
+                self.alloc_infos.push( (size, trace) );
+            },
+            "+" => {
+                let index = parse_hex( tokens.next(), "allocation info index" )? as usize;
+                if tokens.next().is_some() {
+                    return Err( invalid_data( "unsupported heaptrack file format; only the output of heaptrack_interpret is supported" ) );
+                }
+
+                let (size, trace) = *self.alloc_infos.get( index ).ok_or_else( || invalid_data( format!( "unknown allocation info index: {}", index ) ) )?;
+
+                self.emit_header( sink )?;
+                let backtrace = self.resolve_trace( sink, trace )?;
+                let pointer = self.heap.alloc( sink, self.timestamp, size, backtrace, THREAD )?;
+                self.live.entry( index ).or_insert_with( Vec::new ).push( pointer );
+            },
+            "-" => {
+                let index = parse_hex( tokens.next(), "allocation info index" )? as usize;
+                let pointer = self.live.get_mut( &index ).and_then( |pointers| pointers.pop() );
+                if let Some( pointer ) = pointer {
+                    self.heap.free( sink, self.timestamp, pointer, THREAD )?;
+                } else {
+                    debug!( "Deallocation of an unknown allocation with allocation info index {}", index );
+                }
+            },
+            "c" => {
+                self.timestamp = Timestamp::from_msecs( parse_hex( tokens.next(), "timestamp" )? );
+            },
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn import_heaptrack< R: BufRead >( input: R, id: DataId, sink: &mut dyn EventSink ) -> io::Result< () > {
+    let mut importer = HeaptrackImporter {
+        id,
+        header_emitted: false,
+        file_format_version: 0,
+        executable: String::new(),
+        strings: Vec::new(),
+        ips: Vec::new(),
+        traces: Vec::new(),
+        alloc_infos: Vec::new(),
+        ip_to_frames: HashMap::new(),
+        trace_to_backtrace: HashMap::new(),
+        live: HashMap::new(),
+        timestamp: Timestamp::min(),
+        emitter: FrameEmitter::default(),
+        heap: SyntheticHeap::new()
+    };
+
+    for_each_line( input, |line| importer.process_line( sink, line ) )?;
+    importer.emit_header( sink )
+}
+
+#[test]
+fn test_parse_ip_frames() {
+    assert_eq!( parse_ip_frames( &["5"] ).unwrap(), vec![ (5, None) ] );
+    assert_eq!(
+        parse_ip_frames( &["5", "6", "a", "7", "6", "1f"] ).unwrap(),
+        vec![ (5, Some( (6, 10) )), (7, Some( (6, 31) )) ]
+    );
+    assert!( parse_ip_frames( &["5", "6"] ).is_err() );
+}
@@ -0,0 +1,233 @@
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, BufRead};
+
+use ahash::AHashSet as HashSet;
+use nwind::proc_maps::parse as parse_maps;
+
+use common::Timestamp;
+use common::event::{DataId, Event};
+
+use crate::importer::{
+    EventSink,
+    SyntheticHeap,
+    Target,
+    for_each_line,
+    invalid_data,
+    new_header
+};
+
+/*
+    Format of a jemalloc heap profile:
+        heap_v2/<sample_period>
+          t*: <count>: <bytes> [0: 0]
+          t<thread>: <count>: <bytes> [0: 0]
+          ...
+        @ <address> <address> ...
+          t*: <count>: <bytes> [0: 0]
+          t<thread>: <count>: <bytes> [0: 0]
+          ...
+
+        MAPPED_LIBRARIES:
+        <contents of /proc/self/maps>
+
+    The profile only contains the allocations which were live when it
+    was dumped, and since those were sampled we scale them up the same
+    way as `jeprof` does.
+*/
+
+#[derive(Default)]
+struct Stack {
+    addresses: Vec< u64 >,
+    total: Option< (u64, u64) >,
+    // Every entry is a (thread, count, bytes) tuple.
+    threads: Vec< (u32, u64, u64) >
+}
+
+/// Parses the part of a `t*` or `tN` line after the thread,
+/// which looks like `<count>: <bytes> [<accumulated count>: <accumulated bytes>]`.
+fn parse_counts( value: &str ) -> io::Result< (u64, u64) > {
+    let mut tokens = value.split( |ch: char| ch == ':' || ch.is_whitespace() ).filter( |token| !token.is_empty() );
+    let mut next = |what: &str| -> io::Result< u64 > {
+        let token = tokens.next().ok_or_else( || invalid_data( format!( "missing {}", what ) ) )?;
+        token.parse().map_err( |_| invalid_data( format!( "invalid {}: '{}'", what, token ) ) )
+    };
+
+    let count = next( "object count" )?;
+    let bytes = next( "byte count" )?;
+    Ok( (count, bytes) )
+}
+
+/// Undoes the sampling; returns the estimated object count and their total size.
+///
+/// This is only used to figure out the total size; we don't synthesize the extra
+/// objects since a few sampled small objects can easily turn into millions of them.
+fn scale( sample_period: u64, count: u64, bytes: u64 ) -> (u64, u64) {
+    if count == 0 || sample_period == 0 {
+        return (count, bytes);
+    }
+
+    let average = bytes as f64 / count as f64;
+    let ratio = average / sample_period as f64;
+    let scale = 1.0 / (1.0 - (-ratio).exp());
+
+    ((count as f64 * scale).round() as u64, (bytes as f64 * scale).round() as u64)
+}
+
+pub(crate) fn import_jemalloc< R: BufRead >( input: R, id: DataId, sink: &mut dyn EventSink ) -> io::Result< () > {
+    let mut sample_period = None;
+    let mut stacks: Vec< Stack > = Vec::new();
+    let mut maps = String::new();
+    let mut in_maps = false;
+
+    for_each_line( input, |line| {
+        if in_maps {
+            maps.push_str( line );
+            maps.push( '\n' );
+            return Ok(());
+        }
+
+        if line.starts_with( "heap_v2/" ) {
+            let period = &line[ "heap_v2/".len().. ];
+            sample_period = Some( period.trim().parse().map_err( |_| invalid_data( format!( "invalid sample period: '{}'", period ) ) )? );
+        } else if line.starts_with( "@" ) {
+            let addresses = line[ 1.. ].split_whitespace().map( |address| {
+                let digits = address.trim_start_matches( "0x" );
+                u64::from_str_radix( digits, 16 ).map_err( |_| invalid_data( format!( "invalid address: '{}'", address ) ) )
+            }).collect::< io::Result< Vec< _ > > >()?;
+
+            stacks.push( Stack {
+                addresses,
+                ..Stack::default()
+            });
+        } else if line.starts_with( "MAPPED_LIBRARIES:" ) {
+            in_maps = true;
+        } else if line.trim_start().starts_with( 't' ) {
+            let line = line.trim_start();
+            let colon = line.find( ':' ).ok_or_else( || invalid_data( format!( "malformed line: '{}'", line ) ) )?;
+            let counts = parse_counts( &line[ colon + 1.. ] )?;
+
+            // The counts before the first stack are the totals for the whole profile.
+            let stack = match stacks.last_mut() {
+                Some( stack ) => stack,
+                None => return Ok(())
+            };
+
+            let thread = &line[ 1..colon ];
+            if thread == "*" {
+                stack.total = Some( counts );
+            } else {
+                let thread = thread.parse().map_err( |_| invalid_data( format!( "invalid thread: '{}'", thread ) ) )?;
+                stack.threads.push( (thread, counts.0, counts.1) );
+            }
+        }
+
+        Ok(())
+    })?;
+
+    let sample_period = sample_period.ok_or_else( || invalid_data( "missing the 'heap_v2' header" ) )?;
+
+    let mut binaries = Vec::new();
+    let mut seen = HashSet::new();
+    for region in parse_maps( &maps ) {
+        if region.name.is_empty() || region.name.starts_with( '[' ) || !seen.insert( region.name.clone() ) {
+            continue;
+        }
+
+        let contents = match fs::read( &region.name ) {
+            Ok( contents ) => contents,
+            Err( error ) => {
+                warn!( "Failed to read '{}': {}", region.name, error );
+                continue;
+            }
+        };
+
+        if !contents.starts_with( b"\x7FELF" ) {
+            continue;
+        }
+
+        binaries.push( (region.name, contents) );
+    }
+
+    let target = binaries.iter().find_map( |(_, contents)| Target::from_elf( contents ) );
+    sink.emit( Event::Header( new_header( id, "", "", target ) ) )?;
+
+    for (path, contents) in binaries {
+        sink.emit( Event::File64 {
+            timestamp: Timestamp::min(),
+            path: path.into(),
+            contents: contents.into()
+        })?;
+    }
+
+    sink.emit( Event::File {
+        timestamp: Timestamp::min(),
+        path: "/proc/self/maps".into(),
+        contents: maps.as_bytes().into()
+    })?;
+
+    let mut heap = SyntheticHeap::new();
+    for (index, stack) in stacks.into_iter().enumerate() {
+        let backtrace = index as u64;
+        sink.emit( Event::Backtrace {
+            id: backtrace,
+            addresses: Cow::Owned( stack.addresses )
+        })?;
+
+        let mut threads = stack.threads;
+        if threads.is_empty() {
+            if let Some( (count, bytes) ) = stack.total {
+                threads.push( (0, count, bytes) );
+            }
+        }
+
+        for (thread, count, bytes) in threads {
+            if count == 0 {
+                continue;
+            }
+
+            // Every sampled object stands in for all of the objects which weren't sampled.
+            let (_, bytes) = scale( sample_period, count, bytes );
+            let size = bytes / count;
+            for _ in 0..count {
+                heap.alloc( sink, Timestamp::min(), size, backtrace, thread )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_jemalloc_scaling() {
+    assert_eq!( parse_counts( " 3: 96 [0: 0]" ).unwrap(), (3, 96) );
+    assert_eq!( scale( 0, 3, 96 ), (3, 96) );
+
+    // Allocations bigger than the sampling period are (almost) always sampled.
+    assert_eq!( scale( 16, 1, 1024 * 1024 ), (1, 1024 * 1024) );
+
+    // ...while small ones are not.
+    assert_eq!( scale( 524288, 2, 8192 ), (257, 1052677) );
+}
+
+#[test]
+fn test_import_jemalloc_scaled() {
+    use crate::importer::{ImportFormat, import_as_data};
+
+    let input = "\
+heap_v2/524288
+  t*: 2: 8192 [0: 0]
+@ 0x1 0x2
+  t*: 2: 8192 [0: 0]
+  t0: 2: 8192 [0: 0]
+
+MAPPED_LIBRARIES:
+";
+
+    let data = import_as_data( Some( ImportFormat::Jemalloc ), input.as_bytes(), Vec::< &str >::new() ).unwrap();
+    let sizes: Vec< _ > = data.allocations_with_id().map( |(_, allocation)| allocation.size ).collect();
+
+    // Only the sampled objects are emitted, but their sizes are scaled up.
+    assert_eq!( sizes, vec![ 1052677 / 2, 1052677 / 2 ] );
+    assert_eq!( data.architecture(), "unknown" );
+}
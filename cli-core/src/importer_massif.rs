@@ -0,0 +1,336 @@
+use std::io::{self, BufRead};
+
+use ahash::AHashMap as HashMap;
+
+use common::Timestamp;
+use common::event::{DataId, Event};
+
+use crate::importer::{
+    EventSink,
+    FrameEmitter,
+    ImportedFrame,
+    SyntheticHeap,
+    for_each_line,
+    invalid_data,
+    new_header
+};
+
+const THREAD: u32 = 1;
+
+/*
+    Massif only periodically takes snapshots of the heap, so there's
+    no information about individual allocations. For every snapshot
+    we make sure that for every backtrace there's exactly one live
+    allocation whose size is equal to the amount of memory which
+    massif has attributed to that backtrace.
+
+    Snapshots which don't have a heap tree only contain the total,
+    so for those we reuse the distribution from the last detailed
+    snapshot and attribute whatever doesn't fit to a fake frame.
+*/
+
+#[derive(PartialEq, Eq, Debug)]
+struct Node {
+    depth: usize,
+    children: usize,
+    size: u64,
+    label: String
+}
+
+fn parse_node( line: &str ) -> Option< Node > {
+    let trimmed = line.trim_start();
+    let depth = line.len() - trimmed.len();
+    if !trimmed.starts_with( 'n' ) {
+        return None;
+    }
+
+    let colon = trimmed.find( ':' )?;
+    let children = trimmed[ 1..colon ].parse().ok()?;
+    let rest = trimmed[ colon + 1.. ].trim_start();
+    let (size, label) = match rest.find( ' ' ) {
+        Some( index ) => (&rest[ ..index ], &rest[ index + 1.. ]),
+        None => (rest, "")
+    };
+
+    Some( Node {
+        depth,
+        children,
+        size: size.parse().ok()?,
+        label: label.to_owned()
+    })
+}
+
+/// Parses a node's label, which looks either like
+/// `0x4005BC: main (example.c:12)` or `0x4005BC: ??? (in /usr/lib/libfoo.so)`.
+fn parse_frame( label: &str ) -> ImportedFrame {
+    if !label.starts_with( "0x" ) {
+        let mut frame = ImportedFrame::new( 0 );
+        frame.function = Some( label.to_owned() );
+        return frame;
+    }
+
+    let (address, rest) = match label.find( ':' ) {
+        Some( index ) => (&label[ 2..index ], label[ index + 1.. ].trim()),
+        None => (&label[ 2.. ], "")
+    };
+
+    let mut frame = ImportedFrame::new( u64::from_str_radix( address, 16 ).unwrap_or( 0 ) );
+    let (function, location) = match rest.rfind( " (" ) {
+        Some( index ) if rest.ends_with( ')' ) => (&rest[ ..index ], Some( &rest[ index + 2..rest.len() - 1 ] )),
+        _ => (rest, None)
+    };
+
+    if !function.is_empty() && function != "???" {
+        frame.function = Some( function.to_owned() );
+    }
+
+    if let Some( location ) = location {
+        if location.starts_with( "in " ) {
+            frame.library = Some( location[ 3.. ].to_owned() );
+        } else {
+            match location.rfind( ':' ).and_then( |index| location[ index + 1.. ].parse().ok().map( |line| (index, line) ) ) {
+                Some( (index, line) ) => {
+                    frame.source = Some( location[ ..index ].to_owned() );
+                    frame.line = Some( line );
+                },
+                None => {
+                    frame.source = Some( location.to_owned() );
+                }
+            }
+        }
+    }
+
+    frame
+}
+
+#[derive(Default)]
+struct Snapshot {
+    time: u64,
+    total: u64,
+    is_detailed: bool,
+    // Every entry is a (backtrace, size) pair.
+    distribution: Vec< (u64, u64) >
+}
+
+struct MassifImporter {
+    id: DataId,
+    cmdline: String,
+    time_unit_is_ms: bool,
+    header_emitted: bool,
+    snapshot: Option< Snapshot >,
+    stack: Vec< (u32, usize) >,
+    last_distribution: Vec< (u64, u64) >,
+    live: HashMap< u64, (u64, u64) >,
+    emitter: FrameEmitter,
+    heap: SyntheticHeap
+}
+
+impl MassifImporter {
+    fn emit_header( &mut self, sink: &mut dyn EventSink ) -> io::Result< () > {
+        if self.header_emitted {
+            return Ok(());
+        }
+
+        self.header_emitted = true;
+        let executable = self.cmdline.split_whitespace().next().unwrap_or( "" ).to_owned();
+        sink.emit( Event::Header( new_header( self.id, &executable, &self.cmdline, None ) ) )
+    }
+
+    fn synthetic_backtrace( &mut self, sink: &mut dyn EventSink, label: &str ) -> io::Result< u64 > {
+        let frame = self.emitter.frame( sink, &parse_frame( label ) )?;
+        self.emitter.backtrace( sink, vec![ frame ] )
+    }
+
+    fn flush_snapshot( &mut self, sink: &mut dyn EventSink ) -> io::Result< () > {
+        let mut snapshot = match self.snapshot.take() {
+            Some( snapshot ) => snapshot,
+            None => return Ok(())
+        };
+
+        self.emit_header( sink )?;
+        if snapshot.is_detailed {
+            self.last_distribution = snapshot.distribution.clone();
+        } else {
+            snapshot.distribution = self.last_distribution.clone();
+            let known: u64 = snapshot.distribution.iter().map( |&(_, size)| size ).sum();
+            if snapshot.total > known {
+                let backtrace = self.synthetic_backtrace( sink, "[unattributed]" )?;
+                snapshot.distribution.push( (backtrace, snapshot.total - known) );
+            }
+        }
+
+        let timestamp = if self.time_unit_is_ms {
+            Timestamp::from_msecs( snapshot.time )
+        } else {
+            Timestamp::from_usecs( snapshot.time )
+        };
+
+        let mut sizes: HashMap< u64, u64 > = HashMap::new();
+        for (backtrace, size) in snapshot.distribution {
+            *sizes.entry( backtrace ).or_insert( 0 ) += size;
+        }
+
+        let mut stale: Vec< _ > = self.live.iter()
+            .filter( |&(backtrace, &(_, size))| sizes.get( backtrace ).cloned().unwrap_or( 0 ) != size )
+            .map( |(&backtrace, &(pointer, _))| (backtrace, pointer) )
+            .collect();
+        stale.sort();
+
+        for (backtrace, pointer) in stale {
+            self.heap.free( sink, timestamp, pointer, THREAD )?;
+            self.live.remove( &backtrace );
+        }
+
+        let mut sizes: Vec< _ > = sizes.into_iter().filter( |&(backtrace, size)| size != 0 && !self.live.contains_key( &backtrace ) ).collect();
+        sizes.sort();
+
+        for (backtrace, size) in sizes {
+            let pointer = self.heap.alloc( sink, timestamp, size, backtrace, THREAD )?;
+            self.live.insert( backtrace, (pointer, size) );
+        }
+
+        Ok(())
+    }
+
+    fn process_node( &mut self, sink: &mut dyn EventSink, node: Node ) -> io::Result< () > {
+        let snapshot = match self.snapshot {
+            Some( ref mut snapshot ) => snapshot,
+            None => return Err( invalid_data( "heap tree outside of a snapshot" ) )
+        };
+
+        while self.stack.last().map( |&(_, depth)| depth >= node.depth ).unwrap_or( false ) {
+            self.stack.pop();
+        }
+
+        // The root node only holds the total.
+        if node.depth == 0 {
+            return Ok(());
+        }
+
+        let frame = self.emitter.frame( sink, &parse_frame( &node.label ) )?;
+        self.stack.push( (frame, node.depth) );
+
+        if node.children == 0 && node.size != 0 {
+            // The stack starts with the function which called the allocator, so it's already ordered from the innermost frame.
+            let frames = self.stack.iter().map( |&(frame, _)| frame ).collect();
+            let backtrace = self.emitter.backtrace( sink, frames )?;
+            snapshot.distribution.push( (backtrace, node.size) );
+        }
+
+        Ok(())
+    }
+
+    fn process_line( &mut self, sink: &mut dyn EventSink, line: &str ) -> io::Result< () > {
+        if let Some( node ) = parse_node( line ) {
+            return self.process_node( sink, node );
+        }
+
+        let (key, value) = match line.find( ':' ).or_else( || line.find( '=' ) ) {
+            Some( index ) => (&line[ ..index ], line[ index + 1.. ].trim()),
+            None => return Ok(())
+        };
+
+        match key {
+            "cmd" => {
+                self.cmdline = value.to_owned();
+            },
+            "time_unit" => {
+                self.time_unit_is_ms = value == "ms";
+                if value != "ms" {
+                    warn!( "Unsupported time unit '{}'; the timestamps will be treated as microseconds", value );
+                }
+            },
+            "snapshot" => {
+                self.flush_snapshot( sink )?;
+                self.stack.clear();
+                self.snapshot = Some( Snapshot::default() );
+            },
+            "time" | "mem_heap_B" | "heap_tree" => {
+                let snapshot = match self.snapshot {
+                    Some( ref mut snapshot ) => snapshot,
+                    None => return Err( invalid_data( format!( "'{}' outside of a snapshot", key ) ) )
+                };
+
+                match key {
+                    "time" => snapshot.time = value.parse().map_err( |_| invalid_data( format!( "invalid time: '{}'", value ) ) )?,
+                    "mem_heap_B" => snapshot.total = value.parse().map_err( |_| invalid_data( format!( "invalid heap size: '{}'", value ) ) )?,
+                    _ => snapshot.is_detailed = value != "empty"
+                }
+            },
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn import_massif< R: BufRead >( input: R, id: DataId, sink: &mut dyn EventSink ) -> io::Result< () > {
+    let mut importer = MassifImporter {
+        id,
+        cmdline: String::new(),
+        time_unit_is_ms: false,
+        header_emitted: false,
+        snapshot: None,
+        stack: Vec::new(),
+        last_distribution: Vec::new(),
+        live: HashMap::new(),
+        emitter: FrameEmitter::default(),
+        heap: SyntheticHeap::new()
+    };
+
+    for_each_line( input, |line| importer.process_line( sink, line ) )?;
+    importer.flush_snapshot( sink )?;
+    importer.emit_header( sink )
+}
+
+#[test]
+fn test_parse_massif_frame() {
+    let frame = parse_frame( "0x4005BC: main (example.c:12)" );
+    assert_eq!( frame.address, 0x4005BC );
+    assert_eq!( frame.function.as_ref().map( |name| name.as_str() ), Some( "main" ) );
+    assert_eq!( frame.source.as_ref().map( |name| name.as_str() ), Some( "example.c" ) );
+    assert_eq!( frame.line, Some( 12 ) );
+
+    let frame = parse_frame( "0x4E2B1C7: ??? (in /usr/lib/libfoo.so)" );
+    assert_eq!( frame.function, None );
+    assert_eq!( frame.library.as_ref().map( |name| name.as_str() ), Some( "/usr/lib/libfoo.so" ) );
+
+    let node = parse_node( "  n0: 1024 in 3 places, all below massif's threshold (1.00%)" ).unwrap();
+    assert_eq!( node.depth, 2 );
+    assert_eq!( node.children, 0 );
+    assert_eq!( node.size, 1024 );
+}
+
+#[test]
+fn test_import_massif_backtrace() {
+    use crate::importer::{ImportFormat, import_as_data};
+
+    let input = "\
+desc: (none)
+cmd: ./example
+time_unit: ms
+#-----------
+snapshot=0
+#-----------
+time=10
+mem_heap_B=100
+mem_heap_extra_B=0
+mem_stacks_B=0
+heap_tree=detailed
+n1: 100 (heap allocation functions) malloc/new/new[], --alloc-fns, etc.
+ n1: 100 0x4005BC: allocate (example.c:5)
+  n0: 100 0x4005F0: main (example.c:12)
+";
+
+    let data = import_as_data( Some( ImportFormat::Massif ), input.as_bytes(), Vec::< &str >::new() ).unwrap();
+    let (_, allocation) = data.allocations_with_id().next().unwrap();
+    assert_eq!( allocation.size, 100 );
+
+    let interner = data.interner();
+    let functions: Vec< _ > = data.get_backtrace( allocation.backtrace )
+        .map( |(_, frame)| interner.resolve( frame.any_function().unwrap() ).unwrap() )
+        .collect();
+
+    // The allocator was called from `allocate`, so it must be the innermost (last) frame.
+    assert_eq!( functions, vec![ "main", "allocate" ] );
+}
@@ -10,6 +10,10 @@ extern crate quickcheck;
 pub mod cmd_gather;
 pub mod cmd_analyze_size;
 pub mod cmd_extract;
+pub mod cmd_analyze_churn;
+pub mod cmd_dump;
+pub mod cmd_fsck;
+pub mod cmd_info;
 
 mod filter;
 mod util;
@@ -19,6 +23,8 @@ mod reader;
 mod loader;
 mod postprocessor;
 mod squeeze;
+mod slice;
+mod strip;
 mod frame;
 mod data;
 mod io_adapter;
@@ -26,10 +32,23 @@ mod exporter_replay;
 mod exporter_heaptrack;
 mod exporter_flamegraph;
 mod exporter_flamegraph_pl;
+mod exporter_tables;
+mod exporter_sqlite;
+mod exporter_parquet;
 mod vecvec;
 mod threaded_lz4_stream;
 mod repack;
 mod timeline;
+mod churn;
+mod growth;
+mod slack;
+mod cross_thread;
+mod arena;
+mod session;
+mod importer;
+mod importer_heaptrack;
+mod importer_massif;
+mod importer_jemalloc;
 pub mod script;
 mod script_virtual;
 
@@ -40,17 +59,29 @@ pub use crate::frame::Frame;
 pub use crate::exporter_replay::export_as_replay;
 pub use crate::exporter_heaptrack::export_as_heaptrack;
 pub use crate::exporter_flamegraph_pl::export_as_flamegraph_pl;
-pub use crate::exporter_flamegraph::export_as_flamegraph;
+pub use crate::exporter_flamegraph::{export_as_flamegraph, export_as_flamegraph_by_count};
+pub use crate::exporter_sqlite::export_as_sqlite;
+pub use crate::exporter_parquet::export_as_parquet;
 pub use crate::vecvec::VecVec;
 pub use crate::util::table_to_string;
 pub use crate::postprocessor::{Anonymize, postprocess};
 pub use crate::squeeze::squeeze_data;
+pub use crate::slice::slice_data;
+pub use crate::strip::strip_data;
 pub use crate::reader::parse_events;
 pub use crate::repack::repack;
 pub use crate::script::run_script;
-pub use crate::timeline::build_timeline;
+pub use crate::timeline::{TimelinePoint, build_timeline, build_timeline_with_granularity, build_timeline_with_resolution};
+pub use crate::churn::{ChurnGroup, ChurnSortBy, LIFETIME_BUCKETS, collect_churn_groups};
+pub use crate::growth::{GrowthGroup, collect_growth_groups};
+pub use crate::slack::{SizeClassSlack, collect_slack_by_size_class};
+pub use crate::cross_thread::{ThreadPair, collect_thread_matrix};
+pub use crate::importer::{ImportFormat, import, import_as_data};
+pub use crate::session::{SessionProcess, SessionTimeline, build_process_tree, build_session_timeline};
+pub use crate::arena::{Arena, ArenaHeap, ArenaId, ArenaThread, ArenaTimeline, ArenaTimelineSeries, MAIN_ARENA, build_arena_timeline, heap_max_size};
 
 pub use common::event;
+pub use common::lz4_stream::Compression;
 
 pub use crate::filter::{
     BasicFilter,
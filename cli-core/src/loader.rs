@@ -55,6 +55,7 @@ use crate::data::{
     StringId
 };
 use crate::vecvec::DenseVecVec;
+use crate::arena::Arenas;
 use crate::reader::parse_events;
 
 #[derive(Clone, PartialEq, Eq, Default, Debug, Hash)]
@@ -82,6 +83,13 @@ fn test_clean_symbol() {
     );
 }
 
+/// Loads a data file with the given events; see `bytehound_format::test_util`.
+#[cfg(test)]
+pub(crate) fn load_test_data( events: &[Event] ) -> Data {
+    let data = bytehound_format::test_util::write_test_file( events );
+    Loader::load_from_stream_without_debug_info( io::Cursor::new( data ) ).unwrap()
+}
+
 pub struct Loader {
     id: DataId,
     header: HeaderBody,
@@ -121,7 +129,8 @@ pub struct Loader {
     maximum_backtrace_depth: u32,
     previous_backtrace_on_thread: HashMap< u32, Vec< u64 > >,
     string_id_map: HashMap< u32, StringId >,
-    last_timestamp: Timestamp
+    last_timestamp: Timestamp,
+    parent_pid: Option< u32 >
 }
 
 fn address_to_frame< F: FnMut( Frame ) >( address_space: &dyn IAddressSpace, interner: &mut StringInterner, address: u64, mut callback: F ) {
@@ -201,6 +210,9 @@ impl Loader {
             "x86_64" => Box::new( AddressSpace::< arch::amd64::Arch >::new() ),
             "mips64" => Box::new( AddressSpace::< arch::mips64::Arch >::new() ),
             "aarch64" => Box::new( AddressSpace::< arch::aarch64::Arch >::new() ),
+            // Imported profiles whose architecture isn't known either only contain already decoded
+            // frames or don't include any binaries, so there's nothing to symbolicate anyway.
+            "unknown" => Box::new( AddressSpace::< arch::amd64::Arch >::new() ),
             _ => panic!( "Unknown architecture: {}", header.arch )
         };
 
@@ -248,7 +260,8 @@ impl Loader {
             maximum_backtrace_depth: 0,
             previous_backtrace_on_thread: Default::default(),
             string_id_map: Default::default(),
-            last_timestamp: Timestamp::min()
+            last_timestamp: Timestamp::min(),
+            parent_pid: None
         };
 
         loader.update_timestamp_to_wall_clock( timestamp, wall_clock_secs, wall_clock_nsecs );
@@ -374,6 +387,7 @@ impl Loader {
         group_stats.max_size = cmp::max( group_stats.max_size, allocation.usable_size() );
         group_stats.alloc_count += 1;
         group_stats.alloc_size += allocation.usable_size();
+        group_stats.alloc_slack += allocation.extra_usable_space as u64;
 
         self.allocations.push( allocation );
         entry.or_insert( allocation_id );
@@ -417,6 +431,9 @@ impl Loader {
         let group_stats = &mut self.group_stats[ allocation.backtrace.raw() as usize ];
         group_stats.free_count += 1;
         group_stats.free_size += allocation.usable_size();
+        if allocation.thread != thread {
+            group_stats.cross_thread_free_count += 1;
+        }
 
         let op = OperationId::new_deallocation( allocation_id );
         self.operations.push( (timestamp, op) );
@@ -461,6 +478,9 @@ impl Loader {
             self.total_freed_count += 1;
             self.group_stats[ allocation.backtrace.raw() as usize ].free_count += 1;
             self.group_stats[ allocation.backtrace.raw() as usize ].free_size += allocation.usable_size();
+            if allocation.thread != thread {
+                self.group_stats[ allocation.backtrace.raw() as usize ].cross_thread_free_count += 1;
+            }
         }
 
         let reallocation = Allocation {
@@ -494,6 +514,7 @@ impl Loader {
         group_stats.max_size = cmp::max( group_stats.max_size, reallocation.usable_size() );
         group_stats.alloc_count += 1;
         group_stats.alloc_size += reallocation.usable_size();
+        group_stats.alloc_slack += reallocation.extra_usable_space as u64;
 
         self.allocations.push( reallocation );
         entry.or_insert( reallocation_id );
@@ -931,6 +952,9 @@ impl Loader {
             Event::Environ { .. } => {
                 // TODO
             },
+            Event::ProcessInfo { parent_pid } => {
+                self.parent_pid = Some( parent_pid );
+            },
             Event::WallClock { timestamp, sec, nsec } => {
                 self.update_timestamp_to_wall_clock( timestamp, sec, nsec );
             },
@@ -1107,6 +1131,8 @@ impl Loader {
 
         allocations_by_backtrace.shrink_to_fit();
 
+        let arenas = Arenas::reconstruct( self.header.pointer_size as _, &self.allocations, &sorted_by_timestamp, &self.mmap_operations );
+
         let last_timestamp = self.group_stats.iter().map( |stats| stats.last_allocation ).max().unwrap_or( initial_timestamp );
         let last_timestamp = std::cmp::max( self.last_timestamp, last_timestamp );
         Data {
@@ -1117,6 +1143,8 @@ impl Loader {
             cmdline: String::from_utf8_lossy( &self.header.cmdline ).into_owned(),
             architecture: self.header.arch,
             pointer_size: self.header.pointer_size as _,
+            pid: self.header.pid,
+            parent_pid: self.parent_pid,
             interner: self.interner.into_inner(),
             allocations: self.allocations,
             sorted_by_timestamp,
@@ -1135,7 +1163,8 @@ impl Loader {
             mmap_operations: self.mmap_operations,
             maximum_backtrace_depth: self.maximum_backtrace_depth,
             group_stats: self.group_stats,
-            chains
+            chains,
+            arenas
         }
     }
 }
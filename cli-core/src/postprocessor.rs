@@ -9,9 +9,8 @@ use string_interner::Symbol;
 use nwind::{
     DebugInfoIndex
 };
-use common::speedy::{
-    Writable,
-};
+
+use bytehound_format::Writer;
 
 use common::event::{
     Event,
@@ -19,10 +18,6 @@ use common::event::{
     HeaderBody,
 };
 
-use common::lz4_stream::{
-    Lz4Writer
-};
-
 use crate::loader::Loader;
 use crate::reader::parse_events;
 
@@ -33,7 +28,7 @@ pub enum Anonymize {
     Full
 }
 
-fn anonymize_header( anonymize: Anonymize, header: &mut HeaderBody ) {
+pub(crate) fn anonymize_header( anonymize: Anonymize, header: &mut HeaderBody ) {
     match anonymize {
         Anonymize::None => {},
         Anonymize::Partial => {
@@ -50,20 +45,20 @@ fn anonymize_header( anonymize: Anonymize, header: &mut HeaderBody ) {
     }
 }
 
-struct PathAnonymizer {
+pub(crate) struct PathAnonymizer {
     prefix: &'static str,
     counter: usize
 }
 
 impl PathAnonymizer {
-    fn new( prefix: &'static str ) -> Self {
+    pub(crate) fn new( prefix: &'static str ) -> Self {
         Self {
             prefix,
             counter: 0
         }
     }
 
-    fn anonymize< 'a >( &mut self, anonymize: Anonymize, string: &'a str ) -> Cow< 'a, str > {
+    pub(crate) fn anonymize< 'a >( &mut self, anonymize: Anonymize, string: &'a str ) -> Cow< 'a, str > {
         match anonymize {
             Anonymize::None => string.into(),
             Anonymize::Partial => {
@@ -84,12 +79,12 @@ impl PathAnonymizer {
 }
 
 #[derive(Default)]
-struct FunctionAnonymizer {
+pub(crate) struct FunctionAnonymizer {
     counter: usize
 }
 
 impl FunctionAnonymizer {
-    fn anonymize< 'a >( &mut self, anonymize: Anonymize, string: &'a str ) -> Cow< 'a, str > {
+    pub(crate) fn anonymize< 'a >( &mut self, anonymize: Anonymize, string: &'a str ) -> Cow< 'a, str > {
         if anonymize != Anonymize::Full {
             string.into()
         } else {
@@ -107,7 +102,6 @@ pub fn postprocess< F, G, D, I  >( ifp: F, ofp: G, debug_symbols: I, anonymize:
           D: AsRef< OsStr >,
           I: IntoIterator< Item = D >
 {
-    let mut ofp = Lz4Writer::new( ofp );
     let (mut header, event_stream) = parse_events( ifp )?;
 
     let mut debug_info_index = DebugInfoIndex::new();
@@ -117,7 +111,7 @@ pub fn postprocess< F, G, D, I  >( ifp: F, ofp: G, debug_symbols: I, anonymize:
 
     let mut loader = Loader::new( header.clone(), debug_info_index );
     anonymize_header( anonymize, &mut header );
-    Event::Header( header ).write_to_stream( &mut ofp )?;
+    let mut ofp = Writer::new( ofp, header )?;
 
     let mut anonymizer_library = PathAnonymizer::new( "lib_" );
     let mut anonymizer_source = PathAnonymizer::new( "src_" );
@@ -172,8 +166,9 @@ pub fn postprocess< F, G, D, I  >( ifp: F, ofp: G, debug_symbols: I, anonymize:
                     write = false;
                 }
             },
-            Event::Header( ref mut body ) => {
-                anonymize_header( anonymize, body );
+            // The headers repeated in streamed data are identical to the first one.
+            Event::Header( .. ) => {
+                write = false;
             },
             Event::MemoryDump { .. } => {
                 if anonymize != Anonymize::None {
@@ -187,13 +182,14 @@ pub fn postprocess< F, G, D, I  >( ifp: F, ofp: G, debug_symbols: I, anonymize:
                 }
             },
             Event::WallClock { .. } => {},
+            Event::ProcessInfo { .. } => {},
             Event::String { .. } => {},
             Event::DecodedFrame { .. } => {},
             Event::DecodedBacktrace { .. } => {}
         }
 
         if write {
-            event.write_to_stream( &mut ofp )?;
+            ofp.write_event( &event )?;
         }
 
         if is_backtrace {
@@ -221,10 +217,10 @@ pub fn postprocess< F, G, D, I  >( ifp: F, ofp: G, debug_symbols: I, anonymize:
                             if !emitted_strings.contains( &id ) {
                                 emitted_strings.insert( id );
                                 let string = loader.interner().resolve( id ).unwrap();
-                                Event::String {
+                                ofp.write_event( &Event::String {
                                     id: raw_id,
                                     string: $anonymizer.anonymize( anonymize, string )
-                                }.write_to_stream( &mut ofp )?;
+                                })?;
                             }
 
                             raw_id
@@ -250,7 +246,7 @@ pub fn postprocess< F, G, D, I  >( ifp: F, ofp: G, debug_symbols: I, anonymize:
                 assert_eq!( frame_id, expected_frame_id );
                 expected_frame_id += 1;
 
-                Event::DecodedFrame {
+                ofp.write_event( &Event::DecodedFrame {
                     address: frame.address().raw(),
                     library,
                     raw_function,
@@ -259,21 +255,22 @@ pub fn postprocess< F, G, D, I  >( ifp: F, ofp: G, debug_symbols: I, anonymize:
                     line: frame.line().unwrap_or( 0xFFFFFFFF ),
                     column: frame.column().unwrap_or( 0xFFFFFFFF ),
                     is_inline: frame.is_inline()
-                }.write_to_stream( &mut ofp )?;
+                })?;
             }
 
             if let Some( backtrace_id ) = backtrace_id {
                 assert_eq!( backtrace_id.raw(), expected_backtrace_id );
                 expected_backtrace_id += 1;
 
-                Event::DecodedBacktrace {
+                ofp.write_event( &Event::DecodedBacktrace {
                     frames: (&frames).into()
-                }.write_to_stream( &mut ofp )?;
+                })?;
             }
         } else if process {
             loader.process( event );
         }
     }
 
+    ofp.finish()?;
     Ok(())
 }
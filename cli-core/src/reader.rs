@@ -2,7 +2,8 @@ use std::io::{self, Read};
 
 use common::event::{
     Event,
-    HeaderBody
+    HeaderBody,
+    FORMAT_VERSION
 };
 
 use common::speedy::Readable;
@@ -50,6 +51,10 @@ pub fn parse_events< T >( fp: T ) -> io::Result< (HeaderBody, impl Iterator< Ite
         }
     };
 
+    if header.format_version() > FORMAT_VERSION {
+        return Err( io::Error::new( io::ErrorKind::InvalidData, format!( "data file was written using a newer format version ({}); the newest supported version is {}", header.format_version(), FORMAT_VERSION ) ) );
+    }
+
     let iter = Iter { fp, done: false };
     Ok( (header, iter) )
 }
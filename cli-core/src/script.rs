@@ -2,7 +2,7 @@ use std::cell::Cell;
 use std::path::{Path, PathBuf};
 use std::fs::File;
 use std::sync::Arc;
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize};
 use std::fmt::Write;
 use ahash::AHashMap as HashMap;
 use ahash::AHashSet as HashSet;
@@ -13,7 +13,9 @@ use crate::{AllocationId, BacktraceId, Data, Loader};
 use crate::data::OperationId;
 use crate::exporter_flamegraph_pl::dump_collation_from_iter;
 use crate::filter::{BasicFilter, Duration, Filter, NumberOrFractionOfTotal};
+use crate::growth::collect_growth_groups;
 use crate::timeline::build_timeline;
+use crate::util::write_frame;
 
 pub use rhai;
 pub use crate::script_virtual::VirtualEnvironment;
@@ -201,25 +203,7 @@ impl Backtrace {
             is_first = false;
 
             write!( fmt, "#{:02}", index )?;
-            if let Some( library ) = frame.library() {
-                write!( fmt, " [{}]", interner.resolve( library ).unwrap() )?;
-            }
-            if let Some( function ) = function {
-                write!( fmt, " {}", function )?;
-            } else {
-                write!( fmt, " {:0x}", frame.address().raw() )?;
-            }
-            if let Some( source ) = frame.source() {
-                let mut source = interner.resolve( source ).unwrap();
-                if let Some( index ) = source.rfind( "/" ) {
-                    source = &source[ index + 1.. ];
-                }
-                write!( fmt, " [{}", source )?;
-                if let Some( line ) = frame.line() {
-                    write!( fmt, ":{}", line )?;
-                }
-                write!( fmt, "]" )?;
-            }
+            write_frame( &mut fmt, interner, frame )?;
 
             if self.strip {
                 if let Some( function ) = function {
@@ -487,6 +471,37 @@ impl AllocationList {
         Ok( self.clone() )
     }
 
+    fn save_as_sqlite( &mut self, env: &mut dyn Environment, path: String ) -> Result< Self, Box< rhai::EvalAltResult > > {
+        self.apply_filter();
+
+        // SQLite can only write to a real file, so go through a temporary one.
+        static COUNTER: AtomicUsize = AtomicUsize::new( 0 );
+        let tmp_path = std::env::temp_dir().join( format!( "bytehound-{}-{}.sqlite", std::process::id(), COUNTER.fetch_add( 1, std::sync::atomic::Ordering::SeqCst ) ) );
+
+        let ids = self.unfiltered_allocation_ids().iter().copied();
+        let result = crate::exporter_sqlite::export_ids_as_sqlite( &self.data, &tmp_path, ids ).and_then( |_| std::fs::read( &tmp_path ) );
+        let _ = std::fs::remove_file( &tmp_path );
+
+        let contents = result.map_err( |err| error( format!( "failed to export to SQLite: {}", err ) ) )?;
+        env.file_write( &path, FileKind::Binary, &contents )?;
+        Ok( self.clone() )
+    }
+
+    fn save_as_parquet( &mut self, env: &mut dyn Environment, path: String ) -> Result< Self, Box< rhai::EvalAltResult > > {
+        self.apply_filter();
+
+        let ids = self.unfiltered_allocation_ids().iter().copied();
+        let tables = crate::exporter_parquet::export_ids_as_parquet_in_memory( &self.data, ids )
+            .map_err( |err| error( format!( "failed to export to Parquet: {}", err ) ) )?;
+
+        env.mkdir_p( &path )?;
+        for (name, contents) in tables {
+            env.file_write( &format!( "{}/{}.parquet", path, name ), FileKind::Binary, &contents )?;
+        }
+
+        Ok( self.clone() )
+    }
+
     fn save_as_graph( &self, env: &mut dyn Environment, path: String ) -> Result< Self, Box< rhai::EvalAltResult > > {
         Graph::new().add( self.clone() ).save( env, path )?;
         Ok( self.clone() )
@@ -523,7 +538,8 @@ impl AllocationList {
         #[derive(Default)]
         struct Group {
             allocation_ids: Vec< AllocationId >,
-            size: u64
+            size: u64,
+            slack: u64
         }
 
         self.apply_filter();
@@ -532,6 +548,7 @@ impl AllocationList {
             let allocation = self.data.get_allocation( id );
             let group = groups.entry( allocation.backtrace ).or_insert_with( || Group::default() );
             group.size += allocation.size;
+            group.slack += allocation.extra_usable_space as u64;
             group.allocation_ids.push( id );
         }
 
@@ -540,7 +557,37 @@ impl AllocationList {
             groups: Arc::new( groups.into_iter().map( |(_, group)| {
                 AllocationGroupInner {
                     allocation_ids: Arc::new( group.allocation_ids ),
-                    size: group.size
+                    size: group.size,
+                    slack: group.slack
+                }
+            }).collect() )
+        }
+    }
+
+    fn group_by_growth_pattern( &mut self ) -> AllocationGroupList {
+        self.apply_filter();
+        let groups = collect_growth_groups( &self.data, self.unfiltered_allocation_ids().iter().copied() );
+
+        AllocationGroupList {
+            data: self.data.clone(),
+            groups: Arc::new( groups.into_iter().map( |group| {
+                let mut allocation_ids = Vec::new();
+                let mut size = 0;
+                let mut slack = 0;
+                for first in group.chains {
+                    for id in std::iter::successors( Some( first ), |&current| self.data.get_allocation( current ).reallocation ) {
+                        let allocation = self.data.get_allocation( id );
+                        size += allocation.size;
+                        slack += allocation.extra_usable_space as u64;
+                        allocation_ids.push( id );
+                    }
+                }
+
+                allocation_ids.sort_unstable();
+                AllocationGroupInner {
+                    allocation_ids: Arc::new( allocation_ids ),
+                    size,
+                    slack
                 }
             }).collect() )
         }
@@ -560,7 +607,8 @@ impl AllocationList {
 #[derive(Clone)]
 struct AllocationGroupInner {
     allocation_ids: Arc< Vec< AllocationId > >,
-    size: u64
+    size: u64,
+    slack: u64
 }
 
 struct AllocationGroupListIter {
@@ -652,6 +700,14 @@ impl AllocationGroupList {
         self.sort_by_key( |group| !group.size )
     }
 
+    fn sort_by_slack_ascending( &mut self ) -> AllocationGroupList {
+        self.sort_by_key( |group| group.slack )
+    }
+
+    fn sort_by_slack_descending( &mut self ) -> AllocationGroupList {
+        self.sort_by_key( |group| !group.slack )
+    }
+
     fn sort_by_count_ascending( &mut self ) -> AllocationGroupList {
         self.sort_by_key( |group| group.allocation_ids.len() )
     }
@@ -701,7 +757,8 @@ enum GraphKind {
     MemoryUsage,
     LiveAllocations,
     NewAllocations,
-    Deallocations
+    Deallocations,
+    Slack
 }
 
 #[derive(Clone)]
@@ -741,7 +798,8 @@ fn prepare_graph_datapoints( data: &Data, ops_for_list: &[Vec< OperationId >], k
                 GraphKind::MemoryUsage => point.memory_usage,
                 GraphKind::LiveAllocations => point.allocations,
                 GraphKind::NewAllocations => point.allocations_per_time,
-                GraphKind::Deallocations => point.deallocations_per_time
+                GraphKind::Deallocations => point.deallocations_per_time,
+                GraphKind::Slack => point.slack
             };
             (x, y)
         }).collect();
@@ -903,6 +961,13 @@ impl Graph {
         cloned
     }
 
+    fn show_slack( &mut self ) -> Self {
+        let mut cloned = self.clone();
+        cloned.kind = GraphKind::Slack;
+        cloned.cached_datapoints = None;
+        cloned
+    }
+
     fn generate_ops( &mut self ) -> Result< Vec< Vec< OperationId > >, String > {
         let lists = &mut self.lists;
         if lists.is_empty() {
@@ -1031,7 +1096,7 @@ impl Graph {
                         format!( "{}", value )
                     } else {
                         match KIND.with( |cell| cell.get() ) {
-                            GraphKind::MemoryUsage => {
+                            GraphKind::MemoryUsage | GraphKind::Slack => {
                                 let (unit, multiplier) = {
                                     if max < 1024 * 1024 {
                                         ("KB", 1024)
@@ -1208,7 +1273,8 @@ impl Graph {
                 GraphKind::MemoryUsage => "Memory usage",
                 GraphKind::LiveAllocations => "Live allocations",
                 GraphKind::NewAllocations => "New allocations",
-                GraphKind::Deallocations => "Deallocations"
+                GraphKind::Deallocations => "Deallocations",
+                GraphKind::Slack => "Allocator slack"
             };
             mesh = mesh.x_desc( "Time" ).y_desc( label );
         }
@@ -1465,7 +1531,8 @@ pub fn error( message: impl Into< String > ) -> Box< rhai::EvalAltResult > {
 
 #[derive(Copy, Clone)]
 pub enum FileKind {
-    Svg
+    Svg,
+    Binary
 }
 
 pub struct Engine {
@@ -1476,7 +1543,25 @@ pub struct Engine {
 pub struct EngineArgs {
     pub argv: Vec< String >,
     pub data: Option< Arc< Data > >,
-    pub allocation_ids: Option< Arc< Vec< AllocationId > > >
+    pub allocation_ids: Option< Arc< Vec< AllocationId > > >,
+    pub limits: ScriptLimits,
+    /// When set to `true` the script will be terminated.
+    pub cancel: Option< Arc< AtomicBool > >,
+    /// Updated with the number of operations the script has executed so far.
+    pub progress: Option< Arc< AtomicU64 > >
+}
+
+/// Limits on what a single script can do; `None` means no limit.
+///
+/// These are only checked between the script's operations, so a single
+/// call to a native function (e.g. a filter over millions of allocations)
+/// can still run past them.
+#[derive(Clone, Default, Debug)]
+pub struct ScriptLimits {
+    pub max_operations: Option< u64 >,
+    pub max_duration: Option< std::time::Duration >,
+    /// The maximum size of all of the files and the output which the script can generate; only enforced by the `VirtualEnvironment`.
+    pub max_output_size: Option< usize >
 }
 
 pub trait Environment {
@@ -1626,6 +1711,35 @@ impl Engine {
 
         let argv = args.argv;
 
+        if let Some( max_operations ) = args.limits.max_operations {
+            engine.set_max_operations( max_operations );
+        }
+
+        let deadline = args.limits.max_duration.map( |duration| std::time::Instant::now() + duration );
+        let cancel = args.cancel;
+        let progress = args.progress;
+        if deadline.is_some() || cancel.is_some() || progress.is_some() {
+            engine.on_progress( move |operations| {
+                if let Some( ref progress ) = progress {
+                    progress.store( operations, std::sync::atomic::Ordering::Relaxed );
+                }
+
+                if let Some( ref cancel ) = cancel {
+                    if cancel.load( std::sync::atomic::Ordering::Relaxed ) {
+                        return Some( "the script was cancelled".into() );
+                    }
+                }
+
+                if let Some( deadline ) = deadline {
+                    if operations % 256 == 0 && std::time::Instant::now() > deadline {
+                        return Some( "the script took too long to execute".into() );
+                    }
+                }
+
+                None
+            });
+        }
+
         // Utility functions.
         engine.register_fn( "dirname", dirname );
         engine.register_fn( "h", |value: i64| Duration::from_secs( value as u64 * 3600 ) );
@@ -1697,6 +1811,7 @@ impl Engine {
         engine.register_fn( "show_live_allocations", Graph::show_live_allocations );
         engine.register_fn( "show_new_allocations", Graph::show_new_allocations );
         engine.register_fn( "show_deallocations", Graph::show_deallocations );
+        engine.register_fn( "show_slack", Graph::show_slack );
 
         engine.register_result_fn( "with_gradient_color_scheme", Graph::with_gradient_color_scheme );
         engine.register_fn( "allocations", DataRef::allocations );
@@ -1935,6 +2050,8 @@ impl Engine {
         register_filter!( only_ptmalloc_not_from_main_arena, bool );
         register_filter!( only_jemalloc, bool );
         register_filter!( only_not_jemalloc, bool );
+        register_filter!( only_freed_on_other_thread, bool );
+        register_filter!( only_freed_on_same_thread, bool );
 
         engine.register_fn( "only_with_marker", |list: &mut AllocationList, value: i64| {
             list.add_filter_once( |filter| filter.only_with_marker.is_some(), |filter|
@@ -1942,7 +2059,20 @@ impl Engine {
             )
         });
 
+        engine.register_fn( "only_from_thread", |list: &mut AllocationList, value: i64| {
+            list.add_filter_once( |filter| filter.only_from_thread.is_some(), |filter|
+                filter.only_from_thread = Some( value as u32 )
+            )
+        });
+
+        engine.register_fn( "only_from_arena", |list: &mut AllocationList, value: i64| {
+            list.add_filter_once( |filter| filter.only_from_arena.is_some(), |filter|
+                filter.only_from_arena = Some( value as u32 )
+            )
+        });
+
         engine.register_fn( "group_by_backtrace", AllocationList::group_by_backtrace );
+        engine.register_fn( "group_by_growth_pattern", AllocationList::group_by_growth_pattern );
 
         engine.register_fn( "only_all_leaked", AllocationGroupList::only_all_leaked );
         engine.register_fn( "only_count_at_least", AllocationGroupList::only_count_at_least );
@@ -1950,6 +2080,9 @@ impl Engine {
         engine.register_fn( "sort_by_size_ascending", AllocationGroupList::sort_by_size_ascending );
         engine.register_fn( "sort_by_size_descending", AllocationGroupList::sort_by_size_descending );
         engine.register_fn( "sort_by_size", AllocationGroupList::sort_by_size_descending );
+        engine.register_fn( "sort_by_slack_ascending", AllocationGroupList::sort_by_slack_ascending );
+        engine.register_fn( "sort_by_slack_descending", AllocationGroupList::sort_by_slack_descending );
+        engine.register_fn( "sort_by_slack", AllocationGroupList::sort_by_slack_descending );
         engine.register_fn( "sort_by_count_ascending", AllocationGroupList::sort_by_count_ascending );
         engine.register_fn( "sort_by_count_descending", AllocationGroupList::sort_by_count_descending );
         engine.register_fn( "sort_by_count", AllocationGroupList::sort_by_count_descending );
@@ -2078,6 +2211,20 @@ impl Engine {
                 move |list: &mut AllocationList, path: String| AllocationList::save_as_graph( list, &mut *env.lock(), path )
             );
         }
+        {
+            let env = env.clone();
+            engine.register_result_fn(
+                "save_as_sqlite",
+                move |list: &mut AllocationList, path: String| AllocationList::save_as_sqlite( list, &mut *env.lock(), path )
+            );
+        }
+        {
+            let env = env.clone();
+            engine.register_result_fn(
+                "save_as_parquet",
+                move |list: &mut AllocationList, path: String| AllocationList::save_as_parquet( list, &mut *env.lock(), path )
+            );
+        }
         {
             let env = env.clone();
             let graph_counter = graph_counter.clone();
@@ -2168,8 +2315,14 @@ impl Engine {
             },
             Err( error ) => {
                 let p = error.position();
+                let message = match *error {
+                    rhai::EvalAltResult::ErrorTerminated( ref reason, _ ) => reason.to_string(),
+                    rhai::EvalAltResult::ErrorTooManyOperations( _ ) => "the script has exceeded the maximum number of operations".into(),
+                    _ => error.to_string()
+                };
+
                 Err( EvalError {
-                    message: error.to_string(),
+                    message,
                     line: p.line(),
                     column: p.position()
                 })
@@ -2185,6 +2338,52 @@ pub struct EvalError {
     pub column: Option< usize >
 }
 
+#[test]
+fn test_script_limits() {
+    let run = |args: EngineArgs| {
+        let env = Arc::new( Mutex::new( VirtualEnvironment::new() ) );
+        Engine::new( env, args ).run( "let x = 0; loop { x += 1; }" ).unwrap_err().message
+    };
+
+    let message = run( EngineArgs {
+        limits: ScriptLimits { max_operations: Some( 1000 ), ..ScriptLimits::default() },
+        ..EngineArgs::default()
+    });
+    assert!( message.contains( "maximum number of operations" ) );
+
+    let message = run( EngineArgs {
+        limits: ScriptLimits { max_duration: Some( std::time::Duration::from_millis( 10 ) ), ..ScriptLimits::default() },
+        ..EngineArgs::default()
+    });
+    assert!( message.contains( "took too long" ) );
+
+    let message = run( EngineArgs {
+        cancel: Some( Arc::new( AtomicBool::new( true ) ) ),
+        ..EngineArgs::default()
+    });
+    assert!( message.contains( "cancelled" ) );
+}
+
+/// Evaluates a filter expression which should return an `AllocationList`, e.g. `allocations().only_leaked()`,
+/// and returns the IDs of the matched allocations.
+pub fn evaluate_filter( data: Arc< Data >, code: &str ) -> Result< Vec< AllocationId >, EvalError > {
+    let args = EngineArgs {
+        data: Some( data ),
+        .. EngineArgs::default()
+    };
+
+    let env = Arc::new( Mutex::new( VirtualEnvironment::new() ) );
+    let engine = Engine::new( env, args );
+    match engine.run( code )? {
+        Some( mut list ) => Ok( list.allocation_ids().to_vec() ),
+        None => Err( EvalError {
+            message: "the filter expression didn't evaluate to an allocation list".into(),
+            line: None,
+            column: None
+        })
+    }
+}
+
 pub fn run_script( path: &Path, data_path: Option< &Path >, argv: Vec< String > ) -> Result< (), std::io::Error > {
     let mut args = EngineArgs {
         argv,
@@ -2297,6 +2496,15 @@ pub fn run_script_slave( data_path: Option< &Path > ) -> Result< (), std::io::Er
                                 "data": &data[..]
                             }};
 
+                            println!( "{}", serde_json::to_string( &payload ).unwrap() );
+                        },
+                        ScriptOutputKind::File { path, data } => {
+                            let payload = serde_json::json! {{
+                                "kind": "file",
+                                "path": path,
+                                "data": &data[..]
+                            }};
+
                             println!( "{}", serde_json::to_string( &payload ).unwrap() );
                         }
                     }
@@ -2558,6 +2766,8 @@ impl ToCode for BasicFilter {
             only_group_leaked_allocations_at_most
 
             only_with_marker
+            only_from_thread
+            only_from_arena
         }
 
         out_bool! {
@@ -2570,6 +2780,8 @@ impl ToCode for BasicFilter {
             only_ptmalloc_not_from_main_arena
             only_jemalloc
             only_not_jemalloc
+            only_freed_on_other_thread
+            only_freed_on_same_thread
         }
     }
 }
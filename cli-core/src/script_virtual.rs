@@ -162,12 +162,19 @@ pub enum ScriptOutputKind {
     Image {
         path: String,
         data: Arc< Vec< u8 > >
+    },
+    File {
+        path: String,
+        data: Arc< Vec< u8 > >
     }
 }
 
 pub struct VirtualEnvironment {
     cwd: String,
     root: Arc< Node >,
+    max_size: Option< usize >,
+    size: usize,
+    is_truncated: bool,
     pub output: Vec< ScriptOutputKind >
 }
 
@@ -180,10 +187,30 @@ impl VirtualEnvironment {
                 name: "".into(),
                 kind: Mutex::new( NodeKind::Directory( Default::default() ) )
             }),
+            max_size: None,
+            size: 0,
+            is_truncated: false,
             output: Default::default()
         }
     }
 
+    /// Creates a new environment in which the total size of the written files
+    /// and of the printed output cannot exceed `max_size` bytes.
+    pub fn with_size_limit( max_size: usize ) -> Self {
+        let mut env = Self::new();
+        env.max_size = Some( max_size );
+        env
+    }
+
+    /// The total size of the written files and of the printed output.
+    pub fn size( &self ) -> usize {
+        self.size
+    }
+
+    fn fits( &self, size: usize ) -> bool {
+        self.max_size.map( |max_size| size <= max_size ).unwrap_or( true )
+    }
+
     fn normalize_path( &self, mut path: &str ) -> String {
         if path == "/" {
             return path.into();
@@ -209,6 +236,17 @@ impl VirtualEnvironment {
 
 impl crate::script::Environment for VirtualEnvironment {
     fn println( &mut self, message: &str ) {
+        if self.is_truncated {
+            return;
+        }
+
+        if !self.fits( self.size + message.len() ) {
+            self.is_truncated = true;
+            self.output.push( ScriptOutputKind::PrintLine( "(output truncated: the output size limit was reached)".into() ) );
+            return;
+        }
+
+        self.size += message.len();
         self.output.push( ScriptOutputKind::PrintLine( message.into() ) );
     }
 
@@ -254,6 +292,18 @@ impl crate::script::Environment for VirtualEnvironment {
                 directory.get_or_create_file( filename )?
             };
 
+        let old_size = match *child.kind.lock() {
+            NodeKind::File( ref old_contents ) => old_contents.len(),
+            NodeKind::Directory( .. ) => 0
+        };
+
+        let new_size = self.size - old_size + contents.len();
+        if !self.fits( new_size ) {
+            return Err( crate::script::error( format!( "failed to write \"{}\": the output size limit was reached", path ) ) );
+        }
+
+        self.size = new_size;
+
         let contents: Vec< u8 > = contents.into();
         let contents = Arc::new( contents );
         *child.kind.lock() = NodeKind::File( contents.clone() );
@@ -265,9 +315,35 @@ impl crate::script::Environment for VirtualEnvironment {
                     path,
                     data: contents
                 });
+            },
+            FileKind::Binary => {
+                self.output.push( ScriptOutputKind::File {
+                    path,
+                    data: contents
+                });
             }
         }
 
         Ok(())
     }
 }
+
+#[test]
+fn test_size_limit() {
+    use crate::script::{Environment, FileKind};
+
+    let mut env = VirtualEnvironment::with_size_limit( 10 );
+    env.file_write( "/a", FileKind::Binary, b"12345678" ).unwrap();
+    assert!( env.file_write( "/b", FileKind::Binary, b"123" ).is_err() );
+
+    // Overwriting a file only counts the difference.
+    env.file_write( "/a", FileKind::Binary, b"1234567890" ).unwrap();
+    assert_eq!( env.size(), 10 );
+
+    // Every successful write of a binary file is surfaced.
+    assert_eq!( env.output.len(), 2 );
+
+    env.println( "x" );
+    env.println( "y" );
+    assert_eq!( env.output.len(), 3 );
+}
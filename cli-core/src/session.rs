@@ -0,0 +1,170 @@
+use std::cmp::max;
+
+use crate::data::{Allocation, AllocationId, Data, Operation, Timestamp};
+
+/// A single process of a session, which is a set of recordings
+/// made by the processes of a single process tree.
+#[derive(Clone, Debug)]
+pub struct SessionProcess {
+    /// The index of the process' data in the slice the session was built from.
+    pub index: usize,
+    pub pid: u32,
+    pub parent_pid: Option< u32 >,
+    /// The index of the parent, if its data is also a part of the session.
+    pub parent: Option< usize >,
+    pub depth: u32,
+    /// When the process was started (or forked off); this is a wall clock time.
+    pub started_at: Timestamp,
+    pub finished_at: Timestamp
+}
+
+/// Finds the parent of every process, given their PIDs, parent PIDs and start times.
+fn resolve_parents( processes: &[(u32, Option< u32 >, Timestamp)] ) -> Vec< Option< usize > > {
+    processes.iter().enumerate().map( |(index, &(_, parent_pid, started_at))| {
+        let parent_pid = parent_pid?;
+
+        // The PIDs can be reused, so pick the most recent process which was already running when the child was started.
+        processes.iter().enumerate()
+            .filter( |&(parent_index, &(pid, _, parent_started_at))| parent_index != index && pid == parent_pid && parent_started_at <= started_at )
+            .max_by_key( |&(_, &(_, _, parent_started_at))| parent_started_at )
+            .map( |(parent_index, _)| parent_index )
+    }).collect()
+}
+
+/// Figures out the process hierarchy of a set of recordings.
+///
+/// The processes are returned in a depth-first order, with the children
+/// of every process sorted by the time they were started at.
+pub fn build_process_tree( datasets: &[&Data] ) -> Vec< SessionProcess > {
+    let processes: Vec< _ > = datasets.iter().map( |data| (data.pid(), data.parent_pid(), data.initial_timestamp()) ).collect();
+    let parents = resolve_parents( &processes );
+
+    let mut roots: Vec< usize > = (0..datasets.len()).filter( |&index| parents[ index ].is_none() ).collect();
+    roots.sort_by_key( |&index| datasets[ index ].initial_timestamp() );
+
+    let mut output = Vec::with_capacity( datasets.len() );
+    let mut stack: Vec< (usize, u32) > = roots.into_iter().rev().map( |index| (index, 0) ).collect();
+    while let Some( (index, depth) ) = stack.pop() {
+        let data = datasets[ index ];
+        output.push( SessionProcess {
+            index,
+            pid: data.pid(),
+            parent_pid: data.parent_pid(),
+            parent: parents[ index ],
+            depth,
+            started_at: data.initial_timestamp(),
+            finished_at: data.last_timestamp()
+        });
+
+        let mut children: Vec< usize > = (0..datasets.len()).filter( |&child| parents[ child ] == Some( index ) ).collect();
+        children.sort_by_key( |&child| datasets[ child ].initial_timestamp() );
+        stack.extend( children.into_iter().rev().map( |child| (child, depth + 1) ) );
+    }
+
+    output
+}
+
+#[derive(Clone, Debug)]
+pub struct SessionTimeline {
+    pub timestamps: Vec< Timestamp >,
+    /// The memory usage of every process, in the same order as the datasets.
+    pub usage: Vec< Vec< u64 > >
+}
+
+fn build_usage_series< F >( data: &Data, start: Timestamp, granularity: u64, length: usize, filter: F ) -> Vec< u64 >
+    where F: Fn( AllocationId, &Allocation ) -> bool
+{
+    let bucket_of = |timestamp: Timestamp| {
+        let bucket = (timestamp.as_usecs().saturating_sub( start.as_usecs() ) / granularity) as usize;
+        std::cmp::min( bucket, length - 1 )
+    };
+
+    let mut series = vec![ 0; length ];
+    let mut current: i64 = 0;
+    let mut filled = bucket_of( data.initial_timestamp() );
+    for op in data.operations() {
+        let (timestamp, delta) = match op {
+            Operation::Allocation { allocation, allocation_id } => {
+                let delta = if filter( allocation_id, allocation ) { allocation.size as i64 } else { 0 };
+                (allocation.timestamp, delta)
+            },
+            Operation::Deallocation { allocation, allocation_id, deallocation } => {
+                let delta = if filter( allocation_id, allocation ) { -(allocation.size as i64) } else { 0 };
+                (deallocation.timestamp, delta)
+            },
+            Operation::Reallocation { allocation_id, new_allocation, old_allocation, .. } => {
+                let mut delta = 0;
+                if filter( allocation_id, new_allocation ) {
+                    delta += new_allocation.size as i64;
+                }
+
+                let old_allocation_id = new_allocation.reallocated_from.unwrap();
+                if filter( old_allocation_id, old_allocation ) {
+                    delta -= old_allocation.size as i64;
+                }
+
+                (new_allocation.timestamp, delta)
+            }
+        };
+
+        let bucket = bucket_of( timestamp );
+        while filled < bucket {
+            series[ filled ] = max( series[ filled ], max( current, 0 ) as u64 );
+            filled += 1;
+        }
+
+        current += delta;
+        series[ bucket ] = max( series[ bucket ], max( current, 0 ) as u64 );
+    }
+
+    // Once the process is gone so is all of its memory.
+    let last = bucket_of( data.last_timestamp() );
+    while filled <= last {
+        series[ filled ] = max( series[ filled ], max( current, 0 ) as u64 );
+        filled += 1;
+    }
+
+    series
+}
+
+/// Builds a timeline of the memory usage of every process of a session on a shared time axis,
+/// so that the processes can be stacked on top of each other.
+///
+/// The `filter` is called with the index of the process to which a given allocation belongs.
+pub fn build_session_timeline< F >( datasets: &[&Data], filter: F ) -> SessionTimeline
+    where F: Fn( usize, AllocationId, &Allocation ) -> bool + Sync
+{
+    use rayon::prelude::*;
+
+    let start = match datasets.iter().map( |data| data.initial_timestamp() ).min() {
+        Some( start ) => start,
+        None => return SessionTimeline { timestamps: Vec::new(), usage: Vec::new() }
+    };
+
+    let end = datasets.iter().map( |data| data.last_timestamp() ).max().unwrap();
+    let granularity = max( (end - start).as_usecs() / 1000, 1 );
+    let length = ((end - start).as_usecs() / granularity) as usize + 1;
+
+    let usage = datasets.par_iter().enumerate().map( |(index, data)| {
+        build_usage_series( data, start, granularity, length, |id, allocation| filter( index, id, allocation ) )
+    }).collect();
+
+    SessionTimeline {
+        timestamps: (0..length).map( |bucket| start + Timestamp::from_usecs( bucket as u64 * granularity ) ).collect(),
+        usage
+    }
+}
+
+#[test]
+fn test_resolve_parents() {
+    let processes = [
+        (100, Some( 1 ), Timestamp::from_secs( 0 )),
+        (200, Some( 100 ), Timestamp::from_secs( 5 )),
+        // Same PID as the first one, but started later.
+        (100, Some( 1 ), Timestamp::from_secs( 10 )),
+        (300, Some( 100 ), Timestamp::from_secs( 15 )),
+        (400, None, Timestamp::from_secs( 20 ))
+    ];
+
+    assert_eq!( resolve_parents( &processes ), vec![ None, Some( 0 ), None, Some( 2 ), None ] );
+}
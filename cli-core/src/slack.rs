@@ -0,0 +1,83 @@
+use std::cmp::{max, min};
+use ahash::AHashMap as HashMap;
+use crate::data::{Allocation, AllocationId};
+
+#[derive(Clone, Debug)]
+pub struct SizeClassSlack {
+    pub usable_size: u64,
+    pub count: u64,
+    pub requested_size: u64,
+    pub min_requested_size: u64,
+    pub max_requested_size: u64,
+    pub slack: u64
+}
+
+/// Groups the given allocations by their usable size (which roughly
+/// corresponds to the allocator's size classes) and sums up how much
+/// memory was wasted in each of them.
+///
+/// The size classes are sorted by their total slack, in descending order.
+pub fn collect_slack_by_size_class< 'a >( allocations: impl Iterator< Item = (AllocationId, &'a Allocation) > ) -> Vec< SizeClassSlack > {
+    let mut size_classes: HashMap< u64, SizeClassSlack > = HashMap::new();
+    for (_, allocation) in allocations {
+        let usable_size = allocation.usable_size();
+        let size_class = size_classes.entry( usable_size ).or_insert_with( || SizeClassSlack {
+            usable_size,
+            count: 0,
+            requested_size: 0,
+            min_requested_size: !0,
+            max_requested_size: 0,
+            slack: 0
+        });
+
+        size_class.count += 1;
+        size_class.requested_size += allocation.size;
+        size_class.min_requested_size = min( size_class.min_requested_size, allocation.size );
+        size_class.max_requested_size = max( size_class.max_requested_size, allocation.size );
+        size_class.slack += allocation.extra_usable_space as u64;
+    }
+
+    let mut size_classes: Vec< _ > = size_classes.into_iter().map( |(_, size_class)| size_class ).collect();
+    size_classes.sort_by( |a, b| {
+        b.slack.cmp( &a.slack )
+            .then_with( || a.usable_size.cmp( &b.usable_size ) )
+    });
+
+    size_classes
+}
+
+#[test]
+fn test_collect_slack_by_size_class() {
+    use common::Timestamp;
+    use common::event::{self, AllocBody, Event};
+
+    let sizes = [(10, 6), (12, 4), (16, 0), (20, 12), (30, 2), (64, 0)];
+    let mut events = vec![ Event::Backtrace { id: 1, addresses: vec![ 0x1000 ].into() } ];
+    for (index, &(size, extra_usable_space)) in sizes.iter().enumerate() {
+        let index = index as u64 + 1;
+        events.push( Event::AllocEx {
+            id: event::AllocationId { thread: 1, allocation: index },
+            timestamp: Timestamp::from_usecs( index ),
+            allocation: AllocBody {
+                pointer: 0x1000 * index,
+                size,
+                backtrace: 1,
+                thread: 1,
+                flags: 0,
+                extra_usable_space,
+                preceding_free_space: 0
+            }
+        });
+    }
+
+    let data = crate::loader::load_test_data( &events );
+    let size_classes: Vec< _ > = collect_slack_by_size_class( data.allocations_with_id() ).into_iter().map( |size_class| {
+        (size_class.usable_size, size_class.count, size_class.requested_size, size_class.min_requested_size, size_class.max_requested_size, size_class.slack)
+    }).collect();
+
+    assert_eq!( size_classes, vec![
+        (32, 2, 50, 20, 30, 14),
+        (16, 3, 38, 10, 16, 10),
+        (64, 1, 64, 64, 64, 0)
+    ]);
+}
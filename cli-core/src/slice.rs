@@ -0,0 +1,396 @@
+use std::io::{self, Read, Write};
+
+use ahash::AHashMap as HashMap;
+
+use bytehound_format::Writer;
+
+use common::Timestamp;
+use common::event::{
+    Event,
+    AllocBody,
+    AllocationId
+};
+
+use crate::reader::parse_events;
+
+/*
+    Only the events which happened inside of the window are copied verbatim.
+
+    Everything which was still alive when the window starts (allocations,
+    memory maps, the effects of `mallopt` calls and the current marker)
+    gets emitted as synthetic events timestamped at the very start of
+    the window, so that the resulting file is self-contained.
+
+    Events without a timestamp (backtraces, files, strings, etc.) are always
+    copied since we can't cheaply know whether they'll be needed.
+*/
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Id( AllocationId ),
+    Pointer( u64 )
+}
+
+impl Key {
+    fn new( id: AllocationId, pointer: u64 ) -> Self {
+        if !id.is_invalid() && !id.is_untracked() {
+            Key::Id( id )
+        } else {
+            Key::Pointer( pointer )
+        }
+    }
+}
+
+struct LiveAllocation {
+    id: AllocationId,
+    counter: u64,
+    allocation: AllocBody
+}
+
+#[derive(Clone)]
+struct LiveMap {
+    pointer: u64,
+    length: u64,
+    backtrace: u64,
+    requested_address: u64,
+    mmap_protection: u32,
+    mmap_flags: u32,
+    file_descriptor: u32,
+    thread: u32,
+    offset: u64
+}
+
+/// Removes the `[pointer, pointer + length)` range from the maps, splitting them if necessary.
+fn unmap( maps: &mut Vec< LiveMap >, pointer: u64, length: u64 ) {
+    let end = pointer + length;
+    let mut output = Vec::with_capacity( maps.len() );
+    for map in maps.drain( .. ) {
+        let map_end = map.pointer + map.length;
+        if map_end <= pointer || map.pointer >= end {
+            output.push( map );
+            continue;
+        }
+
+        if map.pointer < pointer {
+            let mut head = map.clone();
+            head.length = pointer - map.pointer;
+            output.push( head );
+        }
+
+        if map_end > end {
+            let mut tail = map.clone();
+            tail.pointer = end;
+            tail.length = map_end - end;
+            tail.offset += end - map.pointer;
+            output.push( tail );
+        }
+    }
+
+    *maps = output;
+}
+
+struct Slicer {
+    start: Timestamp,
+    end: Timestamp,
+    is_inside: bool,
+    counter: u64,
+    live: HashMap< Key, LiveAllocation >,
+    maps: Vec< LiveMap >,
+    mallopts: Vec< Event< 'static > >,
+    last_marker: Option< u32 >
+}
+
+impl Slicer {
+    fn enter_window( &mut self, ofp: &mut Writer< impl Write > ) -> io::Result< () > {
+        if self.is_inside {
+            return Ok(());
+        }
+
+        self.is_inside = true;
+        let timestamp = self.start;
+
+        for mut event in self.mallopts.drain( .. ) {
+            if let Event::Mallopt { timestamp: ref mut event_timestamp, .. } = event {
+                *event_timestamp = timestamp;
+            }
+
+            ofp.write_event( &event )?;
+        }
+
+        for map in self.maps.drain( .. ) {
+            ofp.write_event( &Event::MemoryMap {
+                timestamp,
+                pointer: map.pointer,
+                length: map.length,
+                backtrace: map.backtrace,
+                requested_address: map.requested_address,
+                mmap_protection: map.mmap_protection,
+                mmap_flags: map.mmap_flags,
+                file_descriptor: map.file_descriptor,
+                thread: map.thread,
+                offset: map.offset
+            })?;
+        }
+
+        // Sort it so that the output doesn't differ based on the hashmap's iteration order.
+        let mut live: Vec< _ > = self.live.drain().map( |(_, allocation)| allocation ).collect();
+        live.sort_unstable_by_key( |allocation| allocation.counter );
+
+        info!( "Emitting {} pre-existing allocation(s)...", live.len() );
+        for allocation in live {
+            ofp.write_event( &Event::AllocEx {
+                id: allocation.id,
+                timestamp,
+                allocation: allocation.allocation
+            })?;
+        }
+
+        if let Some( value ) = self.last_marker.take() {
+            ofp.write_event( &Event::Marker { value } )?;
+        }
+
+        Ok(())
+    }
+
+    fn track_alloc( &mut self, id: AllocationId, allocation: AllocBody ) {
+        let counter = self.counter;
+        self.counter += 1;
+        self.live.insert( Key::new( id, allocation.pointer ), LiveAllocation { id, counter, allocation } );
+    }
+
+    fn track_realloc( &mut self, id: AllocationId, old_pointer: u64, allocation: AllocBody ) {
+        match self.live.remove( &Key::new( id, old_pointer ) ) {
+            Some( mut live ) => {
+                live.allocation = allocation;
+                self.live.insert( Key::new( id, live.allocation.pointer ), live );
+            },
+            None => {
+                self.track_alloc( id, allocation );
+            }
+        }
+    }
+
+    fn track_free( &mut self, id: AllocationId, pointer: u64 ) {
+        self.live.remove( &Key::new( id, pointer ) );
+    }
+}
+
+/// Writes out a new data file which only contains what happened between `from` and `to`,
+/// both of which are relative to the start of the original recording.
+pub fn slice_data< F, G >( input_fp: F, output_fp: G, from: Option< Timestamp >, to: Option< Timestamp > ) -> Result< (), io::Error >
+    where F: Read + Send + 'static,
+          G: Write + Send + 'static
+{
+    let (mut header, event_stream) = parse_events( input_fp )?;
+
+    let start = header.initial_timestamp + from.unwrap_or( Timestamp::min() );
+    let end = to.map( |to| header.initial_timestamp + to ).unwrap_or( Timestamp::max() );
+    if start > end {
+        return Err( io::Error::new( io::ErrorKind::InvalidInput, "the start of the window is after its end" ) );
+    }
+
+    let mut slicer = Slicer {
+        start,
+        end,
+        is_inside: false,
+        counter: 0,
+        live: HashMap::new(),
+        maps: Vec::new(),
+        mallopts: Vec::new(),
+        last_marker: None
+    };
+
+    header.initial_timestamp = start;
+
+    let mut ofp = Writer::new( output_fp, header )?;
+
+    for event in event_stream {
+        let event = event?;
+        let timestamp = match event {
+            Event::Alloc { timestamp, .. } |
+            Event::Realloc { timestamp, .. } |
+            Event::Free { timestamp, .. } |
+            Event::AllocEx { timestamp, .. } |
+            Event::ReallocEx { timestamp, .. } |
+            Event::FreeEx { timestamp, .. } |
+            Event::MemoryMap { timestamp, .. } |
+            Event::MemoryUnmap { timestamp, .. } |
+            Event::Mallopt { timestamp, .. } => Some( timestamp ),
+            _ => None
+        };
+
+        let timestamp = match timestamp {
+            Some( timestamp ) => timestamp,
+            None => {
+                match event {
+                    // Those would be inconsistent with what's left.
+                    Event::GroupStatistics { .. } => {},
+                    // The headers repeated in streamed data are identical to the first one.
+                    Event::Header( .. ) => {},
+                    Event::Marker { value } if !slicer.is_inside => {
+                        slicer.last_marker = Some( value );
+                    },
+                    event => ofp.write_event( &event )?
+                }
+
+                continue;
+            }
+        };
+
+        if timestamp > slicer.end {
+            continue;
+        }
+
+        if timestamp >= slicer.start {
+            slicer.enter_window( &mut ofp )?;
+            ofp.write_event( &event )?;
+            continue;
+        }
+
+        match event {
+            Event::Alloc { allocation, .. } => slicer.track_alloc( AllocationId::UNTRACKED, allocation ),
+            Event::AllocEx { id, allocation, .. } => slicer.track_alloc( id, allocation ),
+            Event::Realloc { old_pointer, allocation, .. } => slicer.track_realloc( AllocationId::UNTRACKED, old_pointer, allocation ),
+            Event::ReallocEx { id, old_pointer, allocation, .. } => slicer.track_realloc( id, old_pointer, allocation ),
+            Event::Free { pointer, .. } => slicer.track_free( AllocationId::UNTRACKED, pointer ),
+            Event::FreeEx { id, pointer, .. } => slicer.track_free( id, pointer ),
+            Event::MemoryMap { pointer, length, backtrace, requested_address, mmap_protection, mmap_flags, file_descriptor, thread, offset, .. } => {
+                unmap( &mut slicer.maps, pointer, length );
+                slicer.maps.push( LiveMap { pointer, length, backtrace, requested_address, mmap_protection, mmap_flags, file_descriptor, thread, offset } );
+            },
+            Event::MemoryUnmap { pointer, length, .. } => {
+                unmap( &mut slicer.maps, pointer, length );
+            },
+            event @ Event::Mallopt { .. } => {
+                slicer.mallopts.push( event );
+            },
+            _ => unreachable!()
+        }
+    }
+
+    // The window might have been empty.
+    slicer.enter_window( &mut ofp )?;
+
+    ofp.finish()?;
+    Ok(())
+}
+
+#[test]
+fn test_unmap_splits_maps() {
+    let map = LiveMap {
+        pointer: 0x1000,
+        length: 0x3000,
+        backtrace: 0,
+        requested_address: 0,
+        mmap_protection: 0,
+        mmap_flags: 0,
+        file_descriptor: !0,
+        thread: 1,
+        offset: 0
+    };
+
+    let mut maps = vec![ map ];
+    unmap( &mut maps, 0x2000, 0x1000 );
+
+    let ranges: Vec< _ > = maps.iter().map( |map| (map.pointer, map.length, map.offset) ).collect();
+    assert_eq!( ranges, vec![ (0x1000, 0x1000, 0), (0x3000, 0x1000, 0x2000) ] );
+}
+
+#[cfg(test)]
+fn slice_test_data( from: u64, to: u64 ) -> crate::Data {
+    use std::sync::Arc;
+    use parking_lot::Mutex;
+
+    // The output has to be `'static`, so it can't just borrow a vector.
+    #[derive(Clone, Default)]
+    struct Buffer( Arc< Mutex< Vec< u8 > > > );
+    impl Write for Buffer {
+        fn write( &mut self, data: &[u8] ) -> io::Result< usize > {
+            self.0.lock().extend_from_slice( data );
+            Ok( data.len() )
+        }
+
+        fn flush( &mut self ) -> io::Result< () > {
+            Ok(())
+        }
+    }
+
+    let alloc = |allocation, timestamp, size| Event::AllocEx {
+        id: AllocationId { thread: 1, allocation },
+        timestamp: Timestamp::from_usecs( timestamp ),
+        allocation: AllocBody {
+            pointer: 0x1000 * allocation,
+            size,
+            backtrace: 1,
+            thread: 1,
+            flags: 0,
+            extra_usable_space: 0,
+            preceding_free_space: 0
+        }
+    };
+
+    let free = |allocation, timestamp| Event::FreeEx {
+        id: AllocationId { thread: 1, allocation },
+        timestamp: Timestamp::from_usecs( timestamp ),
+        pointer: 0x1000 * allocation,
+        backtrace: 1,
+        thread: 1
+    };
+
+    let input = bytehound_format::test_util::write_test_file( &[
+        Event::Backtrace { id: 1, addresses: vec![ 0x1000 ].into() },
+        Event::Marker { value: 7 },
+        Event::Mallopt { timestamp: Timestamp::from_usecs( 5 ), backtrace: 1, thread: 1, param: -1, value: 1, result: 1 },
+        alloc( 1, 10, 100 ),
+        alloc( 2, 20, 200 ),
+        free( 2, 30 ),
+        alloc( 3, 150, 300 ),
+        alloc( 4, 250, 400 ),
+        free( 1, 300 )
+    ]);
+
+    let output = Buffer::default();
+    slice_data( io::Cursor::new( input ), output.clone(), Some( Timestamp::from_usecs( from ) ), Some( Timestamp::from_usecs( to ) ) ).unwrap();
+
+    let output = output.0.lock().clone();
+    crate::Loader::load_from_stream_without_debug_info( io::Cursor::new( output ) ).unwrap()
+}
+
+#[cfg(test)]
+fn slice_test_allocations( data: &crate::Data ) -> Vec< (u64, u64, bool, u32) > {
+    let mut allocations: Vec< _ > = data.allocations_with_id().map( |(_, allocation)| {
+        (allocation.size, allocation.timestamp.as_usecs(), allocation.was_deallocated(), allocation.marker)
+    }).collect();
+
+    allocations.sort();
+    allocations
+}
+
+#[test]
+fn test_slice_data() {
+    let data = slice_test_data( 100, 200 );
+    assert_eq!( data.initial_timestamp(), Timestamp::from_usecs( 100 ) );
+
+    // The allocation which was still alive is moved to the start of the window,
+    // and everything which happened after the window is gone. The marker is only
+    // carried over for what happens inside of the window.
+    assert_eq!( slice_test_allocations( &data ), vec![
+        (100, 100, false, 0),
+        (300, 150, false, 7)
+    ]);
+
+    assert_eq!( data.mallopts().len(), 1 );
+    assert_eq!( data.mallopts()[ 0 ].timestamp, Timestamp::from_usecs( 100 ) );
+    assert_eq!( data.mallopts()[ 0 ].value, 1 );
+}
+
+#[test]
+fn test_slice_data_with_an_empty_window() {
+    let data = slice_test_data( 400, 500 );
+    assert_eq!( slice_test_allocations( &data ), vec![
+        (300, 400, false, 0),
+        (400, 400, false, 0)
+    ]);
+
+    assert_eq!( data.mallopts().len(), 1 );
+}
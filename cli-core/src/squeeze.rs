@@ -401,6 +401,7 @@ pub fn squeeze_data< F, G >( input_fp: F, output_fp: G, threshold: Option< u64 >
                 Event::Marker { .. } => {},
                 Event::Environ { .. } => {},
                 Event::WallClock { .. } => {},
+                Event::ProcessInfo { .. } => {},
                 Event::String { .. } => {},
                 Event::DecodedFrame { .. } => {},
                 Event::DecodedBacktrace { .. } => {}
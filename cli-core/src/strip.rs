@@ -0,0 +1,431 @@
+use std::io::{self, Write};
+use std::u64;
+
+use ahash::AHashMap as HashMap;
+use ahash::AHashSet as HashSet;
+use string_interner::Symbol;
+
+use bytehound_format::Writer;
+
+use common::Timestamp;
+use common::event::{
+    self,
+    Event,
+    AllocBody,
+    HeaderBody,
+    HEADER_FLAG_IS_LITTLE_ENDIAN
+};
+
+use crate::data::{Allocation, AllocationFlags, AllocationId, BacktraceId, Data, MmapOperation};
+use crate::postprocessor::{Anonymize, FunctionAnonymizer, PathAnonymizer, anonymize_header};
+
+/*
+    Unlike `squeeze_data` this doesn't work on the raw event stream; the whole file
+    has to be loaded first since the filters need the full picture.
+
+    The output is generated from scratch out of the loaded data: only decoded
+    backtraces are emitted, the strings, frames and backtraces are renumbered
+    so that only the ones which are actually used are kept, and every allocation
+    gets its own synthetic ID so that the pointers don't matter anymore.
+
+    The timestamps in the loaded data are already adjusted to the wall clock,
+    so the header's clock is zeroed out to make the loader not shift them again.
+*/
+
+fn event_flags( allocation: &Allocation ) -> u32 {
+    let mut flags = 0;
+    if allocation.flags.contains( AllocationFlags::IS_PREV_IN_USE ) {
+        flags |= event::ALLOC_FLAG_PREV_IN_USE;
+    }
+
+    if allocation.flags.contains( AllocationFlags::IS_MMAPED ) {
+        flags |= event::ALLOC_FLAG_MMAPED;
+    }
+
+    if allocation.flags.contains( AllocationFlags::IN_NON_MAIN_ARENA ) {
+        flags |= event::ALLOC_FLAG_NON_MAIN_ARENA;
+    }
+
+    if allocation.flags.contains( AllocationFlags::IS_CALLOC ) {
+        flags |= event::ALLOC_FLAG_CALLOC;
+    }
+
+    if allocation.flags.contains( AllocationFlags::IS_JEMALLOC ) {
+        flags |= event::ALLOC_FLAG_JEMALLOC;
+    }
+
+    flags
+}
+
+/// Assigns sequential IDs to whatever gets marked as used, in the order of the original IDs.
+struct Remapping {
+    is_used: Vec< bool >,
+    remapped: Vec< u32 >
+}
+
+impl Remapping {
+    fn new( length: usize ) -> Self {
+        Remapping {
+            is_used: vec![ false; length ],
+            remapped: Vec::new()
+        }
+    }
+
+    fn mark( &mut self, index: usize ) {
+        self.is_used[ index ] = true;
+    }
+
+    fn finish( &mut self ) -> Vec< usize > {
+        let mut counter = 0;
+        let mut used = Vec::new();
+        self.remapped = self.is_used.iter().enumerate().map( |(index, &is_used)| {
+            if is_used {
+                used.push( index );
+                counter += 1;
+                counter - 1
+            } else {
+                !0
+            }
+        }).collect();
+
+        used
+    }
+
+    fn get( &self, index: usize ) -> u32 {
+        let value = self.remapped[ index ];
+        debug_assert_ne!( value, !0 );
+        value
+    }
+}
+
+fn new_header( data: &Data, anonymize: Anonymize ) -> HeaderBody {
+    let mut header = HeaderBody {
+        id: data.id(),
+        initial_timestamp: data.initial_timestamp(),
+        timestamp: Timestamp::min(),
+        wall_clock_secs: 0,
+        wall_clock_nsecs: 0,
+        pid: data.pid(),
+        cmdline: data.cmdline.as_bytes().to_owned(),
+        executable: data.executable().as_bytes().to_owned(),
+        arch: data.architecture().to_owned(),
+        flags: HEADER_FLAG_IS_LITTLE_ENDIAN,
+        pointer_size: data.pointer_size() as u8
+    };
+
+    anonymize_header( anonymize, &mut header );
+    header
+}
+
+/// Writes out a new data file which only contains the allocations matched by the `filter`
+/// along with the backtraces, frames and strings which they actually use.
+///
+/// The memory maps and the `mallopt` calls are always kept.
+pub fn strip_data< G, F >( data: &Data, output_fp: G, anonymize: Anonymize, filter: F ) -> Result< (), io::Error >
+    where G: Write,
+          F: Fn( AllocationId, &Allocation ) -> bool
+{
+    let is_kept: Vec< bool > = data.allocations_with_id().map( |(id, allocation)| filter( id, allocation ) ).collect();
+    let is_kept = |id: AllocationId| is_kept[ id.raw() as usize ];
+
+    let mut backtraces = Remapping::new( data.backtraces.len() );
+    for (id, allocation) in data.allocations_with_id() {
+        if !is_kept( id ) {
+            continue;
+        }
+
+        backtraces.mark( allocation.backtrace.raw() as usize );
+        if let Some( backtrace ) = allocation.deallocation.as_ref().and_then( |deallocation| deallocation.backtrace ) {
+            backtraces.mark( backtrace.raw() as usize );
+        }
+    }
+
+    for operation in data.mmap_operations() {
+        match *operation {
+            MmapOperation::Mmap( ref map ) => backtraces.mark( map.backtrace.raw() as usize ),
+            MmapOperation::Munmap( ref unmap ) => backtraces.mark( unmap.backtrace.raw() as usize )
+        }
+    }
+
+    for mallopt in data.mallopts() {
+        backtraces.mark( mallopt.backtrace.raw() as usize );
+    }
+
+    let used_backtraces = backtraces.finish();
+    let mut frames = Remapping::new( data.frames.len() );
+    for &backtrace in &used_backtraces {
+        for &frame_id in data.get_frame_ids( BacktraceId::new( backtrace as _ ) ) {
+            frames.mark( frame_id );
+        }
+    }
+
+    let used_frames = frames.finish();
+    info!( "Keeping {} backtrace(s) out of {} and {} frame(s) out of {}", used_backtraces.len(), data.backtraces.len(), used_frames.len(), data.frames.len() );
+
+    let mut ofp = Writer::new( output_fp, new_header( data, anonymize ) )?;
+    if let Some( parent_pid ) = data.parent_pid() {
+        ofp.write_event( &Event::ProcessInfo { parent_pid } )?;
+    }
+
+    let mut anonymizer_library = PathAnonymizer::new( "lib_" );
+    let mut anonymizer_source = PathAnonymizer::new( "src_" );
+    let mut anonymizer_function = FunctionAnonymizer::default();
+    let mut emitted_strings = HashSet::new();
+    for &frame_id in &used_frames {
+        let frame = data.get_frame( frame_id );
+        macro_rules! intern {
+            ($value:expr, $anonymizer:ident) => {
+                if let Some( id ) = $value {
+                    let raw_id = id.to_usize() as u32;
+                    if !emitted_strings.contains( &id ) {
+                        emitted_strings.insert( id );
+                        let string = data.interner().resolve( id ).unwrap();
+                        ofp.write_event( &Event::String {
+                            id: raw_id,
+                            string: $anonymizer.anonymize( anonymize, string )
+                        })?;
+                    }
+
+                    raw_id
+                } else {
+                    0xFFFFFFFF
+                }
+            }
+        }
+
+        let library = intern!( frame.library(), anonymizer_library );
+        let source = intern!( frame.source(), anonymizer_source );
+        let raw_function = intern!( frame.raw_function(), anonymizer_function );
+        let function = if anonymize == Anonymize::Full {
+            0xFFFFFFFF
+        } else {
+            intern!( frame.function(), anonymizer_function )
+        };
+
+        ofp.write_event( &Event::DecodedFrame {
+            address: frame.address().raw(),
+            library,
+            raw_function,
+            function,
+            source,
+            line: frame.line().unwrap_or( 0xFFFFFFFF ),
+            column: frame.column().unwrap_or( 0xFFFFFFFF ),
+            is_inline: frame.is_inline()
+        })?;
+    }
+
+    for &backtrace in &used_backtraces {
+        let remapped: Vec< u32 > = data.get_frame_ids( BacktraceId::new( backtrace as _ ) ).iter().map( |&frame_id| frames.get( frame_id ) ).collect();
+        ofp.write_event( &Event::DecodedBacktrace {
+            frames: remapped.into()
+        })?;
+    }
+
+    let remap_backtrace = |backtrace: BacktraceId| backtraces.get( backtrace.raw() as usize ) as u64;
+
+    // Every event is paired with the marker which was active at the time, if it matters.
+    let mut events: Vec< (Timestamp, Option< u32 >, Event< 'static >) > = Vec::new();
+    for operation in data.mmap_operations() {
+        let (timestamp, event) = match *operation {
+            MmapOperation::Mmap( ref map ) => (map.timestamp, Event::MemoryMap {
+                timestamp: map.timestamp,
+                pointer: map.pointer,
+                length: map.length,
+                backtrace: remap_backtrace( map.backtrace ),
+                requested_address: map.requested_address,
+                mmap_protection: map.mmap_protection.0,
+                mmap_flags: map.mmap_flags.0,
+                file_descriptor: map.file_descriptor,
+                thread: map.thread,
+                offset: map.offset
+            }),
+            MmapOperation::Munmap( ref unmap ) => (unmap.timestamp, Event::MemoryUnmap {
+                timestamp: unmap.timestamp,
+                pointer: unmap.pointer,
+                length: unmap.length,
+                backtrace: remap_backtrace( unmap.backtrace ),
+                thread: unmap.thread
+            })
+        };
+
+        events.push( (timestamp, None, event) );
+    }
+
+    for mallopt in data.mallopts() {
+        events.push( (mallopt.timestamp, None, Event::Mallopt {
+            timestamp: mallopt.timestamp,
+            backtrace: remap_backtrace( mallopt.backtrace ),
+            thread: mallopt.thread,
+            param: mallopt.kind.raw(),
+            value: mallopt.value,
+            result: mallopt.result
+        }));
+    }
+
+    // The allocations are numbered in the order in which they were made, so whatever
+    // was reallocated always comes before what it was reallocated into.
+    let mut event_ids: HashMap< AllocationId, event::AllocationId > = HashMap::new();
+    let mut kept_count = 0;
+    for (id, allocation) in data.allocations_with_id() {
+        if !is_kept( id ) {
+            continue;
+        }
+
+        kept_count += 1;
+        let body = AllocBody {
+            pointer: allocation.pointer,
+            size: allocation.size,
+            backtrace: remap_backtrace( allocation.backtrace ),
+            thread: allocation.thread,
+            flags: event_flags( allocation ),
+            extra_usable_space: allocation.extra_usable_space,
+            preceding_free_space: allocation.preceding_free_space as u64
+        };
+
+        let previous = allocation.reallocated_from.and_then( |previous_id| {
+            event_ids.get( &previous_id ).map( |&event_id| (event_id, data.get_allocation( previous_id )) )
+        });
+
+        let event_id;
+        let event = if let Some( (previous_event_id, previous) ) = previous {
+            event_id = previous_event_id;
+            Event::ReallocEx {
+                id: event_id,
+                timestamp: allocation.timestamp,
+                old_pointer: previous.pointer,
+                allocation: body
+            }
+        } else {
+            event_id = event::AllocationId {
+                thread: allocation.thread as u64,
+                allocation: id.raw() + 1
+            };
+
+            Event::AllocEx {
+                id: event_id,
+                timestamp: allocation.timestamp,
+                allocation: body
+            }
+        };
+
+        events.push( (allocation.timestamp, Some( allocation.marker ), event) );
+        event_ids.insert( id, event_id );
+
+        let is_reallocated_into_kept = allocation.reallocation.map( is_kept ).unwrap_or( false );
+        if is_reallocated_into_kept {
+            continue;
+        }
+
+        if let Some( ref deallocation ) = allocation.deallocation {
+            events.push( (deallocation.timestamp, None, Event::FreeEx {
+                id: event_id,
+                timestamp: deallocation.timestamp,
+                pointer: allocation.pointer,
+                backtrace: deallocation.backtrace.map( remap_backtrace ).unwrap_or( u64::MAX ),
+                thread: deallocation.thread
+            }));
+        }
+    }
+
+    info!( "Keeping {} allocation(s) out of {}", kept_count, data.allocations.len() );
+
+    // This has to be a stable sort; an allocation and its deallocation might share a timestamp.
+    events.sort_by_key( |&(timestamp, _, _)| timestamp );
+
+    let mut current_marker = 0;
+    for (_, marker, event) in events {
+        if let Some( marker ) = marker {
+            if marker != current_marker {
+                current_marker = marker;
+                ofp.write_event( &Event::Marker { value: marker } )?;
+            }
+        }
+
+        ofp.write_event( &event )?;
+    }
+
+    ofp.finish()?;
+    Ok(())
+}
+
+#[test]
+fn test_remapping_is_sequential() {
+    let mut remapping = Remapping::new( 5 );
+    remapping.mark( 3 );
+    remapping.mark( 1 );
+    remapping.mark( 3 );
+
+    assert_eq!( remapping.finish(), vec![ 1, 3 ] );
+    assert_eq!( remapping.get( 1 ), 0 );
+    assert_eq!( remapping.get( 3 ), 1 );
+}
+
+#[cfg(test)]
+fn strip_test_data( anonymize: Anonymize, filter: impl Fn( AllocationId, &Allocation ) -> bool ) -> Data {
+    let alloc = |allocation, size, backtrace| Event::AllocEx {
+        id: event::AllocationId { thread: 1, allocation },
+        timestamp: Timestamp::from_usecs( allocation * 10 ),
+        allocation: AllocBody {
+            pointer: 0x1000 * allocation,
+            size,
+            backtrace,
+            thread: 1,
+            flags: 0,
+            extra_usable_space: 0,
+            preceding_free_space: 0
+        }
+    };
+
+    let data = crate::loader::load_test_data( &[
+        Event::Backtrace { id: 1, addresses: vec![ 0x1001 ].into() },
+        Event::Backtrace { id: 2, addresses: vec![ 0x2001, 0x2101 ].into() },
+        Event::Backtrace { id: 3, addresses: vec![ 0x3001, 0x2101 ].into() },
+        alloc( 1, 10, 1 ),
+        alloc( 2, 100, 2 ),
+        alloc( 3, 1000, 3 ),
+        Event::FreeEx {
+            id: event::AllocationId { thread: 1, allocation: 2 },
+            timestamp: Timestamp::from_usecs( 40 ),
+            pointer: 0x2000,
+            backtrace: 2,
+            thread: 1
+        }
+    ]);
+
+    let mut output = Vec::new();
+    strip_data( &data, &mut output, anonymize, filter ).unwrap();
+    crate::Loader::load_from_stream_without_debug_info( io::Cursor::new( output ) ).unwrap()
+}
+
+#[test]
+fn test_strip_data_keeps_only_the_selected_allocations() {
+    let data = strip_test_data( Anonymize::None, |_, allocation| allocation.size >= 100 );
+
+    let allocations: Vec< _ > = data.allocations_with_id().map( |(_, allocation)| {
+        (allocation.size, allocation.was_deallocated(), allocation.backtrace.raw())
+    }).collect();
+
+    assert_eq!( allocations, vec![ (100, true, 0), (1000, false, 1) ] );
+
+    // Only the used backtraces and frames are kept, and are renumbered from zero;
+    // the frame shared between both of the backtraces is only emitted once.
+    assert_eq!( data.backtraces.len(), 2 );
+    assert_eq!( data.frames.len(), 3 );
+
+    let addresses = |backtrace| -> Vec< u64 > {
+        data.get_frame_ids( BacktraceId::new( backtrace ) ).iter().map( |&frame_id| data.get_frame( frame_id ).address().raw() ).collect()
+    };
+
+    assert_eq!( addresses( 0 ), vec![ 0x2000, 0x2100 ] );
+    assert_eq!( addresses( 1 ), vec![ 0x3000, 0x2100 ] );
+}
+
+#[test]
+fn test_strip_data_with_anonymization() {
+    let data = strip_test_data( Anonymize::Partial, |id, _| id.raw() == 0 );
+
+    assert_eq!( data.executable(), "test" );
+    assert_eq!( data.allocations_with_id().map( |(_, allocation)| allocation.size ).collect::< Vec< _ > >(), vec![ 10 ] );
+    assert_eq!( data.backtraces.len(), 1 );
+    assert_eq!( data.frames.len(), 1 );
+}
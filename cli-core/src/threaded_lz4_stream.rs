@@ -5,6 +5,7 @@ use std::marker::PhantomData;
 use std::mem;
 use std::sync::Arc;
 use lz4_compress;
+use common::zstd;
 use byteorder::{ReadBytesExt, WriteBytesExt, LittleEndian};
 use parking_lot::Mutex;
 
@@ -18,10 +19,17 @@ pub struct Lz4Reader< F: io::Read + Send > {
     error: Arc< Mutex< Option< io::Error > > >
 }
 
-fn read_chunk( fp: &mut impl io::Read, buffer: &mut Vec< u8 > ) -> Result< (Vec< u8 >, bool), io::Error > {
-    let kind = fp.read_u8()?;
-    if kind != 1 && kind != 2 {
-        unimplemented!();
+fn read_chunk( fp: &mut impl io::Read, buffer: &mut Vec< u8 > ) -> Result< (Vec< u8 >, u8), io::Error > {
+    let mut kind = fp.read_u8()?;
+    while kind == 4 {
+        // Skip the chunk index.
+        let length = fp.read_u32::< LittleEndian >()? as u64;
+        io::copy( &mut io::Read::take( &mut *fp, length ), &mut io::sink() )?;
+        kind = fp.read_u8()?;
+    }
+
+    if kind != 1 && kind != 2 && kind != 3 {
+        return Err( io::Error::new( io::ErrorKind::InvalidData, format!( "unexpected chunk kind: {}", kind ) ) );
     }
 
     let length = fp.read_u32::< LittleEndian >()? as usize;
@@ -32,7 +40,7 @@ fn read_chunk( fp: &mut impl io::Read, buffer: &mut Vec< u8 > ) -> Result< (Vec<
 
     fp.read_exact( buffer )?;
     let chunk = mem::replace( buffer, Vec::new() );
-    Ok( (chunk, kind == 1) )
+    Ok( (chunk, kind) )
 }
 
 impl< F: io::Read + Send + 'static > Lz4Reader< F > {
@@ -48,7 +56,7 @@ impl< F: io::Read + Send + 'static > Lz4Reader< F > {
             let mut buffer = Vec::new();
             let mut counter = 0;
             loop {
-                let (chunk, is_compressed) = match read_chunk( &mut fp, &mut buffer ) {
+                let (chunk, kind) = match read_chunk( &mut fp, &mut buffer ) {
                     Ok( chunk ) => chunk,
                     Err( ref error ) if error.kind() == io::ErrorKind::UnexpectedEof => {
                         break;
@@ -59,8 +67,8 @@ impl< F: io::Read + Send + 'static > Lz4Reader< F > {
                     }
                 };
 
-                if is_compressed {
-                    if decompress_tx.send( (counter, chunk) ).is_err() {
+                if kind != 2 {
+                    if decompress_tx.send( (counter, kind, chunk) ).is_err() {
                         break;
                     }
                 } else {
@@ -78,9 +86,15 @@ impl< F: io::Read + Send + 'static > Lz4Reader< F > {
             let output_tx = output_tx.clone();
             thread::spawn( move || {
                 let mut output = Vec::new();
-                while let Ok( (counter, input) ) = decompress_rx.recv() {
+                while let Ok( (counter, kind, input) ) = decompress_rx.recv() {
                     output.clear();
-                    if let Ok(()) = lz4_compress::decompress_into( &input, &mut output ) {
+                    let result = if kind == 1 {
+                        lz4_compress::decompress_into( &input, &mut output ).map_err( |_| () )
+                    } else {
+                        zstd::stream::copy_decode( &input[ .. ], &mut output ).map_err( |_| () )
+                    };
+
+                    if let Ok(()) = result {
                         if output_tx.send( (counter, output.clone()) ).is_err() {
                             break;
                         }
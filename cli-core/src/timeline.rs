@@ -6,40 +6,95 @@ pub fn build_timeline(
     timestamp_max: common::Timestamp,
     ops: &[OperationId]
 ) -> Vec< TimelinePoint > {
-    let granularity = std::cmp::max( (timestamp_max - timestamp_min).as_usecs() / 1000, 1 );
-    let mut output = Vec::with_capacity( 1000 + 2 );
+    build_timeline_with_resolution( data, timestamp_min, timestamp_max, ops, 1000 )
+}
+
+/// Builds a timeline of the given range split into `resolution` buckets, keeping the peak values of every bucket.
+///
+/// The operations from before `timestamp_min` only contribute to the initial usage,
+/// and the ones after `timestamp_max` are ignored.
+pub fn build_timeline_with_resolution(
+    data: &Data,
+    timestamp_min: common::Timestamp,
+    timestamp_max: common::Timestamp,
+    ops: &[OperationId],
+    resolution: u64
+) -> Vec< TimelinePoint > {
+    let resolution = std::cmp::max( resolution, 1 );
+    let granularity = (timestamp_max - timestamp_min).as_usecs() / resolution;
+    build_timeline_with_granularity( data, timestamp_min, timestamp_max, ops, granularity )
+}
+
+/// Same as `build_timeline_with_resolution`, except the buckets are always `granularity` microseconds long.
+///
+/// The buckets are aligned to multiples of the `granularity`, so they're the same regardless of the range.
+pub fn build_timeline_with_granularity(
+    data: &Data,
+    timestamp_min: common::Timestamp,
+    timestamp_max: common::Timestamp,
+    ops: &[OperationId],
+    granularity: u64
+) -> Vec< TimelinePoint > {
+    let granularity = std::cmp::max( granularity, 1 );
+    let last_time = timestamp_max.as_usecs() / granularity;
+    let bucket_count = (timestamp_max - timestamp_min).as_usecs() / granularity;
+    let mut output = Vec::with_capacity( bucket_count as usize + 2 );
 
     let mut current_time: u64 = 0;
     let mut current_usage: i64 = 0;
     let mut current_max_usage: i64 = 0;
+    let mut current_slack: i64 = 0;
+    let mut current_max_slack: i64 = 0;
     let mut current_allocations: i64 = 0;
     let mut current_max_allocations: i64 = 0;
     let mut current_allocations_per_time: u64 = 0;
     let mut current_deallocations_per_time: u64 = 0;
+    let mut is_in_range = false;
     for op in ops {
         let timestamp;
 
         let mut next_usage = current_usage;
+        let mut next_slack = current_slack;
         let mut next_allocations = current_allocations;
         let allocation = data.get_allocation( op.id() );
         if op.is_allocation() {
             next_usage += allocation.size as i64;
+            next_slack += allocation.extra_usable_space as i64;
             next_allocations += 1;
             timestamp = allocation.timestamp;
         } else if op.is_deallocation() {
             next_usage -= allocation.size as i64;
+            next_slack -= allocation.extra_usable_space as i64;
             next_allocations -= 1;
             timestamp = allocation.deallocation.as_ref().unwrap().timestamp;
         } else if op.is_reallocation() {
             let old_allocation = data.get_allocation( allocation.reallocated_from.unwrap() );
             next_usage += allocation.size as i64;
             next_usage -= old_allocation.size as i64;
+            next_slack += allocation.extra_usable_space as i64;
+            next_slack -= old_allocation.extra_usable_space as i64;
             timestamp = allocation.timestamp;
         } else {
             unreachable!()
         }
 
+        if !is_in_range && timestamp < timestamp_min {
+            current_usage = next_usage;
+            current_slack = next_slack;
+            current_allocations = next_allocations;
+            current_max_usage = next_usage;
+            current_max_allocations = next_allocations;
+            current_max_slack = next_slack;
+            current_time = timestamp_min.as_usecs() / granularity;
+            continue;
+        }
+
+        is_in_range = true;
         let next_time = timestamp.as_usecs() / granularity;
+        if next_time > last_time {
+            break;
+        }
+
         if current_time == 0 {
             current_time = next_time;
         } else if current_time != next_time {
@@ -47,11 +102,13 @@ pub fn build_timeline(
             // to be strictly ordered we could - in theory - temporarily hit a negative memory usage.
             let memory_usage = std::cmp::max( 0, current_max_usage ) as u64;
             let allocations = std::cmp::max( 0, current_max_allocations ) as u64;
+            let slack = std::cmp::max( 0, current_max_slack ) as u64;
             while current_time < next_time {
                 let point = TimelinePoint {
                     timestamp: current_time * granularity,
                     memory_usage,
                     allocations,
+                    slack,
                     allocations_per_time: current_allocations_per_time,
                     deallocations_per_time: current_deallocations_per_time,
                 };
@@ -62,12 +119,15 @@ pub fn build_timeline(
             }
             current_max_usage = 0;
             current_max_allocations = 0;
+            current_max_slack = 0;
         }
 
         current_usage = next_usage;
+        current_slack = next_slack;
         current_allocations = next_allocations;
         current_max_usage = std::cmp::max( current_max_usage, next_usage );
         current_max_allocations = std::cmp::max( current_max_allocations, next_allocations );
+        current_max_slack = std::cmp::max( current_max_slack, next_slack );
 
         if op.is_deallocation() {
             current_deallocations_per_time += 1;
@@ -78,9 +138,10 @@ pub fn build_timeline(
 
     if output.is_empty() {
         output.push( TimelinePoint {
-            timestamp: current_time * granularity - 1,
+            timestamp: (current_time * granularity).saturating_sub( 1 ),
             memory_usage: 0,
             allocations: 0,
+            slack: 0,
             allocations_per_time: 0,
             deallocations_per_time: 0,
         });
@@ -90,6 +151,7 @@ pub fn build_timeline(
         timestamp: current_time * granularity,
         memory_usage: std::cmp::max( 0, current_max_usage ) as u64,
         allocations: std::cmp::max( 0, current_max_allocations ) as u64,
+        slack: std::cmp::max( 0, current_max_slack ) as u64,
         allocations_per_time: current_allocations_per_time,
         deallocations_per_time: current_deallocations_per_time,
     });
@@ -98,6 +160,7 @@ pub fn build_timeline(
         timestamp: current_time * granularity + 1,
         memory_usage: std::cmp::max( 0, current_usage ) as u64,
         allocations: std::cmp::max( 0, current_allocations ) as u64,
+        slack: std::cmp::max( 0, current_slack ) as u64,
         allocations_per_time: 0,
         deallocations_per_time: 0,
     });
@@ -110,6 +173,57 @@ pub struct TimelinePoint {
     pub timestamp: u64,
     pub memory_usage: u64,
     pub allocations: u64,
+    pub slack: u64,
     pub allocations_per_time: u64,
     pub deallocations_per_time: u64
 }
+
+#[test]
+fn test_timeline_of_a_subrange() {
+    use common::Timestamp;
+    use common::event::{AllocBody, AllocationId, Event};
+
+    let alloc = |allocation, timestamp, size| Event::AllocEx {
+        id: AllocationId { thread: 1, allocation },
+        timestamp: Timestamp::from_usecs( timestamp ),
+        allocation: AllocBody {
+            pointer: 0x1000 * allocation,
+            size,
+            backtrace: 1,
+            thread: 1,
+            flags: 0,
+            extra_usable_space: 0,
+            preceding_free_space: 0
+        }
+    };
+
+    let data = crate::loader::load_test_data( &[
+        Event::Backtrace { id: 1, addresses: vec![ 0x1000 ].into() },
+        alloc( 1, 10, 100 ),
+        alloc( 2, 20, 50 ),
+        Event::FreeEx {
+            id: AllocationId { thread: 1, allocation: 2 },
+            timestamp: Timestamp::from_usecs( 30 ),
+            pointer: 0x2000,
+            backtrace: 1,
+            thread: 1
+        },
+        alloc( 3, 150, 1000 ),
+        alloc( 4, 250, 7 )
+    ]);
+
+    let timeline = build_timeline_with_resolution( &data, Timestamp::from_usecs( 100 ), Timestamp::from_usecs( 200 ), data.operation_ids(), 10 );
+    let points: Vec< _ > = timeline.iter().map( |point| (point.timestamp, point.memory_usage, point.allocations, point.allocations_per_time) ).collect();
+
+    // The usage from before the range is carried into its first buckets,
+    // and the allocation made after the range is ignored.
+    assert_eq!( points, vec![
+        (100, 100, 1, 0),
+        (110, 100, 1, 0),
+        (120, 100, 1, 0),
+        (130, 100, 1, 0),
+        (140, 100, 1, 0),
+        (150, 1100, 2, 1),
+        (151, 1100, 2, 0)
+    ]);
+}
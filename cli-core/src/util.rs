@@ -4,6 +4,9 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::cmp::max;
 use ctrlc;
 
+use crate::data::StringInterner;
+use crate::frame::Frame;
+
 #[derive(Clone)]
 pub struct Sigint {
     flag: Arc< AtomicBool >
@@ -145,3 +148,38 @@ pub fn table_to_string( table: &[ Vec< String > ] ) -> String {
 
     output
 }
+
+pub fn format_count( count: u64 ) -> String {
+    if count < 1000 {
+        format!( "{}", count )
+    } else if count < 1000 * 1000 {
+        format!( "{}K", count / 1000 )
+    } else {
+        format!( "{}M", count / (1000 * 1000) )
+    }
+}
+
+/// Writes out a single backtrace frame as ` [library] function [source:line]`.
+pub fn write_frame( mut output: impl fmt::Write, interner: &StringInterner, frame: &Frame ) -> fmt::Result {
+    if let Some( library ) = frame.library() {
+        write!( output, " [{}]", interner.resolve( library ).unwrap() )?;
+    }
+    if let Some( function ) = frame.any_function() {
+        write!( output, " {}", interner.resolve( function ).unwrap() )?;
+    } else {
+        write!( output, " {:0x}", frame.address().raw() )?;
+    }
+    if let Some( source ) = frame.source() {
+        let mut source = interner.resolve( source ).unwrap();
+        if let Some( index ) = source.rfind( "/" ) {
+            source = &source[ index + 1.. ];
+        }
+        write!( output, " [{}", source )?;
+        if let Some( line ) = frame.line() {
+            write!( output, ":{}", line )?;
+        }
+        write!( output, "]" )?;
+    }
+
+    Ok(())
+}
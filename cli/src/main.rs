@@ -7,14 +7,23 @@ use std::path::PathBuf;
 use std::io;
 use std::fs::File;
 use std::error::Error;
+use std::collections::HashSet;
+use std::sync::Arc;
 
 use structopt::StructOpt;
 
 use cli_core::{
+    AllocationId,
     Anonymize,
+    ChurnSortBy,
+    Compression,
+    ImportFormat,
     Loader,
+    Timestamp,
     export_as_replay,
     export_as_heaptrack,
+    export_as_parquet,
+    export_as_sqlite,
     postprocess
 };
 
@@ -30,6 +39,47 @@ fn parse_anonymize( source: &str ) -> Anonymize {
     }
 }
 
+fn parse_churn_sort_by( source: &str ) -> ChurnSortBy {
+    match source {
+        "rate" => ChurnSortBy::AllocationRate,
+        "short-lived" => ChurnSortBy::ShortLivedCount,
+        _ => unreachable!()
+    }
+}
+
+fn parse_import_format( source: &str ) -> ImportFormat {
+    match source {
+        "heaptrack" => ImportFormat::Heaptrack,
+        "massif" => ImportFormat::Massif,
+        "jemalloc" => ImportFormat::Jemalloc,
+        _ => unreachable!()
+    }
+}
+
+/// Parses a duration like `90`, `1500ms`, `30s`, `10m` or `2h`; a plain number is in seconds.
+fn parse_duration( source: &str ) -> Result< Timestamp, String > {
+    let source = source.trim();
+    let index = source.find( |ch: char| !ch.is_ascii_digit() && ch != '.' ).unwrap_or( source.len() );
+    let (value, unit) = source.split_at( index );
+    let value: f64 = value.parse().map_err( |_| format!( "invalid duration: '{}'", source ) )?;
+    let multiplier = match unit.trim() {
+        "us" => 1.0,
+        "ms" => 1_000.0,
+        "" | "s" => 1_000_000.0,
+        "m" => 60.0 * 1_000_000.0,
+        "h" => 3600.0 * 1_000_000.0,
+        unit => return Err( format!( "invalid duration unit: '{}'", unit ) )
+    };
+
+    Ok( Timestamp::from_usecs( (value * multiplier) as u64 ) )
+}
+
+fn parse_basic_auth( source: &str ) -> Result< (String, String), String > {
+    let index = source.find( ':' ).ok_or_else( || "the credentials should be in the 'USERNAME:PASSWORD' format".to_owned() )?;
+    let (username, password) = source.split_at( index );
+    Ok( (username.to_owned(), password[ 1.. ].to_owned()) )
+}
+
 #[derive(StructOpt, Debug)]
 enum Opt {
     /// Generates a raw data file which can be used to replay all of the allocations
@@ -51,6 +101,30 @@ enum Opt {
         #[structopt(parse(from_os_str))]
         input: PathBuf
     },
+    /// Exports the allocations, backtraces, frames and mmaps as tables of a SQLite database
+    #[structopt(name = "export-sql")]
+    ExportSql {
+        /// A file or directory with extra debugging symbols; can be specified multiple times
+        #[structopt(short = "d", long = "debug-symbols", parse(from_os_str))]
+        debug_symbols: Vec< PathBuf >,
+        /// The SQLite database to create; will be overwritten if it already exists
+        #[structopt(short = "o", long = "output", parse(from_os_str))]
+        output: PathBuf,
+        #[structopt(parse(from_os_str))]
+        input: PathBuf
+    },
+    /// Exports the allocations, backtraces, frames and mmaps as a directory of Parquet files
+    #[structopt(name = "export-parquet")]
+    ExportParquet {
+        /// A file or directory with extra debugging symbols; can be specified multiple times
+        #[structopt(short = "d", long = "debug-symbols", parse(from_os_str))]
+        debug_symbols: Vec< PathBuf >,
+        /// The directory to which the Parquet files will be written
+        #[structopt(short = "o", long = "output", parse(from_os_str))]
+        output: PathBuf,
+        #[structopt(parse(from_os_str))]
+        input: PathBuf
+    },
     /// Gathers memory tracking data from a given machine
     #[structopt(name = "gather")]
     Gather {
@@ -69,6 +143,57 @@ enum Opt {
         /// The port on which to start the HTTP server
         #[structopt(short = "p", long = "port", default_value = "8080")]
         port: u16,
+        /// Treat the inputs as recordings of a single process tree (e.g. a pre-forking server
+        /// profiled with `MEMORY_PROFILER_TRACK_CHILD_PROCESSES`) and group them into one session
+        #[structopt(long = "session")]
+        session: bool,
+        /// A directory to watch; any new data files which appear in it will be loaded automatically
+        #[structopt(long = "watch", parse(from_os_str))]
+        watch: Option< PathBuf >,
+        /// Also load the data files from the watched directory which are still being written to,
+        /// and reload them periodically as they grow
+        #[structopt(long = "live", requires = "watch")]
+        live: bool,
+        /// The maximum number of operations a single script can execute
+        #[structopt(long = "script-max-operations")]
+        script_max_operations: Option< u64 >,
+        /// The maximum time, in seconds, a single script can run for; 0 means no limit
+        #[structopt(long = "script-timeout", default_value = "300")]
+        script_timeout: u64,
+        /// The maximum total size, in megabytes, of the output and the files generated by a single script; 0 means no limit
+        #[structopt(long = "script-max-output-size", default_value = "64")]
+        script_max_output_size: usize,
+        /// The maximum number of scripts, and separately of other background jobs, which can run at the same time
+        #[structopt(long = "max-running-jobs", default_value = "4")]
+        max_running_jobs: usize,
+        /// A PEM file with the certificate chain; if specified the server will only accept HTTPS connections
+        #[structopt(long = "tls-certificate", parse(from_os_str), requires = "tls_key")]
+        tls_certificate: Option< PathBuf >,
+        /// A PEM file with the private key for the certificate passed through `--tls-certificate`
+        #[structopt(long = "tls-key", parse(from_os_str), requires = "tls_certificate")]
+        tls_key: Option< PathBuf >,
+        /// Require every request to include an `Authorization: Bearer <TOKEN>` header
+        /// (or a `token` query parameter for the server-sent events);
+        /// can also be set through the `BYTEHOUND_AUTH_TOKEN` environment variable
+        #[structopt(long = "auth-token")]
+        auth_token: Option< String >,
+        /// Require every request to be authenticated with the given `USERNAME:PASSWORD` through HTTP basic authentication;
+        /// can also be set through the `BYTEHOUND_BASIC_AUTH` environment variable
+        #[structopt(long = "basic-auth", parse(try_from_str = "parse_basic_auth"))]
+        basic_auth: Option< (String, String) >,
+        /// Disallow loading and unloading data, executing scripts (including custom filters) and saving filters, notes and permalinks through the API
+        #[structopt(long = "read-only")]
+        read_only: bool,
+        /// The maximum size, in megabytes, of a single file uploaded through the API; 0 means no limit
+        #[structopt(long = "max-upload-size", default_value = "4096")]
+        max_upload_size: u64,
+        /// A directory from which data files can be loaded by their path through the API; can be specified multiple times
+        #[structopt(long = "load-directory", parse(from_os_str))]
+        load_directories: Vec< PathBuf >,
+        /// A file in which the saved filters, notes and permalinks are kept; if not specified
+        /// they're lost when the server is stopped
+        #[structopt(long = "store", parse(from_os_str))]
+        store: Option< PathBuf >,
         #[structopt(parse(from_os_str), required = false)]
         input: Vec< PathBuf >
     },
@@ -95,9 +220,14 @@ enum Opt {
         #[structopt(parse(from_os_str), required = false)]
         input: PathBuf
     },
-    /// Generates a new data file with temporary allocations stripped away
+    /// Generates a new data file with temporary allocations stripped away,
+    /// or with only the allocations matched by a filter
     #[structopt(name = "strip")]
     Strip {
+        /// A file or directory with extra debugging symbols; can be specified multiple times
+        #[structopt(short = "d", long = "debug-symbols", parse(from_os_str))]
+        debug_symbols: Vec< PathBuf >,
+
         /// The file to which the stripped data will be written
         #[structopt(long, short = "o", parse(from_os_str))]
         output: PathBuf,
@@ -106,6 +236,43 @@ enum Opt {
         #[structopt(long, short = "t")]
         threshold: Option< u64 >,
 
+        /// A script expression returning the allocations to keep (e.g. `allocations().only_leaked()`)
+        #[structopt(long)]
+        filter: Option< String >,
+
+        /// A filter in the same format as the server's allocation query string
+        /// (e.g. `size_min=1024&lifetime=only_leaked`); only allocations matching it will be kept
+        #[structopt(long)]
+        query: Option< String >,
+
+        /// Whenever to anonymize the data
+        #[structopt(long, short = "a", parse(from_str = "parse_anonymize"), default_value="none",
+        raw(possible_values = r#"&[
+            "none",
+            "partial",
+            "full"
+        ]"#))]
+        anonymize: Anonymize,
+
+        #[structopt(parse(from_os_str), required = false)]
+        input: PathBuf
+    },
+    /// Generates a new data file with only the events from a given time window
+    #[structopt(name = "slice")]
+    Slice {
+        /// The file to which the sliced data will be written
+        #[structopt(long, short = "o", parse(from_os_str))]
+        output: PathBuf,
+
+        /// Where the window starts, relative to the start of the recording (e.g. `10m`);
+        /// allocations which are still alive at this point will be kept
+        #[structopt(long, parse(try_from_str = "parse_duration"))]
+        from: Option< Timestamp >,
+
+        /// Where the window ends, relative to the start of the recording (e.g. `25m`)
+        #[structopt(long, parse(try_from_str = "parse_duration"))]
+        to: Option< Timestamp >,
+
         #[structopt(parse(from_os_str), required = false)]
         input: PathBuf
     },
@@ -114,12 +281,32 @@ enum Opt {
         #[structopt(long)]
         disable_compression: bool,
 
+        /// The compression to use for the output; can be `lz4`, `zstd`, `zstd:<level>` or `none`
+        #[structopt(long, default_value = "lz4")]
+        compression: Compression,
+
         #[structopt(long, short = "o", parse(from_os_str))]
         output: PathBuf,
 
         #[structopt(parse(from_os_str), required = false)]
         input: PathBuf
     },
+    /// Checks a data file for truncated chunks, undecodable events and dangling references
+    #[structopt(name = "fsck")]
+    Fsck {
+        /// Writes everything which can be recovered into a new file
+        #[structopt(long, parse(from_os_str))]
+        repair: Option< PathBuf >,
+
+        #[structopt(parse(from_os_str))]
+        input: PathBuf
+    },
+    /// Prints a quick summary of a data file using its chunk index
+    #[structopt(name = "info")]
+    Info {
+        #[structopt(parse(from_os_str))]
+        input: PathBuf
+    },
     #[structopt(name = "analyze-size", raw(setting = "structopt::clap::AppSettings::Hidden"))]
     AnalyzeSize {
         input: PathBuf
@@ -147,6 +334,95 @@ enum Opt {
         #[structopt(long, short = "o", parse(from_os_str))]
         output: PathBuf,
         input: PathBuf,
+    },
+    /// Reports which backtraces allocate most frequently and generate the most short-lived allocations
+    #[structopt(name = "churn")]
+    Churn {
+        /// A file or directory with extra debugging symbols; can be specified multiple times
+        #[structopt(short = "d", long = "debug-symbols", parse(from_os_str))]
+        debug_symbols: Vec< PathBuf >,
+
+        /// The maximum lifetime, in milliseconds, of an allocation to be considered short-lived
+        #[structopt(long, short = "t", default_value = "100")]
+        threshold: u64,
+
+        /// How the backtraces should be ranked
+        #[structopt(long, short = "s", parse(from_str = "parse_churn_sort_by"), default_value = "rate",
+        raw(possible_values = r#"&[
+            "rate",
+            "short-lived"
+        ]"#))]
+        sort_by: ChurnSortBy,
+
+        /// The number of backtraces to show
+        #[structopt(long, short = "n", default_value = "20")]
+        count: usize,
+
+        /// Where to write a flamegraph of temporary allocations weighted by their count
+        #[structopt(long, short = "f", parse(from_os_str))]
+        flamegraph: Option< PathBuf >,
+
+        #[structopt(parse(from_os_str))]
+        input: PathBuf
+    },
+    /// Prints every event of a data file as JSON, one per line
+    #[structopt(name = "dump")]
+    Dump {
+        /// Only print events of this kind (e.g. `Alloc`); can be specified multiple times
+        #[structopt(long, short = "k")]
+        kind: Vec< String >,
+
+        /// Only print events which happened at least this many milliseconds after the start
+        #[structopt(long)]
+        from: Option< u64 >,
+
+        /// Only print events which happened at most this many milliseconds after the start
+        #[structopt(long)]
+        to: Option< u64 >,
+
+        /// Also print the contents of files and memory dumps instead of only their length
+        #[structopt(long)]
+        include_binary: bool,
+
+        /// The file to which the events will be written; if not specified they will be printed out
+        #[structopt(long, short = "o", parse(from_os_str))]
+        output: Option< PathBuf >,
+
+        #[structopt(parse(from_os_str))]
+        input: PathBuf
+    },
+    /// Rebuilds a data file from the output of `dump`
+    #[structopt(name = "undump")]
+    Undump {
+        /// The file to which the rebuilt data will be written
+        #[structopt(long, short = "o", parse(from_os_str))]
+        output: PathBuf,
+
+        /// Write out the elided contents of files and memory dumps as empty instead of failing
+        #[structopt(long)]
+        allow_elided: bool,
+
+        #[structopt(parse(from_os_str))]
+        input: PathBuf
+    },
+    /// Converts a heaptrack data file, a massif output file or a jemalloc heap profile into a bytehound data file
+    #[structopt(name = "import")]
+    Import {
+        /// The format of the input; will be guessed if not specified
+        #[structopt(long, short = "f", parse(from_str = "parse_import_format"),
+        raw(possible_values = r#"&[
+            "heaptrack",
+            "massif",
+            "jemalloc"
+        ]"#))]
+        format: Option< ImportFormat >,
+
+        /// The file to which the converted data will be written
+        #[structopt(long, short = "o", parse(from_os_str))]
+        output: PathBuf,
+
+        #[structopt(parse(from_os_str))]
+        input: PathBuf
     }
 }
 
@@ -168,27 +444,134 @@ fn run( opt: Opt ) -> Result< (), Box< dyn Error > > {
 
             export_as_heaptrack( &data, data_out, |_, _| true )?;
         },
+        Opt::ExportSql { debug_symbols, output, input } => {
+            let fp = File::open( input )?;
+            let data = Loader::load_from_stream( fp, debug_symbols )?;
+            export_as_sqlite( &data, output, |_, _| true )?;
+        },
+        Opt::ExportParquet { debug_symbols, output, input } => {
+            let fp = File::open( input )?;
+            let data = Loader::load_from_stream( fp, debug_symbols )?;
+            export_as_parquet( &data, output, |_, _| true )?;
+        },
         Opt::Gather { target } => {
             cli_core::cmd_gather::main( target.as_ref().map( |target| target.as_str() ) )?;
         },
         #[cfg(feature = "subcommand-server")]
-        Opt::Server { debug_symbols, input, interface, port } => {
-            server_core::main( input, debug_symbols, false, &interface, port )?;
+        Opt::Server {
+            debug_symbols, input, interface, port, session, watch, live,
+            script_max_operations, script_timeout, script_max_output_size, max_running_jobs,
+            tls_certificate, tls_key, auth_token, basic_auth, read_only,
+            max_upload_size, load_directories, store
+        } => {
+            let script_limits = cli_core::script::ScriptLimits {
+                max_operations: script_max_operations,
+                max_duration: Some( std::time::Duration::from_secs( script_timeout ) ).filter( |duration| duration.as_secs() > 0 ),
+                max_output_size: Some( script_max_output_size * 1024 * 1024 ).filter( |&size| size > 0 )
+            };
+
+            let tls = match (tls_certificate, tls_key) {
+                (Some( certificate ), Some( key )) => Some( server_core::TlsConfig { certificate, key } ),
+                _ => None
+            };
+
+            let basic_auth = match basic_auth {
+                Some( basic_auth ) => Some( basic_auth ),
+                None => match env::var( "BYTEHOUND_BASIC_AUTH" ) {
+                    Ok( value ) => Some( parse_basic_auth( &value )? ),
+                    Err( _ ) => None
+                }
+            };
+
+            let auth = server_core::Auth {
+                token: auth_token.or_else( || env::var( "BYTEHOUND_AUTH_TOKEN" ).ok() ).filter( |token| !token.is_empty() ),
+                basic: basic_auth
+            };
+
+            let max_upload_size = Some( max_upload_size * 1024 * 1024 ).filter( |&size| size > 0 );
+            server_core::main( server_core::ServerConfig {
+                inputs: input,
+                debug_symbols,
+                load_in_parallel: false,
+                as_session: session,
+                watch,
+                live,
+                script_limits,
+                tls,
+                auth,
+                read_only,
+                max_upload_size,
+                load_directories,
+                max_running_jobs,
+                store,
+                interface,
+                port
+            })?;
         },
         Opt::Postprocess { debug_symbols, output, input, anonymize } => {
             let ifp = File::open( input )?;
             let ofp = File::create( output )?;
             postprocess( ifp, ofp, debug_symbols, anonymize )?;
         },
-        Opt::Strip { output, input, threshold } => {
+        Opt::Strip { debug_symbols, output, input, threshold, anonymize, filter: None, query: None } if anonymize == Anonymize::None && debug_symbols.is_empty() => {
             let ifp = File::open( &input )?;
             let ofp = File::create( output )?;
             cli_core::squeeze_data( ifp, ofp, threshold )?;
         },
-        Opt::Repack { disable_compression, input, output } => {
+        Opt::Strip { debug_symbols, output, input, threshold, filter, query, anonymize } => {
+            if threshold.is_some() {
+                return Err( "the lifetime threshold can't be used together with a filter, anonymization or debug symbols".into() );
+            }
+
+            let fp = File::open( input )?;
+            let data = Arc::new( Loader::load_from_stream( fp, debug_symbols )? );
+
+            let script_filter: Option< HashSet< AllocationId > > = match filter {
+                Some( filter ) => {
+                    let ids = cli_core::script::evaluate_filter( data.clone(), &filter )
+                        .map_err( |error| format!( "failed to evaluate the filter: {}", error.message ) )?;
+                    Some( ids.into_iter().collect() )
+                },
+                None => None
+            };
+
+            #[cfg(feature = "subcommand-server")]
+            let query_filter = match query {
+                Some( query ) => Some( server_core::parse_filter_query( &data, &query )?.compile( &data ) ),
+                None => None
+            };
+
+            #[cfg(not(feature = "subcommand-server"))]
+            let query_filter: Option< cli_core::CompiledFilter > = match query {
+                Some( _ ) => return Err( "filtering through a query string requires the server to be compiled in".into() ),
+                None => None
+            };
+
+            let ofp = io::BufWriter::new( File::create( output )? );
+            cli_core::strip_data( &data, ofp, anonymize, |id, allocation| {
+                script_filter.as_ref().map( |set| set.contains( &id ) ).unwrap_or( true ) &&
+                query_filter.as_ref().map( |filter| filter.try_match( &data, allocation ) ).unwrap_or( true )
+            })?;
+        },
+        Opt::Slice { output, from, to, input } => {
             let ifp = File::open( &input )?;
             let ofp = File::create( output )?;
-            cli_core::repack( disable_compression, ifp, ofp )?;
+            cli_core::slice_data( ifp, ofp, from, to )?;
+        },
+        Opt::Repack { disable_compression, compression, input, output } => {
+            let compression = if disable_compression { Compression::None } else { compression };
+            let ifp = File::open( &input )?;
+            let ofp = File::create( output )?;
+            cli_core::repack( compression, ifp, ofp )?;
+        },
+        Opt::Fsck { input, repair } => {
+            let is_ok = cli_core::cmd_fsck::fsck( &input, repair.as_ref().map( |path| path.as_path() ) )?;
+            if !is_ok && repair.is_none() {
+                return Err( format!( "{:?} is corrupted; use `--repair` to recover what's left", input ).into() );
+            }
+        },
+        Opt::Info { input } => {
+            cli_core::cmd_info::print_info( &input )?;
         },
         Opt::AnalyzeSize { input } => {
             let ifp = File::open( &input )?;
@@ -203,6 +586,45 @@ fn run( opt: Opt ) -> Result< (), Box< dyn Error > > {
         Opt::Extract { input, output } => {
             cli_core::cmd_extract::extract( input, output )?;
         },
+        Opt::Churn { debug_symbols, threshold, sort_by, count, flamegraph, input } => {
+            let fp = File::open( input )?;
+            let data = Loader::load_from_stream( fp, debug_symbols )?;
+            cli_core::cmd_analyze_churn::analyze_churn(
+                &data,
+                Timestamp::from_msecs( threshold ),
+                sort_by,
+                count,
+                flamegraph.as_ref().map( |path| path.as_path() )
+            )?;
+        },
+        Opt::Dump { kind, from, to, include_binary, output, input } => {
+            let options = cli_core::cmd_dump::DumpOptions {
+                kinds: kind,
+                from: from.map( Timestamp::from_msecs ),
+                to: to.map( Timestamp::from_msecs ),
+                include_binary
+            };
+
+            let ifp = File::open( input )?;
+            if let Some( output ) = output {
+                let ofp = io::BufWriter::new( File::create( output )? );
+                cli_core::cmd_dump::dump( ifp, ofp, options )?;
+            } else {
+                let stdout = io::stdout();
+                let ofp = io::BufWriter::new( stdout.lock() );
+                cli_core::cmd_dump::dump( ifp, ofp, options )?;
+            }
+        },
+        Opt::Undump { output, allow_elided, input } => {
+            let ifp = io::BufReader::new( File::open( input )? );
+            let ofp = io::BufWriter::new( File::create( output )? );
+            cli_core::cmd_dump::undump( ifp, ofp, allow_elided )?;
+        },
+        Opt::Import { format, output, input } => {
+            let ifp = File::open( input )?;
+            let ofp = io::BufWriter::new( File::create( output )? );
+            cli_core::import( format, ifp, ofp )?;
+        },
     }
 
     Ok(())
@@ -0,0 +1,292 @@
+//! A client for the REST API exposed by `bytehound server`.
+//!
+//! ```no_run
+//! use bytehound_client::{Client, AllocFilter, AllocationGroupsParams, LifetimeFilter};
+//!
+//! let client = Client::new( "http://localhost:8080" );
+//! let filter = AllocFilter {
+//!     lifetime: Some( LifetimeFilter::OnlyLeaked ),
+//!     .. AllocFilter::default()
+//! };
+//!
+//! let groups = client.allocation_groups( "last", &filter, &AllocationGroupsParams::default() ).unwrap();
+//! println!( "{} groups of leaked allocations", groups.total_count );
+//! ```
+//!
+//! The full description of the API is served by the server itself at `/openapi.json`;
+//! the endpoints which don't have a dedicated method here can be queried through [`Client::get`].
+
+#[macro_use]
+extern crate serde_derive;
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+mod types;
+pub use crate::types::*;
+
+#[derive(Debug)]
+pub enum Error {
+    Http( attohttpc::Error ),
+    /// The server has responded with an error.
+    Status( u16, String ),
+    Json( serde_json::Error ),
+    Query( serde_urlencoded::ser::Error ),
+    /// The job has failed or was cancelled.
+    Job( String )
+}
+
+impl fmt::Display for Error {
+    fn fmt( &self, fmt: &mut fmt::Formatter ) -> fmt::Result {
+        match *self {
+            Error::Http( ref error ) => write!( fmt, "request failed: {}", error ),
+            Error::Status( status, ref message ) => write!( fmt, "request failed with status {}: {}", status, message ),
+            Error::Json( ref error ) => write!( fmt, "failed to parse the response: {}", error ),
+            Error::Query( ref error ) => write!( fmt, "failed to serialize the query: {}", error ),
+            Error::Job( ref error ) => write!( fmt, "job failed: {}", error )
+        }
+    }
+}
+
+impl StdError for Error {}
+
+impl From< attohttpc::Error > for Error {
+    fn from( error: attohttpc::Error ) -> Self {
+        Error::Http( error )
+    }
+}
+
+impl From< serde_json::Error > for Error {
+    fn from( error: serde_json::Error ) -> Self {
+        Error::Json( error )
+    }
+}
+
+impl From< serde_urlencoded::ser::Error > for Error {
+    fn from( error: serde_urlencoded::ser::Error ) -> Self {
+        Error::Query( error )
+    }
+}
+
+/// Builds a query string out of multiple serializable parts.
+#[derive(Default)]
+pub struct Query {
+    query: String
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Query::default()
+    }
+
+    pub fn add< T: Serialize >( mut self, params: &T ) -> Result< Self, Error > {
+        let params = serde_urlencoded::to_string( params )?;
+        if !params.is_empty() {
+            if !self.query.is_empty() {
+                self.query.push( '&' );
+            }
+            self.query.push_str( &params );
+        }
+
+        Ok( self )
+    }
+
+    pub fn as_str( &self ) -> &str {
+        &self.query
+    }
+}
+
+/// How often the status of a job is checked when waiting for it to finish.
+const JOB_POLL_INTERVAL: Duration = Duration::from_millis( 250 );
+
+pub struct Client {
+    base_url: String,
+    authorization: Option< String >
+}
+
+impl Client {
+    /// Creates a new client for the server at the given URL, e.g. `http://localhost:8080`.
+    pub fn new( base_url: impl Into< String > ) -> Self {
+        let mut base_url = base_url.into();
+        while base_url.ends_with( '/' ) {
+            base_url.pop();
+        }
+
+        Client {
+            base_url,
+            authorization: None
+        }
+    }
+
+    /// Authenticates with a token passed to the server through `--auth-token`.
+    pub fn with_token( mut self, token: &str ) -> Self {
+        self.authorization = Some( format!( "Bearer {}", token ) );
+        self
+    }
+
+    /// Authenticates with the credentials passed to the server through `--basic-auth`.
+    pub fn with_basic_auth( mut self, username: &str, password: &str ) -> Self {
+        self.authorization = Some( format!( "Basic {}", base64::encode( &format!( "{}:{}", username, password ) ) ) );
+        self
+    }
+
+    fn url( &self, path: &str, query: &Query ) -> String {
+        if query.as_str().is_empty() {
+            format!( "{}{}", self.base_url, path )
+        } else {
+            format!( "{}{}?{}", self.base_url, path, query.as_str() )
+        }
+    }
+
+    fn send( &self, request: attohttpc::RequestBuilder ) -> Result< attohttpc::Response, Error > {
+        let request = match self.authorization {
+            Some( ref authorization ) => request.header( attohttpc::header::AUTHORIZATION, authorization.as_str() ),
+            None => request
+        };
+
+        let response = request.send()?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().unwrap_or_default();
+            return Err( Error::Status( status.as_u16(), message ) );
+        }
+
+        Ok( response )
+    }
+
+    /// Fetches the raw body of a response.
+    pub fn get_bytes( &self, path: &str, query: &Query ) -> Result< Vec< u8 >, Error > {
+        let response = self.send( attohttpc::get( &self.url( path, query ) ) )?;
+        Ok( response.bytes()? )
+    }
+
+    /// Fetches any endpoint which returns JSON, e.g. `client.get( "/data/last/slack", &Query::new() )`.
+    pub fn get< T: DeserializeOwned >( &self, path: &str, query: &Query ) -> Result< T, Error > {
+        let response = self.send( attohttpc::get( &self.url( path, query ) ) )?;
+        Ok( serde_json::from_str( &response.text()? )? )
+    }
+
+    fn post< T: DeserializeOwned >( &self, path: &str, query: &Query ) -> Result< T, Error > {
+        let response = self.send( attohttpc::post( &self.url( path, query ) ) )?;
+        Ok( serde_json::from_str( &response.text()? )? )
+    }
+
+    fn delete( &self, path: &str ) -> Result< (), Error > {
+        self.send( attohttpc::delete( &self.url( path, &Query::new() ) ) )?;
+        Ok(())
+    }
+
+    /// Returns the OpenAPI description of the server's API.
+    pub fn openapi( &self ) -> Result< serde_json::Value, Error > {
+        self.get( "/openapi.json", &Query::new() )
+    }
+
+    /// Lists the loaded data files.
+    pub fn list( &self ) -> Result< Vec< Metadata >, Error > {
+        self.get( "/list", &Query::new() )
+    }
+
+    /// Starts loading a data file which is on the same machine as the server;
+    /// the progress can be checked with [`Client::loading`].
+    pub fn load( &self, path: &str ) -> Result< u64, Error > {
+        #[derive(Serialize)]
+        struct Params< 'a > {
+            path: &'a str
+        }
+
+        let response: LoadStarted = self.post( "/load", &Query::new().add( &Params { path } )? )?;
+        Ok( response.id )
+    }
+
+    pub fn loading( &self ) -> Result< Vec< LoadingProgress >, Error > {
+        self.get( "/loading", &Query::new() )
+    }
+
+    pub fn unload( &self, id: &str ) -> Result< (), Error > {
+        self.delete( &format!( "/data/{}", id ) )
+    }
+
+    pub fn timeline( &self, id: &str ) -> Result< Timeline, Error > {
+        self.get( &format!( "/data/{}/timeline", id ), &Query::new() )
+    }
+
+    /// Returns the memory usage over a given time range of only the allocations matching the filter;
+    /// the time range is selected through `params`, so the `from` and `to` of the filter must be left empty.
+    pub fn filtered_timeline( &self, id: &str, params: &TimelineParams, filter: &AllocFilter ) -> Result< Timeline, Error > {
+        self.get( &format!( "/data/{}/timeline", id ), &Query::new().add( params )?.add( filter )? )
+    }
+
+    pub fn backtrace( &self, id: &str, backtrace_id: u32 ) -> Result< Backtrace, Error > {
+        self.get( &format!( "/data/{}/backtrace/{}", id, backtrace_id ), &Query::new() )
+    }
+
+    pub fn allocations( &self, id: &str, filter: &AllocFilter, params: &AllocationsParams ) -> Result< Allocations, Error > {
+        self.get( &format!( "/data/{}/allocations", id ), &Query::new().add( filter )?.add( params )? )
+    }
+
+    pub fn allocation_groups( &self, id: &str, filter: &AllocFilter, params: &AllocationGroupsParams ) -> Result< AllocationGroups, Error > {
+        self.get( &format!( "/data/{}/allocation_groups", id ), &Query::new().add( filter )?.add( params )? )
+    }
+
+    /// Downloads a whole list (`allocations`, `allocation_groups`, `backtraces`, `mmaps` or `mallopts`)
+    /// as CSV, with only the innermost frames of every backtrace.
+    pub fn csv( &self, id: &str, list: &str, query: &Query ) -> Result< String, Error > {
+        let query = Query { query: query.query.clone() }.add( &[("format", "csv")] )?;
+        let bytes = self.get_bytes( &format!( "/data/{}/{}", id, list ), &query )?;
+        Ok( String::from_utf8_lossy( &bytes ).into_owned() )
+    }
+
+    /// Starts an expensive query (e.g. `allocation_groups` or `flamegraph`) in the background.
+    pub fn submit_job( &self, id: &str, kind: &str, query: &Query ) -> Result< Job, Error > {
+        self.post( &format!( "/data/{}/jobs/{}", id, kind ), query )
+    }
+
+    pub fn job( &self, job_id: u64 ) -> Result< Job, Error > {
+        self.get( &format!( "/jobs/{}", job_id ), &Query::new() )
+    }
+
+    pub fn cancel_job( &self, job_id: u64 ) -> Result< (), Error > {
+        self.delete( &format!( "/jobs/{}", job_id ) )
+    }
+
+    /// Waits until a job finishes and returns its result.
+    pub fn wait_for_job( &self, job_id: u64 ) -> Result< Vec< u8 >, Error > {
+        loop {
+            let job = self.job( job_id )?;
+            match job.status {
+                JobStatus::Running => thread::sleep( JOB_POLL_INTERVAL ),
+                JobStatus::Failed => return Err( Error::Job( job.error.unwrap_or_default() ) ),
+                JobStatus::Finished => return self.get_bytes( &format!( "/jobs/{}/result", job_id ), &Query::new() )
+            }
+        }
+    }
+}
+
+#[test]
+fn test_query() {
+    let filter = AllocFilter {
+        size_min: Some( 1024 ),
+        lifetime: Some( LifetimeFilter::OnlyLeaked ),
+        .. AllocFilter::default()
+    };
+
+    let params = AllocationGroupsParams {
+        sort_by: Some( AllocGroupsSortBy::GlobalSize ),
+        .. AllocationGroupsParams::default()
+    };
+
+    let query = Query::new().add( &filter ).unwrap().add( &params ).unwrap();
+    assert_eq!( query.as_str(), "lifetime=only_leaked&size_min=1024&sort_by=all.size" );
+    assert_eq!( Query::new().add( &AllocFilter::default() ).unwrap().as_str(), "" );
+
+    let filter = AllocFilter {
+        backtraces: vec![ 1, 5 ],
+        .. AllocFilter::default()
+    };
+    assert_eq!( Query::new().add( &filter ).unwrap().as_str(), "backtraces=1%2C5" );
+}
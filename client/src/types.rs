@@ -0,0 +1,325 @@
+//! The requests and the responses of the REST API; see `/openapi.json` for their full description.
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Debug, Hash)]
+pub struct Timeval {
+    pub secs: u64,
+    pub fract_nsecs: u32
+}
+
+impl Timeval {
+    pub fn as_secs_f64( &self ) -> f64 {
+        self.secs as f64 + self.fract_nsecs as f64 / 1_000_000_000.0
+    }
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Metadata {
+    pub id: String,
+    pub executable: String,
+    pub cmdline: String,
+    pub architecture: String,
+    pub final_allocated: u64,
+    pub final_allocated_count: u64,
+    pub runtime: Timeval,
+    pub unique_backtrace_count: u64,
+    pub maximum_backtrace_depth: u32,
+    pub timestamp: Timeval,
+    pub pid: u32,
+    pub parent_pid: Option< u32 >
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Timeline {
+    pub xs: Vec< u64 >,
+    pub size_delta: Vec< i64 >,
+    pub count_delta: Vec< i64 >,
+    pub allocated_size: Vec< u64 >,
+    pub allocated_count: Vec< u64 >,
+    pub allocations: Vec< u32 >,
+    pub deallocations: Vec< u32 >,
+    pub allocated_slack: Vec< u64 >
+}
+
+#[derive(Clone, PartialEq, Deserialize, Debug)]
+pub struct Frame {
+    pub address: u64,
+    pub address_s: String,
+    pub count: u64,
+    pub library: Option< String >,
+    pub function: Option< String >,
+    pub raw_function: Option< String >,
+    pub source: Option< String >,
+    pub line: Option< u32 >,
+    pub column: Option< u32 >,
+    pub is_inline: bool
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Backtrace {
+    pub frames: Vec< Frame >
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Deallocation {
+    pub timestamp: Timeval,
+    pub thread: u32,
+    pub backtrace_id: Option< u32 >,
+    pub backtrace: Option< Vec< Frame > >
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Allocation {
+    pub id: u64,
+    pub address: u64,
+    pub address_s: String,
+    pub timestamp: Timeval,
+    pub timestamp_relative: Timeval,
+    pub timestamp_relative_p: f32,
+    pub thread: u32,
+    pub size: u64,
+    pub backtrace_id: u32,
+    pub deallocation: Option< Deallocation >,
+    pub chain_deallocation: Option< Deallocation >,
+    pub backtrace: Vec< Frame >,
+    pub is_mmaped: bool,
+    pub is_jemalloc: bool,
+    pub in_main_arena: bool,
+    pub extra_space: u32,
+    pub chain_lifetime: Option< Timeval >,
+    pub position_in_chain: u32,
+    pub chain_length: u32
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Allocations {
+    pub allocations: Vec< Allocation >,
+    pub total_count: u64
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct AllocationGroupData {
+    pub size: u64,
+    pub min_size: u64,
+    pub max_size: u64,
+    pub min_timestamp: Timeval,
+    pub min_timestamp_relative: Timeval,
+    pub min_timestamp_relative_p: f32,
+    pub max_timestamp: Timeval,
+    pub max_timestamp_relative: Timeval,
+    pub max_timestamp_relative_p: f32,
+    pub interval: Timeval,
+    pub leaked_count: u64,
+    pub allocated_count: u64,
+    pub cross_thread_freed_count: u64,
+    pub cross_thread_ratio: f32,
+    pub slack: u64,
+    pub graph_preview_url: Option< String >,
+    pub graph_url: Option< String >,
+    pub max_total_usage_first_seen_at: Option< Timeval >,
+    pub max_total_usage_first_seen_at_relative: Option< Timeval >,
+    pub max_total_usage_first_seen_at_relative_p: Option< f32 >
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct AllocationGroup {
+    pub all: AllocationGroupData,
+    pub only_matched: AllocationGroupData,
+    pub backtrace_id: u32,
+    pub backtrace: Vec< Frame >
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct AllocationGroups {
+    pub allocations: Vec< AllocationGroup >,
+    pub total_count: u64
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct LoadStarted {
+    pub id: u64
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct LoadingProgress {
+    pub id: u64,
+    pub name: String,
+    pub loaded: u64,
+    pub total: u64,
+    pub error: Option< String >
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug)]
+pub enum JobStatus {
+    #[serde(rename = "running")]
+    Running,
+    #[serde(rename = "finished")]
+    Finished,
+    #[serde(rename = "failed")]
+    Failed
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct Job {
+    pub id: u64,
+    pub kind: String,
+    pub status: JobStatus,
+    pub progress: Option< f64 >,
+    pub elapsed: f64,
+    pub error: Option< String >
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Debug)]
+pub enum LifetimeFilter {
+    #[serde(rename = "all")]
+    All,
+    #[serde(rename = "only_leaked")]
+    OnlyLeaked,
+    #[serde(rename = "only_chain_leaked")]
+    OnlyChainLeaked,
+    #[serde(rename = "only_not_deallocated_in_current_range")]
+    OnlyNotDeallocatedInCurrentRange,
+    #[serde(rename = "only_deallocated_in_current_range")]
+    OnlyDeallocatedInCurrentRange,
+    #[serde(rename = "only_temporary")]
+    OnlyTemporary,
+    #[serde(rename = "only_whole_group_leaked")]
+    OnlyWholeGroupLeaked
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Debug)]
+pub enum Order {
+    #[serde(rename = "asc")]
+    Asc,
+    #[serde(rename = "dsc")]
+    Dsc
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Debug)]
+pub enum AllocSortBy {
+    #[serde(rename = "timestamp")]
+    Timestamp,
+    #[serde(rename = "address")]
+    Address,
+    #[serde(rename = "size")]
+    Size
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Serialize, Debug)]
+pub enum AllocGroupsSortBy {
+    #[serde(rename = "only_matched.min_timestamp")]
+    MinTimestamp,
+    #[serde(rename = "only_matched.max_timestamp")]
+    MaxTimestamp,
+    #[serde(rename = "only_matched.interval")]
+    Interval,
+    #[serde(rename = "only_matched.allocated_count")]
+    AllocatedCount,
+    #[serde(rename = "only_matched.leaked_count")]
+    LeakedCount,
+    #[serde(rename = "only_matched.size")]
+    Size,
+    #[serde(rename = "only_matched.slack")]
+    Slack,
+    #[serde(rename = "all.min_timestamp")]
+    GlobalMinTimestamp,
+    #[serde(rename = "all.max_timestamp")]
+    GlobalMaxTimestamp,
+    #[serde(rename = "all.interval")]
+    GlobalInterval,
+    #[serde(rename = "all.allocated_count")]
+    GlobalAllocatedCount,
+    #[serde(rename = "all.leaked_count")]
+    GlobalLeakedCount,
+    #[serde(rename = "all.size")]
+    GlobalSize,
+    #[serde(rename = "all.slack")]
+    GlobalSlack,
+    #[serde(rename = "all.max_total_usage_first_seen_at")]
+    GlobalMaxTotalUsageFirstSeenAt
+}
+
+fn serialize_ids< S: serde::Serializer >( ids: &[u32], serializer: S ) -> Result< S::Ok, S::Error > {
+    let ids: Vec< _ > = ids.iter().map( |id| id.to_string() ).collect();
+    serializer.serialize_str( &ids.join( "," ) )
+}
+
+/// A filter for the allocations; the fields which are `None` (or empty) are ignored.
+///
+/// The time related fields accept the same formats as the web UI,
+/// e.g. `10s` or `50%` for the intervals.
+#[derive(Clone, Default, Serialize, Debug)]
+pub struct AllocFilter {
+    pub from: Option< String >,
+    pub to: Option< String >,
+    pub lifetime: Option< LifetimeFilter >,
+    pub address_min: Option< u64 >,
+    pub address_max: Option< u64 >,
+    pub size_min: Option< u64 >,
+    pub size_max: Option< u64 >,
+    pub first_size_min: Option< u64 >,
+    pub first_size_max: Option< u64 >,
+    pub last_size_min: Option< u64 >,
+    pub last_size_max: Option< u64 >,
+    pub lifetime_min: Option< String >,
+    pub lifetime_max: Option< String >,
+    pub backtrace_depth_min: Option< u32 >,
+    pub backtrace_depth_max: Option< u32 >,
+    /// Only the allocations with any of these backtraces.
+    #[serde(skip_serializing_if = "Vec::is_empty", serialize_with = "serialize_ids")]
+    pub backtraces: Vec< u32 >,
+    #[serde(skip_serializing_if = "Vec::is_empty", serialize_with = "serialize_ids")]
+    pub deallocation_backtraces: Vec< u32 >,
+    /// Only the allocations made on any of these threads.
+    #[serde(skip_serializing_if = "Vec::is_empty", serialize_with = "serialize_ids")]
+    pub threads: Vec< u32 >,
+    pub function_regex: Option< String >,
+    pub source_regex: Option< String >,
+    pub negative_function_regex: Option< String >,
+    pub negative_source_regex: Option< String >,
+    /// Only the allocations with any of these markers.
+    #[serde(skip_serializing_if = "Vec::is_empty", serialize_with = "serialize_ids")]
+    pub marker: Vec< u32 >,
+    pub group_interval_min: Option< String >,
+    pub group_interval_max: Option< String >,
+    pub group_leaked_allocations_min: Option< String >,
+    pub group_leaked_allocations_max: Option< String >,
+    pub group_allocations_min: Option< u32 >,
+    pub group_allocations_max: Option< u32 >,
+    pub chain_length_min: Option< u32 >,
+    pub chain_length_max: Option< u32 >,
+    pub chain_lifetime_min: Option< String >,
+    pub chain_lifetime_max: Option< String >,
+    pub position_in_chain_min: Option< u32 >,
+    pub position_in_chain_max: Option< u32 >,
+    /// A boolean combination of other filters, e.g. `{"or": ["size_min=1024", {"not": "lifetime=only_leaked"}]}`,
+    /// where every leaf is a query string built with [`crate::Query`].
+    pub expression: Option< String >,
+    /// A script which further filters the allocations.
+    pub custom_filter: Option< String >
+}
+
+#[derive(Clone, Default, Serialize, Debug)]
+pub struct AllocationsParams {
+    pub skip: Option< u64 >,
+    pub count: Option< u32 >,
+    pub sort_by: Option< AllocSortBy >,
+    pub order: Option< Order >,
+    pub strip_template_args: Option< bool >
+}
+
+#[derive(Clone, Default, Serialize, Debug)]
+pub struct AllocationGroupsParams {
+    pub skip: Option< u64 >,
+    pub count: Option< u32 >,
+    pub sort_by: Option< AllocGroupsSortBy >,
+    pub order: Option< Order >,
+    pub strip_template_args: Option< bool >
+}
+
+#[derive(Clone, Default, Serialize, Debug)]
+pub struct TimelineParams {
+    /// The beginning of the time range, in the same format as the `from` of [`AllocFilter`].
+    pub from: Option< String >,
+    pub to: Option< String >,
+    pub max_points: Option< u32 >
+}
@@ -0,0 +1,240 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use byteorder::{ReadBytesExt, LittleEndian};
+use speedy::{Readable, Writable};
+
+use crate::event::Event;
+use crate::timestamp::Timestamp;
+
+pub(crate) const INDEX_MAGIC: &[u8; 8] = b"BHINDEX1";
+pub(crate) const FOOTER_SIZE: usize = 16;
+
+#[derive(Clone, PartialEq, Debug, Readable, Writable)]
+pub struct ChunkIndexEntry {
+    /// The offset of the chunk in the file.
+    pub offset: u64,
+    /// The size of the chunk in the file, including its header.
+    pub compressed_length: u64,
+    /// The size of the chunk once decompressed.
+    pub length: u32,
+    /// Where the first event which starts in this chunk is located, relative to its decompressed data.
+    pub first_event: Option< u32 >,
+    pub event_count: u32,
+    pub min_timestamp: Option< Timestamp >,
+    pub max_timestamp: Option< Timestamp >,
+    /// Whenever any backtraces, strings, frames, maps, etc. start in this chunk.
+    pub has_definitions: bool
+}
+
+/// An index of the chunks of a data file.
+///
+/// It's written as the very last chunk of a file and can be found
+/// through a fixed size footer at the very end of the file, which
+/// allows for random access without decompressing everything.
+#[derive(Clone, PartialEq, Debug, Default, Readable, Writable)]
+pub struct ChunkIndex {
+    pub chunks: Vec< ChunkIndexEntry >
+}
+
+impl ChunkIndex {
+    pub fn event_count( &self ) -> u64 {
+        self.chunks.iter().map( |chunk| chunk.event_count as u64 ).sum()
+    }
+
+    pub fn compressed_size( &self ) -> u64 {
+        self.chunks.iter().map( |chunk| chunk.compressed_length ).sum()
+    }
+
+    pub fn uncompressed_size( &self ) -> u64 {
+        self.chunks.iter().map( |chunk| chunk.length as u64 ).sum()
+    }
+
+    pub fn time_range( &self ) -> Option< (Timestamp, Timestamp) > {
+        let min = self.chunks.iter().filter_map( |chunk| chunk.min_timestamp ).min()?;
+        let max = self.chunks.iter().filter_map( |chunk| chunk.max_timestamp ).max()?;
+        Some( (min, max) )
+    }
+
+    /// Returns the chunks which have to be read to get every event within the given time range,
+    /// not counting the chunks with definitions which come before them.
+    pub fn chunks_in_range( &self, from: Timestamp, to: Timestamp ) -> impl Iterator< Item = &ChunkIndexEntry > {
+        self.chunks.iter().filter( move |chunk| {
+            match (chunk.min_timestamp, chunk.max_timestamp) {
+                (Some( min ), Some( max )) => min <= to && max >= from,
+                _ => false
+            }
+        })
+    }
+
+    /// Returns the chunks which define backtraces, strings, etc.
+    pub fn checkpoints( &self ) -> impl Iterator< Item = &ChunkIndexEntry > {
+        self.chunks.iter().filter( |chunk| chunk.has_definitions )
+    }
+}
+
+/// Returns the timestamp of a given event and whenever other events can refer to it.
+pub fn index_properties( event: &Event ) -> (Option< Timestamp >, bool) {
+    match *event {
+        Event::Alloc { timestamp, .. } |
+        Event::AllocEx { timestamp, .. } |
+        Event::Realloc { timestamp, .. } |
+        Event::ReallocEx { timestamp, .. } |
+        Event::Free { timestamp, .. } |
+        Event::FreeEx { timestamp, .. } |
+        Event::MemoryMap { timestamp, .. } |
+        Event::MemoryUnmap { timestamp, .. } |
+        Event::Mallopt { timestamp, .. } |
+        Event::WallClock { timestamp, .. } => (Some( timestamp ), false),
+        Event::File { timestamp, .. } |
+        Event::File64 { timestamp, .. } => (Some( timestamp ), true),
+        Event::Header( .. ) |
+        Event::Backtrace { .. } |
+        Event::Backtrace32 { .. } |
+        Event::PartialBacktrace { .. } |
+        Event::PartialBacktrace32 { .. } |
+        Event::String { .. } |
+        Event::DecodedFrame { .. } |
+        Event::DecodedBacktrace { .. } |
+        Event::Environ { .. } |
+        Event::ProcessInfo { .. } => (None, true),
+        Event::MemoryDump { .. } |
+        Event::Marker { .. } |
+        Event::GroupStatistics { .. } => (None, false)
+    }
+}
+
+struct Mark {
+    position: u64,
+    timestamp: Option< Timestamp >,
+    is_definition: bool
+}
+
+/// Gathers the index while the chunks are being written.
+pub struct ChunkIndexBuilder {
+    offset: u64,
+    position: u64,
+    marks: Vec< Mark >,
+    index: ChunkIndex
+}
+
+impl ChunkIndexBuilder {
+    /// Creates a new builder; the `offset` is where in the file the next chunk will be written.
+    pub fn new( offset: u64 ) -> Self {
+        ChunkIndexBuilder {
+            offset,
+            position: 0,
+            marks: Vec::new(),
+            index: ChunkIndex::default()
+        }
+    }
+
+    pub(crate) fn offset( &self ) -> u64 {
+        self.offset
+    }
+
+    pub(crate) fn mark( &mut self, buffered: usize, timestamp: Option< Timestamp >, is_definition: bool ) {
+        self.marks.push( Mark {
+            position: self.position + buffered as u64,
+            timestamp,
+            is_definition
+        });
+    }
+
+    pub(crate) fn push_chunk( &mut self, compressed_length: u64, length: u32 ) {
+        let start = self.position;
+        let end = start + length as u64;
+        let count = self.marks.iter().take_while( |mark| mark.position < end ).count();
+
+        let mut entry = ChunkIndexEntry {
+            offset: self.offset,
+            compressed_length,
+            length,
+            first_event: None,
+            event_count: count as u32,
+            min_timestamp: None,
+            max_timestamp: None,
+            has_definitions: false
+        };
+
+        for mark in self.marks.drain( ..count ) {
+            if entry.first_event.is_none() {
+                entry.first_event = Some( (mark.position - start) as u32 );
+            }
+
+            if let Some( timestamp ) = mark.timestamp {
+                entry.min_timestamp = Some( entry.min_timestamp.map( |min| std::cmp::min( min, timestamp ) ).unwrap_or( timestamp ) );
+                entry.max_timestamp = Some( entry.max_timestamp.map( |max| std::cmp::max( max, timestamp ) ).unwrap_or( timestamp ) );
+            }
+
+            entry.has_definitions |= mark.is_definition;
+        }
+
+        self.index.chunks.push( entry );
+        self.offset += compressed_length;
+        self.position = end;
+    }
+
+    pub fn finish( self ) -> ChunkIndex {
+        self.index
+    }
+}
+
+/// Reads the chunk index from the end of a data file, if it has one.
+///
+/// This leaves the file at an unspecified position.
+pub fn read_chunk_index< F: Read + Seek >( fp: &mut F ) -> io::Result< Option< ChunkIndex > > {
+    let size = fp.seek( SeekFrom::End( 0 ) )?;
+    if size < FOOTER_SIZE as u64 {
+        return Ok( None );
+    }
+
+    fp.seek( SeekFrom::End( -(FOOTER_SIZE as i64) ) )?;
+    let offset = fp.read_u64::< LittleEndian >()?;
+    let mut magic = [0; 8];
+    fp.read_exact( &mut magic )?;
+    if &magic != INDEX_MAGIC || offset >= size {
+        return Ok( None );
+    }
+
+    fp.seek( SeekFrom::Start( offset ) )?;
+    if fp.read_u8()? != crate::lz4_stream::KIND_INDEX {
+        return Err( io::Error::new( io::ErrorKind::InvalidData, "the chunk index footer points to a chunk which is not an index" ) );
+    }
+
+    let length = fp.read_u32::< LittleEndian >()? as usize;
+    if length < FOOTER_SIZE {
+        return Err( io::Error::new( io::ErrorKind::InvalidData, "the chunk index is too short" ) );
+    }
+
+    let mut buffer = vec![ 0; length ];
+    fp.read_exact( &mut buffer )?;
+    let index = ChunkIndex::read_from_buffer( &buffer[ ..length - FOOTER_SIZE ] )
+        .map_err( |error| io::Error::new( io::ErrorKind::InvalidData, format!( "failed to parse the chunk index: {}", error ) ) )?;
+
+    Ok( Some( index ) )
+}
+
+#[test]
+fn test_marks_are_assigned_to_chunks() {
+    let mut builder = ChunkIndexBuilder::new( 100 );
+    builder.mark( 0, None, true );
+    builder.mark( 10, Some( Timestamp::from_secs( 2 ) ), false );
+    builder.push_chunk( 20, 15 );
+    builder.mark( 5, Some( Timestamp::from_secs( 1 ) ), false );
+    builder.push_chunk( 30, 10 );
+    builder.push_chunk( 40, 10 );
+    builder.mark( 0, Some( Timestamp::from_secs( 3 ) ), false );
+    builder.push_chunk( 10, 10 );
+
+    let index = builder.finish();
+    let offsets: Vec< _ > = index.chunks.iter().map( |chunk| chunk.offset ).collect();
+    assert_eq!( offsets, vec![ 100, 120, 150, 190 ] );
+
+    let first_events: Vec< _ > = index.chunks.iter().map( |chunk| chunk.first_event ).collect();
+    assert_eq!( first_events, vec![ Some( 0 ), Some( 5 ), None, Some( 0 ) ] );
+
+    assert!( index.chunks[ 0 ].has_definitions );
+    assert_eq!( index.chunks[ 0 ].event_count, 2 );
+    assert_eq!( index.time_range(), Some( (Timestamp::from_secs( 1 ), Timestamp::from_secs( 3 )) ) );
+    assert_eq!( index.chunks_in_range( Timestamp::from_secs( 3 ), Timestamp::from_secs( 4 ) ).count(), 1 );
+}
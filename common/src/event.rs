@@ -8,6 +8,13 @@ use crate::timestamp::Timestamp;
 
 pub const HEADER_FLAG_IS_LITTLE_ENDIAN: u64 = 1;
 
+/// The format version is stored in the upper half of the header's flags.
+///
+/// Version `0` covers every file written before the version was recorded.
+/// Version `1` adds the `ProcessInfo` event, zstd compressed and uncompressed chunks, and the chunk index.
+pub const FORMAT_VERSION: u32 = 1;
+const HEADER_FORMAT_VERSION_SHIFT: u64 = 32;
+
 #[derive(Clone, PartialEq, Debug, Readable, Writable)]
 pub struct HeaderBody {
     pub id: DataId,
@@ -23,6 +30,16 @@ pub struct HeaderBody {
     pub pointer_size: u8
 }
 
+impl HeaderBody {
+    pub fn format_version( &self ) -> u32 {
+        (self.flags >> HEADER_FORMAT_VERSION_SHIFT) as u32
+    }
+
+    pub fn set_format_version( &mut self, version: u32 ) {
+        self.flags = (self.flags & 0xFFFFFFFF) | ((version as u64) << HEADER_FORMAT_VERSION_SHIFT);
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Readable, Writable)]
 pub struct DataId( u64, u64 );
 
@@ -248,6 +265,9 @@ pub enum Event< 'a > {
         #[speedy(length_type = u64)]
         contents: Cow< 'a, [u8] >
     },
+    ProcessInfo {
+        parent_pid: u32
+    },
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
@@ -1,8 +1,10 @@
 pub extern crate speedy;
+pub extern crate zstd;
 
 mod os_util;
 mod timestamp;
 
+pub mod chunk_index;
 pub mod event;
 pub mod lz4_stream;
 pub mod request;
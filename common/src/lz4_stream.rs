@@ -1,10 +1,74 @@
 use std::cmp::min;
+use std::fmt;
 use std::io::{self, Write};
+use std::str::FromStr;
 use lz4_compress;
+use speedy::Writable;
+use zstd;
+
+use crate::chunk_index::{ChunkIndexBuilder, ChunkIndexEntry, INDEX_MAGIC};
+use crate::timestamp::Timestamp;
 use byteorder::{ReadBytesExt, WriteBytesExt, LittleEndian, ByteOrder};
 
 const CHUNK_SIZE: usize = 512 * 1024;
 
+const KIND_LZ4: u8 = 1;
+const KIND_UNCOMPRESSED: u8 = 2;
+const KIND_ZSTD: u8 = 3;
+pub(crate) const KIND_INDEX: u8 = 4;
+
+pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+/// The compression used for the chunks written by the `Lz4Writer`.
+///
+/// The reader always accepts every kind of chunk, so this can be
+/// freely changed in the middle of the stream.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Compression {
+    None,
+    Lz4,
+    Zstd( i32 )
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression::Lz4
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt( &self, fmt: &mut fmt::Formatter ) -> fmt::Result {
+        match *self {
+            Compression::None => write!( fmt, "none" ),
+            Compression::Lz4 => write!( fmt, "lz4" ),
+            Compression::Zstd( level ) => write!( fmt, "zstd:{}", level )
+        }
+    }
+}
+
+impl FromStr for Compression {
+    type Err = &'static str;
+    fn from_str( value: &str ) -> Result< Self, Self::Err > {
+        match value {
+            "none" => return Ok( Compression::None ),
+            "lz4" => return Ok( Compression::Lz4 ),
+            "zstd" => return Ok( Compression::Zstd( DEFAULT_ZSTD_LEVEL ) ),
+            _ => {}
+        }
+
+        if value.starts_with( "zstd:" ) {
+            let level: i32 = value[ 5.. ].parse().map_err( |_| "invalid zstd compression level" )?;
+            if level < 1 || level > 22 {
+                return Err( "the zstd compression level must be between 1 and 22" );
+            }
+
+            return Ok( Compression::Zstd( level ) );
+        }
+
+        Err( "unknown compression; expected 'none', 'lz4', 'zstd' or 'zstd:<level>'" )
+    }
+}
+
 pub struct Lz4Reader< F: io::Read > {
     fp: Option< F >,
     buffer: Vec< u8 >,
@@ -42,7 +106,7 @@ impl< F: io::Read > Lz4Reader< F > {
         let fp = self.fp.as_mut().unwrap();
         let kind = fp.read_u8()?;
         match kind {
-            1 => {
+            KIND_LZ4 | KIND_ZSTD => {
                 let length = fp.read_u32::< LittleEndian >()? as usize;
                 self.compressed_buffer.reserve( length );
                 unsafe {
@@ -50,12 +114,27 @@ impl< F: io::Read > Lz4Reader< F > {
                 }
 
                 fp.read_exact( &mut self.compressed_buffer[ .. ] )?;
-                lz4_compress::decompress_into( &self.compressed_buffer, &mut self.buffer ).map_err( |_| io::Error::new( io::ErrorKind::InvalidData, "decompression error" ) )?;
+                if kind == KIND_LZ4 {
+                    lz4_compress::decompress_into( &self.compressed_buffer, &mut self.buffer ).map_err( |_| io::Error::new( io::ErrorKind::InvalidData, "decompression error" ) )?;
+                } else {
+                    zstd::stream::copy_decode( &self.compressed_buffer[ .. ], &mut self.buffer ).map_err( |_| io::Error::new( io::ErrorKind::InvalidData, "decompression error" ) )?;
+                }
                 clear( &mut self.compressed_buffer );
             },
-            2 => {
-                let _length = fp.read_u32::< LittleEndian >()?;
-                unimplemented!();
+            KIND_UNCOMPRESSED => {
+                let length = fp.read_u32::< LittleEndian >()? as usize;
+                self.buffer.reserve( length );
+                unsafe {
+                    self.buffer.set_len( length );
+                }
+
+                fp.read_exact( &mut self.buffer[ .. ] )?;
+            },
+            KIND_INDEX => {
+                // The index is only useful for random access, so just skip it.
+                let length = fp.read_u32::< LittleEndian >()? as u64;
+                io::copy( &mut io::Read::take( &mut *fp, length ), &mut io::sink() )?;
+                return self.fill_cache();
             },
             _ => {
                 return Err( io::Error::new( io::ErrorKind::InvalidData, format!( "unexpected kind" ) ) );
@@ -66,6 +145,21 @@ impl< F: io::Read > Lz4Reader< F > {
     }
 }
 
+impl< F: io::Read + io::Seek > Lz4Reader< F > {
+    /// Jumps to the first event which starts in a given chunk.
+    pub fn seek_to_event( &mut self, chunk: &ChunkIndexEntry ) -> io::Result< () > {
+        let first_event = chunk.first_event.ok_or_else( || io::Error::new( io::ErrorKind::InvalidInput, "no event starts in the given chunk" ) )?;
+        self.fp.as_mut().unwrap().seek( io::SeekFrom::Start( chunk.offset ) )?;
+
+        self.position = 0;
+        clear( &mut self.buffer );
+        self.fill_cache()?;
+        self.position = min( first_event as usize, self.buffer.len() );
+
+        Ok(())
+    }
+}
+
 impl< F: io::Read > io::Read for Lz4Reader< F > {
     fn read( &mut self, buf: &mut [u8] ) -> io::Result< usize > {
         if self.position < self.buffer.len() {
@@ -84,25 +178,77 @@ pub struct Lz4Writer< F: io::Write > {
     fp: Option< F >,
     buffer: Vec< u8 >,
     compression_buffer: Vec< u8 >,
-    is_compressed: bool
+    compression: Compression,
+    index: Option< ChunkIndexBuilder >
 }
 
 impl< F: io::Write > Lz4Writer< F > {
     pub fn new( fp: F ) -> Self {
+        Self::with_compression( fp, Compression::Lz4 )
+    }
+
+    pub fn with_compression( fp: F, compression: Compression ) -> Self {
         Lz4Writer {
             fp: Some( fp ),
             buffer: Vec::new(),
             compression_buffer: Vec::new(),
-            is_compressed: true
+            compression,
+            index: None
         }
     }
 
     pub fn disable_compression( &mut self ) -> io::Result< () > {
+        self.set_compression( Compression::None )
+    }
+
+    pub fn set_compression( &mut self, compression: Compression ) -> io::Result< () > {
         self.flush()?;
-        self.is_compressed = false;
+        self.compression = compression;
         Ok(())
     }
 
+    /// Starts gathering a chunk index for everything which will be written from now on.
+    pub fn enable_index( &mut self, index: ChunkIndexBuilder ) -> io::Result< () > {
+        self.flush()?;
+        self.index = Some( index );
+        Ok(())
+    }
+
+    /// Stops gathering the chunk index and returns it, so that it can be carried over to another writer.
+    pub fn take_index( &mut self ) -> io::Result< Option< ChunkIndexBuilder > > {
+        self.flush()?;
+        Ok( self.index.take() )
+    }
+
+    /// Marks the place where the next event starts; this is a no-op if the index is not enabled.
+    #[inline]
+    pub fn mark_event( &mut self, timestamp: Option< Timestamp >, is_definition: bool ) {
+        if let Some( ref mut index ) = self.index {
+            index.mark( self.buffer.len(), timestamp, is_definition );
+        }
+    }
+
+    /// Writes out the gathered chunk index, followed by a footer through which it can be found.
+    ///
+    /// The index has to be the very last thing written to the file.
+    pub fn write_index( &mut self ) -> io::Result< () > {
+        let index = match self.take_index()? {
+            Some( index ) => index,
+            None => return Ok(())
+        };
+
+        let offset = index.offset();
+        let mut payload = index.finish().write_to_vec().map_err( |error| io::Error::new( io::ErrorKind::Other, error.to_string() ) )?;
+        payload.write_u64::< LittleEndian >( offset )?;
+        payload.extend_from_slice( INDEX_MAGIC );
+
+        let fp = self.fp.as_mut().unwrap();
+        fp.write_u8( KIND_INDEX )?;
+        fp.write_u32::< LittleEndian >( payload.len() as u32 )?;
+        fp.write_all( &payload )?;
+        fp.flush()
+    }
+
     pub fn replace_inner( &mut self, fp: F ) -> io::Result< () > {
         self.flush()?;
         self.fp = Some( fp );
@@ -136,33 +282,55 @@ impl< F: io::Write > Lz4Writer< F > {
     }
 }
 
-fn write_compressed< T >( mut fp: T, compression_buffer: &mut Vec< u8 >, data: &[u8] ) -> io::Result< usize > where T: io::Write {
+fn write_compressed< T >( mut fp: T, compression_buffer: &mut Vec< u8 >, chunk: &[u8] ) -> io::Result< u64 > where T: io::Write {
     clear( compression_buffer );
     compression_buffer.reserve( CHUNK_SIZE );
-    for chunk in data.chunks( CHUNK_SIZE ) {
-        unsafe {
-            compression_buffer.set_len( 5 );
-        }
+    unsafe {
+        compression_buffer.set_len( 5 );
+    }
 
-        compression_buffer[ 0 ] = 1;
-        lz4_compress::compress_into( chunk, compression_buffer );
+    compression_buffer[ 0 ] = KIND_LZ4;
+    lz4_compress::compress_into( chunk, compression_buffer );
 
-        let length = compression_buffer.len() as u32 - 5;
-        LittleEndian::write_u32( &mut compression_buffer[ 1..5 ], length );
-        fp.write_all( &compression_buffer )?;
+    let length = compression_buffer.len() as u32 - 5;
+    LittleEndian::write_u32( &mut compression_buffer[ 1..5 ], length );
+    fp.write_all( &compression_buffer )?;
 
-        clear( compression_buffer );
-    }
-    Ok( data.len() )
+    clear( compression_buffer );
+    Ok( length as u64 + 5 )
 }
 
-fn write_uncompressed< T >( mut fp: T, data: &[u8] ) -> io::Result< usize > where T: io::Write {
-    fp.write_u8( 2 )?;
-    fp.write_u32::< LittleEndian >( data.len() as u32 )?;
-    let result = fp.write_all( &data );
-    result?;
+fn write_zstd< T >( mut fp: T, chunk: &[u8], level: i32 ) -> io::Result< u64 > where T: io::Write {
+    let compressed = zstd::bulk::compress( chunk, level )?;
+    fp.write_u8( KIND_ZSTD )?;
+    fp.write_u32::< LittleEndian >( compressed.len() as u32 )?;
+    fp.write_all( &compressed )?;
 
-    Ok( data.len() )
+    Ok( compressed.len() as u64 + 5 )
+}
+
+fn write_uncompressed< T >( mut fp: T, chunk: &[u8] ) -> io::Result< u64 > where T: io::Write {
+    fp.write_u8( KIND_UNCOMPRESSED )?;
+    fp.write_u32::< LittleEndian >( chunk.len() as u32 )?;
+    fp.write_all( &chunk )?;
+
+    Ok( chunk.len() as u64 + 5 )
+}
+
+fn write_chunks< T >( mut fp: T, compression: Compression, compression_buffer: &mut Vec< u8 >, mut index: Option< &mut ChunkIndexBuilder >, data: &[u8] ) -> io::Result< () > where T: io::Write {
+    for chunk in data.chunks( CHUNK_SIZE ) {
+        let length = match compression {
+            Compression::Lz4 => write_compressed( &mut fp, compression_buffer, chunk )?,
+            Compression::Zstd( level ) => write_zstd( &mut fp, chunk, level )?,
+            Compression::None => write_uncompressed( &mut fp, chunk )?
+        };
+
+        if let Some( ref mut index ) = index {
+            index.push_chunk( length, chunk.len() as u32 );
+        }
+    }
+
+    Ok(())
 }
 
 impl< F: io::Write > Drop for Lz4Writer< F > {
@@ -176,12 +344,9 @@ impl< F: io::Write > io::Write for Lz4Writer< F > {
         if slice.len() >= CHUNK_SIZE {
             self.flush()?;
 
-            let mut fp = self.fp.as_mut().unwrap();
-            if self.is_compressed {
-                return write_compressed( &mut fp, &mut self.compression_buffer, &slice );
-            } else {
-                return write_uncompressed( &mut fp, &slice );
-            }
+            let fp = self.fp.as_mut().unwrap();
+            write_chunks( fp, self.compression, &mut self.compression_buffer, self.index.as_mut(), &slice )?;
+            return Ok( slice.len() );
         }
 
         let position = self.buffer.len();
@@ -204,14 +369,74 @@ impl< F: io::Write > io::Write for Lz4Writer< F > {
             return Ok(());
         }
 
-        let mut fp = self.fp.as_mut().unwrap();
-        if self.is_compressed {
-            write_compressed( &mut fp, &mut self.compression_buffer, &self.buffer )?;
-        } else {
-            write_uncompressed( &mut fp, &self.buffer )?;
-        }
+        let fp = self.fp.as_mut().unwrap();
+        write_chunks( &mut *fp, self.compression, &mut self.compression_buffer, self.index.as_mut(), &self.buffer )?;
 
         clear( &mut self.buffer );
         fp.flush()
     }
 }
+
+#[test]
+fn test_roundtrip_with_every_compression() {
+    use std::io::Read;
+
+    let data: Vec< u8 > = (0..CHUNK_SIZE * 2 + 1234).map( |nth| (nth % 251) as u8 ).collect();
+    let mut output = Vec::new();
+    {
+        let mut fp = Lz4Writer::new( &mut output );
+        fp.write_all( &data[ ..1000 ] ).unwrap();
+        fp.set_compression( Compression::Zstd( DEFAULT_ZSTD_LEVEL ) ).unwrap();
+        fp.write_all( &data[ 1000..CHUNK_SIZE + 1000 ] ).unwrap();
+        fp.disable_compression().unwrap();
+        fp.write_all( &data[ CHUNK_SIZE + 1000.. ] ).unwrap();
+    }
+
+    let mut fp = Lz4Reader::new( &output[ .. ] );
+    let mut input = vec![ 0; data.len() ];
+    fp.read_exact( &mut input ).unwrap();
+    assert!( input == data );
+    assert_eq!( fp.read( &mut [0] ).unwrap_err().kind(), io::ErrorKind::UnexpectedEof );
+}
+
+#[test]
+fn test_parse_compression() {
+    assert_eq!( "lz4".parse(), Ok( Compression::Lz4 ) );
+    assert_eq!( "none".parse(), Ok( Compression::None ) );
+    assert_eq!( "zstd".parse(), Ok( Compression::Zstd( DEFAULT_ZSTD_LEVEL ) ) );
+    assert_eq!( "zstd:19".parse(), Ok( Compression::Zstd( 19 ) ) );
+    assert!( "zstd:0".parse::< Compression >().is_err() );
+    assert!( "gzip".parse::< Compression >().is_err() );
+}
+
+#[test]
+fn test_chunk_index() {
+    use std::io::{Cursor, Read};
+    use crate::chunk_index::read_chunk_index;
+
+    let mut output = Vec::new();
+    {
+        let mut fp = Lz4Writer::new( &mut output );
+        fp.enable_index( ChunkIndexBuilder::new( 0 ) ).unwrap();
+        for nth in 0..CHUNK_SIZE as u32 / 2 {
+            fp.mark_event( Some( Timestamp::from_secs( nth as u64 ) ), false );
+            fp.write_u32::< LittleEndian >( nth ).unwrap();
+        }
+        fp.write_index().unwrap();
+    }
+
+    let index = read_chunk_index( &mut Cursor::new( &output ) ).unwrap().unwrap();
+    assert_eq!( index.chunks.len(), 2 );
+    assert_eq!( index.event_count(), CHUNK_SIZE as u64 / 2 );
+    assert_eq!( index.uncompressed_size(), CHUNK_SIZE as u64 * 2 );
+
+    let chunk = index.chunks_in_range( Timestamp::from_secs( 200000 ), Timestamp::from_secs( 200001 ) ).next().unwrap();
+    let mut fp = Lz4Reader::new( Cursor::new( &output ) );
+    fp.seek_to_event( chunk ).unwrap();
+    assert_eq!( fp.read_u32::< LittleEndian >().unwrap(), CHUNK_SIZE as u32 / 4 );
+
+    let mut fp = Lz4Reader::new( &output[ .. ] );
+    let mut data = vec![ 0; CHUNK_SIZE * 2 ];
+    fp.read_exact( &mut data ).unwrap();
+    assert_eq!( fp.read( &mut [0] ).unwrap_err().kind(), io::ErrorKind::UnexpectedEof );
+}
@@ -0,0 +1,167 @@
+//! Reading and writing of the data files produced by bytehound.
+//!
+//! A data file is a stream of chunks, each of which is either LZ4 compressed,
+//! zstd compressed or stored as-is. Once decompressed the chunks form a single
+//! stream of [`Event`]s, the first of which is always an [`Event::Header`].
+//! Optionally the very last chunk of a file is a [`ChunkIndex`] which allows
+//! for random access.
+//!
+//! # Compatibility
+//!
+//! Every file records the version of the format it was written with in its header
+//! (see [`HeaderBody::format_version`]); files written before the version was
+//! recorded are version `0`.
+//!
+//! - New versions only ever append new event kinds and new chunk kinds; the existing
+//!   ones are never changed, so a reader can always read every older version.
+//! - A [`Reader`] refuses to read files with a version newer than [`FORMAT_VERSION`],
+//!   since it wouldn't be able to decode the new events anyway.
+//! - A [`Writer`] can be asked to produce an older version, in which case it refuses
+//!   to write anything which an older reader wouldn't understand.
+
+mod reader;
+mod writer;
+
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+
+pub use common::event::{
+    AllocBody,
+    AllocationId,
+    DataId,
+    Event,
+    FramesInvalidated,
+    HeaderBody,
+    ALLOC_FLAG_CALLOC,
+    ALLOC_FLAG_JEMALLOC,
+    ALLOC_FLAG_MMAPED,
+    ALLOC_FLAG_NON_MAIN_ARENA,
+    ALLOC_FLAG_PREV_IN_USE,
+    FORMAT_VERSION,
+    HEADER_FLAG_IS_LITTLE_ENDIAN
+};
+
+pub use common::chunk_index::{ChunkIndex, ChunkIndexEntry, read_chunk_index};
+pub use common::lz4_stream::{Compression, DEFAULT_ZSTD_LEVEL};
+pub use common::Timestamp;
+
+pub use crate::reader::Reader;
+pub use crate::writer::{Writer, WriterOptions};
+
+/// Returns the oldest format version in which a given event can be stored.
+pub fn minimum_format_version( event: &Event ) -> u32 {
+    match *event {
+        Event::ProcessInfo { .. } => 1,
+        _ => 0
+    }
+}
+
+/// Returns the oldest format version in which a given compression can be used.
+pub fn minimum_format_version_for_compression( compression: Compression ) -> u32 {
+    match compression {
+        // The uncompressed chunks were added together with the zstd compressed ones.
+        Compression::Zstd( .. ) | Compression::None => 1,
+        Compression::Lz4 => 0
+    }
+}
+
+#[cfg(test)]
+use crate::test_util::test_header;
+
+#[cfg(test)]
+fn test_events() -> Vec< Event< 'static > > {
+    vec![
+        Event::ProcessInfo { parent_pid: 1 },
+        Event::Backtrace { id: 1, addresses: vec![ 0x1000, 0x2000 ].into() },
+        Event::AllocEx {
+            id: AllocationId { thread: 1, allocation: 1 },
+            timestamp: Timestamp::from_secs( 3 ),
+            allocation: AllocBody {
+                pointer: 0x10000,
+                size: 100,
+                backtrace: 1,
+                thread: 1,
+                flags: 0,
+                extra_usable_space: 0,
+                preceding_free_space: 0
+            }
+        },
+        Event::Marker { value: 10 },
+        Event::FreeEx {
+            id: AllocationId { thread: 1, allocation: 1 },
+            timestamp: Timestamp::from_secs( 4 ),
+            pointer: 0x10000,
+            backtrace: 0,
+            thread: 1
+        }
+    ]
+}
+
+#[cfg(test)]
+fn roundtrip( options: WriterOptions ) -> (HeaderBody, Vec< Event< 'static > >) {
+    let mut writer = Writer::with_options( Vec::new(), test_header(), options ).unwrap();
+    for event in test_events() {
+        writer.write_event( &event ).unwrap();
+    }
+
+    let data = writer.finish().unwrap();
+    let reader = Reader::new( &data[ .. ] ).unwrap();
+    let header = reader.header().clone();
+    let events = reader.collect::< Result< Vec< _ >, _ > >().unwrap();
+    (header, events)
+}
+
+#[test]
+fn test_roundtrip() {
+    for &compression in &[ Compression::Lz4, Compression::Zstd( DEFAULT_ZSTD_LEVEL ), Compression::None ] {
+        let (header, events) = roundtrip( WriterOptions { compression, ..WriterOptions::default() } );
+        assert_eq!( header.format_version(), FORMAT_VERSION );
+        assert_eq!( header.pid, 123 );
+        assert_eq!( events, test_events() );
+    }
+}
+
+#[test]
+fn test_roundtrip_with_index() {
+    let mut writer = Writer::new( std::io::Cursor::new( Vec::new() ), test_header() ).unwrap();
+    for event in test_events() {
+        writer.write_event( &event ).unwrap();
+    }
+
+    let mut fp = writer.finish().unwrap();
+    let index = read_chunk_index( &mut fp ).unwrap().unwrap();
+    assert_eq!( index.time_range(), Some( (Timestamp::from_secs( 3 ), Timestamp::from_secs( 4 )) ) );
+}
+
+#[test]
+fn test_older_version_refuses_newer_events() {
+    let options = WriterOptions { version: 0, ..WriterOptions::default() };
+    let mut writer = Writer::with_options( Vec::new(), test_header(), options ).unwrap();
+    assert!( writer.write_event( &Event::ProcessInfo { parent_pid: 1 } ).is_err() );
+    writer.write_event( &Event::Marker { value: 1 } ).unwrap();
+
+    let data = writer.finish().unwrap();
+    let reader = Reader::new( &data[ .. ] ).unwrap();
+    assert_eq!( reader.format_version(), 0 );
+
+    let options = WriterOptions { version: 0, compression: Compression::Zstd( DEFAULT_ZSTD_LEVEL ), ..WriterOptions::default() };
+    assert!( Writer::with_options( Vec::new(), test_header(), options ).is_err() );
+
+    let options = WriterOptions { version: 0, compression: Compression::None, ..WriterOptions::default() };
+    assert!( Writer::with_options( Vec::new(), test_header(), options ).is_err() );
+}
+
+#[test]
+fn test_reader_refuses_newer_versions() {
+    let mut header = test_header();
+    header.set_format_version( FORMAT_VERSION + 1 );
+
+    let mut data = Vec::new();
+    {
+        use common::speedy::Writable;
+        let mut fp = common::lz4_stream::Lz4Writer::new( &mut data );
+        Event::Header( header ).write_to_stream( &mut fp ).unwrap();
+    }
+
+    assert!( Reader::new( &data[ .. ] ).is_err() );
+}
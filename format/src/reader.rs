@@ -0,0 +1,76 @@
+use std::io::{self, Read};
+
+use common::event::{Event, HeaderBody, FORMAT_VERSION};
+use common::lz4_stream::Lz4Reader;
+use common::speedy::Readable;
+
+/// A streaming reader of data files.
+///
+/// The events are returned one by one in the order they were written,
+/// not counting the header which is read when the reader is created.
+pub struct Reader< R: Read > {
+    fp: Lz4Reader< R >,
+    header: HeaderBody,
+    done: bool
+}
+
+impl< R: Read > Reader< R > {
+    pub fn new( fp: R ) -> io::Result< Self > {
+        let mut fp = Lz4Reader::new( fp );
+        let header = match Event::read_from_stream_unbuffered( &mut fp ) {
+            Ok( Event::Header( header ) ) => header,
+            Ok( _ ) => return Err( io::Error::new( io::ErrorKind::InvalidData, "data file doesn't start with a proper header" ) ),
+            Err( error ) => return Err( error.into() )
+        };
+
+        if header.format_version() > FORMAT_VERSION {
+            return Err( io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!( "data file was written using a newer format version ({}); the newest supported version is {}", header.format_version(), FORMAT_VERSION )
+            ));
+        }
+
+        Ok( Reader {
+            fp,
+            header,
+            done: false
+        })
+    }
+
+    pub fn header( &self ) -> &HeaderBody {
+        &self.header
+    }
+
+    /// The version of the format the file was written with.
+    pub fn format_version( &self ) -> u32 {
+        self.header.format_version()
+    }
+
+    /// Reads the next event; returns `None` at the end of the file.
+    pub fn next_event( &mut self ) -> io::Result< Option< Event< 'static > > > {
+        if self.done {
+            return Ok( None );
+        }
+
+        match Event::read_from_stream_unbuffered( &mut self.fp ) {
+            Ok( event ) => Ok( Some( event ) ),
+            Err( error ) => {
+                self.done = true;
+                let error: io::Error = error.into();
+                if error.kind() == io::ErrorKind::UnexpectedEof {
+                    Ok( None )
+                } else {
+                    Err( error )
+                }
+            }
+        }
+    }
+}
+
+impl< R: Read > Iterator for Reader< R > {
+    type Item = io::Result< Event< 'static > >;
+
+    fn next( &mut self ) -> Option< Self::Item > {
+        self.next_event().transpose()
+    }
+}
@@ -0,0 +1,30 @@
+//! Helpers for building small data files in tests.
+
+use crate::{DataId, Event, HeaderBody, Timestamp, Writer, HEADER_FLAG_IS_LITTLE_ENDIAN};
+
+/// Returns a header of a little endian x86_64 recording whose timestamps start at zero.
+pub fn test_header() -> HeaderBody {
+    HeaderBody {
+        id: DataId::new( 1, 2 ),
+        initial_timestamp: Timestamp::min(),
+        timestamp: Timestamp::min(),
+        wall_clock_secs: 0,
+        wall_clock_nsecs: 0,
+        pid: 123,
+        cmdline: b"./test\0".to_vec(),
+        executable: b"/usr/bin/test".to_vec(),
+        arch: "x86_64".into(),
+        flags: HEADER_FLAG_IS_LITTLE_ENDIAN,
+        pointer_size: 8
+    }
+}
+
+/// Writes out a data file with the `test_header` followed by the given events.
+pub fn write_test_file( events: &[Event] ) -> Vec< u8 > {
+    let mut writer = Writer::new( Vec::new(), test_header() ).unwrap();
+    for event in events {
+        writer.write_event( event ).unwrap();
+    }
+
+    writer.finish().unwrap()
+}
@@ -0,0 +1,95 @@
+use std::io::{self, Write};
+
+use common::chunk_index::{ChunkIndexBuilder, index_properties};
+use common::event::{Event, HeaderBody, FORMAT_VERSION};
+use common::lz4_stream::{Compression, Lz4Writer};
+use common::speedy::Writable;
+
+use crate::{minimum_format_version, minimum_format_version_for_compression};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct WriterOptions {
+    /// The version of the format to write; older versions can be
+    /// used to produce files which older tools can still read.
+    pub version: u32,
+    pub compression: Compression,
+    /// Whenever to write a chunk index at the end of the file;
+    /// this is only supported starting from version `1`.
+    pub write_index: bool
+}
+
+impl Default for WriterOptions {
+    fn default() -> Self {
+        WriterOptions {
+            version: FORMAT_VERSION,
+            compression: Compression::Lz4,
+            write_index: true
+        }
+    }
+}
+
+/// A streaming writer of data files.
+///
+/// The header is written when the writer is created; the events
+/// must then be written in the order in which they've happened.
+pub struct Writer< W: Write > {
+    fp: Lz4Writer< W >,
+    version: u32
+}
+
+impl< W: Write > Writer< W > {
+    pub fn new( fp: W, header: HeaderBody ) -> io::Result< Self > {
+        Self::with_options( fp, header, WriterOptions::default() )
+    }
+
+    pub fn with_options( fp: W, mut header: HeaderBody, options: WriterOptions ) -> io::Result< Self > {
+        if options.version > FORMAT_VERSION {
+            return Err( io::Error::new( io::ErrorKind::InvalidInput, format!( "unsupported format version: {}", options.version ) ) );
+        }
+
+        if minimum_format_version_for_compression( options.compression ) > options.version {
+            return Err( io::Error::new( io::ErrorKind::InvalidInput, format!( "the '{}' compression is not supported in format version {}", options.compression, options.version ) ) );
+        }
+
+        let mut fp = Lz4Writer::with_compression( fp, options.compression );
+        if options.write_index && options.version >= 1 {
+            fp.enable_index( ChunkIndexBuilder::new( 0 ) )?;
+        }
+
+        header.set_format_version( options.version );
+        fp.mark_event( None, true );
+        Event::Header( header ).write_to_stream( &mut fp )?;
+
+        Ok( Writer {
+            fp,
+            version: options.version
+        })
+    }
+
+    pub fn format_version( &self ) -> u32 {
+        self.version
+    }
+
+    pub fn write_event( &mut self, event: &Event ) -> io::Result< () > {
+        if let Event::Header( .. ) = *event {
+            return Err( io::Error::new( io::ErrorKind::InvalidInput, "the header can only be written once" ) );
+        }
+
+        if minimum_format_version( event ) > self.version {
+            return Err( io::Error::new( io::ErrorKind::InvalidInput, format!( "the event is not supported in format version {}: {:?}", self.version, event ) ) );
+        }
+
+        let (timestamp, is_definition) = index_properties( event );
+        self.fp.mark_event( timestamp, is_definition );
+        event.write_to_stream( &mut self.fp )?;
+        Ok(())
+    }
+
+    /// Flushes everything, writes out the chunk index, and returns the underlying writer.
+    pub fn finish( mut self ) -> io::Result< W > {
+        self.fp.write_index()?;
+        let mut fp = self.fp.into_inner()?;
+        fp.flush()?;
+        Ok( fp )
+    }
+}
@@ -1,3 +1,5 @@
+use common::lz4_stream::Compression;
+
 use crate::utils::Buffer;
 
 pub struct Opts {
@@ -23,7 +25,8 @@ pub struct Opts {
     pub cull_temporary_allocations: bool,
     pub temporary_allocation_lifetime_threshold: u64,
     pub temporary_allocation_pending_threshold: Option< usize >,
-    pub track_child_processes: bool
+    pub track_child_processes: bool,
+    pub compression: Compression
 }
 
 static mut OPTS: Opts = Opts {
@@ -49,7 +52,8 @@ static mut OPTS: Opts = Opts {
     cull_temporary_allocations: false,
     temporary_allocation_lifetime_threshold: 10000,
     temporary_allocation_pending_threshold: None,
-    track_child_processes: false
+    track_child_processes: false,
+    compression: Compression::Lz4
 };
 
 trait ParseVar: Sized {
@@ -92,6 +96,12 @@ impl ParseVar for Buffer {
     }
 }
 
+impl ParseVar for Compression {
+    fn parse_var( value: Buffer ) -> Option< Self > {
+        value.to_str()?.parse().ok()
+    }
+}
+
 impl< T > ParseVar for Option< T > where T: ParseVar {
     fn parse_var( value: Buffer ) -> Option< Self > {
         if let Some( value ) = T::parse_var( value ) {
@@ -147,7 +157,9 @@ pub unsafe fn initialize() {
         "MEMORY_PROFILER_TEMPORARY_ALLOCATION_PENDING_THRESHOLD"
             => &mut opts.temporary_allocation_pending_threshold,
         "MEMORY_PROFILER_TRACK_CHILD_PROCESSES"
-            => &mut opts.track_child_processes
+            => &mut opts.track_child_processes,
+        "MEMORY_PROFILER_COMPRESSION"
+            => &mut opts.compression
     }
 
     opts.is_initialized = true;
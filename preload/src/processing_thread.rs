@@ -19,6 +19,7 @@ use std::io::{
 use common::speedy::{Writable, Readable};
 
 use common::event::{DataId, Event};
+use common::chunk_index::ChunkIndexBuilder;
 use common::lz4_stream::Lz4Writer;
 use common::request::{
     PROTOCOL_VERSION,
@@ -239,7 +240,7 @@ impl Client {
     fn stream_initial_data( &mut self, id: DataId, initial_timestamp: Timestamp, path: &Path, file: &mut File ) -> io::Result< () > {
         if !opt::get().write_binaries_to_output {
             info!( "Streaming the binaries which were suppressed in the original output file..." );
-            let mut serializer = Lz4Writer::new( &mut *self );
+            let mut serializer = Lz4Writer::with_compression( &mut *self, opt::get().compression );
             writers::write_header( id, initial_timestamp, &mut serializer )?;
             writers::write_binaries( &mut serializer )?;
             serializer.flush()?;
@@ -275,7 +276,7 @@ impl Client {
         }
 
         {
-            let mut serializer = Lz4Writer::new( &mut *self );
+            let mut serializer = Lz4Writer::with_compression( &mut *self, opt::get().compression );
             writers::write_header( id, initial_timestamp, &mut serializer )?;
             writers::write_maps( &mut serializer )?;
             writers::write_binaries( &mut serializer )?;
@@ -468,7 +469,7 @@ impl BacktraceCache {
     }
 }
 
-fn emit_allocation_bucket( mut bucket: AllocationBucket, backtrace_cache: &mut BacktraceCache, fp: &mut impl Write ) -> Result< (), std::io::Error > {
+fn emit_allocation_bucket( mut bucket: AllocationBucket, backtrace_cache: &mut BacktraceCache, fp: &mut Lz4Writer< impl Write > ) -> Result< (), std::io::Error > {
     if bucket.events.len() == 0 {
         return Ok(());
     }
@@ -478,6 +479,7 @@ fn emit_allocation_bucket( mut bucket: AllocationBucket, backtrace_cache: &mut B
     let BufferedAllocation { timestamp, allocation, backtrace } = iter.next().unwrap();
     let mut old_pointer = allocation.address;
     let backtrace = writers::write_backtrace( &mut *fp, backtrace, backtrace_cache )?;
+    fp.mark_event( Some( timestamp ), false );
     Event::AllocEx {
         id: bucket.id,
         timestamp,
@@ -495,6 +497,7 @@ fn emit_allocation_bucket( mut bucket: AllocationBucket, backtrace_cache: &mut B
     while let Some( BufferedAllocation { timestamp, allocation, backtrace } ) = iter.next() {
         let backtrace = writers::write_backtrace( &mut *fp, backtrace, backtrace_cache )?;
 
+        fp.mark_event( Some( timestamp ), false );
         Event::ReallocEx {
             id: bucket.id,
             timestamp,
@@ -522,16 +525,22 @@ pub(crate) fn thread_main() {
     let initial_timestamp = get_timestamp();
     info!( "Data ID: {}", uuid );
 
-    let mut output_writer = Lz4Writer::new( Output::new() );
+    let mut output_writer = Lz4Writer::with_compression( Output::new(), opt::get().compression );
     if let Some( (fp, path) ) = initialize_output_file() {
-        let mut fp = Lz4Writer::new( fp );
+        let mut fp = Lz4Writer::with_compression( fp, opt::get().compression );
+        fp.enable_index( ChunkIndexBuilder::new( 0 ) ).unwrap();
+        fp.mark_event( None, true );
         match writers::write_initial_data( uuid, initial_timestamp, &mut fp ) {
             Ok(()) => {
+                let index = fp.take_index().unwrap();
                 let fp = fp.into_inner().unwrap();
 
                 let mut output = Output::new();
                 output.set_file( fp, path );
                 output_writer.replace_inner( output ).unwrap();
+                if let Some( index ) = index {
+                    output_writer.enable_index( index ).unwrap();
+                }
             },
             Err( error ) => {
                 warn!( "Failed to write initial data: {}", error );
@@ -603,6 +612,12 @@ pub(crate) fn thread_main() {
         if events.is_empty() {
             if let Some( _lock ) = allocation_lock_for_memory_dump.take() {
                 if !output_writer.inner().is_none() {
+                    // The dump is written by a forked off child, so we can't keep track of the chunks anymore;
+                    // an index which doesn't cover the whole file would be useless, so what we have so far is dropped.
+                    if let Ok( Some( _ ) ) = output_writer.take_index() {
+                        info!( "The chunk index will not be written due to a memory dump; run the file through `repack` to add one" );
+                    }
+
                     let _ = writer_memory::write_memory_dump( &mut output_writer );
                 }
             }
@@ -629,6 +644,7 @@ pub(crate) fn thread_main() {
                     timestamp = timestamp_override.take().unwrap_or( timestamp );
 
                     if let Ok( backtrace ) = writers::write_backtrace( &mut *serializer, backtrace, &mut backtrace_cache ) {
+                        serializer.mark_event( Some( timestamp ), false );
                         let _ = Event::AllocEx {
                             id,
                             timestamp,
@@ -662,6 +678,7 @@ pub(crate) fn thread_main() {
                     timestamp = timestamp_override.take().unwrap_or( timestamp );
 
                     if let Ok( backtrace ) = writers::write_backtrace( &mut *serializer, backtrace, &mut backtrace_cache ) {
+                        serializer.mark_event( Some( timestamp ), false );
                         let event = Event::ReallocEx {
                             id,
                             timestamp,
@@ -704,6 +721,7 @@ pub(crate) fn thread_main() {
                         };
 
                     if let Some( backtrace ) = backtrace {
+                        serializer.mark_event( Some( timestamp ), false );
                         let _ = Event::FreeEx {
                             id: id.into(),
                             timestamp,
@@ -735,6 +753,7 @@ pub(crate) fn thread_main() {
                     timestamp = timestamp_override.take().unwrap_or( timestamp );
 
                     if let Ok( backtrace ) = writers::write_backtrace( &mut *serializer, backtrace, &mut backtrace_cache ) {
+                        serializer.mark_event( Some( timestamp ), false );
                         let event = Event::MemoryMap {
                             timestamp,
                             pointer: pointer as u64,
@@ -766,6 +785,7 @@ pub(crate) fn thread_main() {
                     let timestamp = timestamp_override.take().unwrap_or( timestamp );
 
                     if let Ok( backtrace ) = writers::write_backtrace( &mut *serializer, backtrace, &mut backtrace_cache ) {
+                        serializer.mark_event( Some( timestamp ), false );
                         let event = Event::MemoryUnmap { timestamp, pointer: ptr as u64, length: len as u64, backtrace, thread: system_tid };
                         let _ = event.write_to_stream( &mut *serializer );
                     }
@@ -785,6 +805,7 @@ pub(crate) fn thread_main() {
                     let timestamp = timestamp_override.take().unwrap_or( timestamp );
 
                     if let Ok( backtrace ) = writers::write_backtrace( &mut *serializer, backtrace, &mut backtrace_cache ) {
+                        serializer.mark_event( Some( timestamp ), false );
                         let event = Event::Mallopt { timestamp, param, value, result, backtrace, thread: system_tid };
                         let _ = event.write_to_stream( &mut *serializer );
                     }
@@ -805,6 +826,7 @@ pub(crate) fn thread_main() {
                         continue;
                     }
 
+                    serializer.mark_event( None, false );
                     let event = Event::Marker { value };
                     let _ = event.write_to_stream( &mut *serializer );
                 },
@@ -816,6 +838,7 @@ pub(crate) fn thread_main() {
                     if opt::get().write_binaries_to_output || serializer.inner_mut_without_flush().file.is_none() {
                         for binary in new_binaries {
                             debug!( "Writing new binary: {}", binary.name() );
+                            serializer.mark_event( Some( timestamp ), true );
                             let event = Event::File64 {
                                 timestamp,
                                 path: binary.name().into(),
@@ -827,6 +850,7 @@ pub(crate) fn thread_main() {
                     }
 
                     debug!( "Writing new maps..." );
+                    serializer.mark_event( Some( timestamp ), true );
                     let event = Event::File64 {
                         timestamp,
                         path: "/proc/self/maps".into(),
@@ -844,6 +868,16 @@ pub(crate) fn thread_main() {
         }
     }
 
+    if output_writer.inner().file.is_some() {
+        // The offsets in the index are only valid for the file, so it's not sent to the clients.
+        let clients = output_writer.inner_mut().map( |output| mem::take( &mut output.clients ) ).unwrap_or_default();
+        if let Err( error ) = output_writer.write_index() {
+            warn!( "Failed to write the chunk index: {}", error );
+        }
+
+        output_writer.inner_mut_without_flush().clients = clients;
+    }
+
     let _ = output_writer.flush();
     for client in &mut output_writer.inner_mut_without_flush().clients {
         let _ = Response::Finished.write_to_stream( &mut client.stream );
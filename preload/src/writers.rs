@@ -9,7 +9,8 @@ use std::path::Path;
 use nwind::proc_maps::Region;
 use nwind::proc_maps::parse as parse_maps;
 
-use common::event::{DataId, Event, HeaderBody, HEADER_FLAG_IS_LITTLE_ENDIAN};
+use common::event::{DataId, Event, HeaderBody, FORMAT_VERSION, HEADER_FLAG_IS_LITTLE_ENDIAN};
+use common::lz4_stream::Lz4Writer;
 use common::speedy::Writable;
 use common::Timestamp;
 
@@ -53,7 +54,7 @@ fn new_header_body( id: DataId, initial_timestamp: Timestamp ) -> io::Result< He
         flags |= HEADER_FLAG_IS_LITTLE_ENDIAN;
     }
 
-    Ok( HeaderBody {
+    let mut header = HeaderBody {
         id,
         initial_timestamp,
         timestamp,
@@ -65,7 +66,10 @@ fn new_header_body( id: DataId, initial_timestamp: Timestamp ) -> io::Result< He
         arch: arch::TARGET_ARCH.to_string(),
         flags,
         pointer_size: mem::size_of::< usize >() as u8
-    })
+    };
+
+    header.set_format_version( FORMAT_VERSION );
+    Ok( header )
 }
 
 pub fn write_header< U: Write >( id: DataId, initial_timestamp: Timestamp, serializer: &mut U ) -> io::Result< () > {
@@ -120,6 +124,12 @@ fn write_wallclock< U: Write >( serializer: &mut U ) -> io::Result< () > {
     Ok(())
 }
 
+fn write_process_info< U: Write >( serializer: &mut U ) -> io::Result< () > {
+    let parent_pid = unsafe { libc::getppid() } as u32;
+    Event::ProcessInfo { parent_pid }.write_to_stream( serializer )?;
+    Ok(())
+}
+
 fn write_uptime< U: Write >( serializer: &mut U ) -> io::Result< () > {
     let uptime = fs::read( "/proc/uptime" )?;
     write_file( serializer, "/proc/uptime", &uptime )
@@ -145,7 +155,7 @@ fn write_environ< U: Write >( mut serializer: U ) -> io::Result< () > {
     Ok(())
 }
 
-pub(crate) fn write_backtrace< U: Write >( serializer: &mut U, backtrace: Backtrace, cache: &mut BacktraceCache ) -> io::Result< u64 > {
+pub(crate) fn write_backtrace< U: Write >( serializer: &mut Lz4Writer< U >, backtrace: Backtrace, cache: &mut BacktraceCache ) -> io::Result< u64 > {
     let (id, is_new) = cache.assign_id( &backtrace );
     debug_assert_ne!( id, 0 );
 
@@ -153,6 +163,8 @@ pub(crate) fn write_backtrace< U: Write >( serializer: &mut U, backtrace: Backtr
         return Ok( id );
     }
 
+    serializer.mark_event( None, true );
+
     let frames = backtrace.frames();
 
     // TODO: Get rid of this.
@@ -223,6 +235,7 @@ fn write_included_files< U: Write >( serializer: &mut U ) -> io::Result< () > {
 pub fn write_initial_data< T >( id: DataId, initial_timestamp: Timestamp, mut fp: T ) -> Result< (), io::Error > where T: Write {
     info!( "Writing initial header..." );
     write_header( id, initial_timestamp, &mut fp )?;
+    write_process_info( &mut fp )?;
 
     info!( "Writing wall clock..." );
     write_wallclock( &mut fp )?;
@@ -0,0 +1,116 @@
+/// The credentials which the clients have to provide to access the server.
+#[derive(Clone, Default)]
+pub struct Auth {
+    /// Accepted through the `Authorization: Bearer <token>` header, or the `token` query parameter for the server-sent events.
+    pub token: Option< String >,
+    /// A username and a password accepted through HTTP basic authentication.
+    pub basic: Option< (String, String) >
+}
+
+impl Auth {
+    pub fn is_enabled( &self ) -> bool {
+        self.token.is_some() || self.basic.is_some()
+    }
+
+    /// Checks the value of the `Authorization` header of a request.
+    pub fn is_authorized( &self, header: Option< &str > ) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+
+        let header = match header {
+            Some( header ) => header.trim(),
+            None => return false
+        };
+
+        if let Some( ref token ) = self.token {
+            if let Some( value ) = strip_scheme( header, "Bearer" ) {
+                if constant_time_eq( value.as_bytes(), token.as_bytes() ) {
+                    return true;
+                }
+            }
+        }
+
+        if let Some( (ref username, ref password) ) = self.basic {
+            if let Some( value ) = strip_scheme( header, "Basic" ) {
+                let expected = base64::encode( &format!( "{}:{}", username, password ) );
+                if constant_time_eq( value.as_bytes(), expected.as_bytes() ) {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Checks a token which was passed through the `token` query parameter.
+    ///
+    /// This is only meant for the endpoints consumed through `EventSource`, which can't set any headers.
+    pub fn is_authorized_by_query( &self, query: &str ) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+
+        let expected = match self.token {
+            Some( ref token ) => token,
+            None => return false
+        };
+
+        let params: Vec< (String, String) > = serde_urlencoded::from_str( query ).unwrap_or_default();
+        params.iter().any( |(key, value)| key == "token" && constant_time_eq( value.as_bytes(), expected.as_bytes() ) )
+    }
+}
+
+fn strip_scheme< 'a >( header: &'a str, scheme: &str ) -> Option< &'a str > {
+    let mut parts = header.splitn( 2, ' ' );
+    let name = parts.next()?;
+    let value = parts.next()?;
+    if name.eq_ignore_ascii_case( scheme ) {
+        Some( value.trim() )
+    } else {
+        None
+    }
+}
+
+/// Compares two byte strings in a way which doesn't leak how many of the leading bytes match.
+fn constant_time_eq( lhs: &[u8], rhs: &[u8] ) -> bool {
+    if lhs.len() != rhs.len() {
+        return false;
+    }
+
+    lhs.iter().zip( rhs ).fold( 0, |acc, (a, b)| acc | (a ^ b) ) == 0
+}
+
+#[test]
+fn test_auth_token() {
+    let auth = Auth {
+        token: Some( "secret".into() ),
+        basic: None
+    };
+
+    assert!( auth.is_authorized( Some( "Bearer secret" ) ) );
+    assert!( auth.is_authorized( Some( "bearer  secret" ) ) );
+    assert!( !auth.is_authorized( Some( "Bearer secreT" ) ) );
+    assert!( !auth.is_authorized( Some( "Bearer" ) ) );
+    assert!( !auth.is_authorized( Some( "Basic secret" ) ) );
+    assert!( !auth.is_authorized( None ) );
+
+    assert!( auth.is_authorized_by_query( "token=secret" ) );
+    assert!( auth.is_authorized_by_query( "x=1&token=secret" ) );
+    assert!( !auth.is_authorized_by_query( "token=secreT" ) );
+    assert!( !auth.is_authorized_by_query( "" ) );
+}
+
+#[test]
+fn test_auth_basic() {
+    let auth = Auth {
+        token: None,
+        basic: Some( ("user".into(), "password".into()) )
+    };
+
+    assert!( auth.is_authorized( Some( "Basic dXNlcjpwYXNzd29yZA==" ) ) );
+    assert!( !auth.is_authorized( Some( "Basic dXNlcjpwYXNzd29yZB==" ) ) );
+    assert!( !auth.is_authorized( Some( "Bearer dXNlcjpwYXNzd29yZA==" ) ) );
+    assert!( !auth.is_authorized_by_query( "token=dXNlcjpwYXNzd29yZA==" ) );
+    assert!( Auth::default().is_authorized( None ) );
+}
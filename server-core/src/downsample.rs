@@ -0,0 +1,58 @@
+/// Picks at most `max_points` indices of the points of a series in a way which preserves its shape.
+///
+/// The series is split into equally sized buckets, and from each bucket only the points with
+/// the minimum and the maximum value are kept; the first and the last point are always kept.
+pub fn min_max_indices( ys: &[u64], max_points: usize ) -> Vec< usize > {
+    if ys.len() <= max_points {
+        return (0..ys.len()).collect();
+    }
+
+    let mut indices = Vec::with_capacity( max_points );
+    indices.push( 0 );
+
+    let inner_length = ys.len() - 2;
+    let bucket_count = max_points.saturating_sub( 2 ) / 2;
+    for bucket in 0..bucket_count {
+        let start = 1 + bucket * inner_length / bucket_count;
+        let end = 1 + (bucket + 1) * inner_length / bucket_count;
+        if start == end {
+            continue;
+        }
+
+        let mut index_min = start;
+        let mut index_max = start;
+        for index in start + 1..end {
+            if ys[ index ] < ys[ index_min ] {
+                index_min = index;
+            }
+            if ys[ index ] > ys[ index_max ] {
+                index_max = index;
+            }
+        }
+
+        indices.push( std::cmp::min( index_min, index_max ) );
+        if index_min != index_max {
+            indices.push( std::cmp::max( index_min, index_max ) );
+        }
+    }
+
+    indices.push( ys.len() - 1 );
+    indices
+}
+
+pub fn pick< T: Copy >( values: &[T], indices: &[usize] ) -> Vec< T > {
+    indices.iter().map( |&index| values[ index ] ).collect()
+}
+
+#[test]
+fn test_min_max_indices() {
+    let ys = [0, 5, 1, 1, 9, 2, 3, 3, 0, 7];
+    assert_eq!( min_max_indices( &ys, 10 ), (0..10).collect::< Vec< _ > >() );
+
+    let indices = min_max_indices( &ys, 6 );
+    assert!( indices.len() <= 6 );
+    assert_eq!( indices, vec![ 0, 2, 4, 6, 8, 9 ] );
+    assert_eq!( pick( &ys, &indices ), vec![ 0, 1, 9, 3, 0, 7 ] );
+
+    assert_eq!( min_max_indices( &ys, 2 ), vec![ 0, 9 ] );
+}
@@ -44,7 +44,11 @@ impl From< crate::protocol::NumberOrPercentage > for cli_core::NumberOrFractionO
     }
 }
 
-fn run_custom_filter( data: &Arc< Data >, custom_filter: &protocol::CustomFilter ) -> Result< Option< Arc< HashSet< AllocationId > > >, cli_core::script::EvalError > {
+fn run_custom_filter(
+    data: &Arc< Data >,
+    custom_filter: &protocol::CustomFilter,
+    limits: &cli_core::script::ScriptLimits
+) -> Result< Option< Arc< HashSet< AllocationId > > >, PrepareFilterError > {
     let mut custom_set = None;
     if let Some( ref custom_filter ) = custom_filter.custom_filter {
         if custom_filter.is_empty() {
@@ -53,13 +57,15 @@ fn run_custom_filter( data: &Arc< Data >, custom_filter: &protocol::CustomFilter
 
         let args = cli_core::script::EngineArgs {
             data: Some( data.clone() ),
+            limits: limits.clone(),
             .. cli_core::script::EngineArgs::default()
         };
 
         let env = Arc::new( Mutex::new( cli_core::script::VirtualEnvironment::new() ) );
         let engine = cli_core::script::Engine::new( env.clone(), args );
         let custom_set = custom_set.get_or_insert( HashSet::new() );
-        match engine.run( &custom_filter )? {
+        let result = engine.run( &custom_filter ).map_err( |error| PrepareFilterError::InvalidCustomFilter( error.message ) )?;
+        match result {
             Some( mut list ) => {
                 custom_set.extend( list.allocation_ids().iter().copied() );
             },
@@ -92,13 +98,22 @@ impl AllocationFilter {
     }
 }
 
-pub fn prepare_filter(
+/// Prepares a filter for the allocations of a given data file.
+///
+/// The custom filter is a script, so it's rejected when the server is running in read-only mode.
+pub(crate) fn prepare_filter(
     data: &Arc< Data >,
     filter: &protocol::AllocFilter,
-    custom_filter: &protocol::CustomFilter
+    custom_filter: &protocol::CustomFilter,
+    state: &crate::State
 ) -> Result< AllocationFilter, PrepareFilterError > {
+    let has_custom_filter = custom_filter.custom_filter.as_ref().map( |code| !code.is_empty() ).unwrap_or( false );
+    if has_custom_filter && state.read_only {
+        return Err( PrepareFilterError::CustomFilterNotAllowed );
+    }
+
     let filter = prepare_raw_filter( data, filter )?.compile( data );
-    let custom_filter = run_custom_filter( data, custom_filter ).map_err( |error| PrepareFilterError::InvalidCustomFilter( error.message ) )?;
+    let custom_filter = run_custom_filter( data, custom_filter, &state.script_limits )?;
 
     Ok( AllocationFilter { filter, custom_filter } )
 }
@@ -125,13 +140,8 @@ pub fn prepare_raw_filter( data: &Data, filter: &protocol::AllocFilter ) -> Resu
     output.only_backtrace_length_at_least = filter.backtrace_depth_min.map( |value| value as usize );
     output.only_backtrace_length_at_most = filter.backtrace_depth_max.map( |value| value as usize );
 
-    if let Some( id ) = filter.backtraces {
-        output.only_matching_backtraces = Some( std::iter::once( BacktraceId::new( id ) ).collect() );
-    }
-
-    if let Some( id ) = filter.deallocation_backtraces {
-        output.only_matching_deallocation_backtraces = Some( std::iter::once( BacktraceId::new( id ) ).collect() );
-    }
+    output.only_matching_backtraces = backtrace_set( &filter.backtraces );
+    output.only_matching_deallocation_backtraces = backtrace_set( &filter.deallocation_backtraces );
 
     match filter.mmaped {
         None => {},
@@ -151,6 +161,12 @@ pub fn prepare_raw_filter( data: &Data, filter: &protocol::AllocFilter ) -> Resu
         Some( protocol::ArenaFilter::NonMain ) => output.only_ptmalloc_not_from_main_arena = true
     }
 
+    match filter.freed_on_thread {
+        None => {},
+        Some( protocol::FreedOnThreadFilter::Other ) => output.only_freed_on_other_thread = true,
+        Some( protocol::FreedOnThreadFilter::Same ) => output.only_freed_on_same_thread = true
+    }
+
     if let Some( ref pattern ) = filter.function_regex {
         output.only_passing_through_function = Some(
             Regex::new( &pattern ).map_err( |err| PrepareFilterError::InvalidRegex( "function_regex", err ) )?
@@ -175,7 +191,17 @@ pub fn prepare_raw_filter( data: &Data, filter: &protocol::AllocFilter ) -> Resu
         );
     }
 
-    output.only_with_marker = filter.marker;
+    let markers = id_list( &filter.marker );
+    if markers.len() == 1 {
+        output.only_with_marker = Some( markers[ 0 ] );
+    }
+
+    let threads = id_list( &filter.threads );
+    if threads.len() == 1 {
+        output.only_from_thread = Some( threads[ 0 ] );
+    }
+
+    output.only_from_arena = filter.arena_id;
 
     output.only_group_interval_at_least = filter.group_interval_min.map( |ts| Duration( ts.to_timestamp( data.initial_timestamp(), data.last_timestamp() ) ) );
     output.only_group_interval_at_most = filter.group_interval_max.map( |ts| Duration( ts.to_timestamp( data.initial_timestamp(), data.last_timestamp() ) ) );
@@ -223,13 +249,104 @@ pub fn prepare_raw_filter( data: &Data, filter: &protocol::AllocFilter ) -> Resu
         }
     }
 
-    let output: cli_core::Filter = output.into();
+    let mut output: cli_core::Filter = output.into();
+    if markers.len() > 1 {
+        output = and( output, any_of( markers, |filter, marker| filter.only_with_marker = Some( marker ) ) );
+    }
+
+    if threads.len() > 1 {
+        output = and( output, any_of( threads, |filter, thread| filter.only_from_thread = Some( thread ) ) );
+    }
+
+    if let Some( ref expression ) = filter.expression {
+        output = and( output, prepare_filter_expression( data, expression )? );
+    }
+
     Ok( output )
 }
 
+fn id_list( list: &Option< protocol::IdList > ) -> &[u32] {
+    list.as_ref().map( |list| list.0.as_slice() ).unwrap_or( &[] )
+}
+
+/// An empty list is treated the same as if it wasn't specified at all.
+fn backtrace_set( list: &Option< protocol::IdList > ) -> Option< HashSet< BacktraceId > > {
+    let list = id_list( list );
+    if list.is_empty() {
+        return None;
+    }
+
+    Some( list.iter().map( |&id| BacktraceId::new( id ) ).collect() )
+}
+
+fn and( lhs: cli_core::Filter, rhs: cli_core::Filter ) -> cli_core::Filter {
+    cli_core::Filter::And( Box::new( lhs ), Box::new( rhs ) )
+}
+
+fn or( lhs: cli_core::Filter, rhs: cli_core::Filter ) -> cli_core::Filter {
+    cli_core::Filter::Or( Box::new( lhs ), Box::new( rhs ) )
+}
+
+fn everything() -> cli_core::Filter {
+    cli_core::BasicFilter::default().into()
+}
+
+/// Builds a filter which matches when any of the `values` matches; `callback` sets a single value on a filter.
+fn any_of( values: &[u32], callback: impl Fn( &mut cli_core::BasicFilter, u32 ) ) -> cli_core::Filter {
+    values.iter().map( |&value| {
+        let mut filter = cli_core::BasicFilter::default();
+        callback( &mut filter, value );
+        cli_core::Filter::from( filter )
+    }).fold( None, |lhs, rhs| Some( match lhs {
+        Some( lhs ) => or( lhs, rhs ),
+        None => rhs
+    })).unwrap_or_else( || cli_core::Filter::Not( Box::new( everything() ) ) )
+}
+
+fn prepare_filter_expression( data: &Data, expression: &protocol::FilterExpression ) -> Result< cli_core::Filter, PrepareFilterError > {
+    let (list, is_and) = match *expression {
+        protocol::FilterExpression::Filter( ref filter ) => return prepare_raw_filter( data, filter ),
+        protocol::FilterExpression::Not( ref expression ) => {
+            return Ok( cli_core::Filter::Not( Box::new( prepare_filter_expression( data, expression )? ) ) );
+        },
+        protocol::FilterExpression::And( ref list ) => (list, true),
+        protocol::FilterExpression::Or( ref list ) => (list, false)
+    };
+
+    let mut output = None;
+    for expression in list {
+        let rhs = prepare_filter_expression( data, expression )?;
+        output = Some( match output {
+            None => rhs,
+            Some( lhs ) if is_and => and( lhs, rhs ),
+            Some( lhs ) => or( lhs, rhs )
+        });
+    }
+
+    // An empty `and` matches everything, and an empty `or` matches nothing.
+    Ok( output.unwrap_or_else( || {
+        if is_and {
+            everything()
+        } else {
+            cli_core::Filter::Not( Box::new( everything() ) )
+        }
+    }))
+}
+
 pub enum PrepareFilterError {
     InvalidRegex( &'static str, regex::Error ),
-    InvalidCustomFilter( String )
+    InvalidCustomFilter( String ),
+    CustomFilterNotAllowed
+}
+
+impl std::fmt::Display for PrepareFilterError {
+    fn fmt( &self, fmt: &mut std::fmt::Formatter ) -> std::fmt::Result {
+        match *self {
+            PrepareFilterError::InvalidRegex( field, ref inner_err ) => write!( fmt, "invalid '{}': {}", field, inner_err ),
+            PrepareFilterError::InvalidCustomFilter( ref message ) => write!( fmt, "failed to evaluate custom filter: {}", message ),
+            PrepareFilterError::CustomFilterNotAllowed => write!( fmt, "custom filters can't be used when the server is running in read-only mode" )
+        }
+    }
 }
 
 pub fn prepare_backtrace_filter( filter: &protocol::BacktraceFilter ) -> Result< BacktraceFilter, PrepareFilterError > {
@@ -374,3 +491,85 @@ pub fn match_backtrace< 'a >(
 
     positive_matched && !negative_matched
 }
+
+#[cfg(test)]
+fn load_test_data() -> Data {
+    use common::event::{AllocBody, AllocationId, Event};
+
+    // Every allocation has its own size, backtrace and thread.
+    let mut events = Vec::new();
+    for (index, &size) in [10, 100, 1000].iter().enumerate() {
+        let nth = index as u64 + 1;
+        events.push( Event::Backtrace { id: nth, addresses: vec![ 0x1000 * nth ].into() } );
+        events.push( Event::AllocEx {
+            id: AllocationId { thread: nth, allocation: nth },
+            timestamp: Timestamp::from_usecs( nth ),
+            allocation: AllocBody {
+                pointer: 0x10000 * nth,
+                size,
+                backtrace: nth,
+                thread: nth as u32,
+                flags: 0,
+                extra_usable_space: 0,
+                preceding_free_space: 0
+            }
+        });
+    }
+
+    let data = bytehound_format::test_util::write_test_file( &events );
+    cli_core::Loader::load_from_stream_without_debug_info( std::io::Cursor::new( data ) ).unwrap()
+}
+
+#[cfg(test)]
+fn matched_sizes( data: &Data, query: &str ) -> Vec< u64 > {
+    let filter: protocol::AllocFilter = serde_urlencoded::from_str( query ).unwrap();
+    let filter = prepare_raw_filter( data, &filter ).ok().unwrap().compile( data );
+    let mut sizes: Vec< _ > = data.allocations_with_id()
+        .filter( |(_, allocation)| filter.try_match( data, allocation ) )
+        .map( |(_, allocation)| allocation.size )
+        .collect();
+
+    sizes.sort();
+    sizes
+}
+
+#[cfg(test)]
+fn expression_query( expression: &str ) -> String {
+    serde_urlencoded::to_string( &[ ("expression", expression) ] ).unwrap()
+}
+
+#[cfg(test)]
+fn backtrace_of( data: &Data, size: u64 ) -> u32 {
+    data.allocations_with_id().find( |(_, allocation)| allocation.size == size ).unwrap().1.backtrace.raw()
+}
+
+#[test]
+fn test_filter_any_of() {
+    let data = load_test_data();
+    let query = format!( "backtraces={},{}", backtrace_of( &data, 10 ), backtrace_of( &data, 100 ) );
+    assert_eq!( matched_sizes( &data, &query ), vec![ 10, 100 ] );
+    assert_eq!( matched_sizes( &data, "threads=1,3" ), vec![ 10, 1000 ] );
+    assert_eq!( matched_sizes( &data, "threads=2" ), vec![ 100 ] );
+}
+
+#[test]
+fn test_filter_expression() {
+    let data = load_test_data();
+    assert_eq!( matched_sizes( &data, &expression_query( r#"{"not": "size_min=100"}"# ) ), vec![ 10 ] );
+    assert_eq!( matched_sizes( &data, &expression_query( r#"{"or": ["threads=1", "size_min=1000"]}"# ) ), vec![ 10, 1000 ] );
+    assert_eq!( matched_sizes( &data, &expression_query( r#"{"and": ["size_min=50", {"not": "threads=3"}]}"# ) ), vec![ 100 ] );
+
+    // An empty `and` matches everything, and an empty `or` matches nothing.
+    assert_eq!( matched_sizes( &data, &expression_query( r#"{"and": []}"# ) ), vec![ 10, 100, 1000 ] );
+    assert_eq!( matched_sizes( &data, &expression_query( r#"{"or": []}"# ) ), Vec::< u64 >::new() );
+}
+
+#[test]
+fn test_filter_expression_with_other_conditions() {
+    let data = load_test_data();
+    let query = format!( "size_min=50&{}", expression_query( r#"{"or": ["threads=1", "threads=3"]}"# ) );
+    assert_eq!( matched_sizes( &data, &query ), vec![ 1000 ] );
+
+    let query = format!( "threads=1,2&{}", expression_query( r#"{"not": "size_max=50"}"# ) );
+    assert_eq!( matched_sizes( &data, &query ), vec![ 100 ] );
+}
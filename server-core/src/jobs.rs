@@ -0,0 +1,332 @@
+use std::fmt;
+use std::io;
+use std::panic;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ahash::AHashMap as HashMap;
+use parking_lot::Mutex;
+
+use cli_core::DataId;
+
+/// How long failed and cancelled jobs are kept around so that their status can still be checked.
+const FAILED_JOB_RETENTION: Duration = Duration::from_secs( 600 );
+
+#[derive(Default)]
+pub struct JobProgress {
+    done: AtomicU64,
+    total: AtomicU64,
+    cancelled: AtomicBool
+}
+
+impl JobProgress {
+    pub fn set_total( &self, total: u64 ) {
+        self.done.store( 0, Ordering::Relaxed );
+        self.total.store( total, Ordering::Relaxed );
+    }
+
+    #[inline]
+    pub fn advance( &self ) {
+        self.done.fetch_add( 1, Ordering::Relaxed );
+    }
+
+    /// Returns how much of the job is done, if known.
+    pub fn fraction( &self ) -> Option< f64 > {
+        let total = self.total.load( Ordering::Relaxed );
+        if total == 0 {
+            return None;
+        }
+
+        let done = self.done.load( Ordering::Relaxed );
+        Some( (done as f64 / total as f64).min( 1.0 ) )
+    }
+
+    pub fn cancel( &self ) {
+        self.cancelled.store( true, Ordering::Relaxed );
+    }
+
+    #[inline]
+    pub fn is_cancelled( &self ) -> bool {
+        self.cancelled.load( Ordering::Relaxed )
+    }
+}
+
+#[derive(Clone)]
+pub struct JobOutput {
+    pub mime: &'static str,
+    pub data: Arc< Vec< u8 > >
+}
+
+#[derive(Clone)]
+pub enum JobStatus {
+    Running,
+    Finished( JobOutput ),
+    Failed( String )
+}
+
+pub struct Job {
+    pub id: u64,
+    pub kind: &'static str,
+    pub data_id: DataId,
+    pub started_at: Instant,
+    pub progress: JobProgress,
+    key: String,
+    status: Mutex< (JobStatus, Option< Instant >) >
+}
+
+impl Job {
+    pub fn status( &self ) -> JobStatus {
+        self.status.lock().0.clone()
+    }
+
+    pub fn elapsed( &self ) -> Duration {
+        match self.status.lock().1 {
+            Some( finished_at ) => finished_at - self.started_at,
+            None => self.started_at.elapsed()
+        }
+    }
+
+    fn finished_at( &self ) -> Option< Instant > {
+        self.status.lock().1
+    }
+
+    fn finish( &self, status: JobStatus ) {
+        *self.status.lock() = (status, Some( Instant::now() ));
+    }
+}
+
+/// A buffer into which the jobs write their results.
+#[derive(Default)]
+pub struct JobBuffer {
+    pub data: Vec< u8 >
+}
+
+impl io::Write for JobBuffer {
+    fn write( &mut self, buffer: &[u8] ) -> io::Result< usize > {
+        self.data.extend_from_slice( buffer );
+        Ok( buffer.len() )
+    }
+
+    fn flush( &mut self ) -> io::Result< () > {
+        Ok(())
+    }
+}
+
+impl fmt::Write for JobBuffer {
+    fn write_str( &mut self, s: &str ) -> fmt::Result {
+        self.data.extend_from_slice( s.as_bytes() );
+        Ok(())
+    }
+}
+
+/// Every submitted job; the finished ones double as a cache of the results.
+pub struct Jobs {
+    next_id: u64,
+    by_id: HashMap< u64, Arc< Job > >,
+    by_key: HashMap< String, u64 >,
+    /// The jobs which were removed while still running; they still count towards the limit until they finish.
+    cancelled: Vec< Arc< Job > >,
+    max_cache_size: usize,
+    max_running: usize
+}
+
+impl Jobs {
+    pub fn new( max_cache_size: usize, max_running: usize ) -> Self {
+        Jobs {
+            next_id: 0,
+            by_id: HashMap::new(),
+            by_key: HashMap::new(),
+            cancelled: Vec::new(),
+            max_cache_size,
+            max_running
+        }
+    }
+
+    pub fn get( &self, id: u64 ) -> Option< Arc< Job > > {
+        self.by_id.get( &id ).cloned()
+    }
+
+    /// Returns a job which is either running or has already finished for the given key,
+    /// or creates a new one; the second value is `true` if the job was created
+    /// and has to be started by the caller.
+    ///
+    /// Returns `None` if a new job would have to be created while too many are already running.
+    pub fn get_or_insert( &mut self, kind: &'static str, data_id: DataId, key: String ) -> Option< (Arc< Job >, bool) > {
+        self.purge();
+
+        if let Some( job ) = self.by_key.get( &key ).and_then( |id| self.by_id.get( id ) ) {
+            match job.status() {
+                JobStatus::Running | JobStatus::Finished( .. ) if !job.progress.is_cancelled() => {
+                    return Some( (job.clone(), false) );
+                },
+                _ => {}
+            }
+        }
+
+        // The cancelled jobs are also counted since they're still running until they notice.
+        let running = self.by_id.values().chain( self.cancelled.iter() ).filter( |job| job.finished_at().is_none() ).count();
+        if running >= self.max_running {
+            return None;
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let job = Arc::new( Job {
+            id,
+            kind,
+            data_id,
+            started_at: Instant::now(),
+            progress: JobProgress::default(),
+            key: key.clone(),
+            status: Mutex::new( (JobStatus::Running, None) )
+        });
+
+        self.by_id.insert( id, job.clone() );
+        self.by_key.insert( key, id );
+        Some( (job, true) )
+    }
+
+    pub fn for_data( &self, data_id: DataId ) -> Vec< Arc< Job > > {
+        self.by_id.values().filter( |job| job.data_id == data_id ).cloned().collect()
+    }
+
+    pub fn remove_for_data( &mut self, data_id: DataId ) {
+        let cancelled = &mut self.cancelled;
+        self.by_id.retain( |_, job| {
+            if job.data_id != data_id {
+                return true;
+            }
+
+            job.progress.cancel();
+            if job.finished_at().is_none() {
+                cancelled.push( job.clone() );
+            }
+
+            false
+        });
+
+        let by_id = &self.by_id;
+        self.by_key.retain( |_, id| by_id.contains_key( id ) );
+    }
+
+    /// Drops old failed jobs, and the oldest results if the cache gets too big.
+    fn purge( &mut self ) {
+        self.cancelled.retain( |job| job.finished_at().is_none() );
+        self.by_id.retain( |_, job| {
+            match job.status() {
+                JobStatus::Failed( .. ) => job.finished_at().map( |finished_at| finished_at.elapsed() < FAILED_JOB_RETENTION ).unwrap_or( true ),
+                _ => true
+            }
+        });
+
+        let mut finished: Vec< _ > = self.by_id.values().filter_map( |job| {
+            match job.status() {
+                JobStatus::Finished( output ) => Some( (job.finished_at(), job.id, output.data.len()) ),
+                _ => None
+            }
+        }).collect();
+
+        let mut total_size: usize = finished.iter().map( |&(_, _, size)| size ).sum();
+        finished.sort();
+        for (_, id, size) in finished {
+            if total_size <= self.max_cache_size {
+                break;
+            }
+
+            total_size -= size;
+            self.by_id.remove( &id );
+        }
+
+        let by_id = &self.by_id;
+        self.by_key.retain( |key, id| by_id.get( id ).map( |job| job.key == *key ).unwrap_or( false ) );
+    }
+}
+
+/// Runs a job in the background.
+pub fn run_job< F >( job: Arc< Job >, callback: F )
+    where F: FnOnce( &JobProgress ) -> Result< JobOutput, String > + Send + 'static
+{
+    thread::spawn( move || {
+        let result = panic::catch_unwind( panic::AssertUnwindSafe( || callback( &job.progress ) ) )
+            .unwrap_or_else( |_| Err( "internal error".into() ) );
+
+        let status = match result {
+            _ if job.progress.is_cancelled() => JobStatus::Failed( "cancelled".into() ),
+            Ok( output ) => JobStatus::Finished( output ),
+            Err( error ) => JobStatus::Failed( error )
+        };
+
+        job.finish( status );
+    });
+}
+
+/// Generates a cache key for a query; the order of the parameters and the empty parameters don't matter.
+pub fn normalized_key( kind: &str, data_id: DataId, query: &str ) -> Result< String, serde_urlencoded::de::Error > {
+    let mut params: Vec< (String, String) > = serde_urlencoded::from_str( query )?;
+    params.retain( |(_, value)| !value.is_empty() );
+    params.sort();
+
+    let params = serde_urlencoded::to_string( &params ).unwrap_or_default();
+    Ok( format!( "{:x}", md5::compute( format!( "{}\n{}\n{}", kind, data_id, params ) ) ) )
+}
+
+#[test]
+fn test_normalized_key() {
+    let id = DataId::new( 1, 2 );
+    let key = |kind, query| normalized_key( kind, id, query ).unwrap();
+    assert_eq!( key( "a", "size_min=1&size_max=2" ), key( "a", "size_max=2&function_regex=&size_min=1" ) );
+    assert_ne!( key( "a", "size_min=1" ), key( "a", "size_min=2" ) );
+    assert_ne!( key( "a", "size_min=1" ), key( "b", "size_min=1" ) );
+}
+
+#[test]
+fn test_results_are_reused() {
+    let mut jobs = Jobs::new( 1024, 4 );
+    let id = DataId::new( 1, 2 );
+    let (first, is_new) = jobs.get_or_insert( "a", id, "key".into() ).unwrap();
+    assert!( is_new );
+
+    let (second, is_new) = jobs.get_or_insert( "a", id, "key".into() ).unwrap();
+    assert!( !is_new );
+    assert_eq!( first.id, second.id );
+
+    first.finish( JobStatus::Failed( "error".into() ) );
+    let (third, is_new) = jobs.get_or_insert( "a", id, "key".into() ).unwrap();
+    assert!( is_new );
+    assert_ne!( first.id, third.id );
+}
+
+#[test]
+fn test_running_jobs_are_capped() {
+    let mut jobs = Jobs::new( 1024, 1 );
+    let id = DataId::new( 1, 2 );
+    let (first, _) = jobs.get_or_insert( "a", id, "first".into() ).unwrap();
+    assert!( jobs.get_or_insert( "a", id, "second".into() ).is_none() );
+
+    // The running jobs can still be looked up.
+    assert!( jobs.get_or_insert( "a", id, "first".into() ).is_some() );
+
+    first.finish( JobStatus::Finished( JobOutput { mime: "text/plain", data: Arc::new( Vec::new() ) } ) );
+    assert!( jobs.get_or_insert( "a", id, "second".into() ).is_some() );
+}
+
+#[test]
+fn test_removed_jobs_are_counted_until_they_finish() {
+    let mut jobs = Jobs::new( 1024, 1 );
+    let id = DataId::new( 1, 2 );
+    let (first, _) = jobs.get_or_insert( "a", id, "first".into() ).unwrap();
+
+    jobs.remove_for_data( id );
+    assert!( first.progress.is_cancelled() );
+    assert!( jobs.get( first.id ).is_none() );
+    assert!( jobs.for_data( id ).is_empty() );
+    assert!( jobs.get_or_insert( "a", id, "second".into() ).is_none() );
+
+    first.finish( JobStatus::Failed( "cancelled".into() ) );
+    let (second, is_new) = jobs.get_or_insert( "a", id, "second".into() ).unwrap();
+    assert!( is_new );
+    assert_ne!( first.id, second.id );
+}
@@ -4,9 +4,12 @@ extern crate log;
 extern crate serde_derive;
 
 use std::collections::BTreeMap;
-use std::fs::File;
+use std::ffi::OsStr;
+use std::fs::{self, File};
 use std::error::Error;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::panic;
 use std::ops::Bound::{self, Unbounded};
 use std::fmt::{self, Write};
 use std::thread;
@@ -14,13 +17,15 @@ use std::io;
 use std::borrow::Cow;
 use std::cmp::{min, max};
 use std::path::PathBuf;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use actix_web::{
     body::{
         Body,
         BodyStream
     },
+    dev::Service,
+    http::header,
     web,
     App,
     HttpRequest,
@@ -30,15 +35,16 @@ use actix_web::{
 };
 
 use ahash::AHashMap as HashMap;
+use ahash::AHashSet as HashSet;
 
-use actix_web::error::{ErrorNotFound, ErrorBadRequest, ErrorInternalServerError};
+use actix_web::error::{ErrorNotFound, ErrorBadRequest, ErrorConflict, ErrorForbidden, ErrorInternalServerError, ErrorPayloadTooLarge, ErrorServiceUnavailable};
 use actix_web::error::Error as ActixWebError;
 use actix_cors::Cors;
-use futures::Stream;
+use futures::{future, Future, Stream};
 use serde::Serialize;
 use itertools::Itertools;
 use lru::LruCache;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
 use rayon::prelude::*;
 
 use cli_core::{
@@ -63,6 +69,7 @@ use cli_core::{
     export_as_replay,
     export_as_heaptrack,
     export_as_flamegraph,
+    export_as_flamegraph_by_count,
     export_as_flamegraph_pl,
     table_to_string
 };
@@ -75,10 +82,21 @@ mod streaming_channel;
 mod byte_channel;
 mod streaming_serializer;
 mod filter;
+mod jobs;
+mod auth;
+mod openapi;
+mod store;
+mod downsample;
+mod table;
 
 use crate::byte_channel::byte_channel;
 use crate::streaming_serializer::StreamingSerializer;
 use crate::filter::{AllocationFilter, PrepareFilterError, prepare_filter, prepare_raw_filter};
+use crate::jobs::{Job, JobBuffer, JobOutput, JobProgress, JobStatus, Jobs};
+use crate::store::Store;
+use crate::table::{Separator, TableWriter};
+
+pub use crate::auth::Auth;
 
 struct AllocationGroups {
     allocations_by_backtrace: VecVec< BacktraceId, AllocationId >
@@ -180,34 +198,165 @@ impl GeneratedFilesCollection {
     }
 }
 
+#[derive(Default)]
+struct Datasets {
+    by_id: HashMap< DataId, Arc< Data > >,
+    ids: Vec< DataId >,
+    sessions: Vec< Vec< DataId > >
+}
+
+struct LoadingEntry {
+    id: u64,
+    name: String,
+    total: u64,
+    loaded: Arc< AtomicU64 >,
+    error: Option< String >
+}
+
+#[derive(Default)]
+struct LoadingQueue {
+    next_id: u64,
+    entries: Vec< LoadingEntry >
+}
+
+/// How many bytes of finished job results are kept around to be reused.
+const JOB_CACHE_SIZE: usize = 256 * 1024 * 1024;
+
 struct State {
-    data: HashMap< DataId, Arc< Data > >,
-    data_ids: Vec< DataId >,
+    datasets: RwLock< Datasets >,
+    loading: Mutex< LoadingQueue >,
+    debug_symbols: Vec< PathBuf >,
+    script_limits: cli_core::script::ScriptLimits,
+    read_only: bool,
+    max_upload_size: Option< u64 >,
+    load_directories: Vec< PathBuf >,
+    max_running_jobs: usize,
+    script_jobs: Mutex< ScriptJobs >,
+    jobs: Mutex< Jobs >,
+    store: Mutex< Store >,
     allocation_group_cache: Mutex< LruCache< AllocationGroupsKey, Arc< AllocationGroups > > >,
+    live_timelines: Mutex< HashMap< DataId, (Weak< Data >, Arc< protocol::ResponseTimeline >) > >,
     generated_files: Mutex< GeneratedFilesCollection >
 }
 
 impl State {
-    fn new() -> Self {
+    fn new(
+        debug_symbols: Vec< PathBuf >,
+        script_limits: cli_core::script::ScriptLimits,
+        read_only: bool,
+        max_upload_size: Option< u64 >,
+        load_directories: Vec< PathBuf >,
+        max_running_jobs: usize,
+        store: Store
+    ) -> Self {
         State {
-            data: HashMap::new(),
-            data_ids: Vec::new(),
+            datasets: Default::default(),
+            loading: Default::default(),
+            debug_symbols,
+            script_limits,
+            read_only,
+            max_upload_size,
+            load_directories,
+            max_running_jobs,
+            script_jobs: Default::default(),
+            jobs: Mutex::new( Jobs::new( JOB_CACHE_SIZE, max_running_jobs ) ),
+            store: Mutex::new( store ),
             allocation_group_cache: Mutex::new( LruCache::new( 4 ) ),
+            live_timelines: Default::default(),
             generated_files: Default::default(),
         }
     }
 
-    fn add_data( &mut self, data: Data ) {
-        if self.data.contains_key( &data.id() ) {
-            return;
+    fn add_data( &self, data: Data ) -> DataId {
+        let id = data.id();
+        let mut datasets = self.datasets.write();
+        if datasets.by_id.contains_key( &id ) {
+            return id;
+        }
+
+        datasets.ids.push( id );
+        datasets.by_id.insert( id, Arc::new( data ) );
+        id
+    }
+
+    /// Adds the data, replacing any data with the same ID; used for recordings which are still growing.
+    fn replace_data( &self, data: Data ) -> DataId {
+        let id = data.id();
+        {
+            let mut datasets = self.datasets.write();
+            if !datasets.by_id.contains_key( &id ) {
+                datasets.ids.push( id );
+            }
+
+            datasets.by_id.insert( id, Arc::new( data ) );
+        }
+
+        self.drop_cached( id );
+        id
+    }
+
+    fn remove_data( &self, id: DataId ) -> bool {
+        {
+            let mut datasets = self.datasets.write();
+            if datasets.by_id.remove( &id ).is_none() {
+                return false;
+            }
+
+            datasets.ids.retain( |&data_id| data_id != id );
+            for session in &mut datasets.sessions {
+                session.retain( |&data_id| data_id != id );
+            }
+        }
+
+        self.drop_cached( id );
+        true
+    }
+
+    fn drop_cached( &self, id: DataId ) {
+        let mut cache = self.allocation_group_cache.lock();
+        let stale: Vec< _ > = cache.iter().map( |(key, _)| key ).filter( |key| key.data_id == id ).cloned().collect();
+        for key in stale {
+            cache.pop( &key );
+        }
+
+        self.jobs.lock().remove_for_data( id );
+        self.live_timelines.lock().remove( &id );
+    }
+
+    /// Returns the timeline which is streamed to the clients watching a given data file.
+    ///
+    /// It's only built once for every version of the data, no matter how many clients are watching.
+    fn live_timeline( &self, data: &Arc< Data > ) -> Arc< protocol::ResponseTimeline > {
+        let mut timelines = self.live_timelines.lock();
+        if let Some( (cached_data, timeline) ) = timelines.get( &data.id() ) {
+            if cached_data.ptr_eq( &Arc::downgrade( data ) ) {
+                return timeline.clone();
+            }
         }
 
-        self.data_ids.push( data.id() );
-        self.data.insert( data.id(), Arc::new( data ) );
+        let timeline = cli_core::build_timeline_with_granularity(
+            data,
+            data.initial_timestamp(),
+            data.last_timestamp(),
+            data.operation_ids(),
+            LIVE_TIMELINE_GRANULARITY
+        );
+
+        let timeline = Arc::new( timeline_response( timeline ) );
+        timelines.insert( data.id(), (Arc::downgrade( data ), timeline.clone()) );
+        timeline
+    }
+
+    fn add_session( &self, data_ids: Vec< DataId > ) {
+        self.datasets.write().sessions.push( data_ids );
+    }
+
+    fn get( &self, id: DataId ) -> Option< Arc< Data > > {
+        self.datasets.read().by_id.get( &id ).cloned()
     }
 
     fn last_id( &self ) -> Option< DataId > {
-        self.data_ids.last().cloned()
+        self.datasets.read().ids.last().cloned()
     }
 }
 
@@ -228,6 +377,15 @@ fn query< 'a, T: serde::Deserialize< 'a > >( req: &'a HttpRequest ) -> Result< T
         .map_err( |e| e.into() )
 }
 
+/// Makes sure that the request is allowed to modify the state of the server or execute scripts.
+fn check_writable( req: &HttpRequest ) -> Result< () > {
+    if req.state().read_only {
+        return Err( ErrorForbidden( "the server is running in read-only mode" ) );
+    }
+
+    Ok(())
+}
+
 fn get_data_id( req: &HttpRequest ) -> Result< DataId > {
     let id = req.match_info().get( "id" ).unwrap();
     if id == "last" {
@@ -235,26 +393,196 @@ fn get_data_id( req: &HttpRequest ) -> Result< DataId > {
     }
 
     let id: DataId = id.parse().map_err( |_| ErrorNotFound( "data not found" ) )?;
-    if !req.state().data.contains_key( &id ) {
+    if !req.state().datasets.read().by_id.contains_key( &id ) {
         return Err( ErrorNotFound( "data not found" ) );
     }
     Ok( id )
 }
 
-fn get_data( req: &HttpRequest ) -> Result< &Arc< Data > > {
+fn get_data( req: &HttpRequest ) -> Result< Arc< Data > > {
     let id = get_data_id( req )?;
-    req.state().data.get( &id ).ok_or_else( || ErrorNotFound( "data not found" ) )
+    req.state().get( id ).ok_or_else( || ErrorNotFound( "data not found" ) )
+}
+
+fn get_session( req: &HttpRequest ) -> Result< Vec< Arc< Data > > > {
+    let id: usize = req.match_info().get( "id" ).unwrap().parse().map_err( |_| ErrorNotFound( "session not found" ) )?;
+    let datasets = req.state().datasets.read();
+    let session = datasets.sessions.get( id ).ok_or_else( || ErrorNotFound( "session not found" ) )?;
+    Ok( session.iter().map( |id| datasets.by_id[ id ].clone() ).collect() )
+}
+
+struct ProgressReader< F > {
+    fp: F,
+    counter: Arc< AtomicU64 >
+}
+
+impl< F: io::Read > io::Read for ProgressReader< F > {
+    fn read( &mut self, buffer: &mut [u8] ) -> io::Result< usize > {
+        let count = self.fp.read( buffer )?;
+        self.counter.fetch_add( count as u64, Ordering::Relaxed );
+        Ok( count )
+    }
+}
+
+/// Starts loading a given file in the background; the progress can be checked through `/loading`.
+fn start_loading( state: &StateRef, path: PathBuf, name: String, remove_when_done: bool, replace_existing: bool ) -> io::Result< u64 > {
+    let fp = File::open( &path )?;
+    let total = fp.metadata()?.len();
+    let loaded = Arc::new( AtomicU64::new( 0 ) );
+    let id = {
+        let mut queue = state.loading.lock();
+        let id = queue.next_id;
+        queue.next_id += 1;
+        queue.entries.push( LoadingEntry {
+            id,
+            name,
+            total,
+            loaded: loaded.clone(),
+            error: None
+        });
+        id
+    };
+
+    let state = state.clone();
+    thread::spawn( move || {
+        info!( "Trying to load {:?}...", path );
+        let fp = ProgressReader { fp, counter: loaded };
+        let result = panic::catch_unwind( panic::AssertUnwindSafe( || Loader::load_from_stream( fp, &state.debug_symbols ) ) )
+            .unwrap_or_else( |_| Err( io::Error::new( io::ErrorKind::Other, "the loader has crashed" ) ) );
+
+        if remove_when_done {
+            let _ = fs::remove_file( &path );
+        }
+
+        match result {
+            Ok( data ) => {
+                let data_id = if replace_existing {
+                    state.replace_data( data )
+                } else {
+                    state.add_data( data )
+                };
+
+                info!( "Loaded {:?} as {}", path, data_id );
+                state.loading.lock().entries.retain( |entry| entry.id != id );
+            },
+            Err( error ) => {
+                error!( "Failed to load {:?}: {}", path, error );
+                if let Some( entry ) = state.loading.lock().entries.iter_mut().find( |entry| entry.id == id ) {
+                    entry.error = Some( error.to_string() );
+                }
+            }
+        }
+    });
+
+    Ok( id )
+}
+
+const WATCH_INTERVAL: Duration = Duration::from_secs( 2 );
+
+/// How often a recording which is still being written to is reloaded.
+const LIVE_RELOAD_INTERVAL: Duration = Duration::from_secs( 10 );
+
+/// Every reload parses the whole file again, so the longer it takes the less often it's done;
+/// a recording is only reloaded after this many times the duration of its previous reload.
+const LIVE_RELOAD_BACKOFF: u32 = 5;
+
+struct LiveFile {
+    size: u64,
+    loading_id: u64,
+    reloaded_at: Instant,
+    reload_duration: Option< Duration >
+}
+
+/// Periodically scans a given directory and loads every new data file which appears in it.
+///
+/// If `live` is set then the files are loaded even if the profiler is still writing into them,
+/// and are periodically reloaded as they grow.
+fn watch_directory( state: StateRef, directory: PathBuf, mut seen: HashSet< PathBuf >, live: bool ) {
+    let mut last_sizes: HashMap< PathBuf, u64 > = HashMap::new();
+    let mut live_files: HashMap< PathBuf, LiveFile > = HashMap::new();
+    loop {
+        match fs::read_dir( &directory ) {
+            Ok( entries ) => {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if path.extension() != Some( OsStr::new( "dat" ) ) {
+                        continue;
+                    }
+
+                    let size = match entry.metadata() {
+                        Ok( metadata ) if metadata.is_file() => metadata.len(),
+                        _ => continue
+                    };
+
+                    let name = path.to_string_lossy().into_owned();
+                    if let Some( file ) = live_files.get_mut( &path ) {
+                        {
+                            let mut queue = state.loading.lock();
+                            if queue.entries.iter().any( |entry| entry.id == file.loading_id && entry.error.is_none() ) {
+                                continue;
+                            }
+
+                            // This is only as precise as the interval at which the directory is scanned, which is good enough.
+                            let elapsed = file.reloaded_at.elapsed();
+                            let reload_duration = *file.reload_duration.get_or_insert( elapsed );
+                            let interval = max( LIVE_RELOAD_INTERVAL, reload_duration * LIVE_RELOAD_BACKOFF );
+                            if size <= file.size || elapsed < interval {
+                                continue;
+                            }
+
+                            // Don't keep around the errors from the previous attempts.
+                            queue.entries.retain( |entry| entry.id != file.loading_id );
+                        }
+
+                        match start_loading( &state, path.clone(), name, false, true ) {
+                            Ok( loading_id ) => {
+                                file.size = size;
+                                file.loading_id = loading_id;
+                                file.reloaded_at = Instant::now();
+                                file.reload_duration = None;
+                            },
+                            Err( error ) => warn!( "Failed to open {:?}: {}", path, error )
+                        }
+
+                        continue;
+                    }
+
+                    if seen.contains( &path ) {
+                        continue;
+                    }
+
+                    // The profiler might still be writing into it, so wait until it stops growing.
+                    if size == 0 || (!live && last_sizes.insert( path.clone(), size ) != Some( size )) {
+                        continue;
+                    }
+
+                    last_sizes.remove( &path );
+                    seen.insert( path.clone() );
+
+                    match start_loading( &state, path.clone(), name, false, live ) {
+                        Ok( loading_id ) => {
+                            if live {
+                                live_files.insert( path, LiveFile { size, loading_id, reloaded_at: Instant::now(), reload_duration: None } );
+                            }
+                        },
+                        Err( error ) => warn!( "Failed to open {:?}: {}", path, error )
+                    }
+                }
+            },
+            Err( error ) => {
+                warn!( "Failed to scan {:?}: {}", directory, error );
+            }
+        }
+
+        thread::sleep( WATCH_INTERVAL );
+    }
 }
 
 impl From< PrepareFilterError > for ActixWebError {
     fn from( error: PrepareFilterError ) -> Self {
         match error {
-            PrepareFilterError::InvalidRegex( field, inner_err ) => {
-                ErrorBadRequest( format!( "invalid '{}': {}", field, inner_err ) )
-            },
-            PrepareFilterError::InvalidCustomFilter( message ) => {
-                ErrorBadRequest( format!( "failed to evaluate custom filter: {}", message ) )
-            }
+            PrepareFilterError::CustomFilterNotAllowed => ErrorForbidden( error.to_string() ),
+            _ => ErrorBadRequest( error.to_string() )
         }
     }
 }
@@ -268,17 +596,68 @@ fn async_data_handler< F: FnOnce( Arc< Data >, byte_channel::ByteSender ) + Send
     let data_id = get_data_id( &req )?;
     let state = req.state().clone();
     thread::spawn( move || {
-        let data = match state.data.get( &data_id ) {
+        let data = match state.get( data_id ) {
             Some( data ) => data,
             None => return
         };
 
-        callback( data.clone(), tx );
+        callback( data, tx );
     });
 
     Ok( body )
 }
 
+trait QueryOutput: io::Write + fmt::Write {}
+impl< T: io::Write + fmt::Write > QueryOutput for T {}
+
+type QueryCallback = Box< dyn FnOnce( &Arc< Data >, AllocationFilter, &JobProgress, &mut dyn QueryOutput ) -> io::Result< () > + Send >;
+
+/// An expensive query which can be either streamed back directly or executed in the background as a job.
+struct Query {
+    mime: &'static str,
+    filter: protocol::AllocFilter,
+    custom_filter: protocol::CustomFilter,
+    callback: QueryCallback
+}
+
+impl Query {
+    fn new< F >( mime: &'static str, filter: protocol::AllocFilter, custom_filter: protocol::CustomFilter, callback: F ) -> Self
+        where F: FnOnce( &Arc< Data >, AllocationFilter, &JobProgress, &mut dyn QueryOutput ) -> io::Result< () > + Send + 'static
+    {
+        Query {
+            mime,
+            filter,
+            custom_filter,
+            callback: Box::new( callback )
+        }
+    }
+
+    fn run( self, state: &State, data: &Arc< Data >, progress: &JobProgress, output: &mut dyn QueryOutput ) -> std::result::Result< (), String > {
+        let filter = prepare_filter( data, &self.filter, &self.custom_filter, state ).map_err( |error| error.to_string() )?;
+        (self.callback)( data, filter, progress, output ).map_err( |error| error.to_string() )
+    }
+}
+
+fn respond_with_query( req: &HttpRequest, query: Query ) -> Result< HttpResponse > {
+    let data = get_data( req )?;
+    let filter = prepare_filter( &data, &query.filter, &query.custom_filter, req.state() )?;
+    let callback = query.callback;
+    let body = async_data_handler( req, move |data, mut tx| {
+        let _ = callback( &data, filter, &JobProgress::default(), &mut tx );
+    })?;
+
+    Ok( HttpResponse::Ok().content_type( query.mime ).body( body ) )
+}
+
+/// Wraps a filter so that the progress is updated as the allocations are matched; once the job
+/// is cancelled nothing matches anymore so that whatever is using the filter finishes quickly.
+fn track_progress< 'a >( data: &'a Data, filter: &'a AllocationFilter, progress: &'a JobProgress ) -> impl Fn( AllocationId, &Allocation ) -> bool + Send + Sync + 'a {
+    move |id: AllocationId, allocation: &Allocation| {
+        progress.advance();
+        !progress.is_cancelled() && filter.try_match( data, id, allocation )
+    }
+}
+
 fn strip_template( input: &str ) -> String {
     let mut out = String::new();
     let mut buffered = String::new();
@@ -343,6 +722,84 @@ fn get_frame< 'a >( data: &'a Data, format: &protocol::BacktraceFormat, frame: &
     }
 }
 
+/// How many of the innermost frames of every backtrace are included by default in a table.
+const DEFAULT_TABLE_FRAMES: u32 = 5;
+
+#[derive(Copy, Clone)]
+struct TableFormat {
+    separator: Separator,
+    frames: usize
+}
+
+impl TableFormat {
+    fn mime( &self ) -> &'static str {
+        match self.separator {
+            Separator::Comma => "text/csv; charset=utf-8",
+            Separator::Tab => "text/tab-separated-values; charset=utf-8"
+        }
+    }
+}
+
+/// Returns `None` if the list should be returned as JSON.
+fn get_table_format( req: &HttpRequest ) -> Result< Option< TableFormat > > {
+    let params: protocol::RequestListFormat = query( req )?;
+    let separator = match params.format.unwrap_or_default() {
+        protocol::ListFormat::Json => return Ok( None ),
+        protocol::ListFormat::Csv => Separator::Comma,
+        protocol::ListFormat::Tsv => Separator::Tab
+    };
+
+    Ok( Some( TableFormat {
+        separator,
+        frames: params.frames.unwrap_or( DEFAULT_TABLE_FRAMES ) as usize
+    }))
+}
+
+fn frame_cell( frame: &protocol::Frame ) -> String {
+    let name = match (frame.function.as_ref(), frame.raw_function) {
+        (Some( function ), _) => function.to_string(),
+        (None, Some( raw_function )) => raw_function.to_owned(),
+        (None, None) => format!( "0x{}", frame.address_s )
+    };
+
+    match (frame.source, frame.line, frame.library) {
+        (Some( source ), Some( line ), _) => format!( "{} at {}:{}", name, source, line ),
+        (_, _, Some( library )) => format!( "{} in {}", name, library ),
+        _ => name
+    }
+}
+
+fn table_frames_header< W: fmt::Write >( table: &mut TableWriter< W >, format: &TableFormat ) -> fmt::Result {
+    for index in 0..format.frames {
+        table.cell( format_args!( "frame_{}", index + 1 ) )?;
+    }
+
+    Ok(())
+}
+
+/// Writes the innermost frames of a given backtrace, starting with the one which called the allocator.
+fn table_frames< W: fmt::Write >(
+    table: &mut TableWriter< W >,
+    format: &TableFormat,
+    data: &Data,
+    backtrace_format: &protocol::BacktraceFormat,
+    backtrace_id: BacktraceId
+) -> fmt::Result {
+    let mut frames = data.get_backtrace( backtrace_id ).rev();
+    for _ in 0..format.frames {
+        match frames.next() {
+            Some( (_, frame) ) => table.cell( frame_cell( &get_frame( data, backtrace_format, frame ) ) )?,
+            None => table.empty_cell()?
+        }
+    }
+
+    Ok(())
+}
+
+fn table_response( format: &TableFormat, body: Body ) -> HttpResponse {
+    HttpResponse::Ok().content_type( format.mime() ).body( body )
+}
+
 impl protocol::ResponseMetadata {
     fn new( data: &Data ) -> Self {
         protocol::ResponseMetadata {
@@ -361,19 +818,134 @@ impl protocol::ResponseMetadata {
             runtime: (data.last_timestamp() - data.initial_timestamp()).into(),
             unique_backtrace_count: data.unique_backtrace_count() as u64,
             maximum_backtrace_depth: data.maximum_backtrace_depth(),
-            timestamp: data.initial_timestamp().into()
+            timestamp: data.initial_timestamp().into(),
+            pid: data.pid(),
+            parent_pid: data.parent_pid()
         }
     }
 }
 
+fn handler_openapi() -> HttpResponse {
+    HttpResponse::Ok().json( openapi::generate() )
+}
+
 fn handler_list( req: HttpRequest ) -> HttpResponse {
-    let list: Vec< _ > = req.state().data.values().map( |data| {
+    let list: Vec< _ > = req.state().datasets.read().by_id.values().map( |data| {
         protocol::ResponseMetadata::new( data )
     }).collect();
 
     HttpResponse::Ok().json( list )
 }
 
+fn handler_unload( req: HttpRequest ) -> Result< HttpResponse > {
+    check_writable( &req )?;
+    let id = get_data_id( &req )?;
+    req.state().remove_data( id );
+    Ok( HttpResponse::Ok().finish() )
+}
+
+fn handler_load( req: HttpRequest ) -> Result< HttpResponse > {
+    check_writable( &req )?;
+    let params: protocol::RequestLoad = query( &req )?;
+    let path = fs::canonicalize( &params.path ).map_err( |_| ErrorBadRequest( "no such file" ) )?;
+    if !req.state().load_directories.iter().any( |directory| path.starts_with( directory ) ) {
+        return Err( ErrorForbidden( "loading files from this directory is not allowed" ) );
+    }
+
+    if !path.is_file() {
+        return Err( ErrorBadRequest( "no such file" ) );
+    }
+
+    let name = path.to_string_lossy().into_owned();
+    let id = start_loading( req.state(), path, name, false, false ).map_err( |error| ErrorBadRequest( format!( "failed to open the file: {}", error ) ) )?;
+    Ok( HttpResponse::Ok().json( protocol::ResponseLoadStarted { id } ) )
+}
+
+static UPLOAD_COUNTER: AtomicU64 = AtomicU64::new( 0 );
+
+fn handler_upload( req: HttpRequest, payload: web::Payload ) -> Box< dyn Future< Item = HttpResponse, Error = ActixWebError > > {
+    if let Err( error ) = check_writable( &req ) {
+        return Box::new( future::err::< HttpResponse, _ >( error ) );
+    }
+
+    let params: protocol::RequestUpload = match query( &req ) {
+        Ok( params ) => params,
+        Err( error ) => return Box::new( future::err::< HttpResponse, _ >( error ) )
+    };
+
+    let max_upload_size = req.state().max_upload_size;
+    let content_length = req.headers().get( header::CONTENT_LENGTH )
+        .and_then( |value| value.to_str().ok() )
+        .and_then( |value| value.parse::< u64 >().ok() );
+
+    if let (Some( content_length ), Some( max_upload_size )) = (content_length, max_upload_size) {
+        if content_length > max_upload_size {
+            return Box::new( future::err::< HttpResponse, _ >( ErrorPayloadTooLarge( "the file is too big" ) ) );
+        }
+    }
+
+    let name = params.name.unwrap_or_else( || "upload".to_owned() );
+    let path = std::env::temp_dir().join( format!( "bytehound-upload-{}-{}.dat", std::process::id(), UPLOAD_COUNTER.fetch_add( 1, Ordering::SeqCst ) ) );
+    let fp = match File::create( &path ) {
+        Ok( fp ) => fp,
+        Err( error ) => return Box::new( future::err::< HttpResponse, _ >( ErrorInternalServerError( error ) ) )
+    };
+
+    let state = req.state().clone();
+    let response = payload
+        .map_err( ActixWebError::from )
+        .fold( (fp, 0), move |(fp, size), chunk| {
+            // The length in the headers is not required to be there nor to be truthful.
+            let size = size + chunk.len() as u64;
+            if max_upload_size.map( |max_upload_size| size > max_upload_size ).unwrap_or( false ) {
+                return future::Either::A( future::err( ErrorPayloadTooLarge( "the file is too big" ) ) );
+            }
+
+            let write = web::block( move || {
+                let mut fp = fp;
+                io::Write::write_all( &mut fp, &chunk ).map( |_| fp )
+            });
+
+            future::Either::B( write.map( move |fp| (fp, size) ).map_err( ErrorInternalServerError ) )
+        })
+        .then( move |result| {
+            if let Err( error ) = result {
+                let _ = fs::remove_file( &path );
+                return Err( error );
+            }
+
+            let id = start_loading( &state, path, name, true, false ).map_err( ErrorInternalServerError )?;
+            Ok( HttpResponse::Ok().json( protocol::ResponseLoadStarted { id } ) )
+        });
+
+    Box::new( response )
+}
+
+fn loading_progress( state: &State ) -> Vec< protocol::LoadingProgress > {
+    state.loading.lock().entries.iter().map( |entry| {
+        protocol::LoadingProgress {
+            id: entry.id,
+            name: entry.name.clone(),
+            loaded: min( entry.loaded.load( Ordering::Relaxed ), entry.total ),
+            total: entry.total,
+            error: entry.error.clone()
+        }
+    }).collect()
+}
+
+fn handler_loading( req: HttpRequest ) -> HttpResponse {
+    HttpResponse::Ok().json( loading_progress( req.state() ) )
+}
+
+fn handler_dismiss_loading( req: HttpRequest ) -> Result< HttpResponse > {
+    check_writable( &req )?;
+    let id: u64 = req.match_info().get( "id" ).unwrap().parse().map_err( |_| ErrorNotFound( "not found" ) )?;
+    let mut queue = req.state().loading.lock();
+    let index = queue.entries.iter().position( |entry| entry.id == id && entry.error.is_some() ).ok_or_else( || ErrorNotFound( "not found" ) )?;
+    queue.entries.remove( index );
+    Ok( HttpResponse::Ok().finish() )
+}
+
 fn get_fragmentation_timeline( data: &Data ) -> protocol::ResponseFragmentationTimeline {
     #[inline(always)]
     fn is_matched( allocation: &Allocation ) -> bool {
@@ -555,73 +1127,295 @@ fn get_fragmentation_timeline( data: &Data ) -> protocol::ResponseFragmentationT
 }
 
 fn handler_fragmentation_timeline( req: HttpRequest ) -> Result< HttpResponse > {
-    let data = get_data( &req )?;
-    let response = get_fragmentation_timeline( data );
+    let data = &get_data( &req )?;
+    let range = get_timeline_range( &req, data )?;
+    let timeline = get_fragmentation_timeline( data );
+
+    // The timeline has a point for every second, so for long recordings it has to be downsampled.
+    let from = range.from.as_usecs() / 1000;
+    let to = range.to.as_usecs() / 1000;
+    let start = timeline.xs.iter().position( |&x| x >= from ).unwrap_or( timeline.xs.len() ).saturating_sub( 1 );
+    let end = timeline.xs.iter().rposition( |&x| x <= to ).map( |index| index + 1 ).unwrap_or( 0 );
+    let (xs, fragmentation) = if start < end {
+        (&timeline.xs[ start..end ], &timeline.fragmentation[ start..end ])
+    } else {
+        (&[][..], &[][..])
+    };
+
+    let indices = downsample::min_max_indices( fragmentation, range.max_points );
+    let response = protocol::ResponseFragmentationTimeline {
+        xs: downsample::pick( xs, &indices ),
+        fragmentation: downsample::pick( fragmentation, &indices )
+    };
+
     Ok( HttpResponse::Ok().json( response ) )
 }
 
-fn build_timeline( data: &Data, ops: &[OperationId] ) -> protocol::ResponseTimeline {
-    let timeline = cli_core::build_timeline( data, data.initial_timestamp(), data.last_timestamp(), ops );
-
-    let mut xs = Vec::with_capacity( timeline.len() );
-    let mut size_delta = Vec::with_capacity( timeline.len() );
-    let mut count_delta = Vec::with_capacity( timeline.len() );
-    let mut allocated_size = Vec::with_capacity( timeline.len() );
-    let mut allocated_count = Vec::with_capacity( timeline.len() );
-    let mut allocations = Vec::with_capacity( timeline.len() );
-    let mut deallocations = Vec::with_capacity( timeline.len() );
+fn handler_arenas( req: HttpRequest ) -> Result< HttpResponse > {
+    let data = &get_data( &req )?;
+    let mut arenas_by_thread: BTreeMap< u32, Vec< u32 > > = BTreeMap::new();
+    let arenas = data.arenas().iter().map( |arena| {
+        for thread in &arena.threads {
+            arenas_by_thread.entry( thread.thread ).or_insert_with( Vec::new ).push( arena.id );
+        }
 
-    let mut last_size = 0;
-    let mut last_count = 0;
-    for point in timeline {
-        xs.push( point.timestamp / 1000 );
-        size_delta.push( point.memory_usage as i64 - last_size );
-        count_delta.push( point.allocations as i64 - last_count );
-        allocated_size.push( point.memory_usage );
-        allocated_count.push( point.allocations );
-        allocations.push( point.allocations_per_time as u32 );
-        deallocations.push( point.deallocations_per_time as u32 );
+        protocol::Arena {
+            id: arena.id,
+            is_main: arena.is_main(),
+            heaps: arena.heaps.iter().map( |heap| protocol::ArenaHeap {
+                address: heap.address,
+                address_s: format!( "{:016X}", heap.address ),
+                first_used_at: heap.first_used_at.into(),
+                last_used_at: heap.last_used_at.into(),
+                mapped_at: heap.mapped_at.map( |timestamp| timestamp.into() ),
+                unmapped_at: heap.unmapped_at.map( |timestamp| timestamp.into() )
+            }).collect(),
+            threads: arena.threads.iter().map( |thread| protocol::ArenaThread {
+                thread: thread.thread,
+                allocated_count: thread.allocated_count,
+                allocated_size: thread.allocated_size
+            }).collect(),
+            allocated_count: arena.allocated_count,
+            allocated_size: arena.allocated_size
+        }
+    }).collect();
 
-        last_size = point.memory_usage as i64;
-        last_count = point.allocations as i64;
-    }
+    let response = protocol::ResponseArenas {
+        heap_max_size: cli_core::heap_max_size( data.pointer_size() ),
+        arenas,
+        threads: arenas_by_thread.into_iter().map( |(thread, arenas)| protocol::ThreadArenas { thread, arenas } ).collect()
+    };
 
-    protocol::ResponseTimeline {
-        xs,
-        size_delta,
-        count_delta,
-        allocated_size,
-        allocated_count,
-        allocations,
-        deallocations
-    }
+    Ok( HttpResponse::Ok().json( response ) )
 }
 
-fn handler_timeline( req: HttpRequest ) -> Result< HttpResponse > {
-    let data = get_data( &req )?;
-    let timeline = build_timeline( &data, data.operation_ids() );
-    Ok( HttpResponse::Ok().json( timeline ) )
+fn handler_arena_timeline( req: HttpRequest ) -> Result< HttpResponse > {
+    let data = &get_data( &req )?;
+    let timeline = cli_core::build_arena_timeline( data );
+    let response = protocol::ResponseArenaTimeline {
+        xs: timeline.timestamps.iter().map( |timestamp| timestamp.as_usecs() / 1000 ).collect(),
+        arenas: timeline.arenas.into_iter().map( |series| protocol::ArenaTimelineSeries {
+            id: series.arena,
+            usage: series.usage,
+            fragmentation: series.fragmentation
+        }).collect()
+    };
+
+    Ok( HttpResponse::Ok().json( response ) )
 }
 
-fn handler_timeline_leaked( req: HttpRequest ) -> Result< HttpResponse > {
-    let data = get_data( &req )?;
-    let ops: Vec< _ > = data.operation_ids().par_iter().flat_map( |op| {
-        let allocation = data.get_allocation( op.id() );
-        if allocation.deallocation.is_some() {
-            None
-        } else {
-            Some( OperationId::new_allocation( op.id() ) )
+fn handler_sessions( req: HttpRequest ) -> HttpResponse {
+    let loaded = req.state().datasets.read();
+    let sessions: Vec< _ > = loaded.sessions.iter().enumerate().filter( |(_, data_ids)| !data_ids.is_empty() ).map( |(id, data_ids)| {
+        let datasets: Vec< &Data > = data_ids.iter().map( |data_id| &*loaded.by_id[ data_id ] ).collect();
+        let processes = cli_core::build_process_tree( &datasets ).into_iter().map( |process| {
+            let data = datasets[ process.index ];
+            protocol::SessionProcess {
+                id: format!( "{}", data.id() ),
+                pid: process.pid,
+                parent_pid: process.parent_pid,
+                parent_id: process.parent.map( |parent| format!( "{}", datasets[ parent ].id() ) ),
+                depth: process.depth,
+                executable: data.executable().to_owned(),
+                started_at: process.started_at.into(),
+                finished_at: process.finished_at.into()
+            }
+        }).collect();
+
+        protocol::ResponseSession {
+            id,
+            processes
         }
     }).collect();
 
-    let timeline = build_timeline( &data, &ops );
-    Ok( HttpResponse::Ok().json( timeline ) )
+    HttpResponse::Ok().json( sessions )
 }
 
-fn prefiltered_allocation_ids< 'a >(
-    data: &'a Data,
-    sort_by: protocol::AllocSortBy,
-    _filter: &AllocationFilter
+fn handler_session_timeline( req: HttpRequest ) -> Result< HttpResponse > {
+    let datasets = get_session( &req )?;
+    let params: protocol::RequestSessionTimeline = query( &req )?;
+    let filter: protocol::AllocFilter = query( &req )?;
+    let custom_filter: protocol::CustomFilter = query( &req )?;
+
+    let pids: Option< Vec< u32 > > = match params.pids {
+        Some( ref pids ) => {
+            let pids: std::result::Result< Vec< u32 >, _ > = pids.split( "," ).map( |pid| pid.trim().parse() ).collect();
+            Some( pids.map_err( |_| ErrorBadRequest( "invalid 'pids'" ) )? )
+        },
+        None => None
+    };
+
+    let mut filters = Vec::with_capacity( datasets.len() );
+    for data in &datasets {
+        let is_included = pids.as_ref().map( |pids| pids.contains( &data.pid() ) ).unwrap_or( true );
+        if is_included {
+            filters.push( Some( prepare_filter( data, &filter, &custom_filter, req.state() )? ) );
+        } else {
+            filters.push( None );
+        }
+    }
+
+    let datasets: Vec< &Data > = datasets.iter().map( |data| &**data ).collect();
+    let timeline = cli_core::build_session_timeline( &datasets, |index, id, allocation| {
+        match filters[ index ] {
+            Some( ref filter ) => filter.try_match( datasets[ index ], id, allocation ),
+            None => false
+        }
+    });
+
+    let response = protocol::ResponseSessionTimeline {
+        xs: timeline.timestamps.iter().map( |timestamp| timestamp.as_usecs() / 1000 ).collect(),
+        processes: timeline.usage.into_iter().enumerate().filter( |(index, _)| filters[ *index ].is_some() ).map( |(index, usage)| {
+            protocol::SessionTimelineSeries {
+                id: format!( "{}", datasets[ index ].id() ),
+                pid: datasets[ index ].pid(),
+                usage
+            }
+        }).collect()
+    };
+
+    Ok( HttpResponse::Ok().json( response ) )
+}
+
+/// The default number of points returned by the timeline endpoints.
+const DEFAULT_TIMELINE_POINTS: u32 = 1000;
+const MAX_TIMELINE_POINTS: u32 = 100_000;
+
+struct TimelineRange {
+    from: Timestamp,
+    to: Timestamp,
+    max_points: usize
+}
+
+fn get_timeline_range( req: &HttpRequest, data: &Data ) -> Result< TimelineRange > {
+    let params: protocol::RequestTimeline = query( req )?;
+    let start = data.initial_timestamp();
+    let end = data.last_timestamp();
+    let from = params.from.map( |from| start + from.to_timestamp( start, end ) ).unwrap_or( start );
+    let to = params.to.map( |to| start + to.to_timestamp( start, end ) ).unwrap_or( end );
+    let from = max( from, start );
+    let to = min( to, end );
+    if from > to {
+        return Err( ErrorBadRequest( "the beginning of the range is after its end" ) );
+    }
+
+    let max_points = max( min( params.max_points.unwrap_or( DEFAULT_TIMELINE_POINTS ), MAX_TIMELINE_POINTS ), 4 );
+    Ok( TimelineRange { from, to, max_points: max_points as usize } )
+}
+
+/// Returns the operations of only those allocations which match the filter, if there's any filter.
+fn get_filtered_operations( req: &HttpRequest, data: &Arc< Data > ) -> Result< Option< Vec< OperationId > > > {
+    let mut filter: protocol::AllocFilter = query( req )?;
+    let custom_filter: protocol::CustomFilter = query( req )?;
+
+    // The `from` and `to` only select the range of the timeline.
+    filter.from = None;
+    filter.to = None;
+
+    let no_filter: protocol::AllocFilter = serde_urlencoded::from_str( "" )?;
+    if filter == no_filter && custom_filter.custom_filter.as_ref().map( |code| code.is_empty() ).unwrap_or( true ) {
+        return Ok( None );
+    }
+
+    let filter = prepare_filter( data, &filter, &custom_filter, req.state() )?;
+    let ops = data.operation_ids().par_iter().flat_map( |op| {
+        let allocation = data.get_allocation( op.id() );
+        let is_matched = filter.try_match( data, op.id(), allocation );
+        if !op.is_reallocation() {
+            return if is_matched { Some( *op ) } else { None };
+        }
+
+        let old_id = allocation.reallocated_from.unwrap();
+        let old_allocation = data.get_allocation( old_id );
+        match (is_matched, filter.try_match( data, old_id, old_allocation )) {
+            (true, true) => Some( *op ),
+            (true, false) => Some( OperationId::new_allocation( op.id() ) ),
+            (false, true) if old_allocation.deallocation.is_some() => Some( OperationId::new_deallocation( old_id ) ),
+            _ => None
+        }
+    }).collect();
+
+    Ok( Some( ops ) )
+}
+
+fn build_timeline( data: &Data, ops: &[OperationId], range: &TimelineRange ) -> protocol::ResponseTimeline {
+    // The builder can add up to three extra points at the edges.
+    let resolution = max( range.max_points.saturating_sub( 3 ), 1 ) as u64;
+    let timeline = cli_core::build_timeline_with_resolution( data, range.from, range.to, ops, resolution );
+    timeline_response( timeline )
+}
+
+fn timeline_response( timeline: Vec< cli_core::TimelinePoint > ) -> protocol::ResponseTimeline {
+    let mut xs = Vec::with_capacity( timeline.len() );
+    let mut size_delta = Vec::with_capacity( timeline.len() );
+    let mut count_delta = Vec::with_capacity( timeline.len() );
+    let mut allocated_size = Vec::with_capacity( timeline.len() );
+    let mut allocated_count = Vec::with_capacity( timeline.len() );
+    let mut allocations = Vec::with_capacity( timeline.len() );
+    let mut deallocations = Vec::with_capacity( timeline.len() );
+    let mut allocated_slack = Vec::with_capacity( timeline.len() );
+
+    let mut last_size = 0;
+    let mut last_count = 0;
+    for point in timeline {
+        xs.push( point.timestamp / 1000 );
+        size_delta.push( point.memory_usage as i64 - last_size );
+        count_delta.push( point.allocations as i64 - last_count );
+        allocated_size.push( point.memory_usage );
+        allocated_count.push( point.allocations );
+        allocations.push( point.allocations_per_time as u32 );
+        deallocations.push( point.deallocations_per_time as u32 );
+        allocated_slack.push( point.slack );
+
+        last_size = point.memory_usage as i64;
+        last_count = point.allocations as i64;
+    }
+
+    protocol::ResponseTimeline {
+        xs,
+        size_delta,
+        count_delta,
+        allocated_size,
+        allocated_count,
+        allocations,
+        deallocations,
+        allocated_slack
+    }
+}
+
+fn handler_timeline( req: HttpRequest ) -> Result< HttpResponse > {
+    let data = &get_data( &req )?;
+    let range = get_timeline_range( &req, data )?;
+    let timeline = match get_filtered_operations( &req, data )? {
+        Some( ops ) => build_timeline( &data, &ops, &range ),
+        None => build_timeline( &data, data.operation_ids(), &range )
+    };
+
+    Ok( HttpResponse::Ok().json( timeline ) )
+}
+
+fn handler_timeline_leaked( req: HttpRequest ) -> Result< HttpResponse > {
+    let data = &get_data( &req )?;
+    let range = get_timeline_range( &req, data )?;
+    let ops = get_filtered_operations( &req, data )?;
+    let ops = ops.as_ref().map( |ops| ops.as_slice() ).unwrap_or( data.operation_ids() );
+    let ops: Vec< _ > = ops.par_iter().flat_map( |op| {
+        let allocation = data.get_allocation( op.id() );
+        if allocation.deallocation.is_some() {
+            None
+        } else {
+            Some( OperationId::new_allocation( op.id() ) )
+        }
+    }).collect();
+
+    let timeline = build_timeline( &data, &ops, &range );
+    Ok( HttpResponse::Ok().json( timeline ) )
+}
+
+fn prefiltered_allocation_ids< 'a >(
+    data: &'a Data,
+    sort_by: protocol::AllocSortBy,
+    _filter: &AllocationFilter
  ) -> &'a [AllocationId] {
     // TODO: Use the filter to narrow down the range.
     match sort_by {
@@ -735,14 +1529,69 @@ fn get_allocations< 'a >(
     }
 }
 
+fn write_allocations_table< W: fmt::Write >(
+    data: &Data,
+    backtrace_format: &protocol::BacktraceFormat,
+    params: protocol::RequestAllocations,
+    filter: AllocationFilter,
+    format: &TableFormat,
+    output: W
+) -> fmt::Result {
+    let remaining = params.count.unwrap_or( -1_i32 as _ ) as usize;
+    let skip = params.skip.unwrap_or( 0 ) as usize;
+    let sort_by = params.sort_by.unwrap_or( protocol::AllocSortBy::Timestamp );
+    let order = params.order.unwrap_or( protocol::Order::Asc );
+
+    let mut table = TableWriter::new( output, format.separator );
+    table.cells( &[
+        "id", "address", "size", "timestamp", "timestamp_relative", "thread",
+        "deallocation_timestamp", "deallocation_thread", "chain_lifetime", "chain_length",
+        "extra_space", "is_mmaped", "in_main_arena", "backtrace_id"
+    ])?;
+    table_frames_header( &mut table, format )?;
+    table.end_row()?;
+
+    let allocation_ids = prefiltered_allocation_ids( data, sort_by, &filter );
+    for (allocation_id, allocation) in allocations_iter( data, allocation_ids, order, filter ).skip( skip ).take( remaining ) {
+        let deallocation = allocation.deallocation.as_ref();
+        let chain = data.get_chain_by_any_allocation( allocation_id );
+        table.cell( allocation_id.raw() )?;
+        table.cell( format_args!( "0x{:016X}", allocation.pointer ) )?;
+        table.cell( allocation.size )?;
+        table.cell( protocol::Timeval::from( allocation.timestamp ) )?;
+        table.cell( protocol::Timeval::from( allocation.timestamp - data.initial_timestamp() ) )?;
+        table.cell( allocation.thread )?;
+        table.optional_cell( deallocation.map( |deallocation| protocol::Timeval::from( deallocation.timestamp ) ) )?;
+        table.optional_cell( deallocation.map( |deallocation| deallocation.thread ) )?;
+        table.optional_cell( chain.lifetime( data ).map( protocol::Timeval::from ) )?;
+        table.cell( chain.length )?;
+        table.cell( allocation.extra_usable_space )?;
+        table.cell( allocation.is_mmaped() )?;
+        table.cell( !allocation.in_non_main_arena() )?;
+        table.cell( allocation.backtrace.raw() )?;
+        table_frames( &mut table, format, data, backtrace_format, allocation.backtrace )?;
+        table.end_row()?;
+    }
+
+    Ok(())
+}
+
 fn handler_allocations( req: HttpRequest ) -> Result< HttpResponse > {
-    let data = get_data( &req )?;
+    let data = &get_data( &req )?;
     let params: protocol::RequestAllocations = query( &req )?;
     let filter: protocol::AllocFilter = query( &req )?;
     let custom_filter: protocol::CustomFilter = query( &req )?;
-    let filter = prepare_filter( data, &filter, &custom_filter )?;
+    let filter = prepare_filter( data, &filter, &custom_filter, req.state() )?;
     let backtrace_format: protocol::BacktraceFormat = query( &req )?;
 
+    if let Some( format ) = get_table_format( &req )? {
+        let body = async_data_handler( &req, move |data, tx| {
+            let _ = write_allocations_table( &data, &backtrace_format, params, filter, &format, tx );
+        })?;
+
+        return Ok( table_response( &format, body ) );
+    }
+
     let body = async_data_handler( &req, move |data, tx| {
         let response = get_allocations( &data, backtrace_format, params, filter );
         let _ = serde_json::to_writer( tx, &response );
@@ -762,7 +1611,9 @@ fn get_allocation_group_data< 'a, I >( data: &Data, iter: I ) -> protocol::Alloc
         min_timestamp: Timestamp,
         max_timestamp: Timestamp,
         leaked_count: u64,
-        allocated_count: u64
+        allocated_count: u64,
+        cross_thread_freed_count: u64,
+        slack: u64
     }
 
     impl Default for Group {
@@ -774,7 +1625,9 @@ fn get_allocation_group_data< 'a, I >( data: &Data, iter: I ) -> protocol::Alloc
                 min_timestamp: Timestamp::max(),
                 max_timestamp: Timestamp::min(),
                 leaked_count: 0,
-                allocated_count: 0
+                allocated_count: 0,
+                cross_thread_freed_count: 0,
+                slack: 0
             }
         }
     }
@@ -785,14 +1638,17 @@ fn get_allocation_group_data< 'a, I >( data: &Data, iter: I ) -> protocol::Alloc
             let size = allocation.size;
             let timestamp = allocation.timestamp;
             group.size_sum += size;
+            group.slack += allocation.extra_usable_space as u64;
             group.min_size = min( group.min_size, size );
             group.max_size = max( group.max_size, size );
             group.min_timestamp = min( group.min_timestamp, timestamp );
             group.max_timestamp = max( group.max_timestamp, timestamp );
 
             group.allocated_count += 1;
-            if allocation.deallocation.is_none() {
-                group.leaked_count += 1;
+            match allocation.deallocation {
+                None => group.leaked_count += 1,
+                Some( ref deallocation ) if deallocation.thread != allocation.thread => group.cross_thread_freed_count += 1,
+                Some( _ ) => {}
             }
 
             group
@@ -801,12 +1657,14 @@ fn get_allocation_group_data< 'a, I >( data: &Data, iter: I ) -> protocol::Alloc
         || Group::default(),
         |mut a, b| {
             a.size_sum += b.size_sum;
+            a.slack += b.slack;
             a.min_size = min( a.min_size, b.min_size );
             a.max_size = max( a.max_size, b.max_size );
             a.min_timestamp = min( a.min_timestamp, b.min_timestamp );
             a.max_timestamp = max( a.max_timestamp, b.max_timestamp );
             a.allocated_count += b.allocated_count;
             a.leaked_count += b.leaked_count;
+            a.cross_thread_freed_count += b.cross_thread_freed_count;
 
             a
         }
@@ -815,6 +1673,9 @@ fn get_allocation_group_data< 'a, I >( data: &Data, iter: I ) -> protocol::Alloc
     protocol::AllocationGroupData {
         leaked_count: group.leaked_count,
         allocated_count: group.allocated_count,
+        cross_thread_freed_count: group.cross_thread_freed_count,
+        cross_thread_ratio: cross_thread_ratio( group.cross_thread_freed_count, group.allocated_count - group.leaked_count ),
+        slack: group.slack,
         size: group.size_sum,
         min_size: group.min_size,
         max_size: group.max_size,
@@ -833,6 +1694,14 @@ fn get_allocation_group_data< 'a, I >( data: &Data, iter: I ) -> protocol::Alloc
     }
 }
 
+fn cross_thread_ratio( cross_thread_freed_count: u64, freed_count: u64 ) -> f32 {
+    if freed_count == 0 {
+        0.0
+    } else {
+        cross_thread_freed_count as f32 / freed_count as f32
+    }
+}
+
 fn get_global_group_data( data: &Data, backtrace_id: BacktraceId ) -> protocol::AllocationGroupData {
     let stats = data.get_group_statistics( backtrace_id );
 
@@ -847,6 +1716,9 @@ fn get_global_group_data( data: &Data, backtrace_id: BacktraceId ) -> protocol::
     protocol::AllocationGroupData {
         leaked_count,
         allocated_count,
+        cross_thread_freed_count: stats.cross_thread_free_count,
+        cross_thread_ratio: cross_thread_ratio( stats.cross_thread_free_count, stats.free_count ),
+        slack: stats.alloc_slack,
         size: size_sum,
         min_size,
         max_size,
@@ -956,23 +1828,83 @@ fn get_allocation_groups< 'a >(
     response
 }
 
-fn handler_allocation_groups( req: HttpRequest ) -> Result< HttpResponse > {
-    let data = get_data( &req )?;
-    let filter_params: protocol::AllocFilter = query( &req )?;
-    let custom_filter: protocol::CustomFilter = query( &req )?;
-    let filter = prepare_filter( data, &filter_params, &custom_filter )?;
-    let backtrace_format: protocol::BacktraceFormat = query( &req )?;
-    let params: protocol::RequestAllocationGroups = query( &req )?;
+fn write_allocation_groups_table< W: fmt::Write >(
+    data: &Data,
+    backtrace_format: &protocol::BacktraceFormat,
+    params: protocol::RequestAllocationGroups,
+    allocation_groups: &AllocationGroups,
+    format: &TableFormat,
+    output: W
+) -> fmt::Result {
+    let remaining = params.count.unwrap_or( -1_i32 as _ ) as usize;
+    let skip = params.skip.unwrap_or( 0 ) as usize;
+
+    let mut table = TableWriter::new( output, format.separator );
+    table.cells( &[
+        "backtrace_id", "allocated_count", "leaked_count", "size", "min_size", "max_size", "slack",
+        "min_timestamp", "max_timestamp", "interval", "cross_thread_ratio",
+        "global_allocated_count", "global_leaked_count", "global_size"
+    ])?;
+    table_frames_header( &mut table, format )?;
+    table.end_row()?;
+
+    for index in (0..allocation_groups.allocations_by_backtrace.len()).skip( skip ).take( remaining ) {
+        let (&backtrace_id, matched_allocation_ids) = allocation_groups.allocations_by_backtrace.get( index );
+        let all = get_global_group_data( data, backtrace_id );
+        let only_matched = get_allocation_group_data( data, matched_allocation_ids.into_par_iter().map( |&allocation_id| data.get_allocation( allocation_id ) ) );
+        table.cell( backtrace_id.raw() )?;
+        table.cell( only_matched.allocated_count )?;
+        table.cell( only_matched.leaked_count )?;
+        table.cell( only_matched.size )?;
+        table.cell( only_matched.min_size )?;
+        table.cell( only_matched.max_size )?;
+        table.cell( only_matched.slack )?;
+        table.cell( only_matched.min_timestamp )?;
+        table.cell( only_matched.max_timestamp )?;
+        table.cell( only_matched.interval )?;
+        table.cell( only_matched.cross_thread_ratio )?;
+        table.cell( all.allocated_count )?;
+        table.cell( all.leaked_count )?;
+        table.cell( all.size )?;
+        table_frames( &mut table, format, data, backtrace_format, backtrace_id )?;
+        table.end_row()?;
+    }
+
+    Ok(())
+}
+
+fn query_allocation_groups( req: &HttpRequest ) -> Result< Query > {
+    let data_id = get_data_id( req )?;
+    let filter_params: protocol::AllocFilter = query( req )?;
+    let custom_filter: protocol::CustomFilter = query( req )?;
+    let backtrace_format: protocol::BacktraceFormat = query( req )?;
+    let params: protocol::RequestAllocationGroups = query( req )?;
+    let table_format = get_table_format( req )?;
 
     let key = AllocationGroupsKey {
-        data_id: data.id(),
-        filter: filter_params,
-        custom_filter,
+        data_id,
+        filter: filter_params.clone(),
+        custom_filter: custom_filter.clone(),
         sort_by: params.sort_by.unwrap_or( protocol::AllocGroupsSortBy::MinTimestamp ),
         order: params.order.unwrap_or( protocol::Order::Asc )
     };
 
-    let groups = req.state().allocation_group_cache.lock().get( &key ).cloned();
+    let state = req.state().clone();
+    let mime = table_format.map( |format| format.mime() ).unwrap_or( "application/json" );
+    Ok( Query::new( mime, filter_params, custom_filter, move |data, filter, progress, output| {
+        let allocation_groups = get_or_build_allocation_groups( &state, data, key, filter, progress );
+        if let Some( format ) = table_format {
+            return write_allocation_groups_table( data, &backtrace_format, params, &allocation_groups, &format, output )
+                .map_err( |_| io::Error::new( io::ErrorKind::Other, "write failed" ) );
+        }
+
+        let response = get_allocation_groups( &state, data, backtrace_format, params, allocation_groups );
+        serde_json::to_writer( output, &response ).map_err( io::Error::from )
+    }))
+}
+
+fn get_or_build_allocation_groups( state: &State, data: &Data, key: AllocationGroupsKey, filter: AllocationFilter, progress: &JobProgress ) -> Arc< AllocationGroups > {
+    let groups = state.allocation_group_cache.lock().get( &key ).cloned();
 
     fn sort_by< T, F >( data: &Data, groups: &mut AllocationGroups, order: protocol::Order, is_global: bool, callback: F )
         where F: Fn( &protocol::AllocationGroupData ) -> T + Send + Sync,
@@ -1005,14 +1937,19 @@ fn handler_allocation_groups( req: HttpRequest ) -> Result< HttpResponse > {
         }
     }
 
-    let allocation_groups;
     if let Some( groups ) = groups {
-        allocation_groups = groups;
-    } else {
-        let iter = prefiltered_allocation_ids( data, Default::default(), &filter )
+        return groups;
+    }
+
+    {
+        let allocation_ids = prefiltered_allocation_ids( data, Default::default(), &filter );
+        progress.set_total( allocation_ids.len() as u64 );
+
+        let matches = track_progress( data, &filter, progress );
+        let iter = allocation_ids
             .par_iter()
             .map( |&allocation_id| (allocation_id, data.get_allocation( allocation_id )) )
-            .filter( move |(id, allocation)| filter.try_match( data, *id, allocation ) );
+            .filter( move |(id, allocation)| matches( *id, allocation ) );
 
         let mut groups = AllocationGroups::new( iter );
         match key.sort_by {
@@ -1034,6 +1971,9 @@ fn handler_allocation_groups( req: HttpRequest ) -> Result< HttpResponse > {
             protocol::AllocGroupsSortBy::Size => {
                 sort_by( data, &mut groups, key.order, false, |group_data| group_data.size );
             },
+            protocol::AllocGroupsSortBy::Slack => {
+                sort_by( data, &mut groups, key.order, false, |group_data| group_data.slack );
+            },
             protocol::AllocGroupsSortBy::GlobalMinTimestamp => {
                 sort_by( data, &mut groups, key.order, true, |group_data| group_data.min_timestamp.clone() );
             },
@@ -1052,26 +1992,29 @@ fn handler_allocation_groups( req: HttpRequest ) -> Result< HttpResponse > {
             protocol::AllocGroupsSortBy::GlobalSize => {
                 sort_by( data, &mut groups, key.order, true, |group_data| group_data.size );
             },
+            protocol::AllocGroupsSortBy::GlobalSlack => {
+                sort_by( data, &mut groups, key.order, true, |group_data| group_data.slack );
+            },
             protocol::AllocGroupsSortBy::GlobalMaxTotalUsageFirstSeenAt => {
                 sort_by( data, &mut groups, key.order, true, |group_data| group_data.max_total_usage_first_seen_at.clone() );
             }
         }
 
-        allocation_groups = Arc::new( groups );
-        req.state().allocation_group_cache.lock().put( key, allocation_groups.clone() );
-    }
+        let allocation_groups = Arc::new( groups );
+        if !progress.is_cancelled() {
+            state.allocation_group_cache.lock().put( key, allocation_groups.clone() );
+        }
 
-    let state = req.state().clone();
-    let body = async_data_handler( &req, move |data, tx| {
-        let response = get_allocation_groups( &state, &data, backtrace_format, params, allocation_groups );
-        let _ = serde_json::to_writer( tx, &response );
-    })?;
+        allocation_groups
+    }
+}
 
-    Ok( HttpResponse::Ok().content_type( "application/json" ).body( body ) )
+fn handler_allocation_groups( req: HttpRequest ) -> Result< HttpResponse > {
+    respond_with_query( &req, query_allocation_groups( &req )? )
 }
 
 fn handler_raw_allocations( req: HttpRequest ) -> Result< HttpResponse > {
-    let data = get_data( &req )?;
+    let data = &get_data( &req )?;
     let iter = data.alloc_sorted_by_timestamp( None, None ).iter().map( |&id| data.get_allocation( id ) );
 
     let mut output = String::new();
@@ -1138,10 +2081,10 @@ fn dump_node< T: fmt::Write, K: PartialEq + Clone, V, F: Fn( &mut T, &V ) -> fmt
 }
 
 fn handler_tree( req: HttpRequest ) -> Result< HttpResponse > {
-    let data = get_data( &req )?;
+    let data = &get_data( &req )?;
     let filter: protocol::AllocFilter = query( &req )?;
     let custom_filter: protocol::CustomFilter = query( &req )?;
-    let filter = prepare_filter( data, &filter, &custom_filter )?;
+    let filter = prepare_filter( data, &filter, &custom_filter, req.state() )?;
     let backtrace_format: protocol::BacktraceFormat = query( &req )?;
 
     let body = async_data_handler( &req, move |data, mut tx| {
@@ -1163,9 +2106,108 @@ fn handler_tree( req: HttpRequest ) -> Result< HttpResponse > {
     Ok( HttpResponse::Ok().content_type( "application/json" ).body( body ) )
 }
 
+fn write_mmaps_table< W: fmt::Write >(
+    data: &Data,
+    backtrace_format: &protocol::BacktraceFormat,
+    filter: &protocol::MmapFilter,
+    format: &TableFormat,
+    output: W
+) -> fmt::Result {
+    let mut table = TableWriter::new( output, format.separator );
+    table.cells( &[
+        "kind", "timestamp", "pointer", "length", "requested_address", "protection", "flags",
+        "file_descriptor", "offset", "thread", "backtrace_id"
+    ])?;
+    table_frames_header( &mut table, format )?;
+    table.end_row()?;
+
+    for op in data.mmap_operations() {
+        let length = match *op {
+            MmapOperation::Mmap( MemoryMap { length, .. } ) => length,
+            MmapOperation::Munmap( MemoryUnmap { length, .. } ) => length
+        };
+
+        if filter.size_min.map( |min| length < min ).unwrap_or( false ) || filter.size_max.map( |max| length > max ).unwrap_or( false ) {
+            continue;
+        }
+
+        let backtrace_id = match *op {
+            MmapOperation::Mmap( MemoryMap {
+                timestamp,
+                pointer,
+                length,
+                backtrace,
+                requested_address,
+                mmap_protection,
+                mmap_flags,
+                file_descriptor,
+                thread,
+                offset
+            }) => {
+                let protection: String = [
+                    (mmap_protection.is_readable(), 'r'),
+                    (mmap_protection.is_writable(), 'w'),
+                    (mmap_protection.is_executable(), 'x')
+                ].iter().map( |&(is_set, ch)| if is_set { ch } else { '-' } ).collect();
+
+                let flags: Vec< _ > = [
+                    (mmap_flags.is_shared(), "shared"),
+                    (mmap_flags.is_private(), "private"),
+                    (mmap_flags.is_fixed(), "fixed"),
+                    (mmap_flags.is_anonymous(), "anonymous"),
+                    (mmap_flags.is_uninitialized(), "uninitialized")
+                ].iter().filter( |&&(is_set, _)| is_set ).map( |&(_, name)| name ).collect();
+
+                table.cell( "mmap" )?;
+                table.cell( protocol::Timeval::from( timestamp ) )?;
+                table.cell( format_args!( "0x{:016X}", pointer ) )?;
+                table.cell( length )?;
+                table.cell( format_args!( "0x{:016X}", requested_address ) )?;
+                table.cell( protection )?;
+                table.cell( flags.join( "|" ) )?;
+                table.cell( file_descriptor as i32 )?;
+                table.cell( offset )?;
+                table.cell( thread )?;
+                backtrace
+            },
+            MmapOperation::Munmap( MemoryUnmap {
+                timestamp,
+                pointer,
+                length,
+                backtrace,
+                thread
+            }) => {
+                table.cell( "munmap" )?;
+                table.cell( protocol::Timeval::from( timestamp ) )?;
+                table.cell( format_args!( "0x{:016X}", pointer ) )?;
+                table.cell( length )?;
+                for _ in 0..5 {
+                    table.empty_cell()?;
+                }
+                table.cell( thread )?;
+                backtrace
+            }
+        };
+
+        table.cell( backtrace_id.raw() )?;
+        table_frames( &mut table, format, data, backtrace_format, backtrace_id )?;
+        table.end_row()?;
+    }
+
+    Ok(())
+}
+
 fn handler_mmaps( req: HttpRequest ) -> Result< HttpResponse > {
     let backtrace_format: protocol::BacktraceFormat = query( &req )?;
     let filter: protocol::MmapFilter = query( &req )?;
+    if let Some( format ) = get_table_format( &req )? {
+        let body = async_data_handler( &req, move |data, tx| {
+            let _ = write_mmaps_table( &data, &backtrace_format, &filter, &format, tx );
+        })?;
+
+        return Ok( table_response( &format, body ) );
+    }
+
     let body = async_data_handler( &req, move |data, tx| {
         let factory = || {
             data.mmap_operations().iter().flat_map( |op| {
@@ -1261,7 +2303,7 @@ fn handler_mmaps( req: HttpRequest ) -> Result< HttpResponse > {
 }
 
 fn handler_backtrace( req: HttpRequest ) -> Result< HttpResponse > {
-    let data = get_data( &req )?;
+    let data = &get_data( &req )?;
     let backtrace_id: u32 = req.match_info().get( "backtrace_id" ).unwrap().parse().unwrap();
     let backtrace_id = BacktraceId::new( backtrace_id );
     let backtrace = data.get_backtrace( backtrace_id );
@@ -1279,10 +2321,46 @@ fn handler_backtrace( req: HttpRequest ) -> Result< HttpResponse > {
     Ok( HttpResponse::Ok().json( response ) )
 }
 
+fn write_backtraces_table< W: fmt::Write >(
+    data: &Data,
+    backtrace_format: &protocol::BacktraceFormat,
+    filter: &crate::filter::BacktraceFilter,
+    format: &TableFormat,
+    output: W
+) -> fmt::Result {
+    let mut positive_cache = HashMap::new();
+    let mut negative_cache = HashMap::new();
+    let mut table = TableWriter::new( output, format.separator );
+    table.cells( &["backtrace_id", "depth"] )?;
+    table_frames_header( &mut table, format )?;
+    table.end_row()?;
+
+    for (backtrace_id, backtrace) in data.all_backtraces() {
+        if !crate::filter::match_backtrace( data, &mut positive_cache, &mut negative_cache, filter, backtrace.clone() ) {
+            continue;
+        }
+
+        table.cell( backtrace_id.raw() )?;
+        table.cell( backtrace.len() )?;
+        table_frames( &mut table, format, data, backtrace_format, backtrace_id )?;
+        table.end_row()?;
+    }
+
+    Ok(())
+}
+
 fn handler_backtraces( req: HttpRequest ) -> Result< HttpResponse > {
     let backtrace_format: protocol::BacktraceFormat = query( &req )?;
     let filter: protocol::BacktraceFilter = query( &req )?;
     let filter = crate::filter::prepare_backtrace_filter( &filter )?;
+    if let Some( format ) = get_table_format( &req )? {
+        let body = async_data_handler( &req, move |data, tx| {
+            let _ = write_backtraces_table( &data, &backtrace_format, &filter, &format, tx );
+        })?;
+
+        return Ok( table_response( &format, body ) );
+    }
+
     let body = async_data_handler( &req, move |data, tx| {
         let mut positive_cache = HashMap::new();
         let mut negative_cache = HashMap::new();
@@ -1370,10 +2448,10 @@ fn generate_regions< 'a, F: Fn( AllocationId, &Allocation ) -> bool + Clone + 'a
 }
 
 fn handler_regions( req: HttpRequest ) -> Result< HttpResponse > {
-    let data = get_data( &req )?;
+    let data = &get_data( &req )?;
     let filter: protocol::AllocFilter = query( &req )?;
     let custom_filter: protocol::CustomFilter = query( &req )?;
-    let filter = prepare_filter( data, &filter, &custom_filter )?;
+    let filter = prepare_filter( data, &filter, &custom_filter, req.state() )?;
 
     let body = async_data_handler( &req, move |data, tx| {
         let response = generate_regions( &data, |id, allocation| filter.try_match( &data, id, allocation ) );
@@ -1383,9 +2461,57 @@ fn handler_regions( req: HttpRequest ) -> Result< HttpResponse > {
     Ok( HttpResponse::Ok().content_type( "application/json" ).body( body ) )
 }
 
+fn mallopt_param_name( kind: &MalloptKind ) -> Option< &'static str > {
+    match *kind {
+        MalloptKind::TrimThreshold  => Some( "M_TRIM_THRESHOLD" ),
+        MalloptKind::TopPad         => Some( "M_TOP_PAD" ),
+        MalloptKind::MmapThreshold  => Some( "M_MMAP_THRESHOLD" ),
+        MalloptKind::MmapMax        => Some( "M_MMAP_MAX" ),
+        MalloptKind::CheckAction    => Some( "M_CHECK_ACTION" ),
+        MalloptKind::Perturb        => Some( "M_PERTURB" ),
+        MalloptKind::ArenaTest      => Some( "M_ARENA_TEXT" ),
+        MalloptKind::ArenaMax       => Some( "M_ARENA_MAX" ),
+        MalloptKind::Other( _ )     => None
+    }
+}
+
+fn write_mallopts_table< W: fmt::Write >(
+    data: &Data,
+    backtrace_format: &protocol::BacktraceFormat,
+    format: &TableFormat,
+    output: W
+) -> fmt::Result {
+    let mut table = TableWriter::new( output, format.separator );
+    table.cells( &["timestamp", "thread", "param", "raw_param", "value", "result", "backtrace_id"] )?;
+    table_frames_header( &mut table, format )?;
+    table.end_row()?;
+
+    for mallopt in data.mallopts() {
+        table.cell( protocol::Timeval::from( mallopt.timestamp ) )?;
+        table.cell( mallopt.thread )?;
+        table.optional_cell( mallopt_param_name( &mallopt.kind ) )?;
+        table.cell( mallopt.kind.raw() )?;
+        table.cell( mallopt.value )?;
+        table.cell( mallopt.result )?;
+        table.cell( mallopt.backtrace.raw() )?;
+        table_frames( &mut table, format, data, backtrace_format, mallopt.backtrace )?;
+        table.end_row()?;
+    }
+
+    Ok(())
+}
+
 fn handler_mallopts( req: HttpRequest ) -> Result< HttpResponse > {
-    let data = get_data( &req )?;
+    let data = &get_data( &req )?;
     let backtrace_format: protocol::BacktraceFormat = query( &req )?;
+    if let Some( format ) = get_table_format( &req )? {
+        let body = async_data_handler( &req, move |data, tx| {
+            let _ = write_mallopts_table( &data, &backtrace_format, &format, tx );
+        })?;
+
+        return Ok( table_response( &format, body ) );
+    }
+
 
     let response: Vec< _ > = data.mallopts().iter().map( |mallopt| {
         let mut backtrace = Vec::new();
@@ -1399,17 +2525,7 @@ fn handler_mallopts( req: HttpRequest ) -> Result< HttpResponse > {
             backtrace_id: mallopt.backtrace.raw(),
             backtrace,
             raw_param: mallopt.kind.raw(),
-            param: match mallopt.kind {
-                MalloptKind::TrimThreshold  => Some( "M_TRIM_THRESHOLD" ),
-                MalloptKind::TopPad         => Some( "M_TOP_PAD" ),
-                MalloptKind::MmapThreshold  => Some( "M_MMAP_THRESHOLD" ),
-                MalloptKind::MmapMax        => Some( "M_MMAP_MAX" ),
-                MalloptKind::CheckAction    => Some( "M_CHECK_ACTION" ),
-                MalloptKind::Perturb        => Some( "M_PERTURB" ),
-                MalloptKind::ArenaTest      => Some( "M_ARENA_TEXT" ),
-                MalloptKind::ArenaMax       => Some( "M_ARENA_MAX" ),
-                MalloptKind::Other( _ )     => None
-            }.map( |value| value.into() ),
+            param: mallopt_param_name( &mallopt.kind ).map( |value| value.into() ),
             value: mallopt.value,
             result: mallopt.result
         }
@@ -1418,63 +2534,274 @@ fn handler_mallopts( req: HttpRequest ) -> Result< HttpResponse > {
     Ok( HttpResponse::Ok().json( response ) )
 }
 
+fn query_export_flamegraph_pl( req: &HttpRequest ) -> Result< Query > {
+    let filter: protocol::AllocFilter = query( req )?;
+    let custom_filter: protocol::CustomFilter = query( req )?;
+    Ok( Query::new( "application/octet-stream", filter, custom_filter, |data, filter, progress, output| {
+        progress.set_total( data.alloc_sorted_by_timestamp( None, None ).len() as u64 );
+        export_as_flamegraph_pl( data, output, track_progress( data, &filter, progress ) ).map_err( |_| io::Error::new( io::ErrorKind::Other, "write failed" ) )
+    }))
+}
+
 fn handler_export_flamegraph_pl( req: HttpRequest ) -> Result< HttpResponse > {
-    let data = get_data( &req )?;
+    respond_with_query( &req, query_export_flamegraph_pl( &req )? )
+}
+
+fn query_export_flamegraph( req: &HttpRequest ) -> Result< Query > {
+    let filter: protocol::AllocFilter = query( req )?;
+    let custom_filter: protocol::CustomFilter = query( req )?;
+    Ok( Query::new( "image/svg+xml", filter, custom_filter, |data, filter, progress, output| {
+        progress.set_total( data.alloc_sorted_by_timestamp( None, None ).len() as u64 );
+        export_as_flamegraph( data, output, track_progress( data, &filter, progress ) );
+        Ok(())
+    }))
+}
+
+fn handler_export_flamegraph( req: HttpRequest ) -> Result< HttpResponse > {
+    respond_with_query( &req, query_export_flamegraph( &req )? )
+}
+
+fn handler_churn( req: HttpRequest ) -> Result< HttpResponse > {
+    let data = &get_data( &req )?;
     let filter: protocol::AllocFilter = query( &req )?;
     let custom_filter: protocol::CustomFilter = query( &req )?;
-    let filter = prepare_filter( data, &filter, &custom_filter )?;
+    let filter = prepare_filter( data, &filter, &custom_filter, req.state() )?;
+    let backtrace_format: protocol::BacktraceFormat = query( &req )?;
+    let params: protocol::RequestChurn = query( &req )?;
 
     let body = async_data_handler( &req, move |data, tx| {
-        let _ = export_as_flamegraph_pl( &data, tx, |id, allocation| filter.try_match( &data, id, allocation ) );
+        let sort_by = match params.sort_by.unwrap_or( protocol::ChurnSortBy::AllocationRate ) {
+            protocol::ChurnSortBy::AllocationRate => cli_core::ChurnSortBy::AllocationRate,
+            protocol::ChurnSortBy::ShortLivedCount => cli_core::ChurnSortBy::ShortLivedCount
+        };
+
+        let threshold = params.short_lived_threshold.map( |interval| interval.0 ).unwrap_or( Timestamp::from_msecs( 100 ) );
+        let iter = data.allocations_with_id().filter( |(id, allocation)| filter.try_match( &data, *id, allocation ) );
+        let groups = cli_core::collect_churn_groups( iter, threshold, sort_by );
+        let total_count = groups.len() as u64;
+        let groups = groups.into_iter()
+            .skip( params.skip.unwrap_or( 0 ) as usize )
+            .take( params.count.unwrap_or( -1_i32 as _ ) as usize )
+            .map( |group| {
+                protocol::ChurnGroup {
+                    backtrace_id: group.backtrace.raw(),
+                    backtrace: data.get_backtrace( group.backtrace ).map( |(_, frame)| get_frame( &data, &backtrace_format, frame ) ).collect(),
+                    allocated_count: group.allocated_count,
+                    allocated_size: group.allocated_size,
+                    short_lived_count: group.short_lived_count,
+                    short_lived_size: group.short_lived_size,
+                    allocations_per_second: group.allocations_per_second(),
+                    short_lived_per_second: group.short_lived_per_second(),
+                    first_allocation: group.first_allocation.into(),
+                    last_allocation: group.last_allocation.into(),
+                    lifetime_histogram: group.lifetime_histogram.to_vec()
+                }
+            })
+            .collect();
+
+        let response = protocol::ResponseChurn {
+            lifetime_buckets: cli_core::LIFETIME_BUCKETS,
+            groups,
+            total_count
+        };
+
+        let _ = serde_json::to_writer( tx, &response );
     })?;
 
-    Ok( HttpResponse::Ok().content_type( "application/octet-stream" ).body( body ) )
+    Ok( HttpResponse::Ok().content_type( "application/json" ).body( body ) )
 }
 
-fn handler_export_flamegraph( req: HttpRequest ) -> Result< HttpResponse > {
-    let data = get_data( &req )?;
+fn handler_slack( req: HttpRequest ) -> Result< HttpResponse > {
+    let data = &get_data( &req )?;
     let filter: protocol::AllocFilter = query( &req )?;
     let custom_filter: protocol::CustomFilter = query( &req )?;
-    let filter = prepare_filter( data, &filter, &custom_filter )?;
+    let filter = prepare_filter( data, &filter, &custom_filter, req.state() )?;
+    let params: protocol::RequestSlack = query( &req )?;
 
     let body = async_data_handler( &req, move |data, tx| {
-        let _ = export_as_flamegraph( &data, tx, |id, allocation| filter.try_match( &data, id, allocation ) );
+        let iter = data.allocations_with_id().filter( |(id, allocation)| filter.try_match( &data, *id, allocation ) );
+        let size_classes = cli_core::collect_slack_by_size_class( iter );
+        let total_count = size_classes.len() as u64;
+        let requested_size: u64 = size_classes.iter().map( |size_class| size_class.requested_size ).sum();
+        let slack: u64 = size_classes.iter().map( |size_class| size_class.slack ).sum();
+        let size_classes = size_classes.into_iter()
+            .skip( params.skip.unwrap_or( 0 ) as usize )
+            .take( params.count.unwrap_or( -1_i32 as _ ) as usize )
+            .map( |size_class| {
+                protocol::SizeClassSlack {
+                    usable_size: size_class.usable_size,
+                    count: size_class.count,
+                    requested_size: size_class.requested_size,
+                    min_requested_size: size_class.min_requested_size,
+                    max_requested_size: size_class.max_requested_size,
+                    slack: size_class.slack
+                }
+            })
+            .collect();
+
+        let response = protocol::ResponseSlack {
+            requested_size,
+            usable_size: requested_size + slack,
+            slack,
+            size_classes,
+            total_count
+        };
+
+        let _ = serde_json::to_writer( tx, &response );
     })?;
 
-    Ok( HttpResponse::Ok().content_type( "image/svg+xml" ).body( body ) )
+    Ok( HttpResponse::Ok().content_type( "application/json" ).body( body ) )
 }
 
-fn handler_export_replay( req: HttpRequest ) -> Result< HttpResponse > {
-    let data = get_data( &req )?;
+fn handler_thread_matrix( req: HttpRequest ) -> Result< HttpResponse > {
+    let data = &get_data( &req )?;
     let filter: protocol::AllocFilter = query( &req )?;
     let custom_filter: protocol::CustomFilter = query( &req )?;
-    let filter = prepare_filter( data, &filter, &custom_filter )?;
+    let filter = prepare_filter( data, &filter, &custom_filter, req.state() )?;
+    let params: protocol::RequestThreadMatrix = query( &req )?;
 
     let body = async_data_handler( &req, move |data, tx| {
-        let _ = export_as_replay( &data, tx, |id, allocation| filter.try_match( &data, id, allocation ) );
+        let iter = data.allocations_with_id().filter( |(id, allocation)| filter.try_match( &data, *id, allocation ) );
+        let pairs = cli_core::collect_thread_matrix( iter );
+
+        let mut cross_thread_count = 0;
+        let mut cross_thread_size = 0;
+        let mut same_thread_count = 0;
+        let mut same_thread_size = 0;
+        for pair in &pairs {
+            if pair.allocated_on != pair.freed_on {
+                cross_thread_count += pair.count;
+                cross_thread_size += pair.size;
+            } else {
+                same_thread_count += pair.count;
+                same_thread_size += pair.size;
+            }
+        }
+
+        let only_cross_thread = params.only_cross_thread.unwrap_or( false );
+        let pairs: Vec< _ > = pairs.into_iter()
+            .filter( |pair| !only_cross_thread || pair.allocated_on != pair.freed_on )
+            .collect();
+
+        let total_count = pairs.len() as u64;
+        let pairs = pairs.into_iter()
+            .skip( params.skip.unwrap_or( 0 ) as usize )
+            .take( params.count.unwrap_or( -1_i32 as _ ) as usize )
+            .map( |pair| {
+                protocol::ThreadPair {
+                    allocated_on: pair.allocated_on,
+                    freed_on: pair.freed_on,
+                    count: pair.count,
+                    size: pair.size
+                }
+            })
+            .collect();
+
+        let response = protocol::ResponseThreadMatrix {
+            cross_thread_count,
+            cross_thread_size,
+            same_thread_count,
+            same_thread_size,
+            pairs,
+            total_count
+        };
+
+        let _ = serde_json::to_writer( tx, &response );
     })?;
 
-    Ok( HttpResponse::Ok().content_type( "application/octet-stream" ).body( body ) )
+    Ok( HttpResponse::Ok().content_type( "application/json" ).body( body ) )
 }
 
-fn handler_export_heaptrack( req: HttpRequest ) -> Result< HttpResponse > {
-    let data = get_data( &req )?;
+fn handler_growth_groups( req: HttpRequest ) -> Result< HttpResponse > {
+    let data = &get_data( &req )?;
     let filter: protocol::AllocFilter = query( &req )?;
     let custom_filter: protocol::CustomFilter = query( &req )?;
-    let filter = prepare_filter( data, &filter, &custom_filter )?;
+    let filter = prepare_filter( data, &filter, &custom_filter, req.state() )?;
+    let backtrace_format: protocol::BacktraceFormat = query( &req )?;
+    let params: protocol::RequestGrowthGroups = query( &req )?;
 
     let body = async_data_handler( &req, move |data, tx| {
-        let _ = export_as_heaptrack( &data, tx, |id, allocation| filter.try_match( &data, id, allocation ) );
+        let ids = data.allocations_with_id()
+            .filter( |(id, allocation)| filter.try_match( &data, *id, allocation ) )
+            .map( |(id, _)| id );
+
+        let groups = cli_core::collect_growth_groups( &data, ids );
+        let total_count = groups.len() as u64;
+        let groups = groups.into_iter()
+            .skip( params.skip.unwrap_or( 0 ) as usize )
+            .take( params.count.unwrap_or( -1_i32 as _ ) as usize )
+            .map( |group| {
+                protocol::GrowthGroup {
+                    backtrace_id: group.backtrace.raw(),
+                    backtrace: data.get_backtrace( group.backtrace ).map( |(_, frame)| get_frame( &data, &backtrace_format, frame ) ).collect(),
+                    chain_count: group.chains.len() as u64,
+                    avoidable_reallocations: group.avoidable_reallocations,
+                    copied_bytes: group.copied_bytes,
+                    average_growth_factor: group.average_growth_factor,
+                    min_final_size: group.min_final_size,
+                    max_final_size: group.max_final_size,
+                    suggested_capacity: group.suggested_capacity
+                }
+            })
+            .collect();
+
+        let response = protocol::ResponseGrowthGroups {
+            groups,
+            total_count
+        };
+
+        let _ = serde_json::to_writer( tx, &response );
     })?;
 
-    Ok( HttpResponse::Ok().content_type( "application/octet-stream" ).body( body ) )
+    Ok( HttpResponse::Ok().content_type( "application/json" ).body( body ) )
 }
 
-fn handler_allocation_ascii_tree( req: HttpRequest ) -> Result< HttpResponse > {
-    let data = get_data( &req )?;
-    let filter: protocol::AllocFilter = query( &req )?;
-    let custom_filter: protocol::CustomFilter = query( &req )?;
-    let filter = prepare_filter( &data, &filter, &custom_filter )?;
+fn query_churn_flamegraph( req: &HttpRequest ) -> Result< Query > {
+    let mut filter: protocol::AllocFilter = query( req )?;
+    let custom_filter: protocol::CustomFilter = query( req )?;
+    filter.lifetime = Some( protocol::LifetimeFilter::OnlyTemporary );
+    Ok( Query::new( "image/svg+xml", filter, custom_filter, |data, filter, progress, output| {
+        progress.set_total( data.alloc_sorted_by_timestamp( None, None ).len() as u64 );
+        export_as_flamegraph_by_count( data, output, track_progress( data, &filter, progress ) );
+        Ok(())
+    }))
+}
+
+fn handler_churn_flamegraph( req: HttpRequest ) -> Result< HttpResponse > {
+    respond_with_query( &req, query_churn_flamegraph( &req )? )
+}
+
+fn query_export_replay( req: &HttpRequest ) -> Result< Query > {
+    let filter: protocol::AllocFilter = query( req )?;
+    let custom_filter: protocol::CustomFilter = query( req )?;
+    Ok( Query::new( "application/octet-stream", filter, custom_filter, |data, filter, progress, output| {
+        progress.set_total( data.alloc_sorted_by_timestamp( None, None ).len() as u64 );
+        export_as_replay( data, output, track_progress( data, &filter, progress ) )
+    }))
+}
+
+fn handler_export_replay( req: HttpRequest ) -> Result< HttpResponse > {
+    respond_with_query( &req, query_export_replay( &req )? )
+}
+
+fn query_export_heaptrack( req: &HttpRequest ) -> Result< Query > {
+    let filter: protocol::AllocFilter = query( req )?;
+    let custom_filter: protocol::CustomFilter = query( req )?;
+    Ok( Query::new( "application/octet-stream", filter, custom_filter, |data, filter, progress, output| {
+        progress.set_total( data.alloc_sorted_by_timestamp( None, None ).len() as u64 );
+        export_as_heaptrack( data, output, track_progress( data, &filter, progress ) )
+    }))
+}
+
+fn handler_export_heaptrack( req: HttpRequest ) -> Result< HttpResponse > {
+    respond_with_query( &req, query_export_heaptrack( &req )? )
+}
+
+fn handler_allocation_ascii_tree( req: HttpRequest ) -> Result< HttpResponse > {
+    let data = &get_data( &req )?;
+    let filter: protocol::AllocFilter = query( &req )?;
+    let custom_filter: protocol::CustomFilter = query( &req )?;
+    let filter = prepare_filter( &data, &filter, &custom_filter, req.state() )?;
 
     let body = async_data_handler( &req, move |data, mut tx| {
         let tree = data.tree_by_source( |id, allocation| filter.try_match( &data, id, allocation ) );
@@ -1580,7 +2907,7 @@ fn handler_script_files( req: HttpRequest ) -> Result< HttpResponse > {
 }
 
 fn handler_filter_to_script( req: HttpRequest ) -> Result< HttpResponse > {
-    let data = get_data( &req )?;
+    let data = &get_data( &req )?;
     let filter: protocol::AllocFilter = query( &req )?;
     let filter = prepare_raw_filter( data, &filter )?;
     let custom_filter: protocol::CustomFilter = query( &req )?;
@@ -1610,62 +2937,56 @@ fn handler_filter_to_script( req: HttpRequest ) -> Result< HttpResponse > {
     Ok( HttpResponse::Ok().content_type( "application/json; charset=utf-8" ).body( body ) )
 }
 
-fn handler_execute_script( req: HttpRequest, body: web::Bytes ) -> Result< HttpResponse > {
-    let data = get_data( &req )?;
-    let body = String::from_utf8( body.to_vec() ).unwrap();
-    let args = cli_core::script::EngineArgs {
-        data: Some( data.clone() ),
-        .. cli_core::script::EngineArgs::default()
-    };
-
-    let env = Arc::new( Mutex::new( cli_core::script::VirtualEnvironment::new() ) );
-    let engine = cli_core::script::Engine::new( env.clone(), args );
-    let timestamp = std::time::Instant::now();
-    let result = engine.run( &body );
-    let elapsed = timestamp.elapsed();
-    let data_id = data.id();
-
+/// Moves everything the script has printed or generated so far out of its environment.
+fn take_script_output( state: &State, data_id: DataId, env: &Mutex< cli_core::script::VirtualEnvironment > ) -> Vec< serde_json::Value > {
     let mut new_files = Vec::new();
     let mut output = Vec::new();
     for item in std::mem::take( &mut env.lock().output ) {
-        match item {
+        let (kind, mime, path, data) = match item {
             cli_core::script::ScriptOutputKind::PrintLine( line ) => {
                 output.push( serde_json::json! {{
                     "kind": "println",
                     "value": line
                 }});
+                continue;
             },
-            cli_core::script::ScriptOutputKind::Image { path, data } => {
-                let hash = format!( "{:x}", md5::compute( &*data ) );
-                let basename = path[ path.rfind( "/" ).unwrap() + 1.. ].to_owned();
-                output.push( serde_json::json! {{
-                    "url": format!( "/data/{}/script_files/{}/{}", data_id, hash, basename ),
-                    "kind": "image",
-                    "basename": basename,
-                    "path": path,
-                    "checksum": hash
-                }});
+            cli_core::script::ScriptOutputKind::Image { path, data } => ("image", "image/svg+xml", path, data),
+            cli_core::script::ScriptOutputKind::File { path, data } => ("file", "application/octet-stream", path, data)
+        };
 
-                let entry = GeneratedFile {
-                    timestamp: Instant::now(),
-                    hash,
-                    mime: "image/svg+xml",
-                    data
-                };
+        let hash = format!( "{:x}", md5::compute( &*data ) );
+        let basename = path[ path.rfind( "/" ).unwrap() + 1.. ].to_owned();
+        output.push( serde_json::json! {{
+            "url": format!( "/data/{}/script_files/{}/{}", data_id, hash, basename ),
+            "kind": kind,
+            "basename": basename,
+            "path": path,
+            "checksum": hash
+        }});
+
+        let entry = GeneratedFile {
+            timestamp: Instant::now(),
+            hash,
+            mime,
+            data
+        };
 
-                new_files.push( entry );
-            }
-        }
+        new_files.push( entry );
     }
 
-    let mut generated = req.state().generated_files.lock();
-    generated.purge_old_if_too_big();
-    for entry in new_files {
-        generated.add_file( entry );
+    if !new_files.is_empty() {
+        let mut generated = state.generated_files.lock();
+        generated.purge_old_if_too_big();
+        for entry in new_files {
+            generated.add_file( entry );
+        }
     }
-    std::mem::drop( generated );
 
-    let result = match result {
+    output
+}
+
+fn script_result( result: &std::result::Result< Option< cli_core::script::AllocationList >, cli_core::script::EvalError >, elapsed: Duration, output: &[serde_json::Value] ) -> serde_json::Value {
+    match result {
         Ok( _ ) => {
             serde_json::json! {{
                 "status": "ok",
@@ -1682,8 +3003,29 @@ fn handler_execute_script( req: HttpRequest, body: web::Bytes ) -> Result< HttpR
                 "output": output
             }}
         }
+    }
+}
+
+fn handler_execute_script( req: HttpRequest, body: web::Bytes ) -> Result< HttpResponse > {
+    check_writable( &req )?;
+    let data = &get_data( &req )?;
+    let body = String::from_utf8( body.to_vec() ).unwrap();
+    let state = req.state();
+    let args = cli_core::script::EngineArgs {
+        data: Some( data.clone() ),
+        limits: state.script_limits.clone(),
+        .. cli_core::script::EngineArgs::default()
     };
 
+    let env = Arc::new( Mutex::new( new_script_environment( &state.script_limits ) ) );
+    let engine = cli_core::script::Engine::new( env.clone(), args );
+    let timestamp = std::time::Instant::now();
+    let result = engine.run( &body );
+    let elapsed = timestamp.elapsed();
+
+    let output = take_script_output( state, data.id(), &env );
+    let result = script_result( &result, elapsed, &output );
+
     Ok(
         HttpResponse::Ok()
         .content_type( "application/json; charset=utf-8" )
@@ -1692,6 +3034,525 @@ fn handler_execute_script( req: HttpRequest, body: web::Bytes ) -> Result< HttpR
     )
 }
 
+fn new_script_environment( limits: &cli_core::script::ScriptLimits ) -> cli_core::script::VirtualEnvironment {
+    match limits.max_output_size {
+        Some( max_size ) => cli_core::script::VirtualEnvironment::with_size_limit( max_size ),
+        None => cli_core::script::VirtualEnvironment::new()
+    }
+}
+
+/// How long the results of a finished script job are kept around.
+const SCRIPT_JOB_RETENTION: Duration = Duration::from_secs( 600 );
+
+struct ScriptJob {
+    data_id: DataId,
+    started_at: Instant,
+    env: Arc< Mutex< cli_core::script::VirtualEnvironment > >,
+    cancel: Arc< AtomicBool >,
+    operations: Arc< AtomicU64 >,
+    output: Vec< serde_json::Value >,
+    result: Option< (serde_json::Value, Instant) >
+}
+
+#[derive(Default)]
+struct ScriptJobs {
+    next_id: u64,
+    jobs: HashMap< u64, ScriptJob >
+}
+
+impl ScriptJobs {
+    fn purge_old( &mut self ) {
+        self.jobs.retain( |_, job| {
+            job.result.as_ref().map( |(_, finished_at)| finished_at.elapsed() < SCRIPT_JOB_RETENTION ).unwrap_or( true )
+        });
+    }
+
+    fn running_count( &self ) -> usize {
+        self.jobs.values().filter( |job| job.result.is_none() ).count()
+    }
+}
+
+fn handler_start_script_job( req: HttpRequest, body: web::Bytes ) -> Result< HttpResponse > {
+    check_writable( &req )?;
+    let data = get_data( &req )?;
+    let code = String::from_utf8( body.to_vec() ).map_err( |_| ErrorBadRequest( "the script is not valid UTF-8" ) )?;
+    let state = req.state().clone();
+
+    let cancel = Arc::new( AtomicBool::new( false ) );
+    let operations = Arc::new( AtomicU64::new( 0 ) );
+    let env = Arc::new( Mutex::new( new_script_environment( &state.script_limits ) ) );
+    let args = cli_core::script::EngineArgs {
+        data: Some( data.clone() ),
+        limits: state.script_limits.clone(),
+        cancel: Some( cancel.clone() ),
+        progress: Some( operations.clone() ),
+        .. cli_core::script::EngineArgs::default()
+    };
+
+    let id = {
+        let mut jobs = state.script_jobs.lock();
+        jobs.purge_old();
+
+        if jobs.running_count() >= state.max_running_jobs {
+            return Err( ErrorServiceUnavailable( "too many scripts are already running; try again later" ) );
+        }
+
+        let id = jobs.next_id;
+        jobs.next_id += 1;
+        jobs.jobs.insert( id, ScriptJob {
+            data_id: data.id(),
+            started_at: Instant::now(),
+            env: env.clone(),
+            cancel,
+            operations,
+            output: Vec::new(),
+            result: None
+        });
+        id
+    };
+
+    thread::spawn( move || {
+        let engine = cli_core::script::Engine::new( env.clone(), args );
+        let timestamp = Instant::now();
+        let result = engine.run( &code );
+        let elapsed = timestamp.elapsed();
+
+        let output = take_script_output( &state, data.id(), &env );
+        let mut jobs = state.script_jobs.lock();
+        if let Some( job ) = jobs.jobs.get_mut( &id ) {
+            job.output.extend( output );
+            job.result = Some( (script_result( &result, elapsed, &[] ), Instant::now()) );
+        }
+    });
+
+    Ok( HttpResponse::Ok().json( protocol::ResponseScriptJobStarted { id } ) )
+}
+
+fn get_script_job_id( req: &HttpRequest ) -> Result< u64 > {
+    req.match_info().get( "job_id" ).unwrap().parse().map_err( |_| ErrorNotFound( "job not found" ) )
+}
+
+fn handler_script_job( req: HttpRequest ) -> Result< HttpResponse > {
+    let id = get_script_job_id( &req )?;
+    let params: protocol::RequestScriptJob = query( &req )?;
+    let state = req.state();
+
+    let mut jobs = state.script_jobs.lock();
+    let job = jobs.jobs.get_mut( &id ).ok_or_else( || ErrorNotFound( "job not found" ) )?;
+    if job.result.is_none() {
+        let output = take_script_output( state, job.data_id, &job.env );
+        job.output.extend( output );
+    }
+
+    let since = min( params.since.unwrap_or( 0 ), job.output.len() );
+    let mut response = match job.result {
+        Some( (ref result, _) ) => result.clone(),
+        None => serde_json::json! {{
+            "status": "running",
+            "elapsed": job.started_at.elapsed().as_secs_f64()
+        }}
+    };
+
+    response[ "operations" ] = job.operations.load( Ordering::Relaxed ).into();
+    response[ "output" ] = job.output[ since.. ].to_vec().into();
+    response[ "output_count" ] = job.output.len().into();
+
+    Ok( HttpResponse::Ok().json( response ) )
+}
+
+fn handler_cancel_script_job( req: HttpRequest ) -> Result< HttpResponse > {
+    let id = get_script_job_id( &req )?;
+    let jobs = req.state().script_jobs.lock();
+    let job = jobs.jobs.get( &id ).ok_or_else( || ErrorNotFound( "job not found" ) )?;
+    job.cancel.store( true, Ordering::Relaxed );
+
+    Ok( HttpResponse::Ok().finish() )
+}
+
+/// The queries which can be submitted as jobs, by the kind of the job.
+const JOB_KINDS: &[(&str, fn( &HttpRequest ) -> Result< Query >)] = &[
+    ("allocation_groups", query_allocation_groups),
+    ("flamegraph", query_export_flamegraph),
+    ("flamegraph_pl", query_export_flamegraph_pl),
+    ("churn_flamegraph", query_churn_flamegraph),
+    ("heaptrack", query_export_heaptrack),
+    ("replay", query_export_replay)
+];
+
+/// How often the progress of a job is sent to the clients which are watching it.
+const JOB_EVENT_INTERVAL: Duration = Duration::from_millis( 250 );
+
+fn handler_submit_job( req: HttpRequest ) -> Result< HttpResponse > {
+    let kind = req.match_info().get( "kind" ).unwrap();
+    let &(kind, build_query) = JOB_KINDS.iter().find( |&&(name, _)| name == kind ).ok_or_else( || ErrorNotFound( "unknown job kind" ) )?;
+    let data = get_data( &req )?;
+    let query = build_query( &req )?;
+    let state = req.state().clone();
+
+    let key = jobs::normalized_key( kind, data.id(), req.query_string() ).map_err( ErrorBadRequest )?;
+    let (job, is_new) = state.jobs.lock().get_or_insert( kind, data.id(), key )
+        .ok_or_else( || ErrorServiceUnavailable( "too many jobs are already running; try again later" ) )?;
+    if is_new {
+        jobs::run_job( job.clone(), move |progress| {
+            let mime = query.mime;
+            let mut output = JobBuffer::default();
+            query.run( &state, &data, progress, &mut output )?;
+
+            Ok( JobOutput {
+                mime,
+                data: Arc::new( output.data )
+            })
+        });
+    }
+
+    Ok( HttpResponse::Ok().json( job_response( &job ) ) )
+}
+
+fn job_response( job: &Job ) -> protocol::ResponseJob {
+    let (status, error) = match job.status() {
+        JobStatus::Running => ("running", None),
+        JobStatus::Finished( .. ) => ("finished", None),
+        JobStatus::Failed( error ) => ("failed", Some( error ))
+    };
+
+    protocol::ResponseJob {
+        id: job.id,
+        kind: job.kind,
+        status,
+        progress: job.progress.fraction(),
+        elapsed: job.elapsed().as_secs_f64(),
+        error
+    }
+}
+
+fn get_job( req: &HttpRequest ) -> Result< Arc< Job > > {
+    let id: u64 = req.match_info().get( "job_id" ).unwrap().parse().map_err( |_| ErrorNotFound( "job not found" ) )?;
+    req.state().jobs.lock().get( id ).ok_or_else( || ErrorNotFound( "job not found" ) )
+}
+
+fn handler_job( req: HttpRequest ) -> Result< HttpResponse > {
+    let job = get_job( &req )?;
+    Ok( HttpResponse::Ok().json( job_response( &job ) ) )
+}
+
+fn handler_job_result( req: HttpRequest ) -> Result< HttpResponse > {
+    let job = get_job( &req )?;
+    match job.status() {
+        JobStatus::Running => Err( ErrorConflict( "the job is still running" ) ),
+        JobStatus::Failed( error ) => Err( ErrorBadRequest( error ) ),
+        JobStatus::Finished( output ) => {
+            Ok( HttpResponse::Ok().content_type( output.mime ).body( output.data.as_ref().clone() ) )
+        }
+    }
+}
+
+fn handler_job_events( req: HttpRequest ) -> Result< HttpResponse > {
+    let job = get_job( &req )?;
+    let (mut tx, rx) = byte_channel();
+    let rx = rx.map_err( |_| ErrorInternalServerError( "internal error" ) );
+    let body = Body::Message( Box::new( BodyStream::new( rx ) ) );
+
+    thread::spawn( move || {
+        loop {
+            let response = job_response( &job );
+            let is_running = response.status == "running";
+            if serde_json::to_writer( &mut tx, &response ).is_err() || tx.write_str( "\n" ).is_err() || io::Write::flush( &mut tx ).is_err() {
+                break;
+            }
+
+            if !is_running {
+                break;
+            }
+
+            thread::sleep( JOB_EVENT_INTERVAL );
+        }
+    });
+
+    Ok( HttpResponse::Ok().content_type( "application/x-ndjson" ).body( body ) )
+}
+
+fn handler_cancel_job( req: HttpRequest ) -> Result< HttpResponse > {
+    let job = get_job( &req )?;
+    job.progress.cancel();
+
+    Ok( HttpResponse::Ok().finish() )
+}
+
+/// How often the live event streams check for changes.
+const LIVE_EVENT_INTERVAL: Duration = Duration::from_secs( 1 );
+
+/// The length, in microseconds, of the buckets of the timeline streamed to the clients; it's fixed
+/// so that the points which were already sent don't change as the data grows.
+const LIVE_TIMELINE_GRANULARITY: u64 = 1_000_000;
+
+/// How often an idle live event stream sends a comment, which is how we find out that the client is gone.
+const LIVE_KEEPALIVE_INTERVAL: Duration = Duration::from_secs( 15 );
+
+struct EventSender {
+    tx: byte_channel::ByteSender,
+    last_sent_at: Instant
+}
+
+impl EventSender {
+    fn send( &mut self, event: &str, payload: &str ) -> bool {
+        self.last_sent_at = Instant::now();
+        write!( self.tx, "event: {}\ndata: {}\n\n", event, payload ).is_ok() && io::Write::flush( &mut self.tx ).is_ok()
+    }
+
+    fn send_json< T: Serialize >( &mut self, event: &str, payload: &T ) -> bool {
+        self.send( event, &serde_json::to_string( payload ).unwrap() )
+    }
+
+    fn keep_alive( &mut self ) -> bool {
+        if self.last_sent_at.elapsed() < LIVE_KEEPALIVE_INTERVAL {
+            return true;
+        }
+
+        self.last_sent_at = Instant::now();
+        self.tx.write_str( ": keep-alive\n\n" ).is_ok() && io::Write::flush( &mut self.tx ).is_ok()
+    }
+}
+
+fn timeline_since( timeline: &protocol::ResponseTimeline, x: u64 ) -> protocol::ResponseTimeline {
+    let start = timeline.xs.iter().position( |&point_x| point_x > x ).unwrap_or( timeline.xs.len() );
+    protocol::ResponseTimeline {
+        xs: timeline.xs[ start.. ].to_vec(),
+        size_delta: timeline.size_delta[ start.. ].to_vec(),
+        count_delta: timeline.count_delta[ start.. ].to_vec(),
+        allocated_size: timeline.allocated_size[ start.. ].to_vec(),
+        allocated_count: timeline.allocated_count[ start.. ].to_vec(),
+        allocations: timeline.allocations[ start.. ].to_vec(),
+        deallocations: timeline.deallocations[ start.. ].to_vec(),
+        allocated_slack: timeline.allocated_slack[ start.. ].to_vec()
+    }
+}
+
+/// Returns the allocation groups which weren't seen before, and marks them as seen.
+fn new_allocation_groups( data: &Data, seen: &mut Vec< bool > ) -> Vec< protocol::NewAllocationGroup > {
+    let count = data.unique_backtrace_count();
+    if seen.len() < count {
+        seen.resize( count, false );
+    }
+
+    let mut groups = Vec::new();
+    for index in 0..count {
+        let stats = data.get_group_statistics( BacktraceId::new( index as u32 ) );
+        if stats.alloc_count == 0 || seen[ index ] {
+            continue;
+        }
+
+        seen[ index ] = true;
+        groups.push( protocol::NewAllocationGroup {
+            backtrace_id: index as u32,
+            allocated_count: stats.alloc_count,
+            size: stats.alloc_size,
+            first_allocation: stats.first_allocation.into()
+        });
+    }
+
+    groups
+}
+
+/// Streams the changes to a given data file (when it's still being written to and is periodically
+/// reloaded), the progress of its jobs, and the progress of the data files being loaded.
+fn handler_data_events( req: HttpRequest ) -> Result< HttpResponse > {
+    let data_id = get_data_id( &req )?;
+    let state = req.state().clone();
+    let (tx, rx) = byte_channel();
+    let rx = rx.map_err( |_| ErrorInternalServerError( "internal error" ) );
+    let body = Body::Message( Box::new( BodyStream::new( rx ) ) );
+
+    thread::spawn( move || {
+        let mut sender = EventSender { tx, last_sent_at: Instant::now() };
+        let mut last_data = match state.get( data_id ) {
+            Some( data ) => data,
+            None => return
+        };
+
+        // The client is expected to already have everything up until this point.
+        let mut last_x = last_data.last_timestamp().as_usecs() / 1000;
+        let mut seen_groups = Vec::new();
+        new_allocation_groups( &last_data, &mut seen_groups );
+
+        let mut sent_jobs: HashMap< u64, String > = HashMap::new();
+        let mut sent_loading = "[]".to_owned();
+        loop {
+            let data = match state.get( data_id ) {
+                Some( data ) => data,
+                None => {
+                    sender.send_json( "unloaded", &data_id.to_string() );
+                    break;
+                }
+            };
+
+            let mut is_connected = true;
+            if !Arc::ptr_eq( &data, &last_data ) {
+                let timeline = timeline_since( &state.live_timeline( &data ), last_x );
+                if let Some( &x ) = timeline.xs.last() {
+                    last_x = x;
+                    is_connected &= sender.send_json( "timeline", &timeline );
+                }
+
+                let groups = new_allocation_groups( &data, &mut seen_groups );
+                if !groups.is_empty() {
+                    is_connected &= sender.send_json( "allocation_groups", &groups );
+                }
+
+                last_data = data;
+            }
+
+            let jobs = state.jobs.lock().for_data( data_id );
+            for job in jobs {
+                let response = job_response( &job );
+                // The elapsed time changes all the time, so it's not taken into account here.
+                let key = format!( "{}-{:?}", response.status, response.progress );
+                if sent_jobs.get( &job.id ) != Some( &key ) {
+                    is_connected &= sender.send_json( "job", &response );
+                    sent_jobs.insert( job.id, key );
+                }
+            }
+
+            let loading = serde_json::to_string( &loading_progress( &state ) ).unwrap();
+            if loading != sent_loading {
+                is_connected &= sender.send( "loading", &loading );
+                sent_loading = loading;
+            }
+
+            if !is_connected || !sender.keep_alive() {
+                break;
+            }
+
+            thread::sleep( LIVE_EVENT_INTERVAL );
+        }
+    });
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type( "text/event-stream" )
+            .header( header::CACHE_CONTROL, "no-cache" )
+            .body( body )
+    )
+}
+
+/// The pages of the web UI to which a permalink can point.
+const PERMALINK_PAGES: &[&str] = &[
+    "overview",
+    "allocations",
+    "address_space",
+    "console",
+    "growth",
+    "slack",
+    "thread_matrix",
+    "arenas"
+];
+
+/// Makes sure that the query is a well formed query string, and reencodes it.
+fn normalize_query( query: &str ) -> Result< String > {
+    let pairs: Vec< (String, String) > = serde_urlencoded::from_str( query ).map_err( |_| ErrorBadRequest( "malformed query" ) )?;
+    Ok( serde_urlencoded::to_string( pairs ).unwrap() )
+}
+
+fn get_stored_id( req: &HttpRequest, key: &str ) -> Result< u64 > {
+    req.match_info().get( key ).unwrap().parse().map_err( |_| ErrorNotFound( "not found" ) )
+}
+
+fn handler_filters( req: HttpRequest ) -> Result< HttpResponse > {
+    let id = get_data_id( &req )?;
+    let filters = req.state().store.lock().filters( &id.to_string() );
+    Ok( HttpResponse::Ok().json( filters ) )
+}
+
+fn handler_save_filter( req: HttpRequest, body: web::Json< protocol::RequestSaveFilter > ) -> Result< HttpResponse > {
+    check_writable( &req )?;
+    let data = get_data( &req )?;
+    let name = body.name.trim();
+    if name.is_empty() {
+        return Err( ErrorBadRequest( "the name of the filter cannot be empty" ) );
+    }
+
+    let query = normalize_query( &body.query )?;
+    parse_filter_query( &data, &query ).map_err( ErrorBadRequest )?;
+
+    let filter = req.state().store.lock().add_filter( &data.id().to_string(), name, &query ).map_err( ErrorInternalServerError )?;
+    Ok( HttpResponse::Ok().json( filter ) )
+}
+
+fn handler_delete_filter( req: HttpRequest ) -> Result< HttpResponse > {
+    check_writable( &req )?;
+    let id = get_stored_id( &req, "filter_id" )?;
+    if !req.state().store.lock().remove_filter( id ).map_err( ErrorInternalServerError )? {
+        return Err( ErrorNotFound( "filter not found" ) );
+    }
+
+    Ok( HttpResponse::Ok().finish() )
+}
+
+fn handler_notes( req: HttpRequest ) -> Result< HttpResponse > {
+    let id = get_data_id( &req )?;
+    let notes = req.state().store.lock().notes( &id.to_string() );
+    Ok( HttpResponse::Ok().json( notes ) )
+}
+
+fn handler_add_note( req: HttpRequest, body: web::Json< protocol::RequestAddNote > ) -> Result< HttpResponse > {
+    check_writable( &req )?;
+    let data = get_data( &req )?;
+    let body = body.into_inner();
+    let text = body.text.trim();
+    if text.is_empty() {
+        return Err( ErrorBadRequest( "the note cannot be empty" ) );
+    }
+
+    if let Some( backtrace_id ) = body.backtrace_id {
+        if backtrace_id as usize >= data.unique_backtrace_count() {
+            return Err( ErrorBadRequest( "no such backtrace" ) );
+        }
+    }
+
+    let range: Vec< (&str, &String) > = body.from.iter().map( |from| ("from", from) ).chain( body.to.iter().map( |to| ("to", to) ) ).collect();
+    let range = serde_urlencoded::to_string( range ).unwrap();
+    serde_urlencoded::from_str::< protocol::AllocFilter >( &range ).map_err( |_| ErrorBadRequest( "invalid time range" ) )?;
+
+    let note = req.state().store.lock().add_note( &data.id().to_string(), body.backtrace_id, body.from, body.to, text ).map_err( ErrorInternalServerError )?;
+    Ok( HttpResponse::Ok().json( note ) )
+}
+
+fn handler_delete_note( req: HttpRequest ) -> Result< HttpResponse > {
+    check_writable( &req )?;
+    let id = get_stored_id( &req, "note_id" )?;
+    if !req.state().store.lock().remove_note( id ).map_err( ErrorInternalServerError )? {
+        return Err( ErrorNotFound( "note not found" ) );
+    }
+
+    Ok( HttpResponse::Ok().finish() )
+}
+
+fn handler_create_permalink( req: HttpRequest, body: web::Json< protocol::RequestCreatePermalink > ) -> Result< HttpResponse > {
+    check_writable( &req )?;
+    let id = get_data_id( &req )?;
+    if !PERMALINK_PAGES.contains( &body.page.as_str() ) {
+        return Err( ErrorBadRequest( "unknown page" ) );
+    }
+
+    let query = normalize_query( &body.query )?;
+    let permalink = req.state().store.lock().add_permalink( &id.to_string(), &body.page, &query ).map_err( ErrorInternalServerError )?;
+    Ok( HttpResponse::Ok().json( permalink ) )
+}
+
+fn handler_permalink( req: HttpRequest ) -> Result< HttpResponse > {
+    let token = req.match_info().get( "token" ).unwrap();
+    let location = {
+        let store = req.state().store.lock();
+        let permalink = store.permalink( token ).ok_or( ErrorNotFound( "permalink not found" ) )?;
+        if permalink.query.is_empty() {
+            format!( "/#/{}/{}", permalink.page, permalink.data_id )
+        } else {
+            format!( "/#/{}/{}?{}", permalink.page, permalink.data_id, permalink.query )
+        }
+    };
+
+    Ok( HttpResponse::Found().header( header::LOCATION, location ).finish() )
+}
+
 fn guess_mime( path: &str ) -> &str {
     macro_rules! mimes {
         ($($ext:expr => $mime:expr),+) => {
@@ -1727,6 +3588,41 @@ impl Responder for StaticResponse {
 
 include!( concat!( env!( "OUT_DIR" ), "/webui_assets.rs" ) );
 
+/// The certificate chain and the private key, both in the PEM format, used to serve over HTTPS.
+pub struct TlsConfig {
+    pub certificate: PathBuf,
+    pub key: PathBuf
+}
+
+fn load_tls_config( tls: &TlsConfig ) -> io::Result< rustls::ServerConfig > {
+    use rustls::internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
+
+    fn invalid( path: &PathBuf, message: &str ) -> io::Error {
+        io::Error::new( io::ErrorKind::InvalidData, format!( "{:?}: {}", path, message ) )
+    }
+
+    let certificates = certs( &mut io::BufReader::new( File::open( &tls.certificate )? ) )
+        .map_err( |_| invalid( &tls.certificate, "failed to parse the certificate" ) )?;
+
+    if certificates.is_empty() {
+        return Err( invalid( &tls.certificate, "no certificates found" ) );
+    }
+
+    let mut keys = pkcs8_private_keys( &mut io::BufReader::new( File::open( &tls.key )? ) )
+        .map_err( |_| invalid( &tls.key, "failed to parse the private key" ) )?;
+
+    if keys.is_empty() {
+        keys = rsa_private_keys( &mut io::BufReader::new( File::open( &tls.key )? ) )
+            .map_err( |_| invalid( &tls.key, "failed to parse the private key" ) )?;
+    }
+
+    let key = keys.into_iter().next().ok_or_else( || invalid( &tls.key, "no private keys found" ) )?;
+    let mut config = rustls::ServerConfig::new( rustls::NoClientAuth::new() );
+    config.set_single_cert( certificates, key ).map_err( |error| invalid( &tls.key, &error.to_string() ) )?;
+
+    Ok( config )
+}
+
 #[derive(Debug)]
 pub enum ServerError {
     BindFailed( io::Error ),
@@ -1753,15 +3649,99 @@ impl From< io::Error > for ServerError {
 
 impl Error for ServerError {}
 
-pub fn main( inputs: Vec< PathBuf >, debug_symbols: Vec< PathBuf >, load_in_parallel: bool, interface: &str, port: u16 ) -> Result< (), ServerError > {
-    let mut state = State::new();
+/// Parses a query string in the same format as the one accepted by the allocation
+/// endpoints (e.g. `size_min=1024&lifetime=only_leaked`) into a filter.
+pub fn parse_filter_query( data: &Data, query: &str ) -> std::result::Result< cli_core::Filter, String > {
+    let filter: protocol::AllocFilter = serde_urlencoded::from_str( query ).map_err( |error| format!( "invalid filter query: {}", error ) )?;
+    prepare_raw_filter( data, &filter ).map_err( |error| error.to_string() )
+}
+
+/// Starts the server; if `as_session` is set then all of the `inputs` are treated
+/// as recordings of a single process tree and are grouped into one session.
+///
+/// If `watch` is set then any new data files which appear in that directory are loaded
+/// automatically; more files can also be loaded at runtime through the API. If `live` is
+/// also set then those files are loaded while they're still being written to, and are
+/// reloaded periodically, with the changes streamed through `/data/{id}/events`.
+///
+/// The `script_limits` apply to every script executed through the API, and at most
+/// `max_running_jobs` scripts and as many other background jobs can run at the same time.
+///
+/// If `tls` is set then the server is served over HTTPS; if `auth` has any credentials
+/// then every request has to include them, and if `read_only` is set then loading and
+/// unloading data, executing scripts and saving filters, notes and permalinks through the API is disabled.
+///
+/// The uploaded files can be at most `max_upload_size` bytes big, and only the files
+/// from the `load_directories` and the watched directory can be loaded by their path.
+///
+/// The saved filters, notes and permalinks are kept in the `store` file, if any;
+/// otherwise they're lost when the server is stopped.
+/// Everything needed to start up the server.
+pub struct ServerConfig {
+    /// The data files which are loaded at startup.
+    pub inputs: Vec< PathBuf >,
+    pub debug_symbols: Vec< PathBuf >,
+    pub load_in_parallel: bool,
+    /// Whether the `inputs` should be grouped together into a single session.
+    pub as_session: bool,
+    /// A directory in which the new data files are automatically loaded.
+    pub watch: Option< PathBuf >,
+    /// Whether the watched data files which are still being written to should be periodically reloaded.
+    pub live: bool,
+    pub script_limits: cli_core::script::ScriptLimits,
+    pub tls: Option< TlsConfig >,
+    pub auth: Auth,
+    pub read_only: bool,
+    pub max_upload_size: Option< u64 >,
+    /// The directories from which the data files can be loaded through the API.
+    pub load_directories: Vec< PathBuf >,
+    pub max_running_jobs: usize,
+    /// Where the saved filters, notes and permalinks are stored; they're only kept in memory if not set.
+    pub store: Option< PathBuf >,
+    pub interface: String,
+    pub port: u16
+}
+
+pub fn main( config: ServerConfig ) -> Result< (), ServerError > {
+    let ServerConfig {
+        inputs, debug_symbols, load_in_parallel, as_session, watch, live, script_limits, tls, auth, read_only,
+        max_upload_size, load_directories, max_running_jobs, store, interface, port
+    } = config;
+
+    let tls_config = match tls {
+        Some( ref tls ) => Some( load_tls_config( tls )? ),
+        None => None
+    };
+
+    if !auth.is_enabled() && interface != "127.0.0.1" && interface != "localhost" && interface != "::1" {
+        warn!( "The server is not restricted to localhost and has no authentication enabled; anyone who can reach it will be able to access the data" );
+    }
+
+    let store = match store {
+        Some( path ) => {
+            info!( "Using {:?} to store the saved filters, notes and permalinks...", path );
+            Store::open( path )?
+        },
+        None => Store::in_memory()
+    };
+
+    let watch = match watch {
+        Some( directory ) => Some( fs::canonicalize( &directory )? ),
+        None => None
+    };
+
+    let mut load_directories = load_directories.iter().map( fs::canonicalize ).collect::< Result< Vec< _ >, _ > >()?;
+    load_directories.extend( watch.clone() );
+
+    let state = State::new( debug_symbols.clone(), script_limits, read_only, max_upload_size, load_directories, max_running_jobs, store );
+    let mut data_ids = Vec::new();
 
     if !load_in_parallel {
-        for filename in inputs {
+        for filename in &inputs {
             info!( "Trying to load {:?}...", filename );
             let fp = File::open( filename )?;
             let data = Loader::load_from_stream( fp, &debug_symbols )?;
-            state.add_data( data );
+            data_ids.push( state.add_data( data ) );
         }
     } else {
         let handles: Vec< thread::JoinHandle< io::Result< Data > > > = inputs.iter().map( move |filename| {
@@ -1778,25 +3758,68 @@ pub fn main( inputs: Vec< PathBuf >, debug_symbols: Vec< PathBuf >, load_in_para
 
         for handle in handles {
             let data = handle.join().unwrap()?;
-            state.add_data( data );
+            data_ids.push( state.add_data( data ) );
         }
     }
 
+    if as_session {
+        let mut unique = HashSet::new();
+        data_ids.retain( |&id| unique.insert( id ) );
+        state.add_session( data_ids );
+    }
+
     for (key, bytes) in WEBUI_ASSETS {
         debug!( "Static asset: '{}', length = {}", key, bytes.len() );
     }
 
     let state = Arc::new( state );
+    if let Some( directory ) = watch {
+        let seen: HashSet< PathBuf > = inputs.iter().flat_map( |path| fs::canonicalize( path ).ok() ).collect();
+        let state = state.clone();
+
+        info!( "Watching {:?} for new data files...", directory );
+        thread::Builder::new().name( "watch".into() ).spawn( move || {
+            watch_directory( state, directory, seen, live );
+        })?;
+    }
+
+    let auth = Arc::new( auth );
     let sys = actix::System::new( "server" );
-    actix_web::HttpServer::new( move || {
+    let server = actix_web::HttpServer::new( move || {
+        let auth = auth.clone();
         App::new().data( state.clone() )
+            .wrap_fn( move |req, srv| {
+                let authorization = req.headers().get( header::AUTHORIZATION ).and_then( |value| value.to_str().ok() );
+                // The server-sent events are consumed through `EventSource`, which can't set any headers.
+                let is_event_stream = req.path().starts_with( "/data/" ) && req.path().ends_with( "/events" );
+                if auth.is_authorized( authorization ) || (is_event_stream && auth.is_authorized_by_query( req.query_string() )) {
+                    return future::Either::A( srv.call( req ) );
+                }
+
+                let mut response = HttpResponse::Unauthorized();
+                if auth.basic.is_some() {
+                    response.header( header::WWW_AUTHENTICATE, "Basic realm=\"bytehound\"" );
+                }
+
+                future::Either::B( future::ok::< _, ActixWebError >( req.into_response( response.finish() ) ) )
+            })
             .wrap( Cors::new() )
             .configure( |app| {
                 app
+                    .service( web::resource( "/openapi.json" ).route( web::get().to( handler_openapi ) ) )
                     .service( web::resource( "/list" ).route( web::get().to( handler_list ) ) )
+                    .service( web::resource( "/load" ).route( web::post().to( handler_load ) ) )
+                    .service( web::resource( "/upload" ).route( web::post().to_async( handler_upload ) ) )
+                    .service( web::resource( "/loading" ).route( web::get().to( handler_loading ) ) )
+                    .service( web::resource( "/loading/{id}" ).route( web::delete().to( handler_dismiss_loading ) ) )
+                    .service( web::resource( "/data/{id}" ).route( web::delete().to( handler_unload ) ) )
+                    .service( web::resource( "/sessions" ).route( web::get().to( handler_sessions ) ) )
+                    .service( web::resource( "/session/{id}/timeline" ).route( web::get().to( handler_session_timeline ) ) )
                     .service( web::resource( "/data/{id}/timeline" ).route( web::get().to( handler_timeline ) ) )
                     .service( web::resource( "/data/{id}/timeline_leaked" ).route( web::get().to( handler_timeline_leaked ) ) )
                     .service( web::resource( "/data/{id}/fragmentation_timeline" ).route( web::get().to( handler_fragmentation_timeline ) ) )
+                    .service( web::resource( "/data/{id}/arenas" ).route( web::get().to( handler_arenas ) ) )
+                    .service( web::resource( "/data/{id}/arena_timeline" ).route( web::get().to( handler_arena_timeline ) ) )
                     .service( web::resource( "/data/{id}/allocations" ).route( web::get().to( handler_allocations ) ) )
                     .service( web::resource( "/data/{id}/allocation_groups" ).route( web::get().to( handler_allocation_groups ) ) )
                     .service( web::resource( "/data/{id}/backtraces" ).route( web::get().to( handler_backtraces ) ) )
@@ -1808,6 +3831,12 @@ pub fn main( inputs: Vec< PathBuf >, debug_symbols: Vec< PathBuf >, load_in_para
                     .service( web::resource( "/data/{id}/mallopts" ).route( web::get().to( handler_mallopts ) ) )
                     .service( web::resource( "/data/{id}/export/flamegraph" ).route( web::get().to( handler_export_flamegraph ) ) )
                     .service( web::resource( "/data/{id}/export/flamegraph/{filename}" ).route( web::get().to( handler_export_flamegraph ) ) )
+                    .service( web::resource( "/data/{id}/churn" ).route( web::get().to( handler_churn ) ) )
+                    .service( web::resource( "/data/{id}/growth_patterns" ).route( web::get().to( handler_growth_groups ) ) )
+                    .service( web::resource( "/data/{id}/slack" ).route( web::get().to( handler_slack ) ) )
+                    .service( web::resource( "/data/{id}/thread_matrix" ).route( web::get().to( handler_thread_matrix ) ) )
+                    .service( web::resource( "/data/{id}/churn/flamegraph" ).route( web::get().to( handler_churn_flamegraph ) ) )
+                    .service( web::resource( "/data/{id}/churn/flamegraph/{filename}" ).route( web::get().to( handler_churn_flamegraph ) ) )
                     .service( web::resource( "/data/{id}/export/flamegraph.pl" ).route( web::get().to( handler_export_flamegraph_pl ) ) )
                     .service( web::resource( "/data/{id}/export/flamegraph.pl/{filename}" ).route( web::get().to( handler_export_flamegraph_pl ) ) )
                     .service( web::resource( "/data/{id}/export/heaptrack" ).route( web::get().to( handler_export_heaptrack ) ) )
@@ -1824,6 +3853,35 @@ pub fn main( inputs: Vec< PathBuf >, debug_symbols: Vec< PathBuf >, load_in_para
                     .service( web::resource( "/data/{id}/dynamic_statics_ascii_tree" ).route( web::get().to( handler_dynamic_statics_ascii_tree ) ) )
                     .service( web::resource( "/data/{id}/dynamic_statics_ascii_tree/{filename}" ).route( web::get().to( handler_dynamic_statics_ascii_tree ) ) )
                     .service( web::resource( "/data/{id}/execute_script" ).route( web::post().to( handler_execute_script ) ) )
+                    .service( web::resource( "/data/{id}/script_jobs" ).route( web::post().to( handler_start_script_job ) ) )
+                    .service(
+                        web::resource( "/script_jobs/{job_id}" )
+                            .route( web::get().to( handler_script_job ) )
+                            .route( web::delete().to( handler_cancel_script_job ) )
+                    )
+                    .service( web::resource( "/data/{id}/jobs/{kind}" ).route( web::post().to( handler_submit_job ) ) )
+                    .service(
+                        web::resource( "/jobs/{job_id}" )
+                            .route( web::get().to( handler_job ) )
+                            .route( web::delete().to( handler_cancel_job ) )
+                    )
+                    .service( web::resource( "/jobs/{job_id}/result" ).route( web::get().to( handler_job_result ) ) )
+                    .service( web::resource( "/jobs/{job_id}/events" ).route( web::get().to( handler_job_events ) ) )
+                    .service(
+                        web::resource( "/data/{id}/filters" )
+                            .route( web::get().to( handler_filters ) )
+                            .route( web::post().to( handler_save_filter ) )
+                    )
+                    .service( web::resource( "/filters/{filter_id}" ).route( web::delete().to( handler_delete_filter ) ) )
+                    .service(
+                        web::resource( "/data/{id}/notes" )
+                            .route( web::get().to( handler_notes ) )
+                            .route( web::post().to( handler_add_note ) )
+                    )
+                    .service( web::resource( "/notes/{note_id}" ).route( web::delete().to( handler_delete_note ) ) )
+                    .service( web::resource( "/data/{id}/permalinks" ).route( web::post().to( handler_create_permalink ) ) )
+                    .service( web::resource( "/permalinks/{token}" ).route( web::get().to( handler_permalink ) ) )
+                    .service( web::resource( "/data/{id}/events" ).route( web::get().to( handler_data_events ) ) )
                     .service( web::resource( "/data/{id}/script_files/{hash}/{filename}" ).route( web::get().to( handler_script_files ) ) )
                     .service( web::resource( "/data/{id}/filter_to_script" ).route( web::get().to( handler_filter_to_script ) ) )
                 ;
@@ -1835,7 +3893,15 @@ pub fn main( inputs: Vec< PathBuf >, debug_symbols: Vec< PathBuf >, load_in_para
                     }
                 }
             })
-    }).bind( &format!( "{}:{}", interface, port ) ).map_err( |err| ServerError::BindFailed( err ) )?
+    });
+
+    let address = format!( "{}:{}", interface, port );
+    let server = match tls_config {
+        Some( tls_config ) => server.bind_rustls( &address, tls_config ),
+        None => server.bind( &address )
+    };
+
+    server.map_err( |err| ServerError::BindFailed( err ) )?
         .shutdown_timeout( 1 )
         .start();
 
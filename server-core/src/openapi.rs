@@ -0,0 +1,456 @@
+use schemars::JsonSchema;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use serde_json::{json, Map, Value};
+
+use crate::protocol;
+
+struct Operation< 'a > {
+    gen: &'a mut SchemaGenerator,
+    parameters: Vec< Value >,
+    request_body: Option< Value >,
+    response: Value
+}
+
+impl< 'a > Operation< 'a > {
+    /// Adds every field of `T` as a query parameter, unless a parameter with the same name was already added.
+    fn query< T: JsonSchema >( &mut self ) -> &mut Self {
+        let schema = serde_json::to_value( T::json_schema( self.gen ) ).unwrap();
+        let required = schema.get( "required" ).and_then( |required| required.as_array() ).cloned().unwrap_or_default();
+        if let Some( properties ) = schema.get( "properties" ).and_then( |properties| properties.as_object() ) {
+            for (name, property) in properties {
+                if self.parameters.iter().any( |parameter| parameter[ "name" ] == *name ) {
+                    continue;
+                }
+
+                let mut parameter = json!({
+                    "name": name,
+                    "in": "query",
+                    "required": required.contains( &Value::String( name.clone() ) ),
+                    "schema": property
+                });
+
+                if let Some( description ) = property.get( "description" ) {
+                    parameter[ "description" ] = description.clone();
+                }
+
+                self.parameters.push( parameter );
+            }
+        }
+
+        self
+    }
+
+    fn body( &mut self, mime: &str ) -> &mut Self {
+        self.request_body = Some( json!({
+            "required": true,
+            "content": { mime: {} }
+        }));
+
+        self
+    }
+
+    fn json_body< T: JsonSchema >( &mut self ) -> &mut Self {
+        let schema = self.gen.subschema_for::< T >();
+        self.request_body = Some( json!({
+            "required": true,
+            "content": { "application/json": { "schema": schema } }
+        }));
+
+        self
+    }
+
+    fn json< T: JsonSchema >( &mut self ) -> &mut Self {
+        let schema = self.gen.subschema_for::< T >();
+        self.response[ "content" ] = json!({ "application/json": { "schema": schema } });
+        self
+    }
+
+    /// For responses in JSON whose structure isn't described.
+    fn any_json( &mut self ) -> &mut Self {
+        self.content( "application/json" )
+    }
+
+    fn content( &mut self, mime: &str ) -> &mut Self {
+        self.response[ "content" ] = json!({ mime: {} });
+        self
+    }
+
+    /// For the lists which can also be downloaded as a table with `format=csv` or `format=tsv`.
+    fn or_table( &mut self ) -> &mut Self {
+        self.query::< protocol::RequestListFormat >();
+        self.response[ "content" ][ "text/csv" ] = json!({});
+        self.response[ "content" ][ "text/tab-separated-values" ] = json!({});
+        self
+    }
+}
+
+struct Api {
+    gen: SchemaGenerator,
+    paths: Map< String, Value >
+}
+
+impl Api {
+    fn add< F >( &mut self, method: &str, path: &str, operation_id: &str, summary: &str, callback: F )
+        where F: FnOnce( &mut Operation )
+    {
+        let parameters = path.split( '/' )
+            .filter( |segment| segment.starts_with( '{' ) && segment.ends_with( '}' ) )
+            .map( |segment| {
+                let name = &segment[ 1..segment.len() - 1 ];
+                let mut parameter = json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "description": path_parameter_description( name ),
+                    "schema": { "type": "string" }
+                });
+
+                if name == "kind" {
+                    let kinds: Vec< _ > = crate::JOB_KINDS.iter().map( |&(kind, _)| kind ).collect();
+                    parameter[ "schema" ][ "enum" ] = json!( kinds );
+                }
+
+                parameter
+            })
+            .collect();
+
+        let mut operation = Operation {
+            gen: &mut self.gen,
+            parameters,
+            request_body: None,
+            response: json!({ "description": "Success" })
+        };
+
+        callback( &mut operation );
+
+        let mut value = json!({
+            "operationId": operation_id,
+            "summary": summary,
+            "parameters": operation.parameters,
+            "responses": {
+                "200": operation.response
+            }
+        });
+
+        if let Some( request_body ) = operation.request_body {
+            value[ "requestBody" ] = request_body;
+        }
+
+        let entry = self.paths.entry( path.to_owned() ).or_insert_with( || json!({}) );
+        entry[ method ] = value;
+    }
+}
+
+fn path_parameter_description( name: &str ) -> &'static str {
+    match name {
+        "id" => "The ID of the data file (or a session, for the `/session` endpoints); `last` can be used to refer to the most recently loaded data file.",
+        "kind" => "The kind of the job.",
+        "job_id" => "The ID of the job.",
+        "backtrace_id" => "The ID of the backtrace.",
+        "hash" => "The hash of the generated file.",
+        "filename" => "The name of the file; only used as the name of the downloaded file.",
+        "filter_id" => "The ID of the saved filter.",
+        "note_id" => "The ID of the note.",
+        "token" => "The token of the permalink.",
+        _ => ""
+    }
+}
+
+/// Generates an OpenAPI description of the REST API.
+pub fn generate() -> Value {
+    let mut api = Api {
+        gen: SchemaSettings::openapi3().into_generator(),
+        paths: Map::new()
+    };
+
+    api.add( "get", "/list", "list", "Lists the loaded data files", |op| {
+        op.json::< Vec< protocol::ResponseMetadata > >();
+    });
+
+    api.add( "post", "/load", "load", "Starts loading a data file which is on the same machine as the server", |op| {
+        op.query::< protocol::RequestLoad >().json::< protocol::ResponseLoadStarted >();
+    });
+
+    api.add( "post", "/upload", "upload", "Uploads a data file and starts loading it", |op| {
+        op.query::< protocol::RequestUpload >().body( "application/octet-stream" ).json::< protocol::ResponseLoadStarted >();
+    });
+
+    api.add( "get", "/loading", "loading", "Lists the data files which are being loaded or which have failed to load", |op| {
+        op.json::< Vec< protocol::LoadingProgress > >();
+    });
+
+    api.add( "delete", "/loading/{id}", "dismiss_loading", "Dismisses a data file which has failed to load", |_| {} );
+    api.add( "delete", "/data/{id}", "unload", "Unloads a data file", |_| {} );
+
+    api.add( "get", "/sessions", "sessions", "Lists the sessions", |op| {
+        op.json::< Vec< protocol::ResponseSession > >();
+    });
+
+    api.add( "get", "/session/{id}/timeline", "session_timeline", "Returns the memory usage of every process of a session over time", |op| {
+        op.query::< protocol::RequestSessionTimeline >()
+            .query::< protocol::AllocFilter >()
+            .query::< protocol::CustomFilter >()
+            .json::< protocol::ResponseSessionTimeline >();
+    });
+
+    api.add( "get", "/data/{id}/timeline", "timeline", "Returns the memory usage over time of the allocations matching the filter", |op| {
+        op.query::< protocol::RequestTimeline >()
+            .query::< protocol::AllocFilter >()
+            .query::< protocol::CustomFilter >()
+            .json::< protocol::ResponseTimeline >();
+    });
+
+    api.add( "get", "/data/{id}/timeline_leaked", "timeline_leaked", "Returns the memory usage over time of only the leaked allocations matching the filter", |op| {
+        op.query::< protocol::RequestTimeline >()
+            .query::< protocol::AllocFilter >()
+            .query::< protocol::CustomFilter >()
+            .json::< protocol::ResponseTimeline >();
+    });
+
+    api.add( "get", "/data/{id}/fragmentation_timeline", "fragmentation_timeline", "Returns the fragmentation over time", |op| {
+        op.query::< protocol::RequestTimeline >().json::< protocol::ResponseFragmentationTimeline >();
+    });
+
+    api.add( "get", "/data/{id}/arenas", "arenas", "Returns the glibc arenas", |op| {
+        op.json::< protocol::ResponseArenas >();
+    });
+
+    api.add( "get", "/data/{id}/arena_timeline", "arena_timeline", "Returns the usage of every glibc arena over time", |op| {
+        op.json::< protocol::ResponseArenaTimeline >();
+    });
+
+    api.add( "get", "/data/{id}/allocations", "allocations", "Returns the allocations matching the filter", |op| {
+        op.query::< protocol::AllocFilter >()
+            .query::< protocol::CustomFilter >()
+            .query::< protocol::BacktraceFormat >()
+            .query::< protocol::RequestAllocations >()
+            .json::< protocol::ResponseAllocations< Vec< protocol::Allocation< 'static > > > >()
+            .or_table();
+    });
+
+    api.add( "get", "/data/{id}/allocation_groups", "allocation_groups", "Returns the allocations matching the filter grouped by their backtraces", |op| {
+        op.query::< protocol::AllocFilter >()
+            .query::< protocol::CustomFilter >()
+            .query::< protocol::BacktraceFormat >()
+            .query::< protocol::RequestAllocationGroups >()
+            .json::< protocol::ResponseAllocationGroups< Vec< protocol::AllocationGroup< 'static > > > >()
+            .or_table();
+    });
+
+    api.add( "get", "/data/{id}/backtraces", "backtraces", "Returns the backtraces matching the filter", |op| {
+        op.query::< protocol::BacktraceFilter >()
+            .query::< protocol::BacktraceFormat >()
+            .json::< protocol::ResponseBacktraces< Vec< Vec< protocol::Frame< 'static > > > > >()
+            .or_table();
+    });
+
+    api.add( "get", "/data/{id}/backtrace/{backtrace_id}", "backtrace", "Returns a single backtrace", |op| {
+        op.query::< protocol::BacktraceFormat >().json::< protocol::ResponseBacktrace< 'static > >();
+    });
+
+    api.add( "get", "/data/{id}/raw_allocations", "raw_allocations", "Returns every allocation with its raw backtrace", |op| {
+        op.any_json();
+    });
+
+    api.add( "get", "/data/{id}/tree", "tree", "Returns the allocations matching the filter as a tree of backtraces", |op| {
+        op.query::< protocol::AllocFilter >()
+            .query::< protocol::CustomFilter >()
+            .query::< protocol::BacktraceFormat >()
+            .any_json();
+    });
+
+    api.add( "get", "/data/{id}/mmaps", "mmaps", "Returns the `mmap` and `munmap` calls", |op| {
+        op.query::< protocol::MmapFilter >()
+            .query::< protocol::BacktraceFormat >()
+            .json::< protocol::ResponseMmaps< Vec< protocol::MmapOperation< 'static > > > >()
+            .or_table();
+    });
+
+    api.add( "get", "/data/{id}/regions", "regions", "Returns the address ranges occupied by the allocations matching the filter", |op| {
+        op.query::< protocol::AllocFilter >()
+            .query::< protocol::CustomFilter >()
+            .json::< protocol::ResponseRegions< Vec< [u64; 2] > > >();
+    });
+
+    api.add( "get", "/data/{id}/mallopts", "mallopts", "Returns the `mallopt` calls", |op| {
+        op.query::< protocol::BacktraceFormat >().json::< Vec< protocol::Mallopt< 'static > > >().or_table();
+    });
+
+    api.add( "get", "/data/{id}/churn", "churn", "Returns the backtraces which allocate the most, and how many of those allocations are short lived", |op| {
+        op.query::< protocol::AllocFilter >()
+            .query::< protocol::CustomFilter >()
+            .query::< protocol::BacktraceFormat >()
+            .query::< protocol::RequestChurn >()
+            .json::< protocol::ResponseChurn< 'static > >();
+    });
+
+    api.add( "get", "/data/{id}/growth_patterns", "growth_patterns", "Returns the backtraces whose allocations are repeatedly reallocated to grow", |op| {
+        op.query::< protocol::AllocFilter >()
+            .query::< protocol::CustomFilter >()
+            .query::< protocol::BacktraceFormat >()
+            .query::< protocol::RequestGrowthGroups >()
+            .json::< protocol::ResponseGrowthGroups< 'static > >();
+    });
+
+    api.add( "get", "/data/{id}/slack", "slack", "Returns how much memory is wasted due to rounding up of the allocation sizes", |op| {
+        op.query::< protocol::AllocFilter >()
+            .query::< protocol::CustomFilter >()
+            .query::< protocol::RequestSlack >()
+            .json::< protocol::ResponseSlack >();
+    });
+
+    api.add( "get", "/data/{id}/thread_matrix", "thread_matrix", "Returns how many allocations were freed on a different thread than they were allocated on", |op| {
+        op.query::< protocol::AllocFilter >()
+            .query::< protocol::CustomFilter >()
+            .query::< protocol::RequestThreadMatrix >()
+            .json::< protocol::ResponseThreadMatrix >();
+    });
+
+    let exports: &[(&str, &str, &str, &str)] = &[
+        ("/data/{id}/export/flamegraph", "export_flamegraph", "Exports the allocations matching the filter as a flamegraph", "image/svg+xml"),
+        ("/data/{id}/export/flamegraph.pl", "export_flamegraph_pl", "Exports the allocations matching the filter in a format accepted by `flamegraph.pl`", "application/octet-stream"),
+        ("/data/{id}/churn/flamegraph", "churn_flamegraph", "Exports the short lived allocations matching the filter as a flamegraph weighted by their count", "image/svg+xml"),
+        ("/data/{id}/export/heaptrack", "export_heaptrack", "Exports the allocations matching the filter in a format accepted by heaptrack", "application/octet-stream"),
+        ("/data/{id}/export/replay", "export_replay", "Exports the allocations matching the filter in a format which can be replayed", "application/octet-stream")
+    ];
+
+    for &(path, operation_id, summary, mime) in exports {
+        api.add( "get", path, operation_id, summary, |op| {
+            op.query::< protocol::AllocFilter >()
+                .query::< protocol::CustomFilter >()
+                .content( mime );
+        });
+    }
+
+    api.add( "get", "/data/{id}/allocation_ascii_tree", "allocation_ascii_tree", "Returns the allocations matching the filter as a tree of backtraces in plain text", |op| {
+        op.query::< protocol::AllocFilter >()
+            .query::< protocol::CustomFilter >()
+            .content( "text/plain" );
+    });
+
+    api.add( "get", "/data/{id}/dynamic_constants", "dynamic_constants", "Returns the dynamically allocated constants", |op| {
+        op.any_json();
+    });
+
+    api.add( "get", "/data/{id}/dynamic_statics", "dynamic_statics", "Returns the dynamically allocated statics", |op| {
+        op.any_json();
+    });
+
+    api.add( "get", "/data/{id}/dynamic_constants_ascii_tree", "dynamic_constants_ascii_tree", "Returns the dynamically allocated constants in plain text", |op| {
+        op.content( "text/plain" );
+    });
+
+    api.add( "get", "/data/{id}/dynamic_statics_ascii_tree", "dynamic_statics_ascii_tree", "Returns the dynamically allocated statics in plain text", |op| {
+        op.content( "text/plain" );
+    });
+
+    api.add( "get", "/data/{id}/filter_to_script", "filter_to_script", "Converts the filter into an equivalent script", |op| {
+        op.query::< protocol::AllocFilter >()
+            .query::< protocol::CustomFilter >()
+            .any_json();
+    });
+
+    api.add( "post", "/data/{id}/execute_script", "execute_script", "Executes a script and waits until it finishes", |op| {
+        op.body( "text/plain" ).any_json();
+    });
+
+    api.add( "post", "/data/{id}/script_jobs", "start_script_job", "Starts executing a script in the background", |op| {
+        op.body( "text/plain" ).json::< protocol::ResponseScriptJobStarted >();
+    });
+
+    api.add( "get", "/script_jobs/{job_id}", "script_job", "Returns the status and the output of a script started in the background", |op| {
+        op.query::< protocol::RequestScriptJob >().any_json();
+    });
+
+    api.add( "delete", "/script_jobs/{job_id}", "cancel_script_job", "Cancels a script started in the background", |_| {} );
+
+    api.add( "get", "/data/{id}/script_files/{hash}/{filename}", "script_files", "Returns a file generated by a script", |op| {
+        op.content( "application/octet-stream" );
+    });
+
+    api.add( "post", "/data/{id}/jobs/{kind}", "submit_job", "Starts an expensive query in the background, or returns an already existing job for the same query", |op| {
+        op.query::< protocol::AllocFilter >()
+            .query::< protocol::CustomFilter >()
+            .json::< protocol::ResponseJob >();
+    });
+
+    api.add( "get", "/jobs/{job_id}", "job", "Returns the status of a job", |op| {
+        op.json::< protocol::ResponseJob >();
+    });
+
+    api.add( "delete", "/jobs/{job_id}", "cancel_job", "Cancels a job", |_| {} );
+
+    api.add( "get", "/jobs/{job_id}/result", "job_result", "Returns the result of a finished job, in the same format as the corresponding endpoint", |op| {
+        op.content( "application/octet-stream" );
+    });
+
+    api.add( "get", "/jobs/{job_id}/events", "job_events", "Streams the status of a job as newline delimited JSON until it finishes", |op| {
+        op.content( "application/x-ndjson" );
+    });
+
+    api.add( "get", "/data/{id}/events", "data_events", "Streams new timeline points, new allocation groups, and the progress of jobs and loading as server-sent events; the auth token can also be passed through the `token` query parameter", |op| {
+        op.content( "text/event-stream" );
+    });
+
+    api.add( "get", "/data/{id}/filters", "filters", "Lists the filters saved for a data file", |op| {
+        op.json::< Vec< protocol::SavedFilter > >();
+    });
+
+    api.add( "post", "/data/{id}/filters", "save_filter", "Saves a filter under a name", |op| {
+        op.json_body::< protocol::RequestSaveFilter >().json::< protocol::SavedFilter >();
+    });
+
+    api.add( "delete", "/filters/{filter_id}", "delete_filter", "Deletes a saved filter", |_| {} );
+
+    api.add( "get", "/data/{id}/notes", "notes", "Lists the notes attached to a data file", |op| {
+        op.json::< Vec< protocol::Note > >();
+    });
+
+    api.add( "post", "/data/{id}/notes", "add_note", "Attaches a note to a backtrace or a time range", |op| {
+        op.json_body::< protocol::RequestAddNote >().json::< protocol::Note >();
+    });
+
+    api.add( "delete", "/notes/{note_id}", "delete_note", "Deletes a note", |_| {} );
+
+    api.add( "post", "/data/{id}/permalinks", "create_permalink", "Creates a link to a page of the web UI which stays valid across restarts of the server", |op| {
+        op.json_body::< protocol::RequestCreatePermalink >().json::< protocol::Permalink >();
+    });
+
+    api.add( "get", "/permalinks/{token}", "permalink", "Redirects to the page of the web UI the permalink points to", |_| {} );
+
+    let schemas = api.gen.take_definitions();
+    json!({
+        "openapi": "3.0.0",
+        "info": {
+            "title": "bytehound",
+            "version": env!( "CARGO_PKG_VERSION" )
+        },
+        "paths": api.paths,
+        "components": {
+            "schemas": schemas
+        }
+    })
+}
+
+#[test]
+fn test_generate() {
+    let api = generate();
+    let allocations = &api[ "paths" ][ "/data/{id}/allocations" ][ "get" ];
+    let parameters = allocations[ "parameters" ].as_array().unwrap();
+    assert!( parameters.iter().any( |parameter| parameter[ "name" ] == "id" && parameter[ "in" ] == "path" ) );
+    assert!( parameters.iter().any( |parameter| parameter[ "name" ] == "size_min" && parameter[ "in" ] == "query" ) );
+    assert!( parameters.iter().any( |parameter| parameter[ "name" ] == "sort_by" ) );
+
+    let schema = &allocations[ "responses" ][ "200" ][ "content" ][ "application/json" ][ "schema" ];
+    let name = schema[ "$ref" ].as_str().unwrap().rsplit( '/' ).next().unwrap();
+    assert!( api[ "components" ][ "schemas" ][ name ][ "properties" ][ "total_count" ].is_object() );
+    assert!( allocations[ "responses" ][ "200" ][ "content" ][ "text/csv" ].is_object() );
+    assert!( parameters.iter().any( |parameter| parameter[ "name" ] == "format" ) );
+
+    let timeline = api[ "paths" ][ "/data/{id}/timeline" ][ "get" ][ "parameters" ].as_array().unwrap();
+    assert_eq!( timeline.iter().filter( |parameter| parameter[ "name" ] == "from" ).count(), 1 );
+
+    let kind = &api[ "paths" ][ "/data/{id}/jobs/{kind}" ][ "post" ][ "parameters" ][ 1 ];
+    assert_eq!( kind[ "name" ], "kind" );
+    assert!( kind[ "schema" ][ "enum" ].as_array().unwrap().iter().any( |kind| kind == "allocation_groups" ) );
+}
@@ -4,17 +4,32 @@ use std::str::FromStr;
 use std::fmt;
 
 use serde::Serialize;
+use schemars::JsonSchema;
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, Metadata, Schema, SchemaObject};
 use cli_core::Timestamp;
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, Debug, Hash)]
+/// Describes a type which is deserialized from a string in a custom format.
+fn string_schema( description: &str ) -> Schema {
+    SchemaObject {
+        instance_type: Some( InstanceType::String.into() ),
+        metadata: Some( Box::new( Metadata {
+            description: Some( description.into() ),
+            .. Metadata::default()
+        })),
+        .. SchemaObject::default()
+    }.into()
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, Debug, Hash, JsonSchema)]
 #[serde(transparent)]
 pub struct Secs( u64 );
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, Debug, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, Debug, Hash, JsonSchema)]
 #[serde(transparent)]
 pub struct MSecs( u64 );
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, Debug, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, Debug, Hash, JsonSchema)]
 #[serde(transparent)]
 pub struct FractNanos( u32 );
 
@@ -46,7 +61,7 @@ impl From< Timestamp > for FractNanos {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, JsonSchema)]
 pub struct Timeval {
     pub secs: Secs,
     pub fract_nsecs: FractNanos
@@ -62,7 +77,13 @@ impl From< Timestamp > for Timeval {
     }
 }
 
-#[derive(Serialize)]
+impl fmt::Display for Timeval {
+    fn fmt( &self, fmt: &mut fmt::Formatter ) -> fmt::Result {
+        write!( fmt, "{}.{:06}", self.secs.0, self.fract_nsecs.0 / 1000 )
+    }
+}
+
+#[derive(Serialize, JsonSchema)]
 pub struct ResponseMetadata {
     pub id: String,
     pub executable: String,
@@ -73,10 +94,12 @@ pub struct ResponseMetadata {
     pub runtime: Timeval,
     pub unique_backtrace_count: u64,
     pub maximum_backtrace_depth: u32,
-    pub timestamp: Timeval
+    pub timestamp: Timeval,
+    pub pid: u32,
+    pub parent_pid: Option< u32 >
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct ResponseTimeline {
     pub xs: Vec< u64 >,
     pub size_delta: Vec< i64 >,
@@ -84,16 +107,17 @@ pub struct ResponseTimeline {
     pub allocated_size: Vec< u64 >,
     pub allocated_count: Vec< u64 >,
     pub allocations: Vec< u32 >,
-    pub deallocations: Vec< u32 >
+    pub deallocations: Vec< u32 >,
+    pub allocated_slack: Vec< u64 >
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct ResponseFragmentationTimeline {
     pub xs: Vec< u64 >,
     pub fragmentation: Vec< u64 >
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct Frame< 'a > {
     pub address: u64,
     pub address_s: String,
@@ -113,12 +137,12 @@ pub struct Frame< 'a > {
     pub is_inline: bool
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct ResponseBacktrace< 'a > {
     pub frames: Vec< Frame< 'a > >
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct Deallocation< 'a > {
     pub timestamp: Timeval,
     pub thread: u32,
@@ -128,7 +152,7 @@ pub struct Deallocation< 'a > {
     pub backtrace: Option< Vec< Frame< 'a > > >
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct Allocation< 'a > {
     pub id: u64,
     pub address: u64,
@@ -154,7 +178,7 @@ pub struct Allocation< 'a > {
     pub chain_length: u32,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct AllocationGroupData {
     pub size: u64,
     pub min_size: u64,
@@ -168,6 +192,9 @@ pub struct AllocationGroupData {
     pub interval: Timeval,
     pub leaked_count: u64,
     pub allocated_count: u64,
+    pub cross_thread_freed_count: u64,
+    pub cross_thread_ratio: f32,
+    pub slack: u64,
     pub graph_preview_url: Option< String >,
     pub graph_url: Option< String >,
     pub max_total_usage_first_seen_at: Option< Timeval >,
@@ -175,7 +202,7 @@ pub struct AllocationGroupData {
     pub max_total_usage_first_seen_at_relative_p: Option< f32 >,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct AllocationGroup< 'a > {
     pub all: AllocationGroupData,
     pub only_matched: AllocationGroupData,
@@ -183,7 +210,7 @@ pub struct AllocationGroup< 'a > {
     pub backtrace: Vec< Frame< 'a > >
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct Mallopt< 'a > {
     pub timestamp: Timeval,
     pub thread: u32,
@@ -196,7 +223,7 @@ pub struct Mallopt< 'a > {
     pub result: i32
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub enum MmapOperation< 'a > {
     #[serde(rename = "mmap")]
     Mmap {
@@ -235,24 +262,24 @@ pub enum MmapOperation< 'a > {
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct ResponseAllocations< T: Serialize > {
     pub allocations: T,
     pub total_count: u64
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct ResponseAllocationGroups< T: Serialize > {
     pub allocations: T,
     pub total_count: u64
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct ResponseMmaps< T: Serialize > {
     pub operations: T
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct ResponseRegions< T: Serialize > {
     pub main_heap_start: u64,
     pub main_heap_end: u64,
@@ -261,13 +288,175 @@ pub struct ResponseRegions< T: Serialize > {
     pub regions: T
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct ResponseBacktraces< T: Serialize > {
     pub backtraces: T,
     pub total_count: u64
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug, Hash)]
+#[derive(Serialize, JsonSchema)]
+pub struct ChurnGroup< 'a > {
+    pub backtrace_id: u32,
+    pub backtrace: Vec< Frame< 'a > >,
+    pub allocated_count: u64,
+    pub allocated_size: u64,
+    pub short_lived_count: u64,
+    pub short_lived_size: u64,
+    pub allocations_per_second: f64,
+    pub short_lived_per_second: f64,
+    pub first_allocation: Timeval,
+    pub last_allocation: Timeval,
+    pub lifetime_histogram: Vec< u64 >
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ResponseChurn< 'a > {
+    pub lifetime_buckets: &'static [&'static str],
+    pub groups: Vec< ChurnGroup< 'a > >,
+    pub total_count: u64
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct SizeClassSlack {
+    pub usable_size: u64,
+    pub count: u64,
+    pub requested_size: u64,
+    pub min_requested_size: u64,
+    pub max_requested_size: u64,
+    pub slack: u64
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ResponseSlack {
+    pub requested_size: u64,
+    pub usable_size: u64,
+    pub slack: u64,
+    pub size_classes: Vec< SizeClassSlack >,
+    pub total_count: u64
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ThreadPair {
+    pub allocated_on: u32,
+    pub freed_on: u32,
+    pub count: u64,
+    pub size: u64
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ResponseThreadMatrix {
+    pub cross_thread_count: u64,
+    pub cross_thread_size: u64,
+    pub same_thread_count: u64,
+    pub same_thread_size: u64,
+    pub pairs: Vec< ThreadPair >,
+    pub total_count: u64
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ArenaHeap {
+    pub address: u64,
+    pub address_s: String,
+    pub first_used_at: Timeval,
+    pub last_used_at: Timeval,
+    pub mapped_at: Option< Timeval >,
+    pub unmapped_at: Option< Timeval >
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ArenaThread {
+    pub thread: u32,
+    pub allocated_count: u64,
+    pub allocated_size: u64
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct Arena {
+    pub id: u32,
+    pub is_main: bool,
+    pub heaps: Vec< ArenaHeap >,
+    pub threads: Vec< ArenaThread >,
+    pub allocated_count: u64,
+    pub allocated_size: u64
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ThreadArenas {
+    pub thread: u32,
+    pub arenas: Vec< u32 >
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ResponseArenas {
+    pub heap_max_size: u64,
+    pub arenas: Vec< Arena >,
+    pub threads: Vec< ThreadArenas >
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ArenaTimelineSeries {
+    pub id: u32,
+    pub usage: Vec< u64 >,
+    pub fragmentation: Vec< u64 >
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ResponseArenaTimeline {
+    pub xs: Vec< u64 >,
+    pub arenas: Vec< ArenaTimelineSeries >
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct SessionProcess {
+    pub id: String,
+    pub pid: u32,
+    pub parent_pid: Option< u32 >,
+    pub parent_id: Option< String >,
+    pub depth: u32,
+    pub executable: String,
+    pub started_at: Timeval,
+    pub finished_at: Timeval
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ResponseSession {
+    pub id: usize,
+    pub processes: Vec< SessionProcess >
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct SessionTimelineSeries {
+    pub id: String,
+    pub pid: u32,
+    pub usage: Vec< u64 >
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ResponseSessionTimeline {
+    pub xs: Vec< u64 >,
+    pub processes: Vec< SessionTimelineSeries >
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct GrowthGroup< 'a > {
+    pub backtrace_id: u32,
+    pub backtrace: Vec< Frame< 'a > >,
+    pub chain_count: u64,
+    pub avoidable_reallocations: u64,
+    pub copied_bytes: u64,
+    pub average_growth_factor: f64,
+    pub min_final_size: u64,
+    pub max_final_size: u64,
+    pub suggested_capacity: u64
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ResponseGrowthGroups< 'a > {
+    pub groups: Vec< GrowthGroup< 'a > >,
+    pub total_count: u64
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug, Hash, JsonSchema)]
 pub enum LifetimeFilter {
     #[serde(rename = "all")]
     All,
@@ -285,7 +474,7 @@ pub enum LifetimeFilter {
     OnlyWholeGroupLeaked
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug, Hash, JsonSchema)]
 pub enum MmapedFilter {
     #[serde(rename = "yes")]
     Yes,
@@ -293,7 +482,7 @@ pub enum MmapedFilter {
     No
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug, Hash, JsonSchema)]
 pub enum JemallocFilter {
     #[serde(rename = "yes")]
     Yes,
@@ -301,7 +490,7 @@ pub enum JemallocFilter {
     No
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug, Hash, JsonSchema)]
 pub enum ArenaFilter {
     #[serde(rename = "main")]
     Main,
@@ -309,7 +498,15 @@ pub enum ArenaFilter {
     NonMain
 }
 
-#[derive(Copy, Clone, Deserialize, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug, Hash, JsonSchema)]
+pub enum FreedOnThreadFilter {
+    #[serde(rename = "other")]
+    Other,
+    #[serde(rename = "same")]
+    Same
+}
+
+#[derive(Copy, Clone, Deserialize, Debug, JsonSchema)]
 pub enum AllocSortBy {
     #[serde(rename = "timestamp")]
     Timestamp,
@@ -319,7 +516,7 @@ pub enum AllocSortBy {
     Size
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug, Hash, JsonSchema)]
 pub enum AllocGroupsSortBy {
     #[serde(rename = "only_matched.min_timestamp")]
     MinTimestamp,
@@ -333,6 +530,8 @@ pub enum AllocGroupsSortBy {
     LeakedCount,
     #[serde(rename = "only_matched.size")]
     Size,
+    #[serde(rename = "only_matched.slack")]
+    Slack,
 
     #[serde(rename = "all.min_timestamp")]
     GlobalMinTimestamp,
@@ -346,17 +545,27 @@ pub enum AllocGroupsSortBy {
     GlobalLeakedCount,
     #[serde(rename = "all.size")]
     GlobalSize,
+    #[serde(rename = "all.slack")]
+    GlobalSlack,
     #[serde(rename = "all.max_total_usage_first_seen_at")]
     GlobalMaxTotalUsageFirstSeenAt,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug, Hash, JsonSchema)]
+pub enum ChurnSortBy {
+    #[serde(rename = "allocation_rate")]
+    AllocationRate,
+    #[serde(rename = "short_lived_count")]
+    ShortLivedCount
+}
+
 impl Default for AllocSortBy {
     fn default() -> Self {
         AllocSortBy::Timestamp
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug, Hash)]
+#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug, Hash, JsonSchema)]
 pub enum Order {
     #[serde(rename = "asc")]
     Asc,
@@ -470,6 +679,16 @@ impl< 'de > serde::Deserialize< 'de > for Interval {
     }
 }
 
+impl JsonSchema for Interval {
+    fn schema_name() -> String {
+        "Interval".into()
+    }
+
+    fn json_schema( _: &mut SchemaGenerator ) -> Schema {
+        string_schema( "A time interval, e.g. `1h30m`, `10s` or `500ms`; a number without a unit is in seconds." )
+    }
+}
+
 pub trait TimevalKind {
     fn is_end_of_the_range() -> bool;
     fn is_unitless_absolute() -> bool;
@@ -565,6 +784,24 @@ impl< 'de, K > serde::Deserialize< 'de > for TimestampFilter< K > where K: Timev
     }
 }
 
+impl< K > JsonSchema for TimestampFilter< K > where K: TimevalKind {
+    fn schema_name() -> String {
+        if K::is_unitless_absolute() {
+            "TimestampOrPercentage".into()
+        } else {
+            "IntervalOrPercentage".into()
+        }
+    }
+
+    fn json_schema( _: &mut SchemaGenerator ) -> Schema {
+        if K::is_unitless_absolute() {
+            string_schema( "Either a number of milliseconds since the UNIX epoch, or a percentage (e.g. `50%`) of the whole runtime." )
+        } else {
+            string_schema( "Either a time interval (e.g. `1h30m`), or a percentage (e.g. `50%`) of the whole runtime." )
+        }
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
 pub enum NumberOrPercentage {
     Absolute( u32 ),
@@ -611,18 +848,206 @@ impl< 'de > serde::Deserialize< 'de > for NumberOrPercentage {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Deserialize, Debug, Hash)]
+impl JsonSchema for NumberOrPercentage {
+    fn schema_name() -> String {
+        "NumberOrPercentage".into()
+    }
+
+    fn json_schema( _: &mut SchemaGenerator ) -> Schema {
+        string_schema( "Either an absolute number, or a percentage (e.g. `50%`)." )
+    }
+}
+
+/// A comma separated list of IDs, e.g. `1,5,10`.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub struct IdList( pub Vec< u32 > );
+
+impl FromStr for IdList {
+    type Err = std::num::ParseIntError;
+
+    fn from_str( string: &str ) -> Result< Self, Self::Err > {
+        string.split( ',' )
+            .map( |id| id.trim() )
+            .filter( |id| !id.is_empty() )
+            .map( |id| id.parse() )
+            .collect::< Result< Vec< _ >, _ > >()
+            .map( IdList )
+    }
+}
+
+impl< 'de > serde::Deserialize< 'de > for IdList {
+    fn deserialize< D >( deserializer: D ) -> Result< Self, D::Error >
+        where D: serde::Deserializer< 'de >
+    {
+        struct Visitor;
+        impl< 'de > serde::de::Visitor< 'de > for Visitor {
+            type Value = IdList;
+
+            fn expecting( &self, formatter: &mut fmt::Formatter ) -> fmt::Result {
+                write!( formatter, "a comma separated list of IDs" )
+            }
+
+            fn visit_u64< E >( self, value: u64 ) -> Result< Self::Value, E >
+                where E: serde::de::Error
+            {
+                if value > std::u32::MAX as u64 {
+                    return Err( E::custom( "not a valid ID" ) );
+                }
+
+                Ok( IdList( vec![ value as u32 ] ) )
+            }
+
+            fn visit_str< E >( self, value: &str ) -> Result< Self::Value, E >
+                where E: serde::de::Error
+            {
+                value.parse().map_err( |_| E::custom( "not a valid list of IDs" ) )
+            }
+        }
+
+        deserializer.deserialize_any( Visitor )
+    }
+}
+
+impl JsonSchema for IdList {
+    fn schema_name() -> String {
+        "IdList".into()
+    }
+
+    fn json_schema( _: &mut SchemaGenerator ) -> Schema {
+        string_schema( "A comma separated list of IDs, e.g. `1,5,10`." )
+    }
+}
+
+/// A boolean combination of allocation filters.
+///
+/// It's written as JSON where every leaf is a filter in the same format as the query string
+/// of the allocation endpoints, e.g. `{"or": ["size_min=1048576", {"not": "lifetime=only_leaked"}]}`.
+#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+pub enum FilterExpression {
+    Filter( Box< AllocFilter > ),
+    And( Vec< FilterExpression > ),
+    Or( Vec< FilterExpression > ),
+    Not( Box< FilterExpression > )
+}
+
+impl FilterExpression {
+    fn from_json( value: &serde_json::Value ) -> Result< Self, String > {
+        use serde_json::Value;
+
+        let expression = match *value {
+            Value::String( ref query ) => {
+                let filter: AllocFilter = serde_urlencoded::from_str( query ).map_err( |error| format!( "invalid filter '{}': {}", query, error ) )?;
+                FilterExpression::Filter( Box::new( filter ) )
+            },
+            Value::Object( ref object ) if object.len() == 1 => {
+                let (operator, operand) = object.iter().next().unwrap();
+                match (operator.as_str(), operand) {
+                    ("and", &Value::Array( ref list )) => {
+                        FilterExpression::And( list.iter().map( FilterExpression::from_json ).collect::< Result< _, _ > >()? )
+                    },
+                    ("or", &Value::Array( ref list )) => {
+                        FilterExpression::Or( list.iter().map( FilterExpression::from_json ).collect::< Result< _, _ > >()? )
+                    },
+                    ("not", operand) => {
+                        FilterExpression::Not( Box::new( FilterExpression::from_json( operand )? ) )
+                    },
+                    _ => return Err( format!( "invalid filter expression: {}", value ) )
+                }
+            },
+            _ => return Err( format!( "invalid filter expression: {}", value ) )
+        };
+
+        Ok( expression )
+    }
+}
+
+impl FromStr for FilterExpression {
+    type Err = String;
+
+    fn from_str( string: &str ) -> Result< Self, Self::Err > {
+        let value: serde_json::Value = serde_json::from_str( string ).map_err( |error| format!( "invalid filter expression: {}", error ) )?;
+        FilterExpression::from_json( &value )
+    }
+}
+
+impl< 'de > serde::Deserialize< 'de > for FilterExpression {
+    fn deserialize< D >( deserializer: D ) -> Result< Self, D::Error >
+        where D: serde::Deserializer< 'de >
+    {
+        struct Visitor;
+        impl< 'de > serde::de::Visitor< 'de > for Visitor {
+            type Value = FilterExpression;
+
+            fn expecting( &self, formatter: &mut fmt::Formatter ) -> fmt::Result {
+                write!( formatter, "filter expression" )
+            }
+
+            fn visit_str< E >( self, value: &str ) -> Result< Self::Value, E >
+                where E: serde::de::Error
+            {
+                value.parse().map_err( E::custom )
+            }
+        }
+
+        deserializer.deserialize_any( Visitor )
+    }
+}
+
+impl JsonSchema for FilterExpression {
+    fn schema_name() -> String {
+        "FilterExpression".into()
+    }
+
+    fn json_schema( _: &mut SchemaGenerator ) -> Schema {
+        string_schema(
+            "A boolean combination of filters written as JSON, where every leaf is a filter in the same format as this query string, \
+            e.g. `{\"or\": [\"size_min=1048576\", {\"not\": \"lifetime=only_leaked\"}]}`; it's combined with the rest of the filter."
+        )
+    }
+}
+
+#[test]
+fn test_parse_filter_expression() {
+    let list: IdList = "1, 5,10".parse().unwrap();
+    assert_eq!( list.0, vec![ 1, 5, 10 ] );
+    assert!( "1,x".parse::< IdList >().is_err() );
+
+    let expression: FilterExpression = r#"{"or": ["size_min=1024&backtraces=1,2", {"not": "lifetime=only_leaked"}]}"#.parse().unwrap();
+    match expression {
+        FilterExpression::Or( ref list ) => {
+            match list[ 0 ] {
+                FilterExpression::Filter( ref filter ) => {
+                    assert_eq!( filter.size_min, Some( 1024 ) );
+                    assert_eq!( filter.backtraces, Some( IdList( vec![ 1, 2 ] ) ) );
+                },
+                _ => panic!()
+            }
+
+            match list[ 1 ] {
+                FilterExpression::Not( ref inner ) => assert!( matches!( **inner, FilterExpression::Filter( .. ) ) ),
+                _ => panic!()
+            }
+        },
+        _ => panic!()
+    }
+
+    assert!( r#"{"xor": []}"#.parse::< FilterExpression >().is_err() );
+    assert!( r#"["size_min=1"]"#.parse::< FilterExpression >().is_err() );
+    assert!( r#""size_min=foo""#.parse::< FilterExpression >().is_err() );
+}
+
+#[derive(Clone, PartialEq, Eq, Deserialize, Debug, Hash, JsonSchema)]
 pub struct MmapFilter {
     pub size_min: Option< u64 >,
     pub size_max: Option< u64 >,
 }
 
-#[derive(Clone, PartialEq, Eq, Deserialize, Debug, Hash)]
+#[derive(Clone, PartialEq, Eq, Deserialize, Debug, Hash, JsonSchema)]
 pub struct CustomFilter {
     pub custom_filter: Option< String >
 }
 
-#[derive(Clone, PartialEq, Eq, Deserialize, Debug, Hash)]
+#[derive(Clone, PartialEq, Eq, Deserialize, Debug, Hash, JsonSchema)]
 pub struct AllocFilter {
     pub from: Option< TimestampFilter< OffsetMin > >,
     pub to: Option< TimestampFilter< OffsetMax > >,
@@ -639,16 +1064,19 @@ pub struct AllocFilter {
     pub lifetime_max: Option< Interval >,
     pub backtrace_depth_min: Option< u32 >,
     pub backtrace_depth_max: Option< u32 >,
-    pub backtraces: Option< u32 >, // TODO: Support multiple.
-    pub deallocation_backtraces: Option< u32 >, // TODO: Support multiple.
+    pub backtraces: Option< IdList >,
+    pub deallocation_backtraces: Option< IdList >,
+    pub threads: Option< IdList >,
     pub mmaped: Option< MmapedFilter >,
     pub jemalloc: Option< JemallocFilter >,
     pub arena: Option< ArenaFilter >,
+    pub arena_id: Option< u32 >,
+    pub freed_on_thread: Option< FreedOnThreadFilter >,
     pub function_regex: Option< String >,
     pub source_regex: Option< String >,
     pub negative_function_regex: Option< String >,
     pub negative_source_regex: Option< String >,
-    pub marker: Option< u32 >,
+    pub marker: Option< IdList >,
     pub group_interval_min: Option< TimestampFilter< Interval > >,
     pub group_interval_max: Option< TimestampFilter< Interval > >,
     pub group_max_total_usage_first_seen_min: Option< TimestampFilter< OffsetMin > >,
@@ -663,9 +1091,10 @@ pub struct AllocFilter {
     pub chain_lifetime_max: Option< Interval >,
     pub position_in_chain_min: Option< u32 >,
     pub position_in_chain_max: Option< u32 >,
+    pub expression: Option< FilterExpression >,
 }
 
-#[derive(Clone, PartialEq, Eq, Deserialize, Debug, Hash)]
+#[derive(Clone, PartialEq, Eq, Deserialize, Debug, Hash, JsonSchema)]
 pub struct BacktraceFilter {
     pub backtrace_depth_min: Option< u32 >,
     pub backtrace_depth_max: Option< u32 >,
@@ -675,12 +1104,49 @@ pub struct BacktraceFilter {
     pub negative_source_regex: Option< String >,
 }
 
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Debug, JsonSchema)]
 pub struct BacktraceFormat {
     pub strip_template_args: Option< bool >
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, JsonSchema)]
+pub struct ResponseLoadStarted {
+    pub id: u64
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ResponseScriptJobStarted {
+    pub id: u64
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ResponseJob {
+    pub id: u64,
+    pub kind: &'static str,
+    pub status: &'static str,
+    pub progress: Option< f64 >,
+    pub elapsed: f64,
+    pub error: Option< String >
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct LoadingProgress {
+    pub id: u64,
+    pub name: String,
+    /// How many bytes of the file were already processed.
+    pub loaded: u64,
+    pub total: u64,
+    /// Set if the loading has failed.
+    pub error: Option< String >
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct RequestSessionTimeline {
+    /// A comma separated list of PIDs of the processes to include; all of them are included by default.
+    pub pids: Option< String >
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
 pub struct RequestAllocations {
     pub skip: Option< u64 >,
     pub count: Option< u32 >,
@@ -689,7 +1155,7 @@ pub struct RequestAllocations {
     pub order: Option< Order >
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, JsonSchema)]
 pub struct RequestAllocationGroups {
     pub skip: Option< u64 >,
     pub count: Option< u32 >,
@@ -699,3 +1165,154 @@ pub struct RequestAllocationGroups {
 
     pub generate_graphs: Option< bool >
 }
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct RequestChurn {
+    pub skip: Option< u64 >,
+    pub count: Option< u32 >,
+
+    pub sort_by: Option< ChurnSortBy >,
+    pub short_lived_threshold: Option< Interval >
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct RequestGrowthGroups {
+    pub skip: Option< u64 >,
+    pub count: Option< u32 >
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct RequestSlack {
+    pub skip: Option< u64 >,
+    pub count: Option< u32 >
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct RequestThreadMatrix {
+    pub skip: Option< u64 >,
+    pub count: Option< u32 >,
+    pub only_cross_thread: Option< bool >
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct RequestLoad {
+    /// A path to a data file on the machine on which the server is running;
+    /// it has to be inside one of the directories from which loading is allowed.
+    pub path: String
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct RequestUpload {
+    /// The name under which the uploaded file will be shown while it's being loaded.
+    pub name: Option< String >
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct RequestScriptJob {
+    /// Only return the output starting from this index; useful when polling a running job.
+    pub since: Option< usize >
+}
+
+/// A filter saved under a name for a given data file.
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct SavedFilter {
+    pub id: u64,
+    pub data_id: String,
+    pub name: String,
+    /// The filter in the same format as the query string accepted by the allocation endpoints.
+    pub query: String,
+    /// Seconds since the UNIX epoch.
+    pub created_at: u64
+}
+
+/// A note attached either to a backtrace or to a time range of a given data file.
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct Note {
+    pub id: u64,
+    pub data_id: String,
+    pub backtrace_id: Option< u32 >,
+    pub from: Option< String >,
+    pub to: Option< String >,
+    pub text: String,
+    /// Seconds since the UNIX epoch.
+    pub created_at: u64
+}
+
+/// A link to a given page of the web UI which stays valid when the server is restarted.
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct Permalink {
+    pub token: String,
+    pub data_id: String,
+    pub page: String,
+    pub query: String,
+    /// Seconds since the UNIX epoch.
+    pub created_at: u64
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct RequestSaveFilter {
+    /// Saving a filter with the same name as an existing one replaces it.
+    pub name: String,
+    pub query: String
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct RequestAddNote {
+    pub text: String,
+    pub backtrace_id: Option< u32 >,
+    /// The beginning of the time range, in the same format as the `from` of the allocation filter.
+    pub from: Option< String >,
+    pub to: Option< String >
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct RequestCreatePermalink {
+    /// The page of the web UI to link to, e.g. `allocations`.
+    pub page: String,
+    #[serde(default)]
+    pub query: String
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct RequestTimeline {
+    /// The beginning of the time range to return; unlike for the allocation filter this doesn't
+    /// exclude the allocations made before it, which still count towards the memory usage.
+    pub from: Option< TimestampFilter< OffsetMin > >,
+    /// The end of the time range to return.
+    pub to: Option< TimestampFilter< OffsetMax > >,
+    /// The maximum number of points to return; 1000 by default.
+    pub max_points: Option< u32 >
+}
+
+/// Sent through the live event stream when allocations with a new backtrace appear.
+#[derive(Serialize, JsonSchema)]
+pub struct NewAllocationGroup {
+    pub backtrace_id: u32,
+    pub allocated_count: u64,
+    pub size: u64,
+    pub first_allocation: Timeval
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Deserialize, Debug, JsonSchema)]
+pub enum ListFormat {
+    #[serde(rename = "json")]
+    Json,
+    #[serde(rename = "csv")]
+    Csv,
+    #[serde(rename = "tsv")]
+    Tsv
+}
+
+impl Default for ListFormat {
+    fn default() -> Self {
+        ListFormat::Json
+    }
+}
+
+#[derive(Deserialize, Debug, JsonSchema)]
+pub struct RequestListFormat {
+    /// The format of the response; `csv` and `tsv` return the whole list as a flat table.
+    pub format: Option< ListFormat >,
+    /// How many of the innermost frames of every backtrace are included in a table; 5 by default.
+    pub frames: Option< u32 >
+}
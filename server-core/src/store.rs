@@ -0,0 +1,212 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::protocol::{Note, Permalink, SavedFilter};
+
+#[derive(Default, Serialize, Deserialize)]
+struct Contents {
+    next_id: u64,
+    filters: Vec< SavedFilter >,
+    notes: Vec< Note >,
+    permalinks: Vec< Permalink >
+}
+
+/// Keeps the saved filters, notes and permalinks; if it was opened with a path
+/// then every change is immediately written to that file.
+pub struct Store {
+    path: Option< PathBuf >,
+    contents: Contents
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since( UNIX_EPOCH ).map( |duration| duration.as_secs() ).unwrap_or( 0 )
+}
+
+impl Store {
+    pub fn in_memory() -> Self {
+        Store {
+            path: None,
+            contents: Contents::default()
+        }
+    }
+
+    pub fn open( path: impl Into< PathBuf > ) -> io::Result< Self > {
+        let path = path.into();
+        let contents = match fs::read( &path ) {
+            Ok( blob ) => {
+                serde_json::from_slice( &blob ).map_err( |error| {
+                    io::Error::new( io::ErrorKind::InvalidData, format!( "failed to parse {:?}: {}", path, error ) )
+                })?
+            },
+            Err( ref error ) if error.kind() == io::ErrorKind::NotFound => Contents::default(),
+            Err( error ) => return Err( error )
+        };
+
+        Ok( Store {
+            path: Some( path ),
+            contents
+        })
+    }
+
+    fn save( &self ) -> io::Result< () > {
+        let path = match self.path {
+            Some( ref path ) => path,
+            None => return Ok(())
+        };
+
+        // Write into a temporary file first so that a crash doesn't leave a truncated store behind.
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push( ".tmp" );
+
+        let blob = serde_json::to_vec_pretty( &self.contents ).map_err( |error| io::Error::new( io::ErrorKind::Other, error ) )?;
+        fs::write( &tmp_path, blob )?;
+        fs::rename( &tmp_path, path )
+    }
+
+    fn next_id( &mut self ) -> u64 {
+        self.contents.next_id += 1;
+        self.contents.next_id
+    }
+
+    pub fn filters( &self, data_id: &str ) -> Vec< SavedFilter > {
+        self.contents.filters.iter().filter( |filter| filter.data_id == data_id ).cloned().collect()
+    }
+
+    pub fn add_filter( &mut self, data_id: &str, name: &str, query: &str ) -> io::Result< SavedFilter > {
+        let filter = SavedFilter {
+            id: self.next_id(),
+            data_id: data_id.to_owned(),
+            name: name.to_owned(),
+            query: query.to_owned(),
+            created_at: now()
+        };
+
+        self.contents.filters.retain( |existing| existing.data_id != data_id || existing.name != name );
+        self.contents.filters.push( filter.clone() );
+        self.save()?;
+
+        Ok( filter )
+    }
+
+    pub fn remove_filter( &mut self, id: u64 ) -> io::Result< bool > {
+        let length = self.contents.filters.len();
+        self.contents.filters.retain( |filter| filter.id != id );
+        if self.contents.filters.len() == length {
+            return Ok( false );
+        }
+
+        self.save()?;
+        Ok( true )
+    }
+
+    pub fn notes( &self, data_id: &str ) -> Vec< Note > {
+        self.contents.notes.iter().filter( |note| note.data_id == data_id ).cloned().collect()
+    }
+
+    pub fn add_note( &mut self, data_id: &str, backtrace_id: Option< u32 >, from: Option< String >, to: Option< String >, text: &str ) -> io::Result< Note > {
+        let note = Note {
+            id: self.next_id(),
+            data_id: data_id.to_owned(),
+            backtrace_id,
+            from,
+            to,
+            text: text.to_owned(),
+            created_at: now()
+        };
+
+        self.contents.notes.push( note.clone() );
+        self.save()?;
+
+        Ok( note )
+    }
+
+    pub fn remove_note( &mut self, id: u64 ) -> io::Result< bool > {
+        let length = self.contents.notes.len();
+        self.contents.notes.retain( |note| note.id != id );
+        if self.contents.notes.len() == length {
+            return Ok( false );
+        }
+
+        self.save()?;
+        Ok( true )
+    }
+
+    pub fn permalink( &self, token: &str ) -> Option< &Permalink > {
+        self.contents.permalinks.iter().find( |permalink| permalink.token == token )
+    }
+
+    /// Returns a permalink for the given page; the same link is reused if it was already created before.
+    pub fn add_permalink( &mut self, data_id: &str, page: &str, query: &str ) -> io::Result< Permalink > {
+        if let Some( permalink ) = self.contents.permalinks.iter().find( |permalink| {
+            permalink.data_id == data_id && permalink.page == page && permalink.query == query
+        }) {
+            return Ok( permalink.clone() );
+        }
+
+        let id = self.next_id();
+        let seed = format!( "{}\0{}\0{}\0{}\0{:?}", id, data_id, page, query, SystemTime::now() );
+        let mut token = format!( "{:x}", md5::compute( seed.as_bytes() ) );
+        token.truncate( 16 );
+
+        let permalink = Permalink {
+            token,
+            data_id: data_id.to_owned(),
+            page: page.to_owned(),
+            query: query.to_owned(),
+            created_at: now()
+        };
+
+        self.contents.permalinks.push( permalink.clone() );
+        self.save()?;
+
+        Ok( permalink )
+    }
+}
+
+#[test]
+fn test_store_in_memory() {
+    let mut store = Store::in_memory();
+    let a = store.add_filter( "a", "leaks", "lifetime=only_leaked" ).unwrap();
+    store.add_filter( "b", "leaks", "lifetime=only_leaked" ).unwrap();
+    let c = store.add_filter( "a", "leaks", "lifetime=only_leaked&size_min=1024" ).unwrap();
+
+    let filters = store.filters( "a" );
+    assert_eq!( filters.len(), 1 );
+    assert_eq!( filters[ 0 ].id, c.id );
+    assert!( !store.remove_filter( a.id ).unwrap() );
+    assert!( store.remove_filter( c.id ).unwrap() );
+    assert!( store.filters( "a" ).is_empty() );
+
+    let first = store.add_permalink( "a", "allocations", "size_min=1" ).unwrap();
+    let second = store.add_permalink( "a", "allocations", "size_min=1" ).unwrap();
+    let third = store.add_permalink( "a", "allocations", "size_min=2" ).unwrap();
+    assert_eq!( first.token, second.token );
+    assert_ne!( first.token, third.token );
+}
+
+#[test]
+fn test_store_persistence() {
+    let path = std::env::temp_dir().join( format!( "bytehound-store-test-{}.json", std::process::id() ) );
+    let _ = fs::remove_file( &path );
+
+    let (note, permalink) = {
+        let mut store = Store::open( &path ).unwrap();
+        store.add_filter( "a", "leaks", "lifetime=only_leaked" ).unwrap();
+        let note = store.add_note( "a", Some( 10 ), None, None, "a known leak" ).unwrap();
+        let permalink = store.add_permalink( "a", "allocations", "lifetime=only_leaked" ).unwrap();
+        (note, permalink)
+    };
+
+    let mut store = Store::open( &path ).unwrap();
+    assert_eq!( store.filters( "a" )[ 0 ].name, "leaks" );
+    assert_eq!( store.notes( "a" )[ 0 ].text, "a known leak" );
+    assert_eq!( store.permalink( &permalink.token ).unwrap().query, "lifetime=only_leaked" );
+
+    // The IDs must not be reused after a restart.
+    let filter = store.add_filter( "a", "big", "size_min=1024" ).unwrap();
+    assert!( filter.id > note.id );
+
+    let _ = fs::remove_file( &path );
+}
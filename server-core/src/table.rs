@@ -0,0 +1,119 @@
+use std::fmt::{self, Write};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Separator {
+    Comma,
+    Tab
+}
+
+/// Writes tabular data as CSV (RFC 4180) or as tab separated values, one row at a time.
+pub struct TableWriter< W: Write > {
+    output: W,
+    separator: Separator,
+    is_first_cell: bool,
+    buffer: String
+}
+
+impl< W: Write > TableWriter< W > {
+    pub fn new( output: W, separator: Separator ) -> Self {
+        TableWriter {
+            output,
+            separator,
+            is_first_cell: true,
+            buffer: String::new()
+        }
+    }
+
+    pub fn cell< T: fmt::Display >( &mut self, value: T ) -> fmt::Result {
+        self.buffer.clear();
+        write!( self.buffer, "{}", value )?;
+
+        if !self.is_first_cell {
+            match self.separator {
+                Separator::Comma => self.output.write_char( ',' )?,
+                Separator::Tab => self.output.write_char( '\t' )?
+            }
+        }
+        self.is_first_cell = false;
+
+        match self.separator {
+            Separator::Comma => {
+                if !self.buffer.contains( |ch| ch == ',' || ch == '"' || ch == '\n' || ch == '\r' ) {
+                    return self.output.write_str( &self.buffer );
+                }
+
+                self.output.write_char( '"' )?;
+                for (index, chunk) in self.buffer.split( '"' ).enumerate() {
+                    if index != 0 {
+                        self.output.write_str( "\"\"" )?;
+                    }
+                    self.output.write_str( chunk )?;
+                }
+                self.output.write_char( '"' )
+            },
+            Separator::Tab => {
+                // There's no way to escape these, so they're replaced.
+                for ch in self.buffer.chars() {
+                    match ch {
+                        '\t' | '\n' | '\r' => self.output.write_char( ' ' )?,
+                        ch => self.output.write_char( ch )?
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn empty_cell( &mut self ) -> fmt::Result {
+        self.cell( "" )
+    }
+
+    pub fn optional_cell< T: fmt::Display >( &mut self, value: Option< T > ) -> fmt::Result {
+        match value {
+            Some( value ) => self.cell( value ),
+            None => self.empty_cell()
+        }
+    }
+
+    pub fn cells< T: fmt::Display >( &mut self, cells: &[T] ) -> fmt::Result {
+        for cell in cells {
+            self.cell( cell )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn end_row( &mut self ) -> fmt::Result {
+        self.is_first_cell = true;
+        self.output.write_str( "\r\n" )
+    }
+}
+
+#[test]
+fn test_table_writer_csv() {
+    let mut output = String::new();
+    let mut table = TableWriter::new( &mut output, Separator::Comma );
+    table.cells( &["id", "name"] ).unwrap();
+    table.end_row().unwrap();
+    table.cell( 1 ).unwrap();
+    table.cell( "foo, \"bar\"" ).unwrap();
+    table.end_row().unwrap();
+    table.cell( 2 ).unwrap();
+    table.optional_cell( None as Option< &str > ).unwrap();
+    table.end_row().unwrap();
+
+    assert_eq!( output, "id,name\r\n1,\"foo, \"\"bar\"\"\"\r\n2,\r\n" );
+}
+
+#[test]
+fn test_table_writer_tsv() {
+    let mut output = String::new();
+    let mut table = TableWriter::new( &mut output, Separator::Tab );
+    table.cells( &["id", "name"] ).unwrap();
+    table.end_row().unwrap();
+    table.cell( 1 ).unwrap();
+    table.cell( "foo\t\"bar\"\n" ).unwrap();
+    table.end_row().unwrap();
+
+    assert_eq!( output, "id\tname\r\n1\tfoo \"bar\" \r\n" );
+}
@@ -7,6 +7,11 @@ import PageDataOverview from "./PageDataOverview.js";
 import PageDataAllocations from "./PageDataAllocations.js";
 import PageDataAddressSpace from "./PageDataAddressSpace.js";
 import PageDataConsole from "./PageDataConsole.js";
+import PageDataGrowth from "./PageDataGrowth.js";
+import PageDataSlack from "./PageDataSlack.js";
+import PageDataThreadMatrix from "./PageDataThreadMatrix.js";
+import PageDataArenas from "./PageDataArenas.js";
+import PageSession from "./PageSession.js";
 
 export default class App extends React.Component {
     render() {
@@ -25,6 +30,21 @@ export default class App extends React.Component {
                     <Route exact path="/console/:id" render={ ({ match, location, history }) => {
                         return <PageDataConsole key="console" location={location} sourceUrl={this.props.sourceUrl} id={match.params.id} />;
                     }} />
+                    <Route exact path="/growth/:id" render={ ({ match, location, history }) => {
+                        return <PageDataGrowth key="growth" location={location} sourceUrl={this.props.sourceUrl} id={match.params.id} />;
+                    }} />
+                    <Route exact path="/slack/:id" render={ ({ match, location, history }) => {
+                        return <PageDataSlack key="slack" location={location} sourceUrl={this.props.sourceUrl} id={match.params.id} />;
+                    }} />
+                    <Route exact path="/thread_matrix/:id" render={ ({ match, location, history }) => {
+                        return <PageDataThreadMatrix key="thread_matrix" location={location} sourceUrl={this.props.sourceUrl} id={match.params.id} />;
+                    }} />
+                    <Route exact path="/arenas/:id" render={ ({ match, location, history }) => {
+                        return <PageDataArenas key="arenas" location={location} sourceUrl={this.props.sourceUrl} id={match.params.id} />;
+                    }} />
+                    <Route exact path="/session/:id" render={ ({ match, location, history }) => {
+                        return <PageSession key="session" location={location} sourceUrl={this.props.sourceUrl} id={match.params.id} />;
+                    }} />
                     <Route exact path="/" render={ () => {
                         return <PageDataList key="list" sourceUrl={this.props.sourceUrl} />;
                     }} />
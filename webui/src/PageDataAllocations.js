@@ -1,20 +1,21 @@
 import _ from "lodash";
 import React from "react";
 import ReactTable from "react-table";
-import { FormGroup, Label, Input, Button, ButtonGroup, Modal, ModalFooter, ModalBody, ModalHeader, Badge } from "reactstrap";
+import { FormGroup, Label, Input, Button, ButtonGroup, Modal, ModalFooter, ModalBody, ModalHeader, Badge, Progress } from "reactstrap";
 import { Link } from "react-router-dom";
 import { ContextMenu, MenuItem, ContextMenuTrigger } from "react-contextmenu";
 import AceEditor from "react-ace";
 import classNames from "classnames";
 import Feather from "./Feather.js";
 import Tabbed from "./Tabbed.js";
-import { fmt_size, fmt_date_unix_ms, fmt_date_timeval, fmt_hex16, fmt_uptime, fmt_uptime_timeval, update_query, create_query, extract_query, format_frame } from "./utils.js";
+import { fmt_size, fmt_date_unix_ms, fmt_date_timeval, fmt_hex16, fmt_uptime, fmt_uptime_timeval, update_query, create_query, extract_query, format_frame, run_job } from "./utils.js";
 
 const PERCENTAGE_REGEX = /^(\d+)%$/;
 const DATE_REGEX = /^(\d{4})-(\d{1,2})-(\d{1,2})\s+(\d{1,2}):(\d{1,2}):(\d{1,2})(\.\d{3})?$/;
 const SIZE_REGEX = /^(\d+)(k|m|g|t)?$/;
 const DURATION_REGEX = /^(\d+d)?(\d+h)?(\d+m)?(\d+s)?(\d+ms)?(\d+us)?|\d+$/;
 const POSITIVE_INTEGER_REGEX = /^(\d+)$/;
+const ID_LIST_REGEX = /^\s*\d+\s*(,\s*\d+\s*)*$/;
 
 function validate_percentage( value ) {
     return value.match( PERCENTAGE_REGEX );
@@ -34,6 +35,10 @@ function validate_positive_integer( value ) {
     return value.match( POSITIVE_INTEGER_REGEX );
 }
 
+function validate_id_list( value ) {
+    return value.match( ID_LIST_REGEX );
+}
+
 function format_or_percentage( alt ) {
     return (value) => {
         if( validate_percentage( value ) ) {
@@ -144,6 +149,13 @@ const POSITIVE_INTEGER_FIELD = {
     parse: parse_integer
 };
 
+const ID_LIST_FIELD = {
+    kind: "entry",
+    validate: validate_id_list,
+    format: value => "" + value,
+    parse: value => value.replace( /\s+/g, "" )
+};
+
 const POSITIVE_INTEGER_OR_PERCENTAGE_FIELD = {
     kind: "entry",
     validate: validate_or_percentage( POSITIVE_INTEGER_FIELD.validate ),
@@ -293,11 +305,13 @@ const FIELDS = {
         badge: value => "Sources NOT matching /" + value + "/"
     },
     backtraces: {
-        label: "Backtrace",
+        ...ID_LIST_FIELD,
+        label: "Backtrace IDs",
         badge: value => "Matching backtrace with ID " + value
     },
     deallocation_backtraces: {
-        label: "Dealloc backtrace",
+        ...ID_LIST_FIELD,
+        label: "Dealloc backtrace IDs",
         badge: value => "Matching dealloc backtrace with ID " + value
     },
     group_interval_min: {
@@ -392,6 +406,53 @@ const FIELDS = {
             non_main: "Only from non-main arena (ptmalloc)"
         }
     },
+    arena_id: {
+        ...POSITIVE_INTEGER_FIELD,
+        label: "Arena ID",
+        badge: value => "Only from arena #" + value + " (ptmalloc)"
+    },
+    threads: {
+        ...ID_LIST_FIELD,
+        label: "Thread IDs",
+        badge: value => "Only from thread #" + value.split( "," ).join( ", #" )
+    },
+    marker: {
+        ...ID_LIST_FIELD,
+        label: "Markers",
+        badge: value => "Only with marker " + value.split( "," ).join( ", " )
+    },
+    expression: {
+        kind: "entry",
+        label: "Expression (JSON, e.g. {\"or\": [\"size_min=1024\", {\"not\": \"threads=1\"}]})",
+        validate: function( value ) {
+            if( value === "" ) {
+                return false;
+            }
+
+            try {
+                JSON.parse( value );
+            } catch( exception ) {
+                return false;
+            }
+
+            return true;
+        },
+        format: identity,
+        parse: identity,
+        badge: value => "Matching " + value
+    },
+    freed_on_thread: {
+        ...RADIO_FIELD,
+        variants: {
+            "": "Show all",
+            other: "Only freed on another thread",
+            same: "Only freed on the same thread"
+        },
+        badge: {
+            other: "Only freed on another thread",
+            same: "Only freed on the same thread"
+        }
+    },
     mmaped: {
         ...RADIO_FIELD,
         variants: {
@@ -563,6 +624,11 @@ class FilterEditor extends React.Component {
                         <div className="px-2" />
                         {this.field("backtrace_depth_max")}
                     </div>
+                    <div className="d-flex flex-row">
+                        {this.field("backtraces")}
+                        <div className="px-2" />
+                        {this.field("deallocation_backtraces")}
+                    </div>
                 </div>
                 <div title="By group (global)" className="d-flex flex-column">
                     <div className="d-flex flex-row">
@@ -602,7 +668,19 @@ class FilterEditor extends React.Component {
                     <div className="px-2" />
                     {this.field("arena")}
                     <div className="px-2" />
+                    {this.field("arena_id")}
+                    <div className="px-2" />
                     {this.field("jemalloc")}
+                    <div className="px-2" />
+                    {this.field("freed_on_thread")}
+                    <div className="px-2" />
+                    <div className="d-flex flex-column">
+                        {this.field("threads")}
+                        {this.field("marker")}
+                    </div>
+                </div>
+                <div title="Expression">
+                    {this.field("expression")}
                 </div>
                 <div title="Custom">
                     <div className="editor-pane">
@@ -663,6 +741,13 @@ class FilterEditor extends React.Component {
     }
 }
 
+// The parameters which only affect how the allocations are listed, and the `from` and `to`
+// which for the timelines select the time range instead of filtering the allocations.
+const TIMELINE_IGNORED_PARAMS = [
+    "from", "to", "page", "page_size", "sort_by", "order",
+    "group_allocations", "generate_graphs", "show_full_backtraces"
+];
+
 class Control extends React.Component {
     state = {}
 
@@ -705,6 +790,7 @@ class Control extends React.Component {
         });
 
         let fullDataUrl;
+        let csvUrl;
         let heaptrackUrl;
         let treeUrl;
         let flamegraphUrl;
@@ -715,6 +801,10 @@ class Control extends React.Component {
             data_url.search = "?" + create_query( q ).toString();
             fullDataUrl = data_url.toString();
 
+            data_url.search = "?" + create_query( {..._.omit( q, "generate_graphs" ), format: "csv"} ).toString();
+            csvUrl = data_url.toString();
+            data_url.search = "?" + create_query( q ).toString();
+
             data_url.pathname = "/data/" + this.props.id + "/export/heaptrack/heaptrack.dat";
             heaptrackUrl = data_url.toString();
 
@@ -769,6 +859,9 @@ class Control extends React.Component {
                                 <MenuItem>
                                     <a href={flamegraphUrl || "#"}>Open flamegraph</a>
                                 </MenuItem>
+                                <MenuItem>
+                                    <Link to={"/overview/" + this.props.id + "?" + create_query( _.omit( this.props.filter, TIMELINE_IGNORED_PARAMS ) ).toString()}>Show the timeline of these allocations</Link>
+                                </MenuItem>
                                 <MenuItem>
                                     <Link onClick={this.openScriptingConsole.bind( this )} onAuxClick={this.openScriptingConsole.bind( this )} to={"/console/" + this.props.id}>Open scripting console</Link>
                                 </MenuItem>
@@ -778,12 +871,31 @@ class Control extends React.Component {
                                 <MenuItem>
                                     <a href={this.props.dataUrl || "#"}>Download as JSON (only this page)</a>
                                 </MenuItem>
+                                <MenuItem>
+                                    <a href={csvUrl || "#"} download={(this.props.groupByBacktraces ? "allocation_groups_" : "allocations_") + this.props.id + ".csv"}>Download as CSV (every page)</a>
+                                </MenuItem>
                                 <MenuItem>
                                     <a href={heaptrackUrl || "#"}>Download as Heaptrack data file</a>
                                 </MenuItem>
                                 <MenuItem>
                                     <a href={treeUrl || "#"}>Download as ASCII tree</a>
                                 </MenuItem>
+                                <MenuItem divider />
+                                <MenuItem onClick={this.onSaveFilter.bind( this )}>
+                                    Save filter...
+                                </MenuItem>
+                                <MenuItem onClick={this.onCreatePermalink.bind( this )}>
+                                    Copy permalink...
+                                </MenuItem>
+                                {(this.props.savedFilters || []).map( saved_filter => (
+                                    <MenuItem key={saved_filter.id}>
+                                        <Link to={"/allocations/" + this.props.id + "?" + saved_filter.query}>Saved filter: {saved_filter.name}</Link>
+                                        {" "}<Feather name="trash-2" onClick={event => {
+                                            event.preventDefault();
+                                            this.onDeleteFilter( saved_filter );
+                                        }} />
+                                    </MenuItem>
+                                ))}
                             </ContextMenu>
                         </div>
                     </div>
@@ -840,6 +952,24 @@ class Control extends React.Component {
         }
     }
 
+    onSaveFilter() {
+        if( this.props.onSaveFilter ) {
+            this.props.onSaveFilter();
+        }
+    }
+
+    onDeleteFilter( saved_filter ) {
+        if( this.props.onDeleteFilter ) {
+            this.props.onDeleteFilter( saved_filter );
+        }
+    }
+
+    onCreatePermalink() {
+        if( this.props.onCreatePermalink ) {
+            this.props.onCreatePermalink();
+        }
+    }
+
     openScriptingConsole() {
         let code = "";
         if( this.props.filterAsScript.prologue !== "" ) {
@@ -867,7 +997,7 @@ class Filter {
     }
 }
 
-function get_data_url( source_url, id, params ) {
+function get_data_query( params ) {
     params = {...params};
 
     const page = (parseInt( params.page, 10 ) || 1) - 1;
@@ -885,8 +1015,12 @@ function get_data_url( source_url, id, params ) {
     }
 
     const encoded_body = create_query( _.omit( params, "page", "page_size", "show_full_backtraces", "group_allocations" ) ).toString();
-    const data_url = source_url + "/data/" + id + "/" + source + "?" + encoded_body;
-    return data_url;
+    return { source, query: encoded_body };
+}
+
+function get_data_url( source_url, id, params ) {
+    const { source, query } = get_data_query( params );
+    return source_url + "/data/" + id + "/" + source + "?" + query;
 }
 
 function timestamp_cell( absolute, relative, relative_p ) {
@@ -977,13 +1111,21 @@ function backtrace_cell( show_full_backtraces, frames ) {
 }
 
 export default class PageDataAllocations extends React.Component {
-    state = { pages: null, data: {}, loading: false };
+    state = { pages: null, data: {}, loading: false, progress: null, savedFilters: [], notes: [] };
+
+    componentDidMount() {
+        this.fetchAnnotations();
+    }
 
     componentDidUpdate( prev_props ) {
         if( this.props.location !== prev_props.location ) {
             const params = extract_query( this.props.location.search );
             this.fetchData( params );
         }
+
+        if( this.props.id !== prev_props.id ) {
+            this.fetchAnnotations();
+        }
     }
 
     render() {
@@ -1176,6 +1318,24 @@ export default class PageDataAllocations extends React.Component {
                 maxWidth: 75,
                 view: "grouped"
             },
+            {
+                id: "all.slack",
+                Header: <div>(global)<br />Slack</div>,
+                Cell: cell => {
+                    return fmt_size( cell.original.all.slack );
+                },
+                maxWidth: 85,
+                view: "grouped"
+            },
+            {
+                id: "all.cross_thread_ratio",
+                Header: <div>(global)<br />Cross-thread</div>,
+                Cell: cell => {
+                    return <span title={cell.original.all.cross_thread_freed_count + " freed on another thread"}>{(cell.original.all.cross_thread_ratio * 100).toFixed( 1 )}%</span>;
+                },
+                maxWidth: 95,
+                view: "grouped"
+            },
 
             {
                 id: "only_matched.min_timestamp",
@@ -1230,6 +1390,24 @@ export default class PageDataAllocations extends React.Component {
                 },
                 maxWidth: 85,
                 view: "grouped"
+            },
+            {
+                id: "only_matched.slack",
+                Header: <div>(matched)<br />Slack</div>,
+                Cell: cell => {
+                    return fmt_size( cell.original.only_matched.slack );
+                },
+                maxWidth: 85,
+                view: "grouped"
+            },
+            {
+                id: "only_matched.cross_thread_ratio",
+                Header: <div>(matched)<br />Cross-thread</div>,
+                Cell: cell => {
+                    return <span title={cell.original.only_matched.cross_thread_freed_count + " freed on another thread"}>{(cell.original.only_matched.cross_thread_ratio * 100).toFixed( 1 )}%</span>;
+                },
+                maxWidth: 95,
+                view: "grouped"
             }
         ].filter( (column) => {
             if( column.view === "allocations" && this.state.group ) {
@@ -1303,7 +1481,14 @@ export default class PageDataAllocations extends React.Component {
                     onFilterChange={(filter) => {
                         update_query( this.props, filter );
                     }}
+                    savedFilters={this.state.savedFilters}
+                    onSaveFilter={this.saveFilter.bind( this )}
+                    onDeleteFilter={this.deleteFilter.bind( this )}
+                    onCreatePermalink={this.createPermalink.bind( this )}
                 />
+                {(this.state.loading && this.state.progress !== null) ? (
+                    <Progress className="mb-2" value={Math.floor( this.state.progress * 100 )}>{Math.floor( this.state.progress * 100 )}%</Progress>
+                ) : null}
                 <ReactTable
                     manual
                     data={this.state.data.allocations}
@@ -1397,6 +1582,20 @@ export default class PageDataAllocations extends React.Component {
                             cell = allocation_backtrace;
                         }
 
+                        const notes = this.state.notes.filter( note => note.backtrace_id === row.original.backtrace_id );
+                        if( notes.length > 0 ) {
+                            cell = [
+                                notes.map( note => (
+                                    <div key={"note-" + note.id} className="mb-1" style={{fontStyle: "italic"}}>
+                                        <Feather name="message-square" />{" "}
+                                        {note.text}
+                                        {" "}<Feather name="x" onClick={() => this.deleteNote( note )} />
+                                    </div>
+                                )),
+                                cell
+                            ];
+                        }
+
                         let graph = "";
                         if( row.original.only_matched && row.original.only_matched.graph_url ) {
                             const url_preview = (this.props.sourceUrl || "") + row.original.only_matched.graph_preview_url;
@@ -1427,6 +1626,9 @@ export default class PageDataAllocations extends React.Component {
                     <MenuItem>
                         <a href={this.state.showOnlyAllocationsUrl || "#"}>Show only allocations with this backtrace...</a>
                     </MenuItem>
+                    <MenuItem onClick={() => this.addNote( this.state.selectedBacktrace )}>
+                        Add a note to this backtrace...
+                    </MenuItem>
                 </ContextMenu>
                 <ContextMenu id="deallocation_context_menu">
                     <MenuItem>
@@ -1437,6 +1639,81 @@ export default class PageDataAllocations extends React.Component {
         );
     }
 
+    fetchAnnotations() {
+        const base = (this.props.sourceUrl || "") + "/data/" + this.props.id;
+        Promise.all([
+            fetch( base + "/filters", { cache: "no-cache" } ).then( response => response.json() ),
+            fetch( base + "/notes", { cache: "no-cache" } ).then( response => response.json() )
+        ])
+            .then( ([savedFilters, notes]) => this.setState( { savedFilters, notes } ) )
+            .catch( () => this.setState( { savedFilters: [], notes: [] } ) );
+    }
+
+    postAnnotation( path, body ) {
+        return fetch( (this.props.sourceUrl || "") + path, {
+            method: "POST",
+            headers: { "Content-Type": "application/json" },
+            body: JSON.stringify( body )
+        })
+            .then( response => {
+                if( response.status !== 200 ) {
+                    return response.text().then( error => Promise.reject( error ) );
+                }
+
+                return response.json();
+            });
+    }
+
+    deleteAnnotation( path ) {
+        fetch( (this.props.sourceUrl || "") + path, { method: "DELETE" } )
+            .then( () => this.fetchAnnotations() );
+    }
+
+    saveFilter() {
+        const name = window.prompt( "Save the current filter as:" );
+        if( !name ) {
+            return;
+        }
+
+        const query = create_query( _.omit( extract_query( this.props.location.search ), "page" ) ).toString();
+        this.postAnnotation( "/data/" + this.props.id + "/filters", { name, query } )
+            .then( () => this.fetchAnnotations() )
+            .catch( error => alert( "Failed to save the filter: " + error ) );
+    }
+
+    deleteFilter( saved_filter ) {
+        if( window.confirm( "Delete the saved filter '" + saved_filter.name + "'?" ) ) {
+            this.deleteAnnotation( "/filters/" + saved_filter.id );
+        }
+    }
+
+    addNote( backtrace_id ) {
+        const text = window.prompt( "Note:" );
+        if( !text ) {
+            return;
+        }
+
+        this.postAnnotation( "/data/" + this.props.id + "/notes", { text, backtrace_id } )
+            .then( () => this.fetchAnnotations() )
+            .catch( error => alert( "Failed to add the note: " + error ) );
+    }
+
+    deleteNote( note ) {
+        if( window.confirm( "Delete the note?" ) ) {
+            this.deleteAnnotation( "/notes/" + note.id );
+        }
+    }
+
+    createPermalink() {
+        const query = create_query( extract_query( this.props.location.search ) ).toString();
+        this.postAnnotation( "/data/" + this.props.id + "/permalinks", { page: "allocations", query } )
+            .then( permalink => {
+                const base = this.props.sourceUrl || window.location.origin;
+                window.prompt( "Permalink:", base + "/permalinks/" + permalink.token );
+            })
+            .catch( error => alert( "Failed to create the permalink: " + error ) );
+    }
+
     fetchData( params ) {
         if( this.state.loading ) {
             return;
@@ -1449,9 +1726,17 @@ export default class PageDataAllocations extends React.Component {
 
         this.setState({
             loading: true,
+            progress: null,
             filterAsScript: null
         });
-        fetch( data_url )
+
+        // Grouping can take a long time, so it's done as a job which can report its progress.
+        const { source, query } = get_data_query( params );
+        const request = (source === "allocation_groups")
+            ? run_job( this.props.sourceUrl, this.props.id, source, query, progress => this.setState( { progress } ) )
+            : fetch( data_url );
+
+        request
             .then( response => {
                 if( response.status !== 200 ) {
                     return response.text().then( error => Promise.reject( error ) );
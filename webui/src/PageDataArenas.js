@@ -0,0 +1,165 @@
+import React from "react";
+import { Link } from "react-router-dom";
+import Graph from "./Graph.js";
+import { fmt_size, fmt_full_size, fmt_date_timeval } from "./utils.js";
+import Feather from "./Feather.js";
+
+function arena_name( id ) {
+    return id === 0 ? "main" : "#" + id;
+}
+
+export default class PageDataArenas extends React.Component {
+    state = { arenas: null, timeline: null }
+
+    componentDidMount() {
+        fetch( (this.props.sourceUrl || "") + "/data/" + this.props.id + "/arenas" )
+            .then( rsp => rsp.json() )
+            .then( json => this.setState( {arenas: json} ) );
+
+        fetch( (this.props.sourceUrl || "") + "/data/" + this.props.id + "/arena_timeline" )
+            .then( rsp => rsp.json() )
+            .then( json => {
+                let timeline = { xs: json.xs, accessors: [], labels: [] };
+                json.arenas.forEach( (arena) => {
+                    timeline[ "usage_" + arena.id ] = arena.usage;
+                    timeline[ "fragmentation_" + arena.id ] = arena.fragmentation;
+                    timeline.accessors.push( arena.id );
+                    timeline.labels.push( "Arena " + arena_name( arena.id ) );
+                });
+                this.setState( {timeline} );
+            });
+    }
+
+    renderTimeline() {
+        const timeline = this.state.timeline;
+        if( timeline === null ) {
+            return <div>Loading...</div>;
+        }
+
+        if( timeline.xs.length < 2 ) {
+            return null;
+        }
+
+        return (
+            <div>
+                <Graph
+                    key="usage"
+                    title="Live usage per arena"
+                    data={timeline}
+                    y_accessors={timeline.accessors.map( id => "usage_" + id )}
+                    y_labels={timeline.labels}
+                    y_label=""
+                    fill={true}
+                    xUnit="unix_timestamp_ms"
+                />
+                <Graph
+                    key="fragmentation"
+                    title="Memory lost to fragmentation per arena"
+                    data={timeline}
+                    y_accessors={timeline.accessors.map( id => "fragmentation_" + id )}
+                    y_labels={timeline.labels}
+                    y_label=""
+                    fill={true}
+                    xUnit="unix_timestamp_ms"
+                />
+            </div>
+        );
+    }
+
+    renderArenas() {
+        const response = this.state.arenas;
+        if( response === null ) {
+            return <div>Loading...</div>;
+        }
+
+        const arenas = response.arenas.map( (arena) => {
+            const heaps = arena.heaps.map( (heap) => {
+                let lifetime = "first used at " + fmt_date_timeval( heap.first_used_at );
+                if( heap.mapped_at ) {
+                    lifetime = "mapped at " + fmt_date_timeval( heap.mapped_at );
+                }
+                if( heap.unmapped_at ) {
+                    lifetime += ", unmapped at " + fmt_date_timeval( heap.unmapped_at );
+                }
+
+                return <div key={"heap_" + heap.address}>{heap.address_s} <span className="text-muted">({lifetime})</span></div>;
+            });
+
+            return (
+                <tr key={"arena_" + arena.id}>
+                    <td>{arena_name( arena.id )}</td>
+                    <td><Link to={"/allocations/" + this.props.id + "?arena_id=" + arena.id}>{arena.allocated_count}</Link></td>
+                    <td title={fmt_full_size( arena.allocated_size )}>{fmt_size( arena.allocated_size )}</td>
+                    <td>{arena.threads.length}</td>
+                    <td>{arena.is_main ? "-" : heaps}</td>
+                </tr>
+            );
+        });
+
+        const threads = response.threads.map( (thread) => {
+            return (
+                <tr key={"thread_" + thread.thread}>
+                    <td>{thread.thread}</td>
+                    <td>{thread.arenas.map( arena_name ).join( ", " )}</td>
+                </tr>
+            );
+        });
+
+        return (
+            <div>
+                <div className="mb-3">
+                    Found {response.arenas.length} arena(s). The non-main arenas were reconstructed from the addresses of their
+                    heaps, which are always aligned to {fmt_size( response.heap_max_size )}.
+                </div>
+                <table className="table table-sm">
+                    <thead>
+                        <tr>
+                            <th>Arena</th>
+                            <th>Allocations</th>
+                            <th>Allocated</th>
+                            <th>Threads</th>
+                            <th>Heaps</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {arenas}
+                    </tbody>
+                </table>
+                <table className="table table-sm">
+                    <thead>
+                        <tr>
+                            <th>Thread</th>
+                            <th>Arenas</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        {threads}
+                    </tbody>
+                </table>
+            </div>
+        );
+    }
+
+    render() {
+        return (
+            <div className="PageDataArenas">
+                <div className="navbar flex-column flex-md-nonwrap shadow w-100 px-3 py-2">
+                    <div className="d-flex justify-content-between w-100">
+                        <div className="d-flex align-items-center flex-grow-0">
+                            <Link to="/" className="mr-3"><Feather name="grid" /></Link>
+                            <Link to={"/overview/" + this.props.id} className="mr-3"><Feather name="bar-chart-2" /></Link>
+                            <Link to={this.props.location} className="mr-3"><Feather name="anchor" /></Link>
+                        </div>
+                        <div className="flex-grow-1 text-center">
+                            glibc arenas
+                        </div>
+                    </div>
+                </div>
+                <div className="pt-3 px-4">
+                    {this.renderTimeline()}
+                    {this.renderArenas()}
+                </div>
+            </div>
+        );
+    }
+}
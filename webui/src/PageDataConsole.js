@@ -58,6 +58,14 @@ export default class PageDataConsole extends React.Component {
                             </a>
                         </div>
                     );
+                } else if( entry.kind === "file" ) {
+                    const url = (this.props.sourceUrl || "") + entry.url;
+                    const key = "file-" + entry.checksum;
+                    list.push(
+                        <div key={key} className="script-file">
+                            <a href={url} download={entry.basename}>Download {entry.path}</a>
+                        </div>
+                    );
                 }
             }
 
@@ -65,6 +73,12 @@ export default class PageDataConsole extends React.Component {
                 list.push(
                     <div key="response-error" className="error">ERROR: {this.state.response.message}</div>
                 );
+            } else if( this.state.response.status == "running" ) {
+                list.push(
+                    <div key="response-message" className="message">
+                        Running for {Math.floor( this.state.response.elapsed || 0 )}s; {this.state.response.operations || 0} operations executed so far...
+                    </div>
+                );
             } else if( this.state.response.elapsed ) {
                 list.push(
                     <div key="response-message" className="message">Script finished in {this.state.response.elapsed}s</div>
@@ -112,7 +126,7 @@ export default class PageDataConsole extends React.Component {
                         <br />
                         <div className="d-flex justify-content-between">
                             <Button outline color="primary" className="ml-2 btn-sm" onClick={this.run.bind( this )} style={{minWidth: "8em"}}>{
-                                (!this.state.running) ? ("Run") : ("Running...")
+                                (!this.state.running) ? ("Run") : ("Cancel")
                             }</Button>
                             <Button outline color="dark" className="ml-2 btn-sm" onClick={this.copy.bind( this )}>Copy script to clipboard</Button>
                         </div>
@@ -127,30 +141,72 @@ export default class PageDataConsole extends React.Component {
         navigator.clipboard.writeText(this.state.code);
     }
 
+    componentWillUnmount() {
+        clearTimeout( this.timer );
+    }
+
     run() {
+        if( this.state.running ) {
+            this.cancel();
+            return;
+        }
+
         this.setState({
-            running: true
+            running: true,
+            response: { status: "running", output: [] }
         });
 
-        fetch( (this.props.sourceUrl || "") + "/data/" + this.props.id + "/execute_script", {
+        fetch( (this.props.sourceUrl || "") + "/data/" + this.props.id + "/script_jobs", {
             method: "POST",
             cache: "no-cache",
             body: this.state.code
         })
         .then( response => response.json() )
         .then( response => {
+            this.job = response.id;
+            this.poll();
+        })
+        .catch( error => this.fail( error ) );
+    }
+
+    poll() {
+        const job = this.job;
+        const since = this.state.response.output.length;
+        fetch( (this.props.sourceUrl || "") + "/script_jobs/" + job + "?since=" + since, { cache: "no-cache" } )
+        .then( response => response.json() )
+        .then( response => {
+            if( job !== this.job ) {
+                return;
+            }
+
+            response.output = this.state.response.output.concat( response.output );
+            const running = response.status === "running";
             this.setState({
-                running: false,
+                running,
                 response
             });
+
+            if( running ) {
+                this.timer = setTimeout( () => this.poll(), 250 );
+            }
         })
-        .catch( error => {
-            this.setState({
-                running: false,
-                response: {
-                    error: "Failed: " + error
-                }
-            });
+        .catch( error => this.fail( error ) );
+    }
+
+    cancel() {
+        fetch( (this.props.sourceUrl || "") + "/script_jobs/" + this.job, {
+            method: "DELETE"
+        });
+    }
+
+    fail( error ) {
+        this.setState({
+            running: false,
+            response: {
+                status: "error",
+                message: "Failed: " + error,
+                output: []
+            }
         });
     }
 }
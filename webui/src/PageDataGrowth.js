@@ -0,0 +1,92 @@
+import React from "react";
+import { Link } from "react-router-dom";
+import { fmt_size, fmt_full_size, create_query, extract_query, format_frame } from "./utils.js";
+import Feather from "./Feather.js";
+
+export default class PageDataGrowth extends React.Component {
+    state = { response: null }
+
+    componentDidMount() {
+        const params = extract_query( this.props.location.search );
+        if( params.count === undefined ) {
+            params.count = 100;
+        }
+
+        const encoded_body = create_query( params ).toString();
+        const url = (this.props.sourceUrl || "") + "/data/" + this.props.id + "/growth_patterns?" + encoded_body;
+        fetch( url )
+            .then( rsp => rsp.json() )
+            .then( json => this.setState( {response: json} ) );
+    }
+
+    render() {
+        let body;
+        if( this.state.response === null ) {
+            body = <div>Loading...</div>;
+        } else if( this.state.response.groups.length === 0 ) {
+            body = <div>No geometrically growing reallocation chains were found.</div>;
+        } else {
+            const rows = this.state.response.groups.map( (group) => {
+                const backtrace = group.backtrace.map( (frame, index) => format_frame( index, frame ) );
+                return (
+                    <tr key={"group_" + group.backtrace_id}>
+                        <td>{fmt_size( group.copied_bytes )}</td>
+                        <td>{group.avoidable_reallocations}</td>
+                        <td>
+                            <Link to={"/allocations/" + this.props.id + "?backtraces=" + group.backtrace_id}>{group.chain_count}</Link>
+                        </td>
+                        <td>{group.average_growth_factor.toFixed( 2 )}x</td>
+                        <td>{fmt_size( group.min_final_size )} - {fmt_size( group.max_final_size )}</td>
+                        <td title={fmt_full_size( group.suggested_capacity )}>{fmt_size( group.suggested_capacity )}</td>
+                        <td className="backtrace">{backtrace}</td>
+                    </tr>
+                );
+            });
+
+            body = (
+                <div>
+                    <div className="mb-3">
+                        Found {this.state.response.total_count} backtrace(s) with geometrically growing reallocation chains.
+                        Those reallocations could have been avoided by reserving the suggested capacity up-front.
+                    </div>
+                    <table className="table table-sm">
+                        <thead>
+                            <tr>
+                                <th>Copied</th>
+                                <th>Avoidable reallocations</th>
+                                <th>Chains</th>
+                                <th>Growth factor</th>
+                                <th>Final size</th>
+                                <th>Suggested capacity</th>
+                                <th>Backtrace</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            {rows}
+                        </tbody>
+                    </table>
+                </div>
+            );
+        }
+
+        return (
+            <div className="PageDataGrowth">
+                <div className="navbar flex-column flex-md-nonwrap shadow w-100 px-3 py-2">
+                    <div className="d-flex justify-content-between w-100">
+                        <div className="d-flex align-items-center flex-grow-0">
+                            <Link to="/" className="mr-3"><Feather name="grid" /></Link>
+                            <Link to={"/overview/" + this.props.id} className="mr-3"><Feather name="bar-chart-2" /></Link>
+                            <Link to={this.props.location} className="mr-3"><Feather name="anchor" /></Link>
+                        </div>
+                        <div className="flex-grow-1 text-center">
+                            Reallocation growth patterns
+                        </div>
+                    </div>
+                </div>
+                <div className="pt-3 px-4">
+                    {body}
+                </div>
+            </div>
+        );
+    }
+}
@@ -1,14 +1,25 @@
 import React from "react";
 import ReactTable from "react-table";
-import { Button } from "reactstrap";
+import { Button, Input, Progress } from "reactstrap";
 import { Link } from "react-router-dom";
 import { fmt_uptime, fmt_size, fmt_date_unix } from "./utils.js";
 
 export default class PageDataList extends React.Component {
-    state = { datasets: [] }
+    state = { datasets: [], sessions: [], loading: [], path: "" }
 
     componentDidMount() {
         this.updateDatasetList();
+        this.updateLoading();
+
+        // Files can also be loaded by the server on its own when it's watching a directory.
+        this.timer = setInterval( () => {
+            this.updateLoading();
+            this.updateDatasetList();
+        }, 1000 );
+    }
+
+    componentWillUnmount() {
+        clearInterval( this.timer );
     }
 
     render() {
@@ -58,7 +69,11 @@ export default class PageDataList extends React.Component {
                 Header: "...",
                 Cell: row => {
                     return (
-                        <Link to={"/overview/" + row.original.id}>Open</Link>
+                        <span>
+                            <Link to={"/overview/" + row.original.id}>Open</Link>
+                            {" "}
+                            <Button size="sm" color="link" className="p-0 align-baseline" onClick={() => this.unload( row.original.id )}>Unload</Button>
+                        </span>
                     );
                 },
                 maxWidth: 150
@@ -74,6 +89,9 @@ export default class PageDataList extends React.Component {
                     </div>
                 </div>
                 <div className="px-4 pt-4">
+                    {this.renderLoadForm()}
+                    {this.renderLoading()}
+                    {this.renderSessions()}
                     <ReactTable
                         columns={columns}
                         data={this.state.datasets}
@@ -85,6 +103,101 @@ export default class PageDataList extends React.Component {
         );
     }
 
+    renderSessions() {
+        if( this.state.sessions.length === 0 ) {
+            return null;
+        }
+
+        const sessions = this.state.sessions.map( session => {
+            const root = session.processes[ 0 ];
+            const label = root ? root.executable.match( /[^/]+$/ )[ 0 ] + " (PID " + root.pid + ")" : "empty";
+            return (
+                <li key={"session_" + session.id}>
+                    <Link to={"/session/" + session.id}>Session #{session.id}</Link>: {label}, {session.processes.length} process(es)
+                </li>
+            );
+        });
+
+        return <ul className="mb-4">{sessions}</ul>;
+    }
+
+    renderLoadForm() {
+        return (
+            <div className="d-flex mb-4">
+                <Input
+                    type="text"
+                    className="mr-2"
+                    placeholder="Path to a data file on the server"
+                    value={this.state.path}
+                    onChange={event => this.setState( { path: event.target.value } )}
+                />
+                <Button className="mr-4" disabled={this.state.path === ""} onClick={() => this.load()}>Load</Button>
+                <Input type="file" onChange={event => this.upload( event.target.files[ 0 ] )} />
+            </div>
+        );
+    }
+
+    renderLoading() {
+        if( this.state.loading.length === 0 ) {
+            return null;
+        }
+
+        const entries = this.state.loading.map( entry => {
+            if( entry.error !== null ) {
+                return (
+                    <div key={"loading_" + entry.id} className="mb-2 text-danger">
+                        Failed to load {entry.name}: {entry.error}
+                        {" "}
+                        <Button size="sm" color="link" className="p-0 align-baseline" onClick={() => this.dismiss( entry.id )}>Dismiss</Button>
+                    </div>
+                );
+            }
+
+            const percent = entry.total > 0 ? Math.floor( entry.loaded / entry.total * 100 ) : 100;
+            return (
+                <div key={"loading_" + entry.id} className="mb-2">
+                    Loading {entry.name} ({fmt_size( entry.loaded )}B / {fmt_size( entry.total )}B)
+                    <Progress value={percent}>{percent}%</Progress>
+                </div>
+            );
+        });
+
+        return <div className="mb-4">{entries}</div>;
+    }
+
+    load() {
+        fetch( this.props.sourceUrl + "/load?path=" + encodeURIComponent( this.state.path ), { method: "POST" } )
+            .then( () => {
+                this.setState( { path: "" } );
+                this.updateLoading();
+            });
+    }
+
+    upload( file ) {
+        if( !file ) {
+            return;
+        }
+
+        fetch( this.props.sourceUrl + "/upload?name=" + encodeURIComponent( file.name ), { method: "POST", body: file } )
+            .then( () => this.updateLoading() );
+    }
+
+    unload( id ) {
+        fetch( this.props.sourceUrl + "/data/" + id, { method: "DELETE" } )
+            .then( () => this.updateDatasetList() );
+    }
+
+    dismiss( id ) {
+        fetch( this.props.sourceUrl + "/loading/" + id, { method: "DELETE" } )
+            .then( () => this.updateLoading() );
+    }
+
+    updateLoading() {
+        fetch( this.props.sourceUrl + "/loading" )
+            .then( response => response.json() )
+            .then( loading => this.setState( { loading } ) );
+    }
+
     preprocess( data ) {
         return data.map( in_row => {
             let row = {...in_row};
@@ -97,5 +210,9 @@ export default class PageDataList extends React.Component {
         fetch( this.props.sourceUrl + "/list" )
             .then( response => response.json() )
             .then( list => this.setState( { datasets: list } ) );
+
+        fetch( this.props.sourceUrl + "/sessions" )
+            .then( response => response.json() )
+            .then( sessions => this.setState( { sessions } ) );
     }
 }
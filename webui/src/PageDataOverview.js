@@ -5,7 +5,7 @@ import { Button, ButtonGroup, DropdownMenu, DropdownItem } from "reactstrap";
 import { ContextMenu, MenuItem, ContextMenuTrigger } from "react-contextmenu";
 import { Link } from "react-router-dom";
 import classNames from "classnames";
-import { fmt_date_unix_ms, fmt_uptime, fmt_size } from "./utils.js";
+import { fmt_date_unix_ms, fmt_uptime, fmt_size, create_query, extract_query } from "./utils.js";
 import Feather from "./Feather.js";
 
 class Switcher extends React.Component {
@@ -49,25 +49,95 @@ class Switcher extends React.Component {
     }
 }
 
+// How many points are fetched for every graph; the server downsamples the timelines to this.
+const TIMELINE_POINTS = 2000;
+
 export default class PageDataOverview extends React.Component {
     state = {}
 
+    constructor() {
+        super()
+        this.timelineRequest = 0;
+        this.fetchZoomedTimelines = _.debounce( (x0, x1) => this.fetchTimelines( x0, x1 ), 250 );
+    }
+
     componentDidMount() {
         fetch( this.props.sourceUrl + "/list" )
             .then( response => response.json() )
             .then( list => this.setState( {general: _.find( list, entry => entry.id === this.props.id ) } ) );
 
-        fetch( (this.props.sourceUrl || "") + "/data/" + this.props.id + "/timeline" )
-            .then( rsp => rsp.json() )
-            .then( json => this.setState( {timeline: json} ) );
+        this.fetchTimelines();
+        this.subscribe();
+    }
+
+    componentWillUnmount() {
+        this.fetchZoomedTimelines.cancel();
+        if( this.events ) {
+            this.events.close();
+        }
+    }
+
+    // Recordings which are still being written to are periodically reloaded by the server, which tells us what has changed.
+    subscribe() {
+        if( typeof EventSource === "undefined" ) {
+            return;
+        }
+
+        this.events = new EventSource( (this.props.sourceUrl || "") + "/data/" + this.props.id + "/events" );
+        this.events.addEventListener( "timeline", event => {
+            if( _.isNumber( this.state.x0 ) ) {
+                return;
+            }
+
+            if( !_.isEmpty( this.getFilter() ) || !this.state.timeline ) {
+                this.fetchTimelines();
+                return;
+            }
+
+            const points = JSON.parse( event.data );
+            const timeline = _.mapValues( this.state.timeline, (values, key) => values.concat( points[ key ] || [] ) );
+            this.setState( {timeline} );
+        });
+        this.events.addEventListener( "allocation_groups", event => {
+            const groups = JSON.parse( event.data );
+            this.setState( {new_groups: (this.state.new_groups || 0) + groups.length} );
+        });
+        this.events.addEventListener( "unloaded", () => {
+            this.events.close();
+            this.setState( {unloaded: true} );
+        });
+    }
+
+    getFilter() {
+        // The `from` and `to` of the timelines select the range which is shown instead of filtering the allocations.
+        return _.omit( extract_query( this.props.location.search ), "from", "to" );
+    }
 
-        fetch( (this.props.sourceUrl || "") + "/data/" + this.props.id + "/timeline_leaked" )
-            .then( rsp => rsp.json() )
-            .then( json => this.setState( {timeline_leaked: json} ) );
+    fetchTimelines( x0, x1 ) {
+        const q = this.getFilter();
+        q.max_points = TIMELINE_POINTS;
+        if( x0 !== undefined && x1 !== undefined ) {
+            q.from = Math.floor( x0 );
+            q.to = Math.ceil( x1 );
+        }
+
+        const query = "?" + create_query( q ).toString();
+        const prefix = (this.props.sourceUrl || "") + "/data/" + this.props.id;
+        const request = ++this.timelineRequest;
+        const load = (path, key) => {
+            fetch( prefix + path + query )
+                .then( rsp => rsp.json() )
+                .then( json => {
+                    // Ignore the responses for a zoom level which is not shown anymore.
+                    if( request === this.timelineRequest ) {
+                        this.setState( {[key]: json} );
+                    }
+                });
+        };
 
-        fetch( (this.props.sourceUrl || "") + "/data/" + this.props.id + "/fragmentation_timeline" )
-            .then( rsp => rsp.json() )
-            .then( json => this.setState( {fragmentation_timeline: json} ) );
+        load( "/timeline", "timeline" );
+        load( "/timeline_leaked", "timeline_leaked" );
+        load( "/fragmentation_timeline", "fragmentation_timeline" );
     }
 
     render() {
@@ -195,6 +265,19 @@ export default class PageDataOverview extends React.Component {
                             fill={true}
                             xUnit="unix_timestamp_ms"
                         />
+                        <Graph
+                            key="slack"
+                            title="Allocator slack"
+                            data={this.state.timeline}
+                            y_accessor="allocated_slack"
+                            y_label=""
+                            onZoom={this.onZoom.bind(this)}
+                            onRightClick={this.onRightClick.bind(this)}
+                            x0={this.state.x0}
+                            x1={this.state.x1}
+                            fill={true}
+                            xUnit="unix_timestamp_ms"
+                        />
                     </Switcher>
                 );
             }
@@ -266,7 +349,16 @@ export default class PageDataOverview extends React.Component {
                             <Link to={this.props.location} className="mr-3"><Feather name="anchor" /></Link>
                         </div>
                         <div className="flex-grow-1 text-center">
-                            Overview of {this.props.id}
+                            Overview of {this.props.id}{_.isEmpty( this.getFilter() ) ? "" : " (only the filtered allocations)"}
+                            {this.state.new_groups ? (
+                                <span>{" "}(<Link to={"/allocations/" + this.props.id}>{this.state.new_groups} new allocation groups</Link>)</span>
+                            ) : null}
+                            {this.state.unloaded ? " (this data was unloaded)" : null}
+                        </div>
+                        <div className="flex-grow-0">
+                            {_.isNumber( this.state.x0 ) ? (
+                                <Button outline color="primary" className="btn-sm" onClick={this.resetZoom.bind( this )}>Reset zoom</Button>
+                            ) : null}
                         </div>
                     </div>
                 </div>
@@ -290,6 +382,10 @@ export default class PageDataOverview extends React.Component {
                                 <div><a href={(this.props.sourceUrl || "") + "/data/" + this.props.id + "/export/heaptrack/heaptrack.dat"}>...as Heaptrack data</a></div>
                             </div>
                             <div><Link to={"/address_space/" + this.props.id + "?lifetime=only_not_deallocated_in_current_range&mmaped=no"}>Address space fragmentation</Link></div>
+                            <div><Link to={"/growth/" + this.props.id}>Reallocation growth patterns</Link></div>
+                            <div><Link to={"/slack/" + this.props.id}>Allocator slack</Link></div>
+                            <div><Link to={"/thread_matrix/" + this.props.id}>Cross-thread frees</Link></div>
+                            <div><Link to={"/arenas/" + this.props.id}>glibc arenas</Link></div>
                             <div>
                                 <a href={(this.props.sourceUrl || "") + "/data/" + this.props.id + "/dynamic_constants_ascii_tree/dynamic_constants_" + this.props.id + ".txt"}>Dynamically allocated constants</a>
                                 &nbsp;(<a href={(this.props.sourceUrl || "") + "/data/" + this.props.id + "/dynamic_constants/dynamic_constants_" + this.props.id + ".json"}>.json</a>)
@@ -336,6 +432,13 @@ export default class PageDataOverview extends React.Component {
 
     onZoom( min, max ) {
         this.setState( {x0: min, x1: max} );
+        this.fetchZoomedTimelines( min, max );
+    }
+
+    resetZoom() {
+        this.fetchZoomedTimelines.cancel();
+        this.setState( {x0: undefined, x1: undefined} );
+        this.fetchTimelines();
     }
 
     onRightClick( {event, x, x0, x1} ) {
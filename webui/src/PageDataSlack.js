@@ -0,0 +1,103 @@
+import React from "react";
+import { Link } from "react-router-dom";
+import { fmt_size, fmt_full_size, create_query, extract_query } from "./utils.js";
+import Feather from "./Feather.js";
+
+export default class PageDataSlack extends React.Component {
+    state = { response: null }
+
+    componentDidMount() {
+        const params = extract_query( this.props.location.search );
+        if( params.count === undefined ) {
+            params.count = 100;
+        }
+
+        const encoded_body = create_query( params ).toString();
+        const url = (this.props.sourceUrl || "") + "/data/" + this.props.id + "/slack?" + encoded_body;
+        fetch( url )
+            .then( rsp => rsp.json() )
+            .then( json => this.setState( {response: json} ) );
+    }
+
+    render() {
+        let body;
+        if( this.state.response === null ) {
+            body = <div>Loading...</div>;
+        } else {
+            const response = this.state.response;
+            const rows = response.size_classes.map( (size_class) => {
+                const link = "/allocations/" + this.props.id + "?" + create_query({
+                    size_min: size_class.min_requested_size,
+                    size_max: size_class.max_requested_size
+                }).toString();
+
+                return (
+                    <tr key={"size_class_" + size_class.usable_size}>
+                        <td title={fmt_full_size( size_class.usable_size )}>{fmt_size( size_class.usable_size )}</td>
+                        <td>{fmt_size( size_class.min_requested_size )} - {fmt_size( size_class.max_requested_size )}</td>
+                        <td><Link to={link}>{size_class.count}</Link></td>
+                        <td>{fmt_size( size_class.requested_size )}</td>
+                        <td>{fmt_size( size_class.slack )}</td>
+                        <td>{(size_class.slack / (size_class.requested_size + size_class.slack) * 100).toFixed( 1 )}%</td>
+                    </tr>
+                );
+            });
+
+            body = (
+                <div>
+                    <table id="overview-table" className="mb-3">
+                        <tbody>
+                            <tr>
+                                <td>Requested</td>
+                                <td>{fmt_size( response.requested_size )}</td>
+                            </tr>
+                            <tr>
+                                <td>Usable</td>
+                                <td>{fmt_size( response.usable_size )}</td>
+                            </tr>
+                            <tr>
+                                <td>Slack</td>
+                                <td>{fmt_size( response.slack )}</td>
+                            </tr>
+                        </tbody>
+                    </table>
+                    <table className="table table-sm">
+                        <thead>
+                            <tr>
+                                <th>Size class</th>
+                                <th>Requested size</th>
+                                <th>Allocations</th>
+                                <th>Total requested</th>
+                                <th>Total slack</th>
+                                <th>Wasted</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            {rows}
+                        </tbody>
+                    </table>
+                </div>
+            );
+        }
+
+        return (
+            <div className="PageDataSlack">
+                <div className="navbar flex-column flex-md-nonwrap shadow w-100 px-3 py-2">
+                    <div className="d-flex justify-content-between w-100">
+                        <div className="d-flex align-items-center flex-grow-0">
+                            <Link to="/" className="mr-3"><Feather name="grid" /></Link>
+                            <Link to={"/overview/" + this.props.id} className="mr-3"><Feather name="bar-chart-2" /></Link>
+                            <Link to={this.props.location} className="mr-3"><Feather name="anchor" /></Link>
+                        </div>
+                        <div className="flex-grow-1 text-center">
+                            Allocator slack by size class
+                        </div>
+                    </div>
+                </div>
+                <div className="pt-3 px-4">
+                    {body}
+                </div>
+            </div>
+        );
+    }
+}
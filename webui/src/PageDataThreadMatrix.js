@@ -0,0 +1,98 @@
+import React from "react";
+import { Link } from "react-router-dom";
+import { fmt_size, fmt_full_size, create_query, extract_query } from "./utils.js";
+import Feather from "./Feather.js";
+
+export default class PageDataThreadMatrix extends React.Component {
+    state = { response: null }
+
+    componentDidMount() {
+        const params = extract_query( this.props.location.search );
+        if( params.count === undefined ) {
+            params.count = 100;
+        }
+
+        const encoded_body = create_query( params ).toString();
+        const url = (this.props.sourceUrl || "") + "/data/" + this.props.id + "/thread_matrix?" + encoded_body;
+        fetch( url )
+            .then( rsp => rsp.json() )
+            .then( json => this.setState( {response: json} ) );
+    }
+
+    render() {
+        let body;
+        if( this.state.response === null ) {
+            body = <div>Loading...</div>;
+        } else {
+            const response = this.state.response;
+            const total_size = response.cross_thread_size + response.same_thread_size;
+            const rows = response.pairs.map( (pair) => {
+                const is_cross_thread = pair.allocated_on !== pair.freed_on;
+                return (
+                    <tr key={"pair_" + pair.allocated_on + "_" + pair.freed_on} className={is_cross_thread ? "" : "text-muted"}>
+                        <td>{pair.allocated_on}</td>
+                        <td>{pair.freed_on}</td>
+                        <td>{pair.count}</td>
+                        <td title={fmt_full_size( pair.size )}>{fmt_size( pair.size )}</td>
+                        <td>{total_size === 0 ? "0.0" : (pair.size / total_size * 100).toFixed( 1 )}%</td>
+                    </tr>
+                );
+            });
+
+            body = (
+                <div>
+                    <table id="overview-table" className="mb-3">
+                        <tbody>
+                            <tr>
+                                <td>Freed on another thread</td>
+                                <td>{response.cross_thread_count} ({fmt_size( response.cross_thread_size )})</td>
+                            </tr>
+                            <tr>
+                                <td>Freed on the same thread</td>
+                                <td>{response.same_thread_count} ({fmt_size( response.same_thread_size )})</td>
+                            </tr>
+                            <tr>
+                                <td>Allocations</td>
+                                <td><Link to={"/allocations/" + this.props.id + "?freed_on_thread=other"}>Show the ones freed on another thread</Link></td>
+                            </tr>
+                        </tbody>
+                    </table>
+                    <table className="table table-sm">
+                        <thead>
+                            <tr>
+                                <th>Allocated on thread</th>
+                                <th>Freed on thread</th>
+                                <th>Allocations</th>
+                                <th>Size</th>
+                                <th>Share</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            {rows}
+                        </tbody>
+                    </table>
+                </div>
+            );
+        }
+
+        return (
+            <div className="PageDataThreadMatrix">
+                <div className="navbar flex-column flex-md-nonwrap shadow w-100 px-3 py-2">
+                    <div className="d-flex justify-content-between w-100">
+                        <div className="d-flex align-items-center flex-grow-0">
+                            <Link to="/" className="mr-3"><Feather name="grid" /></Link>
+                            <Link to={"/overview/" + this.props.id} className="mr-3"><Feather name="bar-chart-2" /></Link>
+                            <Link to={this.props.location} className="mr-3"><Feather name="anchor" /></Link>
+                        </div>
+                        <div className="flex-grow-1 text-center">
+                            Cross-thread frees
+                        </div>
+                    </div>
+                </div>
+                <div className="pt-3 px-4">
+                    {body}
+                </div>
+            </div>
+        );
+    }
+}
@@ -0,0 +1,147 @@
+import React from "react";
+import { Link } from "react-router-dom";
+import Graph from "./Graph.js";
+import { fmt_date_timeval, fmt_uptime_timeval, extract_query, create_query } from "./utils.js";
+import Feather from "./Feather.js";
+
+function process_label( process ) {
+    return process.pid + " (" + process.executable.match( /[^/]*$/ )[ 0 ] + ")";
+}
+
+export default class PageSession extends React.Component {
+    state = { session: null, timeline: null, excluded: {} }
+
+    componentDidMount() {
+        fetch( (this.props.sourceUrl || "") + "/sessions" )
+            .then( rsp => rsp.json() )
+            .then( json => {
+                const session = json.find( session => session.id + "" === this.props.id );
+                this.setState( {session: session || null} );
+            });
+
+        this.fetchTimeline( this.state.excluded );
+    }
+
+    fetchTimeline( excluded ) {
+        // The filters from the URL are applied to every process.
+        let query = extract_query( this.props.location.search );
+        if( this.state.session !== null && Object.keys( excluded ).length > 0 ) {
+            query.pids = this.state.session.processes
+                .filter( process => !excluded[ process.pid ] )
+                .map( process => process.pid )
+                .join( "," );
+        }
+
+        fetch( (this.props.sourceUrl || "") + "/session/" + this.props.id + "/timeline?" + create_query( query ).toString() )
+            .then( rsp => rsp.json() )
+            .then( json => {
+                let timeline = { xs: json.xs, accessors: [], labels: [] };
+                json.processes.forEach( (process) => {
+                    timeline[ "usage_" + process.id ] = process.usage;
+                    timeline.accessors.push( process.id );
+                    timeline.labels.push( "PID " + process.pid );
+                });
+                this.setState( {timeline} );
+            });
+    }
+
+    toggleProcess( pid ) {
+        let excluded = {...this.state.excluded};
+        if( excluded[ pid ] ) {
+            delete excluded[ pid ];
+        } else {
+            excluded[ pid ] = true;
+        }
+
+        this.setState( {excluded} );
+        this.fetchTimeline( excluded );
+    }
+
+    renderTimeline() {
+        const timeline = this.state.timeline;
+        if( timeline === null ) {
+            return <div>Loading...</div>;
+        }
+
+        if( timeline.xs.length < 2 ) {
+            return null;
+        }
+
+        return (
+            <Graph
+                key="usage"
+                title="Memory usage per process"
+                data={timeline}
+                y_accessors={timeline.accessors.map( id => "usage_" + id )}
+                y_labels={timeline.labels}
+                y_label=""
+                fill={true}
+                xUnit="unix_timestamp_ms"
+            />
+        );
+    }
+
+    renderProcesses() {
+        const session = this.state.session;
+        if( session === null ) {
+            return <div>Loading...</div>;
+        }
+
+        const processes = session.processes.map( (process) => {
+            return (
+                <tr key={"process_" + process.id}>
+                    <td>
+                        <input type="checkbox" className="mr-2" checked={!this.state.excluded[ process.pid ]} onChange={() => this.toggleProcess( process.pid )} />
+                    </td>
+                    <td style={{paddingLeft: (process.depth * 1.5 + 0.3) + "em"}}>
+                        {process.depth > 0 ? "└ " : ""}{process_label( process )}
+                    </td>
+                    <td>{process.parent_pid === null ? "-" : process.parent_pid}</td>
+                    <td>{fmt_date_timeval( process.started_at )}</td>
+                    <td>{fmt_uptime_timeval( {secs: process.finished_at.secs - process.started_at.secs, fract_nsecs: 0} )}</td>
+                    <td><Link to={"/overview/" + process.id}>Open</Link></td>
+                </tr>
+            );
+        });
+
+        return (
+            <table className="table table-sm">
+                <thead>
+                    <tr>
+                        <th></th>
+                        <th>Process</th>
+                        <th>Parent PID</th>
+                        <th>Started at</th>
+                        <th>Runtime</th>
+                        <th>...</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {processes}
+                </tbody>
+            </table>
+        );
+    }
+
+    render() {
+        return (
+            <div className="PageSession">
+                <div className="navbar flex-column flex-md-nonwrap shadow w-100 px-3 py-2">
+                    <div className="d-flex justify-content-between w-100">
+                        <div className="d-flex align-items-center flex-grow-0">
+                            <Link to="/" className="mr-3"><Feather name="grid" /></Link>
+                            <Link to={this.props.location} className="mr-3"><Feather name="anchor" /></Link>
+                        </div>
+                        <div className="flex-grow-1 text-center">
+                            Session #{this.props.id}
+                        </div>
+                    </div>
+                </div>
+                <div className="pt-3 px-4">
+                    {this.renderTimeline()}
+                    {this.renderProcesses()}
+                </div>
+            </div>
+        );
+    }
+}
@@ -308,4 +308,38 @@ function def( value, default_value ) {
     }
 }
 
-export { fmt_uptime, fmt_uptime_timeval, fmt_size, fmt_full_size, fmt_date, fmt_date_unix, fmt_date_unix_ms, fmt_date_timeval, fmt_hex16, update_query, create_query, extract_query, format_frame, def }
+// Submits an expensive query as a job on the server and waits until it's done,
+// periodically calling `on_progress` with a fraction of the work done so far (or null
+// if unknown). Resolves to the same response as a direct request would.
+function run_job( source_url, id, kind, query, on_progress ) {
+    const base = source_url || "";
+    return fetch( base + "/data/" + id + "/jobs/" + kind + "?" + query, { method: "POST" } )
+        .then( response => {
+            if( response.status !== 200 ) {
+                return response.text().then( error => Promise.reject( error ) );
+            }
+
+            return response.json();
+        })
+        .then( job => new Promise( (resolve, reject) => {
+            const poll = () => {
+                fetch( base + "/jobs/" + job.id, { cache: "no-cache" } )
+                    .then( response => response.json() )
+                    .then( status => {
+                        if( status.status === "running" ) {
+                            on_progress( status.progress );
+                            setTimeout( poll, 250 );
+                        } else if( status.status === "failed" ) {
+                            reject( status.error );
+                        } else {
+                            resolve( fetch( base + "/jobs/" + job.id + "/result" ) );
+                        }
+                    })
+                    .catch( reject );
+            };
+
+            poll();
+        }));
+}
+
+export { fmt_uptime, fmt_uptime_timeval, fmt_size, fmt_full_size, fmt_date, fmt_date_unix, fmt_date_unix_ms, fmt_date_timeval, fmt_hex16, update_query, create_query, extract_query, format_frame, def, run_job }